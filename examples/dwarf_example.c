@@ -0,0 +1,41 @@
+/* Built into dwarf_example.elf, which the DWARF import tests read, with:
+ * gcc -g -shared -nostdlib -fno-asynchronous-unwind-tables -o dwarf_example.elf dwarf_example.c
+ */
+#include <stdint.h>
+
+typedef uint16_t port_t;
+
+enum state { IDLE, BUSY = 4, FAILED = -1 };
+
+struct point {
+    int16_t x;
+    int16_t y;
+};
+
+union value {
+    uint32_t number;
+    uint8_t bytes[4];
+};
+
+struct packet {
+    char magic[4];
+    uint8_t version;
+    port_t port;
+    struct point points[2];
+    union value value;
+    enum state state;
+    unsigned int ack : 1;
+    unsigned int window : 7;
+    signed int level : 4;
+    double ratio;
+    struct packet *next;
+    union {
+        uint16_t word;
+        uint8_t byte;
+    };
+};
+
+typedef struct packet PACKET;
+
+struct packet packet;
+PACKET *last_packet;
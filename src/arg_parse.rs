@@ -1,5 +1,10 @@
 //! Module for Command line arg parsing.
+use crate::c_types;
 use crate::errors;
+use crate::format;
+use crate::hex_display;
+use crate::rust_convert;
+use crate::theme;
 use std::collections::HashMap;
 use std::env;
 use std::path::Path;
@@ -13,6 +18,23 @@ pub const FMT_ENDIAN_FLAG: &str = "-e";
 pub const HEX_ENDIAN_FLAG: &str = "-E";
 pub const DESCRIPTION_FLAG: &str = "-d";
 pub const HELP_FLAG: &str = "-h";
+pub const OUTPUT_FORMAT_FLAG: &str = "--format";
+pub const COLUMNS_FLAG: &str = "--columns";
+pub const COLOUR_FLAG: &str = "--color";
+pub const THEME_FLAG: &str = "--theme";
+pub const ROW_WIDTH_FLAG: &str = "--row-width";
+pub const GROUP_FLAG: &str = "--group";
+pub const CONTEXT_FLAG: &str = "--context";
+pub const CONTEXT_BEFORE_FLAG: &str = "--before";
+pub const CONTEXT_AFTER_FLAG: &str = "--after";
+pub const SIDE_COLUMN_FLAG: &str = "--side";
+pub const LEGEND_FLAG: &str = "--legend";
+pub const MARKERS_FLAG: &str = "--markers";
+pub const SYNTAX_FLAG: &str = "--syntax";
+pub const LIST_FORMATS_FLAG: &str = "--list-formats";
+pub const ABI_FLAG: &str = "--abi";
+pub const ROOT_FLAG: &str = "--root";
+pub const ELF_FILE_FLAG: &str = "--elf";
 
 /// Holds the state of all of the arguments passed to memgram.
 ///
@@ -28,6 +50,8 @@ pub struct CMDArgParse {
     pub binary_filepath: String,
     /// A filepath to the C struct file.
     pub cstruct_filepath: String,
+    /// A filepath to the ELF file with DWARF debug information.
+    pub elf_filepath: String,
     /// A filepath for the output grammar file of the C struct conversion.
     pub output_filepath: String,
     /// Offset into binary file where the data structure starts.
@@ -44,11 +68,11 @@ pub struct CMDArgParse {
     ///
     /// false = Do not reverse the endianess. This is the state after new().
     pub fmt_endian: bool,
-    /// Determines whether the endianess of non memgram display types will be printed with reversed endianess in the output hex view.
+    /// Determines whether a line of decoded words, with the bytes of each little endian element reversed, is printed beneath each hex view row.
     ///
-    /// true = Reverse the endianess. Set by specifying the value of `HEX_ENDIAN_FLAG` as a CLI argument
+    /// true = Print decoded words. Set by specifying the value of `HEX_ENDIAN_FLAG` as a CLI argument
     ///
-    /// false = Do not reverse the endianess. This is the state after new().
+    /// false = Only print the bytes in file order. This is the state after new().
     pub hex_endian: bool,
     /// Determines whether memgram usage information will be printed to stdout.
     ///
@@ -56,16 +80,122 @@ pub struct CMDArgParse {
     ///
     /// false = Do not print help information. This is the state after new().
     pub help_flag: bool,
+    /// The format the parsed structure is printed to stdout in.
+    ///
+    /// Set by specifying the value of `OUTPUT_FORMAT_FLAG` as a CLI argument. This is `OutputFormats::Table` after new().
+    pub output_format: OutputFormats,
+    /// The columns printed when exporting to CSV, TSV or Markdown, in the order they were specified.
+    ///
+    /// Set by specifying a comma separated list as the value of `COLUMNS_FLAG`. This is empty after new(), meaning the default columns are used.
+    pub columns: Vec<Columns>,
+    /// Determines when output is coloured. Set by specifying the value of `COLOUR_FLAG` as a CLI argument.
+    ///
+    /// This is `ColourModes::Auto` after new().
+    pub colour_mode: ColourModes,
+    /// The palette fields are coloured with. Set by specifying the value of `THEME_FLAG` as a CLI argument.
+    ///
+    /// This is `Palettes::Default` after new().
+    pub palette: theme::Palettes,
+    /// The layout options of the hex view. Set by the hex view flags, e.g `ROW_WIDTH_FLAG`.
+    ///
+    /// This is `HexViewOptions::new()` after new().
+    pub hex_view_options: hex_display::HexViewOptions,
+    /// The assembly syntax x86 code fields are disassembled in. Set by specifying the value of `SYNTAX_FLAG` as a CLI argument.
+    ///
+    /// This is `AsmSyntaxes::Nasm` after new().
+    pub asm_syntax: format::AsmSyntaxes,
+    /// The target ABI C structs are laid out for. Set by specifying the value of `ABI_FLAG` as a CLI argument.
+    ///
+    /// This is the ABI called `c_types::DEFAULT_ABI` after new().
+    pub abi: &'static c_types::Abi,
+    /// The name of the struct, or typedef of a struct, converted from a C header. Set by specifying the value of
+    /// `ROOT_FLAG` as a CLI argument.
+    ///
+    /// This is empty after new(), meaning the last struct in the header is converted.
+    pub root: String,
+}
+
+/// Modes controlling when output is coloured.
+pub enum ColourModes {
+    /// Colour output if stdout is a terminal and `NO_COLOR` is not set.
+    Auto,
+    /// Always colour output.
+    Always,
+    /// Never colour output.
+    Never,
+}
+
+/// Formats the parsed structure can be printed in.
+pub enum OutputFormats {
+    /// The standard table followed by the hex view.
+    Table,
+    /// A single JSON document containing every field.
+    Json,
+    /// JSON Lines, one JSON object per field.
+    JsonLines,
+    /// Comma separated values.
+    Csv,
+    /// Tab separated values.
+    Tsv,
+    /// A GitHub flavoured Markdown table.
+    Markdown,
+    /// A self-contained HTML report linking the tables to the hex view.
+    Html,
+}
+
+/// Columns that can be selected when exporting the standard table.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Columns {
+    Id,
+    Name,
+    Offset,
+    Size,
+    DataType,
+    RawData,
+    FormattedData,
+    Description,
+}
+
+impl Columns {
+    /// Matches a column name passed on the cmdline with one of the `Columns` variants.
+    pub fn from_name(column_name: &str) -> Result<Self, ()> {
+        match column_name {
+            "id" => Ok(Columns::Id),
+            "name" => Ok(Columns::Name),
+            "offset" => Ok(Columns::Offset),
+            "size" => Ok(Columns::Size),
+            "type" => Ok(Columns::DataType),
+            "raw" => Ok(Columns::RawData),
+            "value" => Ok(Columns::FormattedData),
+            "description" => Ok(Columns::Description),
+            _ => {
+                serror!(format!("Invalid column: {}, must be one of the following (id, name, offset, size, type, raw, value, description)", column_name));
+                Err(())
+            }
+        }
+    }
 }
 
 /// Memgram run options.
 pub enum RunOptions {
+    /// List the supported display formats. Nothing is converted or displayed.
+    ListFormats,
     /// Convert the C struct file to a grammar file. Do not display output fmt table/hex view.
     CStructConvertWrite,
     /// Convert the C struct and display the fmt table/hex view output. The converted C struct file is not written to disk.
     CStructConvertDisplay,
     /// Display the fmt table/hex view. No C structs are converted or displayed.
     DisplayNormal,
+    /// Convert the grammar file to a C header file. Do not display output fmt table/hex view.
+    GrammarConvertWrite,
+    /// Convert the grammar file to a Rust module, with a test parsing the binary file if one is given.
+    GrammarConvertRust,
+    /// Convert a struct from the DWARF debug information of the ELF file to a grammar file. Do not display output fmt
+    /// table/hex view.
+    DwarfConvertWrite,
+    /// Convert a struct from the DWARF debug information of the ELF file and display the fmt table/hex view output. The
+    /// converted grammar file is not written to disk.
+    DwarfConvertDisplay,
 }
 
 impl CMDArgParse {
@@ -76,12 +206,21 @@ impl CMDArgParse {
             grammar_filepath: String::from(""),
             binary_filepath: String::from(""),
             cstruct_filepath: String::from(""),
+            elf_filepath: String::from(""),
             output_filepath: String::from(""),
             struct_offset: 0,
             description: false,
             fmt_endian: false,
             hex_endian: false,
             help_flag: false,
+            output_format: OutputFormats::Table,
+            columns: Vec::new(),
+            colour_mode: ColourModes::Auto,
+            palette: theme::Palettes::Default,
+            hex_view_options: hex_display::HexViewOptions::new(),
+            asm_syntax: format::AsmSyntaxes::Nasm,
+            abi: c_types::default_abi(),
+            root: String::from(""),
         }
     }
 
@@ -133,8 +272,9 @@ impl CMDArgParse {
                 GRAMMER_FILE_FLAG => self.grammar_filepath = file_path,
                 BINARY_FILE_FLAG => self.binary_filepath = file_path,
                 CSTRUCT_FILE_FLAG => self.cstruct_filepath = file_path,
+                ELF_FILE_FLAG => self.elf_filepath = file_path,
                 OUTPUT_FILE_FLAG => self.output_filepath = file_path,
-                _ => (serror!(format!("The flag is not a file flag: {}", flag))),
+                _ => serror!(format!("The flag is not a file flag: {}", flag)),
             }
             Ok(self)
         } else {
@@ -157,9 +297,8 @@ impl CMDArgParse {
                 Some(offset) => {
                     self.struct_offset = offset
                         .parse::<u64>()
-                        .or_else(|e| {
+                        .map_err(|e| {
                             serror!(format!("Invalid offset: {}, because: {}", offset, e));
-                            Err(())
                         })
                         .unwrap();
                     Ok(self)
@@ -175,6 +314,246 @@ impl CMDArgParse {
         }
     }
 
+    /// Parses the output format flag into an `OutputFormats` variant and saves it in `self.output_format`.
+    ///
+    /// An `Err(())` is returned if the value is not a supported output format.
+    ///
+    /// If the flag is not found in `self.arg_map`, `self.output_format` is left as `OutputFormats::Table`.
+    pub fn parse_output_format_flag(&mut self, output_format_flag: &str) -> Result<&mut Self, ()> {
+        if let Some(value) = self.arg_map.get(output_format_flag) {
+            self.output_format = match value.as_deref() {
+                Some("table") => OutputFormats::Table,
+                Some("json") => OutputFormats::Json,
+                Some("jsonl") => OutputFormats::JsonLines,
+                Some("csv") => OutputFormats::Csv,
+                Some("tsv") => OutputFormats::Tsv,
+                Some("markdown") => OutputFormats::Markdown,
+                Some("html") => OutputFormats::Html,
+                Some(format) => {
+                    serror!(format!(
+                        "Invalid output format: {}, must be one of the following (table, json, jsonl, csv, tsv, markdown, html)",
+                        format
+                    ));
+                    return Err(());
+                }
+                None => {
+                    serror!("You need to specify an output format");
+                    return Err(());
+                }
+            };
+        }
+        Ok(self)
+    }
+
+    /// Parses the assembly syntax flag into `self.asm_syntax`.
+    ///
+    /// An `Err(())` is returned if the value is not a supported syntax.
+    ///
+    /// If the flag is not found in `self.arg_map`, `self.asm_syntax` is left as `AsmSyntaxes::Nasm`.
+    pub fn parse_syntax_flag(&mut self, syntax_flag: &str) -> Result<&mut Self, ()> {
+        if let Some(value) = self.arg_map.get(syntax_flag) {
+            self.asm_syntax = match value.as_deref() {
+                Some("nasm") => format::AsmSyntaxes::Nasm,
+                Some("intel") => format::AsmSyntaxes::Intel,
+                Some("gas") | Some("att") => format::AsmSyntaxes::Gas,
+                Some("masm") => format::AsmSyntaxes::Masm,
+                Some(syntax) => {
+                    serror!(format!(
+                        "Invalid assembly syntax: {}, must be one of the following (nasm, intel, gas, masm)",
+                        syntax
+                    ));
+                    return Err(());
+                }
+                None => {
+                    serror!("You need to specify an assembly syntax");
+                    return Err(());
+                }
+            };
+        }
+        Ok(self)
+    }
+
+    /// Parses the ABI flag into `self.abi`.
+    ///
+    /// An `Err(())` is returned if the value is missing or not a supported ABI.
+    ///
+    /// If the flag is not found in `self.arg_map`, `self.abi` is left as the default ABI.
+    pub fn parse_abi_flag(&mut self, abi_flag: &str) -> Result<&mut Self, ()> {
+        if let Some(value) = self.arg_map.get(abi_flag) {
+            let abi_name = value.as_ref().ok_or_else(|| {
+                serror!("You need to specify an ABI");
+            })?;
+
+            self.abi = c_types::find_abi(abi_name).ok_or_else(|| {
+                serror!(format!(
+                    "Invalid ABI: {}, must be one of the following ({})",
+                    abi_name,
+                    c_types::ABIS
+                        .iter()
+                        .map(|abi| abi.name)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ));
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Parses the root struct flag into `self.root`.
+    ///
+    /// An `Err(())` is returned if the flag was specified without a value.
+    pub fn parse_root_flag(&mut self, root_flag: &str) -> Result<&mut Self, ()> {
+        if let Some(value) = self.arg_map.get(root_flag) {
+            self.root = value.clone().ok_or_else(|| {
+                serror!("You need to specify the name of the root struct");
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Parses the comma separated columns flag into `self.columns`.
+    ///
+    /// An `Err(())` is returned if no value was specified or a column name is not supported.
+    pub fn parse_columns_flag(&mut self, columns_flag: &str) -> Result<&mut Self, ()> {
+        if let Some(value) = self.arg_map.get(columns_flag) {
+            let column_names = value.as_ref().ok_or_else(|| {
+                serror!("You need to specify a comma separated list of columns");
+            })?;
+
+            for column_name in column_names.split(',') {
+                self.columns.push(Columns::from_name(
+                    column_name.trim().to_lowercase().as_str(),
+                )?);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Parses the colour and theme flags into `self.colour_mode` and `self.palette`.
+    ///
+    /// An `Err(())` is returned if either value is missing or not supported.
+    pub fn parse_colour_flags(
+        &mut self,
+        colour_flag: &str,
+        theme_flag: &str,
+    ) -> Result<&mut Self, ()> {
+        if let Some(value) = self.arg_map.get(colour_flag) {
+            self.colour_mode = match value.as_deref() {
+                Some("auto") => ColourModes::Auto,
+                Some("always") => ColourModes::Always,
+                Some("never") => ColourModes::Never,
+                _ => {
+                    serror!(format!(
+                        "Invalid value for flag {}, must be one of the following (auto, always, never)",
+                        colour_flag
+                    ));
+                    return Err(());
+                }
+            };
+        }
+
+        if let Some(value) = self.arg_map.get(theme_flag) {
+            self.palette = match value.as_deref() {
+                Some("default") => theme::Palettes::Default,
+                Some("colourblind") | Some("colorblind") => theme::Palettes::ColourBlind,
+                Some("light") => theme::Palettes::Light,
+                Some("type") => theme::Palettes::PerType,
+                _ => {
+                    serror!(format!(
+                        "Invalid value for flag {}, must be one of the following (default, colourblind, light, type)",
+                        theme_flag
+                    ));
+                    return Err(());
+                }
+            };
+        }
+        Ok(self)
+    }
+
+    /// Parses the hex view layout flags into `self.hex_view_options`.
+    ///
+    /// `CONTEXT_FLAG` sets the context both before and after the structure, and is overridden by `CONTEXT_BEFORE_FLAG` and `CONTEXT_AFTER_FLAG`.
+    ///
+    /// An `Err(())` is returned if a value is missing or invalid.
+    pub fn parse_hex_view_flags(&mut self) -> Result<&mut Self, ()> {
+        if let Some(row_width) = self.parse_usize_flag(ROW_WIDTH_FLAG)? {
+            if row_width == 0 {
+                serror!(format!(
+                    "The value for flag {} must be greater than 0",
+                    ROW_WIDTH_FLAG
+                ));
+                return Err(());
+            }
+            self.hex_view_options.row_width = row_width;
+        }
+
+        if let Some(group_size) = self.parse_usize_flag(GROUP_FLAG)? {
+            self.hex_view_options.group_size = group_size;
+        }
+
+        if let Some(context) = self.parse_usize_flag(CONTEXT_FLAG)? {
+            self.hex_view_options.context_before = context;
+            self.hex_view_options.context_after = context;
+        }
+
+        if let Some(context_before) = self.parse_usize_flag(CONTEXT_BEFORE_FLAG)? {
+            self.hex_view_options.context_before = context_before;
+        }
+
+        if let Some(context_after) = self.parse_usize_flag(CONTEXT_AFTER_FLAG)? {
+            self.hex_view_options.context_after = context_after;
+        }
+
+        if let Some(value) = self.arg_map.get(SIDE_COLUMN_FLAG) {
+            self.hex_view_options.side_column = match value.as_deref() {
+                Some("cp850") => hex_display::SideColumns::Cp850,
+                Some("ascii") => hex_display::SideColumns::Ascii,
+                Some("utf16le") => hex_display::SideColumns::Utf16Le,
+                Some("utf16be") => hex_display::SideColumns::Utf16Be,
+                Some("ebcdic") => hex_display::SideColumns::Ebcdic,
+                Some("none") => hex_display::SideColumns::Hidden,
+                _ => {
+                    serror!(format!(
+                        "Invalid value for flag {}, must be one of the following (cp850, ascii, utf16le, utf16be, ebcdic, none)",
+                        SIDE_COLUMN_FLAG
+                    ));
+                    return Err(());
+                }
+            };
+        }
+
+        self.hex_view_options.legend = self.arg_map.contains_key(LEGEND_FLAG);
+        self.hex_view_options.markers = self.arg_map.contains_key(MARKERS_FLAG);
+
+        Ok(self)
+    }
+
+    /// Parses the value of `flag` as a decimal or `0x` prefixed hex usize.
+    ///
+    /// `Ok(None)` is returned if the flag was not specified, an `Err(())` is returned if the value is missing or invalid.
+    fn parse_usize_flag(&self, flag: &str) -> Result<Option<usize>, ()> {
+        match self.arg_map.get(flag) {
+            Some(Some(value)) => {
+                let parsed_value = match value.strip_prefix("0x") {
+                    Some(hex_value) => usize::from_str_radix(hex_value, 16),
+                    None => value.parse::<usize>(),
+                };
+
+                parsed_value.map(Some).map_err(|e| {
+                    serror!(format!(
+                        "Invalid value: {} for flag {}, because: {}",
+                        value, flag, e
+                    ));
+                })
+            }
+            Some(None) => {
+                serror!(format!("You need to specify a value for flag {}", flag));
+                Err(())
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Sets `self.help_flag` to true if the help flag/switch is in `self.arg_map`.
     pub fn parse_help_flag(&mut self, help_flag: &str) -> &mut Self {
         if self.arg_map.contains_key(help_flag) {
@@ -204,13 +583,26 @@ impl CMDArgParse {
         self
     }
 
+    /// Get's the extension of the output filepath given on the cmdline, if it has one.
+    fn output_extension(&self) -> Option<&str> {
+        match self.arg_map.get(OUTPUT_FILE_FLAG) {
+            Some(Some(output_path)) => Path::new(output_path)
+                .extension()
+                .and_then(|extension| extension.to_str()),
+            _ => None,
+        }
+    }
+
     /// Used to determine which methods should run in `main.rs`.
     ///
     /// Returns a `RunOption` variant depending on which switches/flags were passed on the cmdline.
     ///
     /// Descriptions of each variant are contained in the `RunOption` enum documentation
     pub fn run_cmds(&mut self) -> Result<RunOptions, ()> {
-        if !self.arg_map.contains_key(CSTRUCT_FILE_FLAG)
+        if self.arg_map.contains_key(LIST_FORMATS_FLAG) {
+            Ok(RunOptions::ListFormats)
+        } else if !self.arg_map.contains_key(CSTRUCT_FILE_FLAG)
+            && !self.arg_map.contains_key(OUTPUT_FILE_FLAG)
             && self.arg_map.contains_key(GRAMMER_FILE_FLAG)
             && self.arg_map.contains_key(BINARY_FILE_FLAG)
         {
@@ -221,12 +613,35 @@ impl CMDArgParse {
             && !self.arg_map.contains_key(BINARY_FILE_FLAG)
         {
             Ok(RunOptions::CStructConvertWrite)
+        } else if self.arg_map.contains_key(OUTPUT_FILE_FLAG)
+            && self.arg_map.contains_key(GRAMMER_FILE_FLAG)
+            && !self.arg_map.contains_key(CSTRUCT_FILE_FLAG)
+            && self.output_extension() == Some(rust_convert::RUST_EXTENSION)
+        {
+            Ok(RunOptions::GrammarConvertRust)
+        } else if self.arg_map.contains_key(OUTPUT_FILE_FLAG)
+            && self.arg_map.contains_key(GRAMMER_FILE_FLAG)
+            && !self.arg_map.contains_key(CSTRUCT_FILE_FLAG)
+            && !self.arg_map.contains_key(BINARY_FILE_FLAG)
+        {
+            Ok(RunOptions::GrammarConvertWrite)
         } else if self.arg_map.contains_key(BINARY_FILE_FLAG)
             && self.arg_map.contains_key(CSTRUCT_FILE_FLAG)
             && !self.arg_map.contains_key(OUTPUT_FILE_FLAG)
             && !self.arg_map.contains_key(GRAMMER_FILE_FLAG)
         {
             Ok(RunOptions::CStructConvertDisplay)
+        } else if self.arg_map.contains_key(OUTPUT_FILE_FLAG)
+            && self.arg_map.contains_key(ELF_FILE_FLAG)
+            && !self.arg_map.contains_key(STRUCT_OFFSET_FLAG)
+            && !self.arg_map.contains_key(BINARY_FILE_FLAG)
+        {
+            Ok(RunOptions::DwarfConvertWrite)
+        } else if self.arg_map.contains_key(BINARY_FILE_FLAG)
+            && self.arg_map.contains_key(ELF_FILE_FLAG)
+            && !self.arg_map.contains_key(OUTPUT_FILE_FLAG)
+        {
+            Ok(RunOptions::DwarfConvertDisplay)
         } else {
             serror!("Unsupported flag combination");
             errors::usage();
@@ -234,3 +649,226 @@ impl CMDArgParse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[(&str, Option<&str>)]) -> CMDArgParse {
+        let mut cmd_args = CMDArgParse::new();
+        for (flag, value) in args {
+            cmd_args
+                .arg_map
+                .insert(flag.to_string(), value.map(String::from));
+        }
+        cmd_args
+    }
+
+    #[test]
+    fn output_format_defaults_to_table() {
+        let mut cmd_args = parse_args(&[]);
+        cmd_args
+            .parse_output_format_flag(OUTPUT_FORMAT_FLAG)
+            .unwrap();
+        assert!(matches!(cmd_args.output_format, OutputFormats::Table));
+    }
+
+    #[test]
+    fn output_format_is_parsed() {
+        let mut cmd_args = parse_args(&[(OUTPUT_FORMAT_FLAG, Some("jsonl"))]);
+        cmd_args
+            .parse_output_format_flag(OUTPUT_FORMAT_FLAG)
+            .unwrap();
+        assert!(matches!(cmd_args.output_format, OutputFormats::JsonLines));
+    }
+
+    #[test]
+    fn unknown_output_format_is_an_error() {
+        let mut cmd_args = parse_args(&[(OUTPUT_FORMAT_FLAG, Some("xml"))]);
+        assert!(cmd_args
+            .parse_output_format_flag(OUTPUT_FORMAT_FLAG)
+            .is_err());
+
+        let mut cmd_args = parse_args(&[(OUTPUT_FORMAT_FLAG, None)]);
+        assert!(cmd_args
+            .parse_output_format_flag(OUTPUT_FORMAT_FLAG)
+            .is_err());
+    }
+
+    #[test]
+    fn columns_are_parsed_in_order() {
+        let mut cmd_args = parse_args(&[(COLUMNS_FLAG, Some("value, Name,offset"))]);
+        cmd_args.parse_columns_flag(COLUMNS_FLAG).unwrap();
+        assert!(cmd_args.columns == [Columns::FormattedData, Columns::Name, Columns::Offset]);
+    }
+
+    #[test]
+    fn unknown_column_is_an_error() {
+        let mut cmd_args = parse_args(&[(COLUMNS_FLAG, Some("id,colour"))]);
+        assert!(cmd_args.parse_columns_flag(COLUMNS_FLAG).is_err());
+    }
+
+    #[test]
+    fn colour_flags_are_parsed() {
+        let mut cmd_args = parse_args(&[
+            (COLOUR_FLAG, Some("never")),
+            (THEME_FLAG, Some("colorblind")),
+        ]);
+        cmd_args
+            .parse_colour_flags(COLOUR_FLAG, THEME_FLAG)
+            .unwrap();
+        assert!(matches!(cmd_args.colour_mode, ColourModes::Never));
+        assert!(matches!(cmd_args.palette, theme::Palettes::ColourBlind));
+    }
+
+    #[test]
+    fn unknown_colour_mode_is_an_error() {
+        let mut cmd_args = parse_args(&[(COLOUR_FLAG, Some("sometimes"))]);
+        assert!(cmd_args
+            .parse_colour_flags(COLOUR_FLAG, THEME_FLAG)
+            .is_err());
+    }
+
+    #[test]
+    fn hex_view_flags_are_parsed() {
+        let mut cmd_args = parse_args(&[
+            (ROW_WIDTH_FLAG, Some("0x20")),
+            (GROUP_FLAG, Some("4")),
+            (CONTEXT_FLAG, Some("16")),
+            (CONTEXT_AFTER_FLAG, Some("0")),
+            (SIDE_COLUMN_FLAG, Some("ebcdic")),
+            (LEGEND_FLAG, None),
+        ]);
+        cmd_args.parse_hex_view_flags().unwrap();
+
+        let options = &cmd_args.hex_view_options;
+        assert_eq!(options.row_width, 0x20);
+        assert_eq!(options.group_size, 4);
+        assert_eq!(options.context_before, 16);
+        assert_eq!(options.context_after, 0);
+        assert!(matches!(
+            options.side_column,
+            hex_display::SideColumns::Ebcdic
+        ));
+        assert!(options.legend);
+        assert!(!options.markers);
+    }
+
+    #[test]
+    fn invalid_hex_view_flags_are_an_error() {
+        let mut cmd_args = parse_args(&[(ROW_WIDTH_FLAG, Some("0"))]);
+        assert!(cmd_args.parse_hex_view_flags().is_err());
+
+        let mut cmd_args = parse_args(&[(CONTEXT_FLAG, Some("-1"))]);
+        assert!(cmd_args.parse_hex_view_flags().is_err());
+
+        let mut cmd_args = parse_args(&[(GROUP_FLAG, None)]);
+        assert!(cmd_args.parse_hex_view_flags().is_err());
+    }
+
+    #[test]
+    fn syntax_flag_is_parsed() {
+        let mut cmd_args = parse_args(&[(SYNTAX_FLAG, Some("att"))]);
+        cmd_args.parse_syntax_flag(SYNTAX_FLAG).unwrap();
+        assert!(matches!(cmd_args.asm_syntax, format::AsmSyntaxes::Gas));
+
+        let mut cmd_args = parse_args(&[(SYNTAX_FLAG, Some("arm"))]);
+        assert!(cmd_args.parse_syntax_flag(SYNTAX_FLAG).is_err());
+    }
+
+    #[test]
+    fn abi_flag_is_parsed() {
+        let mut cmd_args = parse_args(&[]);
+        cmd_args.parse_abi_flag(ABI_FLAG).unwrap();
+        assert_eq!(cmd_args.abi.name, c_types::DEFAULT_ABI);
+
+        let mut cmd_args = parse_args(&[(ABI_FLAG, Some("LP64"))]);
+        cmd_args.parse_abi_flag(ABI_FLAG).unwrap();
+        assert_eq!(cmd_args.abi.name, "lp64");
+    }
+
+    #[test]
+    fn unknown_abi_is_an_error() {
+        assert!(parse_args(&[(ABI_FLAG, Some("vax"))])
+            .parse_abi_flag(ABI_FLAG)
+            .is_err());
+        assert!(parse_args(&[(ABI_FLAG, None)])
+            .parse_abi_flag(ABI_FLAG)
+            .is_err());
+    }
+
+    #[test]
+    fn root_flag_is_parsed() {
+        let mut cmd_args = parse_args(&[(ROOT_FLAG, Some("Header"))]);
+        cmd_args.parse_root_flag(ROOT_FLAG).unwrap();
+        assert_eq!(cmd_args.root, "Header");
+
+        assert!(parse_args(&[(ROOT_FLAG, None)])
+            .parse_root_flag(ROOT_FLAG)
+            .is_err());
+    }
+
+    #[test]
+    fn grammar_and_output_flags_convert_the_grammar() {
+        let mut cmd_args = parse_args(&[
+            (GRAMMER_FILE_FLAG, Some("grammar.toml")),
+            (OUTPUT_FILE_FLAG, Some("header.h")),
+        ]);
+        assert!(matches!(
+            cmd_args.run_cmds(),
+            Ok(RunOptions::GrammarConvertWrite)
+        ));
+
+        let mut cmd_args = parse_args(&[
+            (GRAMMER_FILE_FLAG, Some("grammar.toml")),
+            (BINARY_FILE_FLAG, Some("data.bin")),
+            (OUTPUT_FILE_FLAG, Some("header.h")),
+        ]);
+        assert!(cmd_args.run_cmds().is_err());
+    }
+
+    #[test]
+    fn rust_output_converts_the_grammar_to_rust() {
+        let mut cmd_args = parse_args(&[
+            (GRAMMER_FILE_FLAG, Some("grammar.toml")),
+            (OUTPUT_FILE_FLAG, Some("module.rs")),
+        ]);
+        assert!(matches!(
+            cmd_args.run_cmds(),
+            Ok(RunOptions::GrammarConvertRust)
+        ));
+
+        let mut cmd_args = parse_args(&[
+            (GRAMMER_FILE_FLAG, Some("grammar.toml")),
+            (BINARY_FILE_FLAG, Some("sample.bin")),
+            (OUTPUT_FILE_FLAG, Some("module.rs")),
+        ]);
+        assert!(matches!(
+            cmd_args.run_cmds(),
+            Ok(RunOptions::GrammarConvertRust)
+        ));
+    }
+
+    #[test]
+    fn elf_flag_converts_a_dwarf_struct() {
+        let mut cmd_args = parse_args(&[
+            (ELF_FILE_FLAG, Some("disk_tool")),
+            (OUTPUT_FILE_FLAG, Some("SuperBlock.toml")),
+            (ROOT_FLAG, Some("SuperBlock")),
+        ]);
+        assert!(matches!(
+            cmd_args.run_cmds(),
+            Ok(RunOptions::DwarfConvertWrite)
+        ));
+
+        let mut cmd_args = parse_args(&[
+            (ELF_FILE_FLAG, Some("disk_tool")),
+            (BINARY_FILE_FLAG, Some("disk.img")),
+            (ROOT_FLAG, Some("SuperBlock")),
+        ]);
+        assert!(matches!(
+            cmd_args.run_cmds(),
+            Ok(RunOptions::DwarfConvertDisplay)
+        ));
+    }
+}
@@ -0,0 +1,892 @@
+//! Module for disassembling little endian ARM (A32), Thumb and AArch64 (A64) machine code.
+//!
+//! Only the common integer instructions are decoded, covering the ARMv4T instruction set of the ARM7TDMI plus the
+//! branch, load/store and arithmetic instructions typically found in AArch64 boot code. Anything else is displayed
+//! as a `.word`, `.hword` or `.inst` directive so the rest of the code can still be decoded.
+use crate::format::DissassLine;
+
+/// ARM condition code suffixes indexed by the condition field, `al` is omitted.
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "cs", "cc", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "", "nv",
+];
+
+/// A32 data processing mnemonics indexed by opcode.
+const DATA_PROCESSING: [&str; 16] = [
+    "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr",
+    "mov", "bic", "mvn",
+];
+
+/// Thumb ALU operation mnemonics indexed by opcode.
+const THUMB_ALU: [&str; 16] = [
+    "and", "eor", "lsl", "lsr", "asr", "adc", "sbc", "ror", "tst", "neg", "cmp", "cmn", "orr",
+    "mul", "bic", "mvn",
+];
+
+/// Shift type mnemonics indexed by the shift type field.
+const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+/// Returns the name of an A32/Thumb register.
+fn reg(register: u32) -> String {
+    match register & 0xF {
+        13 => String::from("sp"),
+        14 => String::from("lr"),
+        15 => String::from("pc"),
+        register => format!("r{}", register),
+    }
+}
+
+/// Sign extends the lowest `bits` bits of `value`.
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as i64
+}
+
+/// Returns the 32-bit address `offset` bytes from `address`, wrapping around the 4GB address space.
+fn branch_target32(address: u64, offset: i64) -> u64 {
+    (address as i64 + offset) as u64 & 0xFFFF_FFFF
+}
+
+/// Formats a signed immediate as `#0x..` or `#-0x..`.
+fn signed_imm(value: i64) -> String {
+    if value < 0 {
+        format!("#-{:#x}", -value)
+    } else {
+        format!("#{:#x}", value)
+    }
+}
+
+/// Formats a register list such as `{r0-r3, lr}`, where bit n of `list` represents register n.
+fn reg_list(list: u32) -> String {
+    let mut registers: Vec<String> = Vec::new();
+    let mut register = 0;
+
+    while register < 16 {
+        if list & (1 << register) == 0 {
+            register += 1;
+            continue;
+        }
+
+        let start = register;
+
+        while register < 16 && list & (1 << register) != 0 {
+            register += 1;
+        }
+
+        match register - start {
+            1 => registers.push(reg(start)),
+            2 => registers.push(format!("{}, {}", reg(start), reg(start + 1))),
+            _ => registers.push(format!("{}-{}", reg(start), reg(register - 1))),
+        }
+    }
+
+    format!("{{{}}}", registers.join(", "))
+}
+
+/// Creates a line for `bytes` that could not be decoded, using the assembler directive `directive`.
+fn data_line(machine_code: &[u8], size: usize, address: u64, directive: &str) -> DissassLine {
+    let size = size.min(machine_code.len());
+    let value = machine_code[..size]
+        .iter()
+        .rev()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64);
+
+    DissassLine {
+        address,
+        bytes: machine_code[..size].to_vec(),
+        instruction: match size {
+            1 => format!(".byte {:#04x}", value),
+            _ => format!("{} {:#0width$x}", directive, value, width = size * 2 + 2),
+        },
+        branch_target: None,
+    }
+}
+
+/// Decodes the A32 instruction at the start of `machine_code`, which is located at `address`.
+pub fn decode_arm32(machine_code: &[u8], address: u64) -> DissassLine {
+    if machine_code.len() < 4 {
+        return data_line(machine_code, machine_code.len(), address, ".byte");
+    }
+
+    let word = u32::from_le_bytes([
+        machine_code[0],
+        machine_code[1],
+        machine_code[2],
+        machine_code[3],
+    ]);
+    let (instruction, branch_target) = match arm32_instruction(word, address) {
+        Some(decoded) => decoded,
+        None => return data_line(machine_code, 4, address, ".word"),
+    };
+
+    DissassLine {
+        address,
+        bytes: machine_code[..4].to_vec(),
+        instruction,
+        branch_target,
+    }
+}
+
+/// Returns the A32 assembly of `word` and its branch target, or None if the instruction is not supported.
+fn arm32_instruction(word: u32, address: u64) -> Option<(String, Option<u64>)> {
+    let bits = |shift: u32, mask: u32| (word >> shift) & mask;
+    let condition = bits(28, 0xF);
+    let cond = CONDITIONS[condition as usize];
+
+    if condition == 0xF {
+        // Unconditional space, only BLX immediate is supported.
+        if bits(25, 0x7) == 0x5 {
+            let target = branch_target32(
+                address,
+                8 + (sign_extend(bits(0, 0xFF_FFFF), 24) << 2) + (bits(24, 1) << 1) as i64,
+            );
+            return Some((format!("blx {:#x}", target), Some(target)));
+        }
+        return None;
+    }
+
+    let rn = bits(16, 0xF);
+    let rd = bits(12, 0xF);
+    let rs = bits(8, 0xF);
+    let rm = bits(0, 0xF);
+
+    // Branch and exchange.
+    if word & 0x0FFF_FFD0 == 0x012F_FF10 {
+        let mnemonic = if bits(5, 1) == 1 { "blx" } else { "bx" };
+        return Some((format!("{}{} {}", mnemonic, cond, reg(rm)), None));
+    }
+
+    // Multiply and multiply accumulate.
+    if word & 0x0FC0_00F0 == 0x0000_0090 {
+        let s = if bits(20, 1) == 1 { "s" } else { "" };
+        return Some(match bits(21, 1) {
+            0 => (
+                format!("mul{}{} {}, {}, {}", s, cond, reg(rn), reg(rm), reg(rs)),
+                None,
+            ),
+            _ => (
+                format!(
+                    "mla{}{} {}, {}, {}, {}",
+                    s,
+                    cond,
+                    reg(rn),
+                    reg(rm),
+                    reg(rs),
+                    reg(rd)
+                ),
+                None,
+            ),
+        });
+    }
+
+    // Long multiply.
+    if word & 0x0F80_00F0 == 0x0080_0090 {
+        let sign = if bits(22, 1) == 1 { "s" } else { "u" };
+        let operation = if bits(21, 1) == 1 { "mlal" } else { "mull" };
+        let s = if bits(20, 1) == 1 { "s" } else { "" };
+        return Some((
+            format!(
+                "{}{}{}{} {}, {}, {}, {}",
+                sign,
+                operation,
+                s,
+                cond,
+                reg(rd),
+                reg(rn),
+                reg(rm),
+                reg(rs)
+            ),
+            None,
+        ));
+    }
+
+    // Swap.
+    if word & 0x0FB0_0FF0 == 0x0100_0090 {
+        let b = if bits(22, 1) == 1 { "b" } else { "" };
+        return Some((
+            format!("swp{}{} {}, {}, [{}]", b, cond, reg(rd), reg(rm), reg(rn)),
+            None,
+        ));
+    }
+
+    // Halfword and signed byte transfers.
+    if word & 0x0E00_0090 == 0x0000_0090 && bits(5, 0x3) != 0 {
+        let load = bits(20, 1) == 1;
+        let mnemonic = match (load, bits(5, 0x3)) {
+            (false, 1) => "strh",
+            (true, 1) => "ldrh",
+            (true, 2) => "ldrsb",
+            (true, 3) => "ldrsh",
+            _ => return None,
+        };
+        let offset = if bits(22, 1) == 1 {
+            let imm = ((bits(8, 0xF) << 4) | rm) as i64;
+            signed_imm(if bits(23, 1) == 1 { imm } else { -imm })
+        } else {
+            format!("{}{}", if bits(23, 1) == 1 { "" } else { "-" }, reg(rm))
+        };
+        let operand = address_operand(rn, &offset, bits(24, 1) == 1, bits(21, 1) == 1);
+        return Some((
+            format!("{}{} {}, {}", mnemonic, cond, reg(rd), operand),
+            None,
+        ));
+    }
+
+    // Move status register to register.
+    if word & 0x0FBF_0FFF == 0x010F_0000 {
+        let psr = if bits(22, 1) == 1 { "spsr" } else { "cpsr" };
+        return Some((format!("mrs{} {}, {}", cond, reg(rd), psr), None));
+    }
+
+    // Move to status register.
+    if word & 0x0DB0_F000 == 0x0120_F000 {
+        let psr = if bits(22, 1) == 1 { "spsr" } else { "cpsr" };
+        let fields: String = ['c', 'x', 's', 'f']
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| bits(16 + *index as u32, 1) == 1)
+            .map(|(_, field)| *field)
+            .collect();
+        let operand = if bits(25, 1) == 1 {
+            format!("#{:#x}", rotated_imm(word))
+        } else {
+            reg(rm)
+        };
+        return Some((format!("msr{} {}_{}, {}", cond, psr, fields, operand), None));
+    }
+
+    match bits(25, 0x7) {
+        0 | 1 => {
+            let opcode = bits(21, 0xF);
+            let mnemonic = DATA_PROCESSING[opcode as usize];
+            let s = if bits(20, 1) == 1 { "s" } else { "" };
+            let operand2 = if bits(25, 1) == 1 {
+                format!("#{:#x}", rotated_imm(word))
+            } else {
+                shifted_register(word)
+            };
+
+            Some(match opcode {
+                // Comparisons always set the flags, so no `s` suffix is written.
+                8..=11 => (
+                    format!("{}{} {}, {}", mnemonic, cond, reg(rn), operand2),
+                    None,
+                ),
+                13 | 15 => (
+                    format!("{}{}{} {}, {}", mnemonic, s, cond, reg(rd), operand2),
+                    None,
+                ),
+                _ => (
+                    format!(
+                        "{}{}{} {}, {}, {}",
+                        mnemonic,
+                        s,
+                        cond,
+                        reg(rd),
+                        reg(rn),
+                        operand2
+                    ),
+                    None,
+                ),
+            })
+        }
+        2 | 3 => {
+            if bits(25, 1) == 1 && bits(4, 1) == 1 {
+                return None;
+            }
+
+            let load = bits(20, 1) == 1;
+            let pre_indexed = bits(24, 1) == 1;
+            let writeback = bits(21, 1) == 1;
+            let mnemonic = format!(
+                "{}{}{}{}",
+                if load { "ldr" } else { "str" },
+                if bits(22, 1) == 1 { "b" } else { "" },
+                if !pre_indexed && writeback { "t" } else { "" },
+                cond
+            );
+            let offset = if bits(25, 1) == 0 {
+                let imm = bits(0, 0xFFF) as i64;
+                signed_imm(if bits(23, 1) == 1 { imm } else { -imm })
+            } else {
+                format!(
+                    "{}{}",
+                    if bits(23, 1) == 1 { "" } else { "-" },
+                    shifted_register(word)
+                )
+            };
+            let operand = address_operand(rn, &offset, pre_indexed, writeback);
+
+            Some((format!("{} {}, {}", mnemonic, reg(rd), operand), None))
+        }
+        4 => {
+            let load = bits(20, 1) == 1;
+            let writeback = bits(21, 1) == 1;
+            let list = bits(0, 0xFFFF);
+            let user = if bits(22, 1) == 1 { "^" } else { "" };
+            let mode = match (bits(24, 1), bits(23, 1)) {
+                (0, 1) => "ia",
+                (1, 1) => "ib",
+                (0, _) => "da",
+                _ => "db",
+            };
+
+            // Stack pushes and pops of full descending stacks have a dedicated mnemonic.
+            if rn == 13 && writeback && user.is_empty() {
+                match (load, mode) {
+                    (true, "ia") => return Some((format!("pop{} {}", cond, reg_list(list)), None)),
+                    (false, "db") => {
+                        return Some((format!("push{} {}", cond, reg_list(list)), None))
+                    }
+                    _ => (),
+                }
+            }
+
+            Some((
+                format!(
+                    "{}{}{} {}{}, {}{}",
+                    if load { "ldm" } else { "stm" },
+                    mode,
+                    cond,
+                    reg(rn),
+                    if writeback { "!" } else { "" },
+                    reg_list(list),
+                    user
+                ),
+                None,
+            ))
+        }
+        5 => {
+            let target = branch_target32(address, 8 + (sign_extend(bits(0, 0xFF_FFFF), 24) << 2));
+            let mnemonic = if bits(24, 1) == 1 { "bl" } else { "b" };
+            Some((format!("{}{} {:#x}", mnemonic, cond, target), Some(target)))
+        }
+        7 if bits(24, 1) == 1 => Some((format!("swi{} {:#x}", cond, bits(0, 0xFF_FFFF)), None)),
+        _ => None,
+    }
+}
+
+/// Returns the value of the rotated 8-bit immediate in the lowest 12 bits of an A32 instruction.
+fn rotated_imm(word: u32) -> u32 {
+    (word & 0xFF).rotate_right(((word >> 8) & 0xF) * 2)
+}
+
+/// Formats the shifted register operand in the lowest 12 bits of an A32 instruction, e.g `r1, lsl #2`.
+fn shifted_register(word: u32) -> String {
+    let rm = reg(word & 0xF);
+    let shift_type = ((word >> 5) & 0x3) as usize;
+
+    if (word >> 4) & 1 == 1 {
+        return format!("{}, {} {}", rm, SHIFTS[shift_type], reg((word >> 8) & 0xF));
+    }
+
+    match ((word >> 7) & 0x1F, shift_type) {
+        (0, 0) => rm,
+        (0, 3) => format!("{}, rrx", rm),
+        (0, _) => format!("{}, {} #32", rm, SHIFTS[shift_type]),
+        (amount, _) => format!("{}, {} #{}", rm, SHIFTS[shift_type], amount),
+    }
+}
+
+/// Formats the memory operand of an A32 load or store.
+fn address_operand(rn: u32, offset: &str, pre_indexed: bool, writeback: bool) -> String {
+    match (pre_indexed, offset) {
+        (true, "#0x0") => format!("[{}]", reg(rn)),
+        (true, _) => format!(
+            "[{}, {}]{}",
+            reg(rn),
+            offset,
+            if writeback { "!" } else { "" }
+        ),
+        (false, _) => format!("[{}], {}", reg(rn), offset),
+    }
+}
+
+/// Decodes the Thumb instruction at the start of `machine_code`, which is located at `address`.
+///
+/// `bl` and `blx` instruction pairs are decoded as a single 4 byte instruction.
+pub fn decode_thumb(machine_code: &[u8], address: u64) -> DissassLine {
+    if machine_code.len() < 2 {
+        return data_line(machine_code, machine_code.len(), address, ".byte");
+    }
+
+    let half = u16::from_le_bytes([machine_code[0], machine_code[1]]) as u32;
+
+    // Branch with link prefix, followed by the suffix holding the low half of the offset.
+    if half >> 11 == 0x1E && machine_code.len() >= 4 {
+        let suffix = u16::from_le_bytes([machine_code[2], machine_code[3]]) as u32;
+
+        if suffix >> 11 == 0x1F || suffix >> 11 == 0x1D {
+            let offset = (sign_extend(half & 0x7FF, 11) << 12) + (((suffix & 0x7FF) << 1) as i64);
+            let mut target = branch_target32(address, 4 + offset);
+            let mnemonic = if suffix >> 11 == 0x1F {
+                "bl"
+            } else {
+                target &= !3;
+                "blx"
+            };
+
+            return DissassLine {
+                address,
+                bytes: machine_code[..4].to_vec(),
+                instruction: format!("{} {:#x}", mnemonic, target),
+                branch_target: Some(target),
+            };
+        }
+    }
+
+    match thumb_instruction(half, address) {
+        Some((instruction, branch_target)) => DissassLine {
+            address,
+            bytes: machine_code[..2].to_vec(),
+            instruction,
+            branch_target,
+        },
+        None => data_line(machine_code, 2, address, ".hword"),
+    }
+}
+
+/// Returns the Thumb assembly of the 16-bit instruction `half` and its branch target, or None if it is not supported.
+fn thumb_instruction(half: u32, address: u64) -> Option<(String, Option<u64>)> {
+    let bits = |shift: u32, mask: u32| (half >> shift) & mask;
+    let low_reg = |shift: u32| reg(bits(shift, 0x7));
+
+    let instruction = match half >> 13 {
+        0 if bits(11, 0x3) == 3 => {
+            let mnemonic = if bits(9, 1) == 1 { "sub" } else { "add" };
+            let operand = if bits(10, 1) == 1 {
+                format!("#{}", bits(6, 0x7))
+            } else {
+                low_reg(6)
+            };
+            format!("{} {}, {}, {}", mnemonic, low_reg(0), low_reg(3), operand)
+        }
+        0 => match (bits(11, 0x3), bits(6, 0x1F)) {
+            (0, 0) => format!("mov {}, {}", low_reg(0), low_reg(3)),
+            (shift, amount) => format!(
+                "{} {}, {}, #{}",
+                SHIFTS[shift as usize],
+                low_reg(0),
+                low_reg(3),
+                if amount == 0 { 32 } else { amount }
+            ),
+        },
+        1 => format!(
+            "{} {}, #{:#x}",
+            ["mov", "cmp", "add", "sub"][bits(11, 0x3) as usize],
+            low_reg(8),
+            bits(0, 0xFF)
+        ),
+        2 => match bits(10, 0x7) {
+            0 => format!(
+                "{} {}, {}",
+                THUMB_ALU[bits(6, 0xF) as usize],
+                low_reg(0),
+                low_reg(3)
+            ),
+            1 => {
+                let rd = bits(0, 0x7) | (bits(7, 1) << 3);
+                let rs = bits(3, 0xF);
+                match bits(8, 0x3) {
+                    0 => format!("add {}, {}", reg(rd), reg(rs)),
+                    1 => format!("cmp {}, {}", reg(rd), reg(rs)),
+                    2 => format!("mov {}, {}", reg(rd), reg(rs)),
+                    _ if bits(7, 1) == 1 => format!("blx {}", reg(rs)),
+                    _ => format!("bx {}", reg(rs)),
+                }
+            }
+            2 | 3 => format!("ldr {}, [pc, #{:#x}]", low_reg(8), bits(0, 0xFF) << 2),
+            _ => {
+                let mnemonic = match (bits(9, 1), bits(10, 0x3)) {
+                    (0, 0) => "str",
+                    (0, 1) => "strb",
+                    (0, 2) => "ldr",
+                    (0, _) => "ldrb",
+                    (_, 0) => "strh",
+                    (_, 1) => "ldrsb",
+                    (_, 2) => "ldrh",
+                    _ => "ldrsh",
+                };
+                format!(
+                    "{} {}, [{}, {}]",
+                    mnemonic,
+                    low_reg(0),
+                    low_reg(3),
+                    low_reg(6)
+                )
+            }
+        },
+        3 => {
+            let byte = bits(12, 1) == 1;
+            let mnemonic = match (bits(11, 1), byte) {
+                (0, false) => "str",
+                (0, true) => "strb",
+                (_, false) => "ldr",
+                (_, true) => "ldrb",
+            };
+            let offset = if byte {
+                bits(6, 0x1F)
+            } else {
+                bits(6, 0x1F) << 2
+            };
+            format!(
+                "{} {}, [{}, #{:#x}]",
+                mnemonic,
+                low_reg(0),
+                low_reg(3),
+                offset
+            )
+        }
+        4 => match bits(12, 1) {
+            0 => format!(
+                "{} {}, [{}, #{:#x}]",
+                if bits(11, 1) == 1 { "ldrh" } else { "strh" },
+                low_reg(0),
+                low_reg(3),
+                bits(6, 0x1F) << 1
+            ),
+            _ => format!(
+                "{} {}, [sp, #{:#x}]",
+                if bits(11, 1) == 1 { "ldr" } else { "str" },
+                low_reg(8),
+                bits(0, 0xFF) << 2
+            ),
+        },
+        5 => match (bits(12, 1), bits(8, 0xF)) {
+            (0, _) => format!(
+                "add {}, {}, #{:#x}",
+                low_reg(8),
+                if bits(11, 1) == 1 { "sp" } else { "pc" },
+                bits(0, 0xFF) << 2
+            ),
+            (_, 0) => format!(
+                "{} sp, #{:#x}",
+                if bits(7, 1) == 1 { "sub" } else { "add" },
+                bits(0, 0x7F) << 2
+            ),
+            (_, 4) | (_, 5) => format!("push {}", reg_list(bits(0, 0xFF) | (bits(8, 1) << 14))),
+            (_, 12) | (_, 13) => format!("pop {}", reg_list(bits(0, 0xFF) | (bits(8, 1) << 15))),
+            (_, 14) => format!("bkpt {:#x}", bits(0, 0xFF)),
+            _ => return None,
+        },
+        6 => match (bits(12, 1), bits(8, 0xF)) {
+            (0, _) => format!(
+                "{} {}!, {}",
+                if bits(11, 1) == 1 { "ldmia" } else { "stmia" },
+                low_reg(8),
+                reg_list(bits(0, 0xFF))
+            ),
+            (_, 14) => return None,
+            (_, 15) => format!("swi {:#x}", bits(0, 0xFF)),
+            (_, condition) => {
+                let target = branch_target32(address, 4 + (sign_extend(bits(0, 0xFF), 8) << 1));
+                return Some((
+                    format!("b{} {:#x}", CONDITIONS[condition as usize], target),
+                    Some(target),
+                ));
+            }
+        },
+        _ if bits(11, 0x3) == 0 => {
+            let target = branch_target32(address, 4 + (sign_extend(bits(0, 0x7FF), 11) << 1));
+            return Some((format!("b {:#x}", target), Some(target)));
+        }
+        _ => return None,
+    };
+
+    Some((instruction, None))
+}
+
+/// Returns the name of an AArch64 general purpose register, `sf` selects the 64-bit `x` or 32-bit `w` form.
+///
+/// Register 31 is the stack pointer if `sp` is true, otherwise the zero register.
+fn a64_reg(register: u32, sf: bool, sp: bool) -> String {
+    match (register & 0x1F, sf, sp) {
+        (31, true, true) => String::from("sp"),
+        (31, false, true) => String::from("wsp"),
+        (31, true, false) => String::from("xzr"),
+        (31, false, false) => String::from("wzr"),
+        (register, true, _) => format!("x{}", register),
+        (register, false, _) => format!("w{}", register),
+    }
+}
+
+/// Decodes the AArch64 instruction at the start of `machine_code`, which is located at `address`.
+pub fn decode_aarch64(machine_code: &[u8], address: u64) -> DissassLine {
+    if machine_code.len() < 4 {
+        return data_line(machine_code, machine_code.len(), address, ".byte");
+    }
+
+    let word = u32::from_le_bytes([
+        machine_code[0],
+        machine_code[1],
+        machine_code[2],
+        machine_code[3],
+    ]);
+
+    match aarch64_instruction(word, address) {
+        Some((instruction, branch_target)) => DissassLine {
+            address,
+            bytes: machine_code[..4].to_vec(),
+            instruction,
+            branch_target,
+        },
+        None => data_line(machine_code, 4, address, ".inst"),
+    }
+}
+
+/// Returns the A64 assembly of `word` and its branch target, or None if the instruction is not supported.
+fn aarch64_instruction(word: u32, address: u64) -> Option<(String, Option<u64>)> {
+    let bits = |shift: u32, mask: u32| (word >> shift) & mask;
+    let relative = |offset: i64| (address as i64 + offset) as u64;
+    let sf = bits(31, 1) == 1;
+    let rd = bits(0, 0x1F);
+    let rn = bits(5, 0x1F);
+
+    let instruction = if word & 0x7C00_0000 == 0x1400_0000 {
+        let target = relative(sign_extend(bits(0, 0x3FF_FFFF), 26) << 2);
+        let mnemonic = if sf { "bl" } else { "b" };
+        return Some((format!("{} {:#x}", mnemonic, target), Some(target)));
+    } else if word & 0xFF00_0010 == 0x5400_0000 {
+        let target = relative(sign_extend(bits(5, 0x7_FFFF), 19) << 2);
+        let condition = match bits(0, 0xF) {
+            14 => "al",
+            condition => CONDITIONS[condition as usize],
+        };
+        return Some((format!("b.{} {:#x}", condition, target), Some(target)));
+    } else if word & 0x7E00_0000 == 0x3400_0000 {
+        let target = relative(sign_extend(bits(5, 0x7_FFFF), 19) << 2);
+        let mnemonic = if bits(24, 1) == 1 { "cbnz" } else { "cbz" };
+        return Some((
+            format!("{} {}, {:#x}", mnemonic, a64_reg(rd, sf, false), target),
+            Some(target),
+        ));
+    } else if word & 0x7E00_0000 == 0x3600_0000 {
+        let target = relative(sign_extend(bits(5, 0x3FFF), 14) << 2);
+        let mnemonic = if bits(24, 1) == 1 { "tbnz" } else { "tbz" };
+        let bit = (bits(31, 1) << 5) | bits(19, 0x1F);
+        return Some((
+            format!(
+                "{} {}, #{}, {:#x}",
+                mnemonic,
+                a64_reg(rd, bit > 31, false),
+                bit,
+                target
+            ),
+            Some(target),
+        ));
+    } else if word & 0xFF9F_FC1F == 0xD61F_0000 {
+        match (bits(21, 0x3), rn) {
+            (0, _) => format!("br {}", a64_reg(rn, true, false)),
+            (1, _) => format!("blr {}", a64_reg(rn, true, false)),
+            (2, 30) => String::from("ret"),
+            (_, _) => format!("ret {}", a64_reg(rn, true, false)),
+        }
+    } else if word == 0xD503_201F {
+        String::from("nop")
+    } else if word & 0xFFE0_001F == 0xD400_0001 {
+        format!("svc #{:#x}", bits(5, 0xFFFF))
+    } else if word & 0x1F00_0000 == 0x1000_0000 {
+        let imm = sign_extend((bits(5, 0x7_FFFF) << 2) | bits(29, 0x3), 21);
+        let (mnemonic, target) = match bits(31, 1) {
+            0 => ("adr", relative(imm)),
+            _ => ("adrp", ((address & !0xFFF) as i64 + (imm << 12)) as u64),
+        };
+        format!("{} {}, {:#x}", mnemonic, a64_reg(rd, true, false), target)
+    } else if word & 0x1F80_0000 == 0x1280_0000 {
+        let mnemonic = match bits(29, 0x3) {
+            0 => "movn",
+            2 => "movz",
+            3 => "movk",
+            _ => return None,
+        };
+        let shift = match bits(21, 0x3) {
+            0 => String::new(),
+            hw => format!(", lsl #{}", hw * 16),
+        };
+        format!(
+            "{} {}, #{:#x}{}",
+            mnemonic,
+            a64_reg(rd, sf, false),
+            bits(5, 0xFFFF),
+            shift
+        )
+    } else if word & 0x1F00_0000 == 0x1100_0000 {
+        let subtract = bits(30, 1) == 1;
+        let set_flags = bits(29, 1) == 1;
+        let imm = bits(10, 0xFFF);
+        let shift = if bits(22, 1) == 1 { ", lsl #12" } else { "" };
+
+        match (subtract, set_flags, rd, imm) {
+            (false, false, _, 0) if shift.is_empty() && (rd == 31 || rn == 31) => {
+                format!("mov {}, {}", a64_reg(rd, sf, true), a64_reg(rn, sf, true))
+            }
+            (true, true, 31, _) => {
+                format!("cmp {}, #{:#x}{}", a64_reg(rn, sf, true), imm, shift)
+            }
+            (false, true, 31, _) => {
+                format!("cmn {}, #{:#x}{}", a64_reg(rn, sf, true), imm, shift)
+            }
+            _ => format!(
+                "{}{} {}, {}, #{:#x}{}",
+                if subtract { "sub" } else { "add" },
+                if set_flags { "s" } else { "" },
+                a64_reg(rd, sf, !set_flags),
+                a64_reg(rn, sf, true),
+                imm,
+                shift
+            ),
+        }
+    } else if word & 0x3B00_0000 == 0x3900_0000 && bits(26, 1) == 0 {
+        let size = bits(30, 0x3);
+        let (mnemonic, wide) = match (bits(22, 0x3), size) {
+            (0, 0) => ("strb", false),
+            (1, 0) => ("ldrb", false),
+            (2, 0) => ("ldrsb", true),
+            (3, 0) => ("ldrsb", false),
+            (0, 1) => ("strh", false),
+            (1, 1) => ("ldrh", false),
+            (2, 1) => ("ldrsh", true),
+            (3, 1) => ("ldrsh", false),
+            (0, 2) => ("str", false),
+            (1, 2) => ("ldr", false),
+            (2, 2) => ("ldrsw", true),
+            (0, 3) => ("str", true),
+            (1, 3) => ("ldr", true),
+            _ => return None,
+        };
+        format!(
+            "{} {}, {}",
+            mnemonic,
+            a64_reg(rd, wide, false),
+            a64_address(rn, (bits(10, 0xFFF) << size) as i64, 2)
+        )
+    } else if word & 0x3A00_0000 == 0x2800_0000 && bits(26, 1) == 0 {
+        let wide = match bits(30, 0x3) {
+            0 => false,
+            2 => true,
+            _ => return None,
+        };
+        let mode = bits(23, 0x3);
+        if mode == 0 {
+            return None;
+        }
+        let offset = sign_extend(bits(15, 0x7F), 7) << if wide { 3 } else { 2 };
+        format!(
+            "{} {}, {}, {}",
+            if bits(22, 1) == 1 { "ldp" } else { "stp" },
+            a64_reg(rd, wide, false),
+            a64_reg(bits(10, 0x1F), wide, false),
+            a64_address(rn, offset, mode)
+        )
+    } else if word & 0xBB00_0000 == 0x1800_0000 && bits(26, 1) == 0 {
+        let target = relative(sign_extend(bits(5, 0x7_FFFF), 19) << 2);
+        format!(
+            "ldr {}, {:#x}",
+            a64_reg(rd, bits(30, 1) == 1, false),
+            target
+        )
+    } else {
+        return None;
+    };
+
+    Some((instruction, None))
+}
+
+/// Formats the memory operand of an AArch64 load or store.
+///
+/// `mode` is the A64 indexing mode, 1 = post-index, 2 = signed offset and 3 = pre-index.
+fn a64_address(rn: u32, offset: i64, mode: u32) -> String {
+    let base = a64_reg(rn, true, true);
+
+    match (mode, offset) {
+        (1, _) => format!("[{}], {}", base, signed_imm(offset)),
+        (3, _) => format!("[{}, {}]!", base, signed_imm(offset)),
+        (_, 0) => format!("[{}]", base),
+        (_, _) => format!("[{}, {}]", base, signed_imm(offset)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_arm32() {
+        let cases: [(u32, &str, Option<u64>); 9] = [
+            (0xE12F_FF1E, "bx lr", None),
+            (0xE92D_4010, "push {r4, lr}", None),
+            (0xE8BD_8010, "pop {r4, pc}", None),
+            (0xE3A0_0000, "mov r0, #0x0", None),
+            (0xE591_0004, "ldr r0, [r1, #0x4]", None),
+            (0xE081_0002, "add r0, r1, r2", None),
+            (0xE1A0_0000, "mov r0, r0", None),
+            (0xEAFF_FFFE, "b 0x8000", Some(0x8000)),
+            (0x0A00_0002, "beq 0x8010", Some(0x8010)),
+        ];
+
+        for (word, instruction, branch_target) in cases {
+            let line = decode_arm32(&word.to_le_bytes(), 0x8000);
+            assert_eq!(line.instruction, instruction, "{:#010x}", word);
+            assert_eq!(line.branch_target, branch_target, "{:#010x}", word);
+        }
+    }
+
+    #[test]
+    fn decodes_thumb() {
+        let cases: [(u16, &str, Option<u64>); 7] = [
+            (0x4770, "bx lr", None),
+            (0xB510, "push {r4, lr}", None),
+            (0xBD10, "pop {r4, pc}", None),
+            (0x2001, "mov r0, #0x1", None),
+            (0x6848, "ldr r0, [r1, #0x4]", None),
+            (0x1C40, "add r0, r0, #1", None),
+            (0xD0FE, "beq 0x100", Some(0x100)),
+        ];
+
+        for (half, instruction, branch_target) in cases {
+            let line = decode_thumb(&half.to_le_bytes(), 0x100);
+            assert_eq!(line.instruction, instruction, "{:#06x}", half);
+            assert_eq!(line.branch_target, branch_target, "{:#06x}", half);
+        }
+    }
+
+    #[test]
+    fn decodes_thumb_bl_pairs() {
+        let forward = decode_thumb(&[0x00, 0xF0, 0x7E, 0xF8], 0);
+        assert_eq!(forward.instruction, "bl 0x100");
+        assert_eq!(forward.bytes.len(), 4);
+
+        let backward = decode_thumb(&[0xFF, 0xF7, 0xFE, 0xFF], 0x200);
+        assert_eq!(backward.instruction, "bl 0x200");
+        assert_eq!(backward.branch_target, Some(0x200));
+    }
+
+    #[test]
+    fn decodes_aarch64() {
+        let cases: [(u32, &str, Option<u64>); 9] = [
+            (0xD65F_03C0, "ret", None),
+            (0xA9BF_7BFD, "stp x29, x30, [sp, #-0x10]!", None),
+            (0xA8C1_7BFD, "ldp x29, x30, [sp], #0x10", None),
+            (0x9100_03FD, "mov x29, sp", None),
+            (0xD503_201F, "nop", None),
+            (0xF940_0020, "ldr x0, [x1]", None),
+            (0xD280_0540, "movz x0, #0x2a", None),
+            (0x9400_0004, "bl 0x1010", Some(0x1010)),
+            (0xB400_0040, "cbz x0, 0x1008", Some(0x1008)),
+        ];
+
+        for (word, instruction, branch_target) in cases {
+            let line = decode_aarch64(&word.to_le_bytes(), 0x1000);
+            assert_eq!(line.instruction, instruction, "{:#010x}", word);
+            assert_eq!(line.branch_target, branch_target, "{:#010x}", word);
+        }
+    }
+
+    #[test]
+    fn unknown_encodings_are_data() {
+        assert_eq!(
+            decode_aarch64(&0x0000_0000u32.to_le_bytes(), 0).instruction,
+            ".inst 0x00000000"
+        );
+        assert_eq!(decode_arm32(&[0x12, 0x34], 0).instruction, ".byte 0x3412");
+    }
+}
@@ -0,0 +1,1326 @@
+//! Module for parsing the struct and union declarations of a C header, used by the C struct converter.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Type keywords that make up C basic types, e.g `unsigned long int`.
+const KEYWORD_TYPES: [&str; 11] = [
+    "signed", "unsigned", "char", "short", "int", "long", "float", "double", "void", "_Bool",
+    "bool",
+];
+/// Keywords that do not change the layout of a declaration and are skipped.
+const QUALIFIERS: [&str; 9] = [
+    "const",
+    "volatile",
+    "register",
+    "restrict",
+    "static",
+    "extern",
+    "inline",
+    "__restrict",
+    "__extension__",
+];
+/// Compiler extensions followed by a parenthesised argument list that are skipped.
+const EXTENSIONS: [&str; 5] = [
+    "__attribute__",
+    "__declspec",
+    "__asm__",
+    "alignas",
+    "_Alignas",
+];
+
+/// The alignment given by an `aligned` attribute without an argument, the largest alignment of any type.
+const MAX_ALIGNMENT: usize = 16;
+
+/// A token of C or Rust source, along with the line it is on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Number(u64),
+    /// A number that is not an integer, e.g `0.5f` or `1e3`, kept as written as it can not be used as a constant.
+    Literal(String),
+    Punct(char),
+    /// A whole preprocessor line, e.g `#define MAX 16`.
+    Directive(String),
+}
+
+/// Whether the fields of a record follow each other or overlap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordKinds {
+    Struct,
+    Union,
+}
+
+impl RecordKinds {
+    /// The C keyword of the record kind.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            RecordKinds::Struct => "struct",
+            RecordKinds::Union => "union",
+        }
+    }
+}
+
+/// Layout attributes given by compiler extensions, e.g `__attribute__((packed, aligned(8)))`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attributes {
+    /// Whether the members are packed with an alignment of 1 byte.
+    pub packed: bool,
+    /// The minimum alignment given by `aligned(n)`, `__declspec(align(n))` or `alignas(n)`.
+    pub align: Option<usize>,
+}
+
+impl Attributes {
+    /// Adds the attributes of `other` to these attributes, keeping the largest alignment.
+    fn merge(&mut self, other: Attributes) {
+        self.packed |= other.packed;
+        self.align = self.align.max(other.align);
+    }
+}
+
+/// A struct or union definition.
+#[derive(Debug)]
+pub struct CRecord {
+    pub kind: RecordKinds,
+    /// The tag of the record, generated from the enclosing record for anonymous records.
+    pub name: String,
+    pub members: Vec<CMember>,
+    pub attributes: Attributes,
+    /// The largest member alignment set by `#pragma pack(n)` where the record is defined.
+    pub pack: Option<usize>,
+}
+
+/// The type of a member, without any pointer or array declarators.
+#[derive(Clone, Debug)]
+pub enum TypeSpecs {
+    /// A basic type or typedef, e.g `unsigned int` or `uint32_t`.
+    Named(String),
+    /// A struct or union, referred to by the name of its `CRecord`.
+    Record(String),
+    /// An enum, referred to by its tag which may be empty.
+    Enum(String),
+}
+
+/// A member of a struct or union.
+#[derive(Debug)]
+pub struct CMember {
+    pub type_spec: TypeSpecs,
+    /// The name of the member, generated for anonymous struct and union members and empty for unnamed bitfields.
+    pub name: String,
+    /// The number of pointer declarators, e.g 2 for `**name`, 0 if the member is not a pointer.
+    pub pointer_depth: usize,
+    /// The length of each array dimension, an empty array dimension has a length of 0.
+    pub dimensions: Vec<usize>,
+    /// The width in bits of a bitfield member.
+    pub bit_width: Option<usize>,
+    pub attributes: Attributes,
+}
+
+impl CMember {
+    /// Whether the member is a plain instance of its type, rather than a pointer to it or an array of it.
+    fn is_plain(&self) -> bool {
+        self.pointer_depth == 0 && self.dimensions.is_empty()
+    }
+}
+
+/// An enum definition.
+#[derive(Debug)]
+pub struct CEnum {
+    /// The tag of the enum, or the name of the typedef for an anonymous enum.
+    pub name: String,
+    /// The name and value of each enumerator.
+    pub values: Vec<(String, i64)>,
+}
+
+/// Every struct, union, enum and typedef defined in a C header, nested records come before the records containing
+/// them.
+#[derive(Debug)]
+pub struct CHeader {
+    pub records: Vec<CRecord>,
+    pub enums: Vec<CEnum>,
+    /// Each typedef as a declarator, e.g `typedef struct point *PPOINT;` is a member called `PPOINT` that is a pointer
+    /// to `struct point`.
+    pub typedefs: Vec<CMember>,
+}
+
+impl CHeader {
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+            enums: Vec::new(),
+            typedefs: Vec::new(),
+        }
+    }
+
+    /// Finds the record called `name`.
+    pub fn record(&self, name: &str) -> Option<&CRecord> {
+        self.records.iter().find(|record| record.name == name)
+    }
+
+    /// Finds the enum called `name`.
+    pub fn enum_definition(&self, name: &str) -> Option<&CEnum> {
+        self.enums
+            .iter()
+            .find(|enum_definition| enum_definition.name == name)
+    }
+
+    /// Finds the typedef called `name`.
+    pub fn typedef(&self, name: &str) -> Option<&CMember> {
+        self.typedefs.iter().find(|typedef| typedef.name == name)
+    }
+}
+
+/// Parses the struct, union, enum and typedef definitions of a C header.
+///
+/// `#define` integer constants and enumerators can be used as array lengths. Anything else, e.g function prototypes
+/// or other preprocessor directives, is skipped.
+pub fn parse_header(source: &str) -> Result<CHeader, ()> {
+    let mut parser = Parser {
+        tokens: tokenize(source),
+        position: 0,
+        header: CHeader::new(),
+        constants: HashMap::new(),
+        anonymous_count: 0,
+        pack: None,
+        pack_stack: Vec::new(),
+    };
+
+    parser.parse_top_level()?;
+
+    Ok(parser.header)
+}
+
+/// Splits C source into tokens, removing comments and keeping preprocessor directives as single tokens.
+///
+/// Numbers that are not integer literals, e.g floats in an inline function, become `Token::Literal` and only cause an
+/// error if they are used as a constant.
+fn tokenize(source: &str) -> Vec<(Token, usize)> {
+    let characters: Vec<char> = source.chars().collect();
+    let mut tokens: Vec<(Token, usize)> = Vec::new();
+    let mut line = 1;
+    let mut line_start = true;
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let next = characters.get(index + 1).copied();
+
+        match character {
+            '\n' => {
+                line += 1;
+                line_start = true;
+                index += 1;
+                continue;
+            }
+            _ if character.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '/' if next == Some('/') => {
+                while index < characters.len() && characters[index] != '\n' {
+                    index += 1;
+                }
+                continue;
+            }
+            '/' if next == Some('*') => {
+                index += 2;
+                while index < characters.len()
+                    && !(characters[index] == '*' && characters.get(index + 1) == Some(&'/'))
+                {
+                    if characters[index] == '\n' {
+                        line += 1;
+                    }
+                    index += 1;
+                }
+                index += 2;
+                continue;
+            }
+            '#' if line_start => {
+                let directive_line = line;
+                let mut directive = String::new();
+
+                while index < characters.len() && characters[index] != '\n' {
+                    if characters[index] == '\\' && characters.get(index + 1) == Some(&'\n') {
+                        line += 1;
+                        index += 2;
+                        continue;
+                    }
+                    if characters[index] == '/' && characters.get(index + 1) == Some(&'/') {
+                        while index < characters.len() && characters[index] != '\n' {
+                            index += 1;
+                        }
+                        break;
+                    }
+                    directive.push(characters[index]);
+                    index += 1;
+                }
+
+                tokens.push((
+                    Token::Directive(directive.trim().to_string()),
+                    directive_line,
+                ));
+                continue;
+            }
+            '"' | '\'' => {
+                index += 1;
+                while index < characters.len() && characters[index] != character {
+                    if characters[index] == '\\' {
+                        index += 1;
+                    }
+                    index += 1;
+                }
+                index += 1;
+                tokens.push((Token::Punct(character), line));
+            }
+            _ if character.is_ascii_alphabetic() || character == '_' => {
+                let start = index;
+                while index < characters.len()
+                    && (characters[index].is_ascii_alphanumeric() || characters[index] == '_')
+                {
+                    index += 1;
+                }
+                tokens.push((
+                    Token::Ident(characters[start..index].iter().collect()),
+                    line,
+                ));
+            }
+            _ if character.is_ascii_digit()
+                || (character == '.' && next.is_some_and(|next| next.is_ascii_digit())) =>
+            {
+                let start = index;
+                while index < characters.len() {
+                    let number_character = characters[index];
+                    index += 1;
+
+                    // An exponent may be signed, e.g `1e-3` or `0x1p+4`.
+                    if matches!(number_character, 'e' | 'E' | 'p' | 'P')
+                        && matches!(characters.get(index), Some('+') | Some('-'))
+                    {
+                        index += 1;
+                    } else if !(number_character.is_ascii_alphanumeric()
+                        || number_character == '.'
+                        || number_character == '_')
+                    {
+                        index -= 1;
+                        break;
+                    }
+                }
+                let literal: String = characters[start..index].iter().collect();
+                tokens.push((
+                    match integer_literal(&literal) {
+                        Ok(number) => Token::Number(number),
+                        Err(_) => Token::Literal(literal),
+                    },
+                    line,
+                ));
+            }
+            _ => {
+                tokens.push((Token::Punct(character), line));
+                index += 1;
+            }
+        }
+
+        line_start = false;
+    }
+
+    tokens
+}
+
+/// Parses a decimal, hex or octal C integer literal, ignoring any `u` or `l` suffixes.
+fn parse_integer_literal(literal: &str, line: usize) -> Result<u64, ()> {
+    integer_literal(literal).map_err(|e| {
+        serror!(format!(
+            "Invalid integer: {} on line {}, because {}",
+            literal, line, e
+        ))
+    })
+}
+
+/// Converts a decimal, hex or octal C integer literal into a number, ignoring any `u` or `l` suffixes.
+fn integer_literal(literal: &str) -> Result<u64, std::num::ParseIntError> {
+    let digits = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+
+    if let Some(hex_digits) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex_digits, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse::<u64>()
+    }
+}
+
+/// Evaluates an integer constant expression starting at `position`, leaving `position` after it.
+///
+/// Numbers, names in `constants`, parentheses and the unary `- + ~` and binary `* / % + - << >> & ^ |` operators are
+/// supported. `None` is returned if the tokens are not an integer constant expression.
+pub fn evaluate(
+    tokens: &[(Token, usize)],
+    position: &mut usize,
+    constants: &HashMap<String, i64>,
+) -> Option<i64> {
+    evaluate_binary(tokens, position, constants, 0)
+}
+
+/// Binary operators from lowest to highest precedence, `<` and `>` stand for `<<` and `>>`.
+const BINARY_OPERATORS: [&[char]; 6] = [
+    &['|'],
+    &['^'],
+    &['&'],
+    &['<', '>'],
+    &['+', '-'],
+    &['*', '/', '%'],
+];
+
+/// Evaluates the binary operators of `BINARY_OPERATORS[precedence]` and above.
+fn evaluate_binary(
+    tokens: &[(Token, usize)],
+    position: &mut usize,
+    constants: &HashMap<String, i64>,
+    precedence: usize,
+) -> Option<i64> {
+    if precedence == BINARY_OPERATORS.len() {
+        return evaluate_unary(tokens, position, constants);
+    }
+
+    let mut value = evaluate_binary(tokens, position, constants, precedence + 1)?;
+
+    while let Some((Token::Punct(operator), _)) = tokens.get(*position) {
+        let operator = *operator;
+
+        if !BINARY_OPERATORS[precedence].contains(&operator) {
+            break;
+        }
+
+        // Shifts are two tokens and a single `&` or `|` must not be the start of `&&` or `||`.
+        let length = match (operator, tokens.get(*position + 1)) {
+            ('<', Some((Token::Punct('<'), _))) | ('>', Some((Token::Punct('>'), _))) => 2,
+            ('<', _) | ('>', _) => return Some(value),
+            ('&', Some((Token::Punct('&'), _))) | ('|', Some((Token::Punct('|'), _))) => {
+                return Some(value)
+            }
+            _ => 1,
+        };
+        *position += length;
+
+        let rhs = evaluate_binary(tokens, position, constants, precedence + 1)?;
+
+        value = match operator {
+            '|' => value | rhs,
+            '^' => value ^ rhs,
+            '&' => value & rhs,
+            '<' => value.checked_shl(u32::try_from(rhs).ok()?)?,
+            '>' => value.checked_shr(u32::try_from(rhs).ok()?)?,
+            '+' => value.checked_add(rhs)?,
+            '-' => value.checked_sub(rhs)?,
+            '*' => value.checked_mul(rhs)?,
+            '/' => value.checked_div(rhs)?,
+            _ => value.checked_rem(rhs)?,
+        };
+    }
+
+    Some(value)
+}
+
+/// Evaluates a unary operator, a number, a constant or a parenthesised expression.
+fn evaluate_unary(
+    tokens: &[(Token, usize)],
+    position: &mut usize,
+    constants: &HashMap<String, i64>,
+) -> Option<i64> {
+    let token = &tokens.get(*position)?.0;
+    *position += 1;
+
+    match token {
+        Token::Number(number) => i64::try_from(*number).ok(),
+        Token::Ident(name) => constants.get(name).copied(),
+        Token::Punct('-') => evaluate_unary(tokens, position, constants)?.checked_neg(),
+        Token::Punct('+') => evaluate_unary(tokens, position, constants),
+        Token::Punct('~') => Some(!evaluate_unary(tokens, position, constants)?),
+        Token::Punct('(') => {
+            let value = evaluate(tokens, position, constants)?;
+
+            match tokens.get(*position) {
+                Some((Token::Punct(')'), _)) => {
+                    *position += 1;
+                    Some(value)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Recursive descent parser over the tokens of a C header.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    header: CHeader,
+    /// Values of the `#define` integer constants and enumerators defined so far.
+    constants: HashMap<String, i64>,
+    /// Number of anonymous records so far, used to generate unique names for them.
+    anonymous_count: usize,
+    /// The largest member alignment set by the last `#pragma pack`.
+    pack: Option<usize>,
+    /// The alignments saved by `#pragma pack(push)`.
+    pack_stack: Vec<Option<usize>>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_ident(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Ident(ident)) => Some(ident),
+            _ => None,
+        }
+    }
+
+    fn is_punct(&self, punct: char) -> bool {
+        self.peek() == Some(&Token::Punct(punct))
+    }
+
+    /// The line of the current token, used in error messages.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |(_, line)| *line)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Reports a parse error at the current token.
+    fn error<T>(&self, message: &str) -> Result<T, ()> {
+        serror!(format!(
+            "Invalid C declaration on line {}: {}, found: {}",
+            self.line(),
+            message,
+            match self.peek() {
+                Some(Token::Ident(ident)) => ident.clone(),
+                Some(Token::Number(number)) => number.to_string(),
+                Some(Token::Literal(literal)) => literal.clone(),
+                Some(Token::Punct(punct)) => punct.to_string(),
+                Some(Token::Directive(directive)) => directive.clone(),
+                None => String::from("end of file"),
+            }
+        ));
+        Err(())
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<(), ()> {
+        if self.is_punct(punct) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", punct))
+        }
+    }
+
+    /// Skips a balanced `open`/`close` block starting at the current token.
+    fn skip_balanced(&mut self, open: char, close: char) -> Result<(), ()> {
+        let mut depth = 0;
+
+        loop {
+            match self.advance() {
+                Some(Token::Punct(punct)) if punct == open => depth += 1,
+                Some(Token::Punct(punct)) if punct == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Some(_) => (),
+                None => return self.error(&format!("expected '{}'", close)),
+            }
+        }
+    }
+
+    /// Skips qualifiers and compiler extensions such as `const` or `__attribute__((packed))`.
+    ///
+    /// Returns the layout attributes given by the skipped extensions.
+    fn skip_qualifiers(&mut self) -> Result<Attributes, ()> {
+        let mut attributes = Attributes::default();
+
+        while let Some(ident) = self.peek_ident() {
+            if QUALIFIERS.contains(&ident) {
+                self.position += 1;
+            } else if EXTENSIONS.contains(&ident) {
+                let alignas = ident == "alignas" || ident == "_Alignas";
+                self.position += 1;
+
+                if self.is_punct('(') {
+                    let start = self.position;
+                    self.skip_balanced('(', ')')?;
+                    attributes.merge(extension_attributes(
+                        &self.tokens[start..self.position],
+                        alignas,
+                    ));
+                }
+            } else {
+                break;
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    /// Applies a `#pragma pack` or `#define` directive, any other directive is ignored.
+    ///
+    /// `pack(n)`, `pack()`, `pack(push)`, `pack(push, n)` and `pack(pop)` are supported, identifiers given to `push` and
+    /// `pop` are ignored.
+    fn apply_directive(&mut self, directive: &str) -> Result<(), ()> {
+        if let Some(definition) = directive
+            .trim_start_matches('#')
+            .trim_start()
+            .strip_prefix("define")
+        {
+            self.define_constant(definition);
+            return Ok(());
+        }
+
+        let arguments = match directive
+            .trim_start_matches('#')
+            .trim_start()
+            .strip_prefix("pragma")
+            .map(str::trim_start)
+            .and_then(|pragma| pragma.strip_prefix("pack"))
+            .map(str::trim)
+            .and_then(|pack| pack.strip_prefix('('))
+            .and_then(|pack| pack.strip_suffix(')'))
+        {
+            Some(arguments) => arguments,
+            None => return Ok(()),
+        };
+
+        let mut alignment = None;
+        let mut set = false;
+
+        for argument in arguments.split(',').map(str::trim) {
+            match argument {
+                "push" => self.pack_stack.push(self.pack),
+                "pop" => {
+                    self.pack = self.pack_stack.pop().flatten();
+                }
+                _ if argument.starts_with(|character: char| character.is_ascii_digit()) => {
+                    alignment = Some(parse_integer_literal(argument, self.line())? as usize);
+                    set = true;
+                }
+                _ => (),
+            }
+        }
+
+        if set {
+            self.pack = alignment.filter(|alignment| *alignment > 0);
+        } else if arguments.trim().is_empty() {
+            self.pack = None;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the value of a `#define` to the constants if it is an integer constant expression, e.g `MAX_NAME (16 + 1)`.
+    ///
+    /// Function-like macros and macros with other values are ignored.
+    fn define_constant(&mut self, definition: &str) {
+        let definition = definition.trim_start();
+        let name_length = definition
+            .find(|character: char| !(character.is_ascii_alphanumeric() || character == '_'))
+            .unwrap_or(definition.len());
+        let (name, value) = definition.split_at(name_length);
+
+        if name.is_empty() || value.starts_with('(') {
+            return;
+        }
+
+        let tokens = tokenize(value);
+        let mut position = 0;
+
+        if let Some(number) = evaluate(&tokens, &mut position, &self.constants) {
+            if position == tokens.len() {
+                self.constants.insert(name.to_string(), number);
+            }
+        }
+    }
+
+    /// Parses every top level struct and union definition, skipping everything else.
+    ///
+    /// Parameter lists are skipped whole, so a struct named in a function prototype is not taken as a declaration.
+    ///
+    /// Layout attributes in front of a struct or union, e.g `__declspec(align(16)) struct name {...}`, are given to it.
+    fn parse_top_level(&mut self) -> Result<(), ()> {
+        let mut attributes = Attributes::default();
+
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Ident(ident) if ident == "struct" || ident == "union" => {
+                    self.parse_record(None, attributes)?;
+                    self.skip_declaration()?;
+                    attributes = Attributes::default();
+                }
+                Token::Ident(ident) if ident == "enum" => {
+                    self.parse_type_spec("anonymous")?;
+                    self.skip_declaration()?;
+                }
+                Token::Ident(ident) if ident == "typedef" => {
+                    self.position += 1;
+                    self.parse_typedef()?;
+                    attributes = Attributes::default();
+                }
+                Token::Ident(ident) if EXTENSIONS.contains(&&ident[..]) => {
+                    attributes.merge(self.skip_qualifiers()?);
+                }
+                Token::Directive(directive) => {
+                    let directive = directive.clone();
+                    self.apply_directive(&directive)?;
+                    self.position += 1;
+                }
+                Token::Punct(';') => {
+                    attributes = Attributes::default();
+                    self.position += 1;
+                }
+                Token::Punct('{') => self.skip_balanced('{', '}')?,
+                Token::Punct('(') => self.skip_balanced('(', ')')?,
+                _ => self.position += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the declarators of a typedef after the `typedef` keyword, e.g `struct {...} POINT, *PPOINT;`.
+    ///
+    /// An anonymous struct, union or enum is named after the first typedef that is not a pointer or array.
+    fn parse_typedef(&mut self) -> Result<(), ()> {
+        let (mut type_spec, mut anonymous, attributes, enum_index) =
+            self.parse_type_spec("anonymous")?;
+
+        loop {
+            let mut typedef = self.parse_declarator(type_spec.clone(), attributes)?;
+
+            if anonymous && typedef.is_plain() {
+                anonymous = false;
+
+                match &type_spec {
+                    TypeSpecs::Record(name) => {
+                        if let Some(record) = self
+                            .header
+                            .records
+                            .iter_mut()
+                            .find(|record| record.name == *name)
+                        {
+                            record.name = typedef.name.clone();
+                        }
+                        type_spec = TypeSpecs::Record(typedef.name.clone());
+                    }
+                    TypeSpecs::Enum(_) => {
+                        if let Some(anonymous_enum) =
+                            enum_index.and_then(|index| self.header.enums.get_mut(index))
+                        {
+                            anonymous_enum.name = typedef.name.clone();
+                        }
+                        type_spec = TypeSpecs::Enum(typedef.name.clone());
+                    }
+                    TypeSpecs::Named(_) => (),
+                }
+
+                typedef.type_spec = type_spec.clone();
+            }
+
+            self.header.typedefs.push(typedef);
+
+            if self.is_punct(',') {
+                self.position += 1;
+            } else {
+                return self.expect_punct(';');
+            }
+        }
+    }
+
+    /// Replaces a typedef used as the type of `member` with the type it names, e.g a member of type `PPOINT` becomes
+    /// a pointer to `struct point`.
+    ///
+    /// Typedefs of basic types or other typedefs, e.g `typedef uint32_t DWORD;`, are kept so their name is shown.
+    fn resolve_typedef(&self, member: &mut CMember) {
+        let typedef = match &member.type_spec {
+            TypeSpecs::Named(name) => match self.header.typedef(name) {
+                Some(typedef) => typedef,
+                None => return,
+            },
+            _ => return,
+        };
+
+        let named = matches!(typedef.type_spec, TypeSpecs::Named(_));
+
+        if named && typedef.is_plain() && typedef.attributes == Attributes::default() {
+            return;
+        }
+
+        member.type_spec = typedef.type_spec.clone();
+        member.attributes.merge(typedef.attributes);
+
+        // A pointer to an array typedef is a single pointer, not an array of pointers.
+        if member.pointer_depth == 0 {
+            member.dimensions.extend(typedef.dimensions.iter());
+        }
+        member.pointer_depth += typedef.pointer_depth;
+    }
+
+    /// Skips the rest of a top level declaration, up to and including its `;`.
+    ///
+    /// A function definition, e.g `struct point *next(struct point *p) {...}`, ends at the `}` of its body.
+    fn skip_declaration(&mut self) -> Result<(), ()> {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct(';') => {
+                    self.position += 1;
+                    break;
+                }
+                Token::Punct('{') => {
+                    let function_body =
+                        self.position > 0 && self.tokens[self.position - 1].0 == Token::Punct(')');
+                    self.skip_balanced('{', '}')?;
+
+                    if function_body {
+                        break;
+                    }
+                }
+                Token::Punct('(') => self.skip_balanced('(', ')')?,
+                _ => self.position += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a struct or union specifier, adding its definition to the header if it has one.
+    ///
+    /// Returns the name of the record and whether it is an anonymous definition. `parent` is the name of the enclosing
+    /// record, used to name anonymous records, and `attributes` are the layout attributes in front of the record.
+    fn parse_record(
+        &mut self,
+        parent: Option<&str>,
+        mut attributes: Attributes,
+    ) -> Result<(String, bool), ()> {
+        let kind = match self.advance() {
+            Some(Token::Ident(ident)) if ident == "union" => RecordKinds::Union,
+            _ => RecordKinds::Struct,
+        };
+
+        attributes.merge(self.skip_qualifiers()?);
+
+        let tag = match self.peek_ident() {
+            Some(ident) => {
+                let tag = ident.to_string();
+                self.position += 1;
+                Some(tag)
+            }
+            None => None,
+        };
+
+        attributes.merge(self.skip_qualifiers()?);
+
+        if !self.is_punct('{') {
+            return match tag {
+                Some(tag) => Ok((tag, false)),
+                None => self.error("expected a struct or union name or '{'"),
+            };
+        }
+
+        let anonymous = tag.is_none();
+        let name = match tag {
+            Some(tag) => tag,
+            None => {
+                self.anonymous_count += 1;
+                format!(
+                    "{}_{}{}",
+                    parent.unwrap_or("anonymous"),
+                    kind.keyword(),
+                    self.anonymous_count
+                )
+            }
+        };
+
+        self.expect_punct('{')?;
+
+        let mut members: Vec<CMember> = Vec::new();
+
+        while !self.is_punct('}') {
+            match self.peek() {
+                Some(Token::Directive(directive)) => {
+                    let directive = directive.clone();
+                    self.apply_directive(&directive)?;
+                    self.position += 1;
+                }
+                Some(Token::Punct(';')) => self.position += 1,
+                Some(_) => self.parse_member_declaration(&name, &mut members)?,
+                None => return self.error("expected '}'"),
+            }
+        }
+
+        self.expect_punct('}')?;
+        attributes.merge(self.skip_qualifiers()?);
+
+        self.header.records.push(CRecord {
+            kind,
+            name: name.clone(),
+            members,
+            attributes,
+            pack: self.pack,
+        });
+
+        Ok((name, anonymous))
+    }
+
+    /// Parses a member declaration, which may declare several members, e.g `int a, *b, c[4];`.
+    fn parse_member_declaration(
+        &mut self,
+        record_name: &str,
+        members: &mut Vec<CMember>,
+    ) -> Result<(), ()> {
+        let (type_spec, anonymous, attributes, _) = self.parse_type_spec(record_name)?;
+
+        if self.is_punct(';') {
+            self.position += 1;
+
+            if let (true, TypeSpecs::Record(name)) = (anonymous, &type_spec) {
+                members.push(CMember {
+                    name: name
+                        .strip_prefix(&format!("{}_", record_name))
+                        .unwrap_or(name)
+                        .to_string(),
+                    type_spec,
+                    pointer_depth: 0,
+                    dimensions: Vec::new(),
+                    bit_width: None,
+                    attributes,
+                });
+            }
+
+            return Ok(());
+        }
+
+        loop {
+            members.push(self.parse_declarator(type_spec.clone(), attributes)?);
+
+            if self.is_punct(',') {
+                self.position += 1;
+            } else {
+                return self.expect_punct(';');
+            }
+        }
+    }
+
+    /// Parses the type of a declaration.
+    ///
+    /// Returns whether the type is an anonymous struct or union definition, the layout attributes given to every
+    /// member of the declaration, e.g `alignas(8)`, and the index in `self.header.enums` of the enum the type defines.
+    fn parse_type_spec(
+        &mut self,
+        record_name: &str,
+    ) -> Result<(TypeSpecs, bool, Attributes, Option<usize>), ()> {
+        let mut attributes = self.skip_qualifiers()?;
+
+        let mut keyword_words: Vec<String> = Vec::new();
+        let mut enum_index = None;
+
+        while let Some(ident) = self.peek_ident() {
+            if KEYWORD_TYPES.contains(&ident) {
+                keyword_words.push(ident.to_string());
+                self.position += 1;
+            } else if QUALIFIERS.contains(&ident) || EXTENSIONS.contains(&ident) {
+                attributes.merge(self.skip_qualifiers()?);
+            } else {
+                break;
+            }
+        }
+
+        let (type_spec, anonymous) = if !keyword_words.is_empty() {
+            (TypeSpecs::Named(keyword_words.join(" ")), false)
+        } else {
+            match self.peek_ident() {
+                Some("struct") | Some("union") => {
+                    let (name, anonymous) =
+                        self.parse_record(Some(record_name), Attributes::default())?;
+                    (TypeSpecs::Record(name), anonymous)
+                }
+                Some("enum") => {
+                    self.position += 1;
+                    self.skip_qualifiers()?;
+                    let tag = match self.peek_ident() {
+                        Some(tag) => {
+                            let tag = tag.to_string();
+                            self.position += 1;
+                            tag
+                        }
+                        None => String::new(),
+                    };
+                    let anonymous = tag.is_empty() && self.is_punct('{');
+                    if self.is_punct('{') {
+                        enum_index = Some(self.parse_enum_body(&tag)?);
+                    }
+                    (TypeSpecs::Enum(tag), anonymous)
+                }
+                Some(typedef) => {
+                    let typedef = typedef.to_string();
+                    self.position += 1;
+                    (TypeSpecs::Named(typedef), false)
+                }
+                None => return self.error("expected a type"),
+            }
+        };
+
+        attributes.merge(self.skip_qualifiers()?);
+
+        Ok((type_spec, anonymous, attributes, enum_index))
+    }
+
+    /// Parses the enumerators of an enum called `name` between braces, adding them to the constants.
+    ///
+    /// Each enumerator is one more than the previous one unless it is given a value. Returns the index of the enum in
+    /// `self.header.enums`.
+    fn parse_enum_body(&mut self, name: &str) -> Result<usize, ()> {
+        self.expect_punct('{')?;
+
+        let mut values: Vec<(String, i64)> = Vec::new();
+        let mut next_value = 0;
+
+        while !self.is_punct('}') {
+            let enumerator = match self.advance() {
+                Some(Token::Ident(enumerator)) => enumerator,
+                Some(Token::Directive(_)) => continue,
+                _ => {
+                    self.position -= 1;
+                    return self.error("expected an enumerator");
+                }
+            };
+            self.skip_qualifiers()?;
+
+            if self.is_punct('=') {
+                self.position += 1;
+                next_value = self.parse_constant()?;
+            }
+
+            self.constants.insert(enumerator.clone(), next_value);
+            values.push((enumerator, next_value));
+            next_value = next_value.wrapping_add(1);
+
+            if self.is_punct(',') {
+                self.position += 1;
+            } else if !self.is_punct('}') {
+                return self.error("expected ',' or '}'");
+            }
+        }
+
+        self.expect_punct('}')?;
+
+        self.header.enums.push(CEnum {
+            name: name.to_string(),
+            values,
+        });
+
+        Ok(self.header.enums.len() - 1)
+    }
+
+    /// Parses a single declarator, e.g `*name`, `name[4][2]` or `name : 3`.
+    ///
+    /// `attributes` are the layout attributes of the whole declaration, any given after the declarator are added to them.
+    fn parse_declarator(
+        &mut self,
+        type_spec: TypeSpecs,
+        mut attributes: Attributes,
+    ) -> Result<CMember, ()> {
+        let mut pointer_depth = 0;
+
+        while self.is_punct('*') {
+            pointer_depth += 1;
+            self.position += 1;
+            self.skip_qualifiers()?;
+        }
+
+        // A function pointer, e.g `(*callback)(int, char *)`, is a pointer whatever it returns.
+        let function_pointer = self.is_punct('(');
+
+        if function_pointer {
+            self.position += 1;
+
+            while self.is_punct('*') {
+                pointer_depth += 1;
+                self.position += 1;
+                self.skip_qualifiers()?;
+            }
+        }
+
+        let name = match self.advance() {
+            Some(Token::Ident(name)) => name,
+            // An unnamed bitfield, e.g `unsigned int : 4;`, only pads the bitfields around it.
+            Some(Token::Punct(':')) if !function_pointer => {
+                self.position -= 1;
+                String::new()
+            }
+            _ => {
+                self.position -= 1;
+                return self.error("expected a member name");
+            }
+        };
+
+        let mut dimensions: Vec<usize> = Vec::new();
+
+        while self.is_punct('[') {
+            self.position += 1;
+
+            if self.is_punct(']') {
+                dimensions.push(0);
+            } else {
+                dimensions.push(self.parse_length()?);
+            }
+
+            self.expect_punct(']')?;
+        }
+
+        if function_pointer {
+            self.expect_punct(')')?;
+
+            if self.is_punct('(') {
+                self.skip_balanced('(', ')')?;
+            }
+        }
+
+        let bit_width = if self.is_punct(':') {
+            self.position += 1;
+            Some(self.parse_length()?)
+        } else {
+            None
+        };
+
+        attributes.merge(self.skip_qualifiers()?);
+
+        let mut member = CMember {
+            type_spec,
+            name,
+            pointer_depth,
+            dimensions,
+            bit_width,
+            attributes,
+        };
+        self.resolve_typedef(&mut member);
+
+        Ok(member)
+    }
+
+    /// Parses an integer constant expression, e.g `MAX_NAME + 1`.
+    ///
+    /// A number that is not an integer, e.g `2.5` or `1e3`, in the expression is reported as an invalid integer.
+    fn parse_constant(&mut self) -> Result<i64, ()> {
+        let start = self.position;
+
+        match evaluate(&self.tokens, &mut self.position, &self.constants) {
+            Some(number) => Ok(number),
+            None => {
+                self.position = start;
+
+                let invalid_literal = self.tokens[start..]
+                    .iter()
+                    .take_while(|(token, _)| {
+                        !matches!(
+                            token,
+                            Token::Punct(']' | ',' | '}' | ';') | Token::Directive(_)
+                        )
+                    })
+                    .find_map(|(token, line)| match token {
+                        Token::Literal(literal) => Some((literal, line)),
+                        _ => None,
+                    });
+
+                if let Some((literal, line)) = invalid_literal {
+                    serror!(format!(
+                        "Invalid integer: {} on line {}, it is not an integer literal",
+                        literal, line
+                    ));
+                    return Err(());
+                }
+
+                self.error("expected an integer constant")
+            }
+        }
+    }
+
+    /// Parses an integer constant expression that can not be negative, e.g an array length.
+    fn parse_length(&mut self) -> Result<usize, ()> {
+        let start = self.position;
+        let number = self.parse_constant()?;
+
+        usize::try_from(number).or_else(|_| {
+            self.position = start;
+            self.error("expected a length that is not negative")
+        })
+    }
+}
+
+/// Gets the layout attributes from the parenthesised arguments of a compiler extension.
+///
+/// `alignas` takes the alignment as its only argument, while `__attribute__` and `__declspec` take a list of attributes
+/// where `packed`, `aligned(n)` and `align(n)` change the layout. An `aligned` attribute without an argument uses the
+/// largest alignment of any type.
+fn extension_attributes(tokens: &[(Token, usize)], alignas: bool) -> Attributes {
+    let mut attributes = Attributes::default();
+    let tokens: Vec<&Token> = tokens.iter().map(|(token, _)| token).collect();
+
+    if alignas {
+        if let [Token::Punct('('), Token::Number(alignment), Token::Punct(')')] = tokens[..] {
+            attributes.align = Some(*alignment as usize);
+        }
+        return attributes;
+    }
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Ident(ident) if ident == "packed" || ident == "__packed__" => {
+                attributes.packed = true;
+            }
+            Token::Ident(ident) if matches!(&ident[..], "aligned" | "__aligned__" | "align") => {
+                let alignment = match tokens.get(index + 1..index + 4) {
+                    Some([Token::Punct('('), Token::Number(alignment), Token::Punct(')')]) => {
+                        *alignment as usize
+                    }
+                    _ => MAX_ALIGNMENT,
+                };
+                attributes.align = attributes.align.max(Some(alignment));
+            }
+            _ => (),
+        }
+    }
+
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_literals_in_inline_functions_are_skipped() {
+        let header = parse_header(
+            "static inline float half(float x) { return x * 0.5f + 1e-3 + .25 + 0x1p+4; }\n\
+             struct a { int x[4]; };",
+        )
+        .unwrap();
+
+        assert_eq!(header.record("a").unwrap().members[0].dimensions, vec![4]);
+    }
+
+    #[test]
+    fn function_definitions_do_not_swallow_the_next_struct() {
+        let header = parse_header(
+            "struct s {int a;}; static inline int geta(struct s *p) { return p->a; } struct t {int b;};\n\
+             struct s *next(struct s *p) { return p; }\n\
+             struct u {int c;};",
+        )
+        .unwrap();
+
+        assert_eq!(header.record("t").unwrap().members[0].name, "b");
+        assert_eq!(header.record("u").unwrap().members[0].name, "c");
+        assert_eq!(header.records.len(), 3);
+    }
+
+    #[test]
+    fn declarators_are_parsed() {
+        let header = parse_header(
+            "struct a { const unsigned long int *const *names[2][3], flags : 3; struct b { char c; } inner; };",
+        )
+        .unwrap();
+
+        let members = &header.record("a").unwrap().members;
+        assert!(
+            matches!(&members[0].type_spec, TypeSpecs::Named(name) if name == "unsigned long int")
+        );
+        assert_eq!(members[0].name, "names");
+        assert_eq!(members[0].pointer_depth, 2);
+        assert_eq!(members[0].dimensions, vec![2, 3]);
+        assert_eq!(members[1].name, "flags");
+        assert_eq!(members[1].bit_width, Some(3));
+        assert!(matches!(&members[2].type_spec, TypeSpecs::Record(name) if name == "b"));
+    }
+
+    #[test]
+    fn nested_records_come_before_their_parent() {
+        let header =
+            parse_header("struct a { union { int i; float f; }; struct b { char c; } inner; };")
+                .unwrap();
+        let names: Vec<&str> = header
+            .records
+            .iter()
+            .map(|record| &record.name[..])
+            .collect();
+
+        assert_eq!(names, vec!["a_union1", "b", "a"]);
+        assert_eq!(header.record("a_union1").unwrap().kind, RecordKinds::Union);
+    }
+
+    #[test]
+    fn typedef_names_the_enum_it_defines() {
+        let header = parse_header(
+            "enum { FIRST_ANON = 5, SECOND_ANON };\n\
+             typedef enum { RED = -1, GREEN, BLUE } colour_t;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            header.enum_definition("colour_t").unwrap().values,
+            vec![
+                (String::from("RED"), -1),
+                (String::from("GREEN"), 0),
+                (String::from("BLUE"), 1)
+            ]
+        );
+        assert!(header.enums[0].name.is_empty());
+    }
+
+    #[test]
+    fn float_literal_array_length_is_an_error() {
+        assert!(parse_header("struct a { int x[2.5]; };").is_err());
+    }
+
+    #[test]
+    fn define_constants_are_evaluated() {
+        let header = parse_header(
+            "#define BASE (4 << 2)\n#define LENGTH BASE + 1\n#define TWICE(x) ((x) * 2)\n\
+             enum { COUNT = LENGTH * 2 };\n\
+             struct s { char name[LENGTH]; int flags[BASE | 1]; int values[COUNT]; };",
+        )
+        .unwrap();
+        let members = &header.record("s").unwrap().members;
+
+        assert_eq!(members[0].dimensions, vec![17]);
+        assert_eq!(members[1].dimensions, vec![17]);
+        assert_eq!(members[2].dimensions, vec![34]);
+        assert!(
+            parse_header("#define TWICE(x) ((x) * 2)\nstruct s { char c[TWICE(2)]; };").is_err()
+        );
+    }
+
+    #[test]
+    fn typedefs_of_records_and_pointers_are_resolved() {
+        let header = parse_header(
+            "typedef struct { int x; } POINT, *PPOINT;\ntypedef PPOINT POINTS[2];\n\
+             typedef unsigned int DWORD;\n\
+             struct s { POINTS points; DWORD flags; };",
+        )
+        .unwrap();
+        let members = &header.record("s").unwrap().members;
+
+        assert!(header.record("POINT").is_some());
+        assert!(matches!(&members[0].type_spec, TypeSpecs::Record(name) if name == "POINT"));
+        assert_eq!(members[0].pointer_depth, 1);
+        assert_eq!(members[0].dimensions, vec![2]);
+        assert!(matches!(&members[1].type_spec, TypeSpecs::Named(name) if name == "DWORD"));
+    }
+}
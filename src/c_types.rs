@@ -0,0 +1,503 @@
+//! Module holding the sizes and default display formats of C types used by the C struct converter.
+use crate::format;
+
+/// The size, alignment and default display format of a C type.
+#[derive(Clone, Copy, Debug)]
+pub struct CType {
+    /// Size of the type in bytes.
+    pub size: usize,
+    /// Alignment of the type in bytes.
+    pub align: usize,
+    /// The display format fields of this type are given in the generated grammar.
+    pub display_format: &'static str,
+}
+
+/// Byte orders of a target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endians {
+    Little,
+    Big,
+}
+
+impl Endians {
+    /// The name of the byte order written to grammar metadata.
+    pub fn name(self) -> &'static str {
+        match self {
+            Endians::Little => "little",
+            Endians::Big => "big",
+        }
+    }
+}
+
+/// The data model of a target, giving the sizes and alignments of the C types that differ between compilers.
+#[derive(Debug)]
+pub struct Abi {
+    /// The name used to select the ABI with `--abi`.
+    pub name: &'static str,
+    pub endian: Endians,
+    pub int_size: usize,
+    pub long_size: usize,
+    pub pointer_size: usize,
+    /// Size of `double`, some 8-bit targets use a 4 byte `double`.
+    pub double_size: usize,
+    pub long_double_size: usize,
+    pub long_double_align: usize,
+    pub wchar_size: usize,
+    /// Size of enums, whose underlying type is `int` on most targets.
+    pub enum_size: usize,
+    /// Size of `time_t`, which MSVC makes 8 bytes even on 32-bit targets.
+    pub time_t_size: usize,
+    /// Alignment of `long long` and 8 byte `double`, which is 4 on some 32-bit targets.
+    pub align_8: usize,
+    /// The largest alignment of any basic type, types larger than this are aligned to it.
+    pub max_align: usize,
+    /// Whether bitfields are allocated the way MSVC does, in whole storage units of their type that are only shared
+    /// by bitfields of the same size, rather than packed next to each other as in the System V ABIs.
+    pub ms_bitfields: bool,
+}
+
+/// The ABI used when none is selected with `--abi`.
+pub const DEFAULT_ABI: &str = "llp64";
+
+/// Supported ABIs, selected by name with `--abi`.
+#[rustfmt::skip]
+pub static ABIS: [Abi; 9] = [
+    // 64-bit Windows (MSVC)
+    Abi { name: "llp64", endian: Endians::Little,
+        int_size: 4, long_size: 4, pointer_size: 8, double_size: 8, long_double_size: 8, long_double_align: 8,
+        wchar_size: 2, enum_size: 4, time_t_size: 8, align_8: 8, max_align: 8, ms_bitfields: true },
+    // 64-bit Linux, macOS and BSD (x86-64 and AArch64 System V)
+    Abi { name: "lp64", endian: Endians::Little,
+        int_size: 4, long_size: 8, pointer_size: 8, double_size: 8, long_double_size: 16, long_double_align: 16,
+        wchar_size: 4, enum_size: 4, time_t_size: 8, align_8: 8, max_align: 16, ms_bitfields: false },
+    // 32-bit x86 Linux and BSD (i386 System V)
+    Abi { name: "ilp32", endian: Endians::Little,
+        int_size: 4, long_size: 4, pointer_size: 4, double_size: 8, long_double_size: 12, long_double_align: 4,
+        wchar_size: 4, enum_size: 4, time_t_size: 4, align_8: 4, max_align: 4, ms_bitfields: false },
+    // 32-bit Windows (MSVC)
+    Abi { name: "win32", endian: Endians::Little,
+        int_size: 4, long_size: 4, pointer_size: 4, double_size: 8, long_double_size: 8, long_double_align: 8,
+        wchar_size: 2, enum_size: 4, time_t_size: 8, align_8: 8, max_align: 8, ms_bitfields: true },
+    // 32-bit ARM AAPCS, e.g Cortex-M (arm-none-eabi)
+    Abi { name: "arm", endian: Endians::Little,
+        int_size: 4, long_size: 4, pointer_size: 4, double_size: 8, long_double_size: 8, long_double_align: 8,
+        wchar_size: 4, enum_size: 4, time_t_size: 4, align_8: 8, max_align: 8, ms_bitfields: false },
+    // 8-bit AVR (avr-gcc)
+    Abi { name: "avr", endian: Endians::Little,
+        int_size: 2, long_size: 4, pointer_size: 2, double_size: 4, long_double_size: 4, long_double_align: 1,
+        wchar_size: 2, enum_size: 2, time_t_size: 4, align_8: 1, max_align: 1, ms_bitfields: false },
+    // 16-bit MSP430 (msp430-elf-gcc)
+    Abi { name: "msp430", endian: Endians::Little,
+        int_size: 2, long_size: 4, pointer_size: 2, double_size: 8, long_double_size: 8, long_double_align: 2,
+        wchar_size: 2, enum_size: 2, time_t_size: 4, align_8: 2, max_align: 2, ms_bitfields: false },
+    // Motorola 68000 (m68k-elf-gcc)
+    Abi { name: "m68k", endian: Endians::Big,
+        int_size: 4, long_size: 4, pointer_size: 4, double_size: 8, long_double_size: 12, long_double_align: 2,
+        wchar_size: 4, enum_size: 4, time_t_size: 4, align_8: 2, max_align: 2, ms_bitfields: false },
+    // 32-bit PowerPC EABI
+    Abi { name: "ppc", endian: Endians::Big,
+        int_size: 4, long_size: 4, pointer_size: 4, double_size: 8, long_double_size: 8, long_double_align: 8,
+        wchar_size: 4, enum_size: 4, time_t_size: 4, align_8: 8, max_align: 8, ms_bitfields: false },
+];
+
+/// Finds the ABI called `name`, ignoring case.
+pub fn find_abi(name: &str) -> Option<&'static Abi> {
+    ABIS.iter().find(|abi| abi.name.eq_ignore_ascii_case(name))
+}
+
+/// Gets the ABI used when none is selected.
+pub fn default_abi() -> &'static Abi {
+    find_abi(DEFAULT_ABI).unwrap()
+}
+
+/// Common typedefs from `<stdint.h>` and friends, the Windows SDK and the Linux and BSD kernels as
+/// (typedef, size, display format).
+#[rustfmt::skip]
+const TYPEDEFS: [(&str, usize, &str); 109] = [
+    // <stdint.h>, <stddef.h>, <uchar.h> and <sys/types.h>
+    ("int8_t", 1, format::INT_TYPE), ("uint8_t", 1, format::UINT_TYPE),
+    ("int16_t", 2, format::INT_TYPE), ("uint16_t", 2, format::UINT_TYPE),
+    ("int32_t", 4, format::INT_TYPE), ("uint32_t", 4, format::UINT_TYPE),
+    ("int64_t", 8, format::INT_TYPE), ("uint64_t", 8, format::UINT_TYPE),
+    ("int_least8_t", 1, format::INT_TYPE), ("uint_least8_t", 1, format::UINT_TYPE),
+    ("int_least16_t", 2, format::INT_TYPE), ("uint_least16_t", 2, format::UINT_TYPE),
+    ("int_least32_t", 4, format::INT_TYPE), ("uint_least32_t", 4, format::UINT_TYPE),
+    ("int_least64_t", 8, format::INT_TYPE), ("uint_least64_t", 8, format::UINT_TYPE),
+    ("intmax_t", 8, format::INT_TYPE), ("uintmax_t", 8, format::UINT_TYPE),
+    ("__int128_t", 16, format::HEXLE_TYPE), ("__uint128_t", 16, format::HEXLE_TYPE),
+    ("char16_t", 2, format::UTF16LE_TYPE), ("char32_t", 4, format::HEXLE_TYPE),
+    ("pid_t", 4, format::INT_TYPE),
+    ("uid_t", 4, format::UINT_TYPE), ("gid_t", 4, format::UINT_TYPE),
+    ("mode_t", 4, format::HEXLE_TYPE), ("dev_t", 8, format::HEXLE_TYPE),
+    ("ino_t", 8, format::UINT_TYPE),
+    // Windows SDK
+    ("BYTE", 1, format::UINT_TYPE), ("UCHAR", 1, format::UINT_TYPE),
+    ("CHAR", 1, format::ASCII_TYPE), ("CCHAR", 1, format::ASCII_TYPE),
+    ("BOOLEAN", 1, format::UINT_TYPE), ("INT8", 1, format::INT_TYPE),
+    ("UINT8", 1, format::UINT_TYPE), ("WORD", 2, format::UINT_TYPE),
+    ("USHORT", 2, format::UINT_TYPE), ("SHORT", 2, format::INT_TYPE),
+    ("WCHAR", 2, format::UTF16LE_TYPE), ("INT16", 2, format::INT_TYPE),
+    ("UINT16", 2, format::UINT_TYPE), ("ATOM", 2, format::UINT_TYPE),
+    ("LANGID", 2, format::HEXLE_TYPE), ("DWORD", 4, format::UINT_TYPE),
+    ("ULONG", 4, format::UINT_TYPE), ("LONG", 4, format::INT_TYPE),
+    ("UINT", 4, format::UINT_TYPE), ("INT", 4, format::INT_TYPE),
+    ("BOOL", 4, format::INT_TYPE), ("FLOAT", 4, format::FLOAT_TYPE),
+    ("DWORD32", 4, format::UINT_TYPE), ("UINT32", 4, format::UINT_TYPE),
+    ("INT32", 4, format::INT_TYPE), ("ULONG32", 4, format::UINT_TYPE),
+    ("LONG32", 4, format::INT_TYPE), ("HRESULT", 4, format::HEXLE_TYPE),
+    ("NTSTATUS", 4, format::HEXLE_TYPE), ("COLORREF", 4, format::HEXLE_TYPE),
+    ("LCID", 4, format::HEXLE_TYPE), ("DWORD64", 8, format::UINT_TYPE),
+    ("QWORD", 8, format::UINT_TYPE), ("ULONGLONG", 8, format::UINT_TYPE),
+    ("LONGLONG", 8, format::INT_TYPE), ("DWORDLONG", 8, format::UINT_TYPE),
+    ("UINT64", 8, format::UINT_TYPE), ("INT64", 8, format::INT_TYPE),
+    ("ULONG64", 8, format::UINT_TYPE), ("LONG64", 8, format::INT_TYPE),
+    ("LARGE_INTEGER", 8, format::INT_TYPE), ("ULARGE_INTEGER", 8, format::UINT_TYPE),
+    ("FILETIME", 8, format::FILETIME_TYPE), ("GUID", 16, format::GUID_TYPE),
+    ("UUID", 16, format::GUID_TYPE), ("CLSID", 16, format::GUID_TYPE),
+    // Linux and BSD kernels
+    ("u8", 1, format::UINT_TYPE), ("s8", 1, format::INT_TYPE),
+    ("u16", 2, format::UINT_TYPE), ("s16", 2, format::INT_TYPE),
+    ("u32", 4, format::UINT_TYPE), ("s32", 4, format::INT_TYPE),
+    ("u64", 8, format::UINT_TYPE), ("s64", 8, format::INT_TYPE),
+    ("__u8", 1, format::UINT_TYPE), ("__s8", 1, format::INT_TYPE),
+    ("__u16", 2, format::UINT_TYPE), ("__s16", 2, format::INT_TYPE),
+    ("__u32", 4, format::UINT_TYPE), ("__s32", 4, format::INT_TYPE),
+    ("__u64", 8, format::UINT_TYPE), ("__s64", 8, format::INT_TYPE),
+    ("__le16", 2, format::UINT_TYPE), ("__be16", 2, format::UINTBE_TYPE),
+    ("__le32", 4, format::UINT_TYPE), ("__be32", 4, format::UINTBE_TYPE),
+    ("__le64", 8, format::UINT_TYPE), ("__be64", 8, format::UINTBE_TYPE),
+    ("__sum16", 2, format::HEXLE_TYPE), ("__wsum", 4, format::HEXLE_TYPE),
+    ("sector_t", 8, format::UINT_TYPE),
+    ("umode_t", 2, format::HEXLE_TYPE), ("gfp_t", 4, format::HEXLE_TYPE),
+    ("time64_t", 8, format::UNIX_TYPE), ("u_char", 1, format::UINT_TYPE),
+    ("u_short", 2, format::UINT_TYPE), ("u_int", 4, format::UINT_TYPE),
+    ("u_int8_t", 1, format::UINT_TYPE),
+    ("u_int16_t", 2, format::UINT_TYPE), ("u_int32_t", 4, format::UINT_TYPE),
+    ("u_int64_t", 8, format::UINT_TYPE),
+];
+
+/// Typedefs the size of a pointer on every target as (typedef, display format).
+#[rustfmt::skip]
+const POINTER_TYPEDEFS: [(&str, &str); 17] = [
+    ("intptr_t", format::INT_TYPE), ("uintptr_t", format::HEXLE_TYPE),
+    ("size_t", format::UINT_TYPE), ("ssize_t", format::INT_TYPE),
+    ("ptrdiff_t", format::INT_TYPE), ("ULONG_PTR", format::UINT_TYPE),
+    ("LONG_PTR", format::INT_TYPE), ("UINT_PTR", format::UINT_TYPE),
+    ("INT_PTR", format::INT_TYPE), ("DWORD_PTR", format::UINT_TYPE),
+    ("SIZE_T", format::UINT_TYPE), ("SSIZE_T", format::INT_TYPE),
+    ("HANDLE", format::HEXLE_TYPE), ("PVOID", format::HEXLE_TYPE),
+    ("LPVOID", format::HEXLE_TYPE), ("HMODULE", format::HEXLE_TYPE),
+    ("HINSTANCE", format::HEXLE_TYPE),
+];
+
+/// Typedefs the size of a `long` on the target as (typedef, display format).
+const LONG_TYPEDEFS: [(&str, &str); 3] = [
+    ("u_long", format::UINT_TYPE),
+    ("off_t", format::INT_TYPE),
+    ("loff_t", format::INT_TYPE),
+];
+
+/// Typedefs whose byte order does not depend on the target, e.g `__be32`.
+const FIXED_ENDIAN_TYPEDEFS: [&str; 6] =
+    ["__le16", "__le32", "__le64", "__be16", "__be32", "__be64"];
+
+/// Typedefs of structs and unions whose alignment is smaller than their size, as (typedef, alignment).
+///
+/// Every other type is aligned to its size.
+const ALIGNMENTS: [(&str, usize); 4] = [("FILETIME", 4), ("GUID", 4), ("UUID", 4), ("CLSID", 4)];
+/// Typedefs aligned to their size even when it is larger than the largest alignment of the target, as GCC, Clang and
+/// rustc align 128-bit integers to 16 bytes whatever the alignment of `long double`.
+const OVERALIGNED_TYPEDEFS: [&str; 2] = ["__int128_t", "__uint128_t"];
+
+/// Gets the size, alignment and default display format of a C type on the target `abi`, either a keyword type or a
+/// known typedef.
+///
+/// Qualifiers such as `const` are ignored and keyword types may be written in any of their equivalent forms,
+/// e.g `long int` or `signed long`. Display formats are big endian on big endian targets, except for typedefs with a
+/// fixed byte order.
+pub fn lookup(type_name: &str, abi: &Abi) -> Option<CType> {
+    let canonical_name = canonical_type_name(type_name);
+
+    let (size, align, display_format) = if let Some(basic_type) = basic_type(&canonical_name, abi) {
+        basic_type
+    } else if let Some((_, display_format)) = POINTER_TYPEDEFS
+        .iter()
+        .find(|(name, _)| *name == canonical_name)
+    {
+        (abi.pointer_size, abi.pointer_size, *display_format)
+    } else if let Some((_, display_format)) = LONG_TYPEDEFS
+        .iter()
+        .find(|(name, _)| *name == canonical_name)
+    {
+        (abi.long_size, abi.long_size, *display_format)
+    } else if canonical_name == "time_t" {
+        let align = match abi.time_t_size {
+            8 => abi.align_8,
+            size => size,
+        };
+
+        (abi.time_t_size, align, format::UNIX_TYPE)
+    } else {
+        let (name, size, display_format) = TYPEDEFS
+            .iter()
+            .find(|(name, _, _)| *name == canonical_name)?;
+        let align = ALIGNMENTS
+            .iter()
+            .find(|(aligned_name, _)| aligned_name == name)
+            .map_or(*size, |(_, align)| *align);
+
+        (*size, align, *display_format)
+    };
+
+    Some(CType {
+        size,
+        align: if OVERALIGNED_TYPEDEFS.contains(&&canonical_name[..]) {
+            align
+        } else {
+            align.min(abi.max_align)
+        },
+        display_format: if FIXED_ENDIAN_TYPEDEFS.contains(&&canonical_name[..]) {
+            display_format
+        } else {
+            native_format(display_format, abi.endian)
+        },
+    })
+}
+
+/// Gets the size, alignment and little endian display format of a keyword type in its canonical form.
+fn basic_type(canonical_name: &str, abi: &Abi) -> Option<(usize, usize, &'static str)> {
+    let basic_type = match canonical_name {
+        "char" => (1, 1, format::ASCII_TYPE),
+        "signed char" => (1, 1, format::INT_TYPE),
+        "unsigned char" | "_Bool" | "bool" => (1, 1, format::UINT_TYPE),
+        "short" => (2, 2, format::INT_TYPE),
+        "unsigned short" => (2, 2, format::UINT_TYPE),
+        "int" => (abi.int_size, abi.int_size, format::INT_TYPE),
+        "unsigned int" => (abi.int_size, abi.int_size, format::UINT_TYPE),
+        "long" => (abi.long_size, abi.long_size, format::INT_TYPE),
+        "unsigned long" => (abi.long_size, abi.long_size, format::UINT_TYPE),
+        "long long" => (8, abi.align_8, format::INT_TYPE),
+        "unsigned long long" => (8, abi.align_8, format::UINT_TYPE),
+        "float" => (4, 4, format::FLOAT_TYPE),
+        "double" if abi.double_size == 8 => (8, abi.align_8, format::FLOAT_TYPE),
+        "double" => (abi.double_size, abi.double_size, format::FLOAT_TYPE),
+        "long double" if abi.long_double_size == abi.double_size => (
+            abi.long_double_size,
+            abi.long_double_align,
+            format::FLOAT_TYPE,
+        ),
+        "long double" => (
+            abi.long_double_size,
+            abi.long_double_align,
+            format::HEX_TYPE,
+        ),
+        "wchar_t" if abi.wchar_size == 2 => (2, 2, format::UTF16LE_TYPE),
+        "wchar_t" => (abi.wchar_size, abi.wchar_size, format::UINT_TYPE),
+        _ => return None,
+    };
+
+    Some(basic_type)
+}
+
+/// Converts a little endian display format into the format used on a target with the byte order `endian`.
+///
+/// Formats without a big endian form, e.g Unix timestamps, are shown as big endian integers on big endian targets.
+pub fn native_format(display_format: &'static str, endian: Endians) -> &'static str {
+    if endian == Endians::Little {
+        return display_format;
+    }
+
+    match display_format {
+        format::UINT_TYPE => format::UINTBE_TYPE,
+        format::INT_TYPE | format::UNIX_TYPE => format::INTBE_TYPE,
+        format::FLOAT_TYPE => format::FLOATBE_TYPE,
+        format::HEXLE_TYPE => format::HEX_TYPE,
+        format::UTF16LE_TYPE => format::UTF16BE_TYPE,
+        _ => display_format,
+    }
+}
+
+/// Removes qualifiers from a type name and converts keyword types into the form used by `basic_type`.
+fn canonical_type_name(type_name: &str) -> String {
+    let words: Vec<&str> = type_name
+        .split_ascii_whitespace()
+        .filter(|word| !matches!(*word, "const" | "volatile" | "register" | "restrict"))
+        .collect();
+
+    let keyword_type = words.iter().all(|word| {
+        matches!(
+            *word,
+            "signed" | "unsigned" | "char" | "short" | "int" | "long" | "float" | "double"
+        )
+    });
+
+    if !keyword_type || words.is_empty() {
+        return words.join(" ");
+    }
+
+    let unsigned = words.contains(&"unsigned");
+    let signed = words.contains(&"signed");
+    let base = match words.iter().filter(|word| **word == "long").count() {
+        _ if words.contains(&"char") => "char",
+        _ if words.contains(&"short") => "short",
+        _ if words.contains(&"float") => "float",
+        _ if words.contains(&"double") && words.contains(&"long") => "long double",
+        _ if words.contains(&"double") => "double",
+        0 => "int",
+        1 => "long",
+        _ => "long long",
+    };
+
+    match (unsigned, signed, base) {
+        (true, _, base) => format!("unsigned {}", base),
+        (false, true, "char") => String::from("signed char"),
+        (_, _, base) => base.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyword_types_are_found_in_any_form() {
+        let abi = default_abi();
+
+        for type_name in ["long int", "signed long", "long signed int", "const long"] {
+            let c_type = lookup(type_name, abi).unwrap();
+            assert_eq!(c_type.size, 4);
+            assert_eq!(c_type.display_format, format::INT_TYPE);
+        }
+
+        assert_eq!(
+            lookup("unsigned", abi).unwrap().display_format,
+            format::UINT_TYPE
+        );
+        assert_eq!(lookup("long long unsigned int", abi).unwrap().size, 8);
+        assert_eq!(
+            lookup("long double", find_abi("lp64").unwrap())
+                .unwrap()
+                .size,
+            16
+        );
+        assert_eq!(
+            lookup("signed char", abi).unwrap().display_format,
+            format::INT_TYPE
+        );
+        assert_eq!(
+            lookup("char", abi).unwrap().display_format,
+            format::ASCII_TYPE
+        );
+    }
+
+    #[test]
+    fn typedefs_have_default_display_formats() {
+        let abi = default_abi();
+
+        let typedefs = [
+            ("uint16_t", 2, format::UINT_TYPE),
+            ("volatile DWORD", 4, format::UINT_TYPE),
+            ("FILETIME", 8, format::FILETIME_TYPE),
+            ("GUID", 16, format::GUID_TYPE),
+            ("__be32", 4, format::UINTBE_TYPE),
+            ("time_t", 8, format::UNIX_TYPE),
+        ];
+
+        for (type_name, size, display_format) in typedefs {
+            let c_type = lookup(type_name, abi).unwrap();
+            assert_eq!(c_type.size, size, "{}", type_name);
+            assert_eq!(c_type.display_format, display_format, "{}", type_name);
+        }
+    }
+
+    #[test]
+    fn unknown_types_are_not_found() {
+        let abi = default_abi();
+
+        assert!(lookup("struct Unknown", abi).is_none());
+        assert!(lookup("dword", abi).is_none());
+        assert!(lookup("", abi).is_none());
+    }
+
+    #[test]
+    fn types_are_aligned_to_their_size_unless_listed() {
+        let abi = default_abi();
+
+        assert_eq!(lookup("double", abi).unwrap().align, 8);
+        assert_eq!(lookup("uint16_t", abi).unwrap().align, 2);
+        assert_eq!(lookup("GUID", abi).unwrap().align, 4);
+        assert_eq!(lookup("FILETIME", abi).unwrap().align, 4);
+    }
+
+    #[test]
+    fn long_typedefs_follow_the_abi() {
+        let size =
+            |type_name: &str, abi: &str| lookup(type_name, find_abi(abi).unwrap()).unwrap().size;
+
+        for type_name in ["off_t", "loff_t", "time_t"] {
+            assert_eq!(size(type_name, "lp64"), 8, "{}", type_name);
+            assert_eq!(size(type_name, "ilp32"), 4, "{}", type_name);
+            assert_eq!(size(type_name, "arm"), 4, "{}", type_name);
+        }
+        assert_eq!(size("off_t", "win32"), 4);
+        assert_eq!(size("time_t", "win32"), 8);
+    }
+
+    #[test]
+    fn time_t_is_aligned_to_its_size() {
+        let align = |abi: &str| lookup("time_t", find_abi(abi).unwrap()).unwrap().align;
+
+        assert_eq!(align("win32"), 8);
+        assert_eq!(align("ilp32"), 4);
+        assert_eq!(align("m68k"), 2);
+    }
+
+    #[test]
+    fn sizes_and_alignments_follow_the_abi() {
+        let c_type =
+            |type_name: &str, abi: &str| lookup(type_name, find_abi(abi).unwrap()).unwrap();
+
+        assert_eq!(c_type("size_t", "avr").size, 2);
+        assert_eq!(c_type("HANDLE", "lp64").size, 8);
+        assert_eq!(c_type("long", "lp64").size, 8);
+        assert_eq!(c_type("int", "msp430").size, 2);
+        assert_eq!(c_type("long long", "ilp32").align, 4);
+        assert_eq!(c_type("long double", "lp64").align, 16);
+        assert_eq!(c_type("uint64_t", "m68k").align, 2);
+        assert_eq!(c_type("wchar_t", "lp64").display_format, format::UINT_TYPE);
+    }
+
+    #[test]
+    fn big_endian_targets_use_big_endian_formats() {
+        let ppc = find_abi("ppc").unwrap();
+
+        assert_eq!(
+            lookup("uint32_t", ppc).unwrap().display_format,
+            format::UINTBE_TYPE
+        );
+        assert_eq!(
+            lookup("double", ppc).unwrap().display_format,
+            format::FLOATBE_TYPE
+        );
+        assert_eq!(
+            lookup("time_t", ppc).unwrap().display_format,
+            format::INTBE_TYPE
+        );
+        assert_eq!(
+            lookup("HANDLE", ppc).unwrap().display_format,
+            format::HEX_TYPE
+        );
+        assert_eq!(
+            lookup("__le32", ppc).unwrap().display_format,
+            format::UINT_TYPE
+        );
+    }
+
+    #[test]
+    fn int128_is_aligned_to_16_bytes_on_every_abi() {
+        for abi in ABIS.iter() {
+            assert_eq!(lookup("__int128_t", abi).unwrap().align, 16);
+            assert_eq!(lookup("__uint128_t", abi).unwrap().size, 16);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Module for printing a disassembly listing of the code fields of a structure.
+use crate::format;
+use crate::gram_parse;
+use crate::theme;
+use hex::ToHex;
+use std::collections::HashMap;
+
+/// Prints the address, machine code and instruction of every instruction in each code field of the grammar.
+///
+/// Branch targets that land on another grammar field are annotated with the ID and name of that field.
+pub fn print_code_listings(
+    parsed_gram: &gram_parse::Grammar,
+    field_hashmap: &HashMap<String, Vec<u8>>,
+    struct_offset: usize,
+    asm_syntax: format::AsmSyntaxes,
+    theme: &theme::Theme,
+) {
+    // Start offset, end offset and index of every field, used to resolve branch targets.
+    let mut field_ranges: Vec<(usize, usize, usize)> = Vec::new();
+    let mut field_offset = struct_offset;
+
+    for (index, field) in parsed_gram.fields.iter().enumerate() {
+        field_ranges.push((field_offset, field_offset + field.size, index));
+        field_offset += field.size;
+    }
+
+    for (start, _, index) in field_ranges.iter() {
+        let field = &parsed_gram.fields[*index];
+
+        if !format::is_code_format(&field.display_format) {
+            continue;
+        }
+
+        let machine_code = match field_hashmap.get(&field.name) {
+            Some(machine_code) => machine_code,
+            None => continue,
+        };
+
+        let mut disassembly = format::DissassOutput::new();
+        disassembly.format_code(
+            &field.display_format,
+            machine_code,
+            *start as u64,
+            asm_syntax,
+        );
+
+        let field_colour = Some(theme.field_colour(*index, field));
+        let bytes_width = disassembly
+            .lines
+            .iter()
+            .map(|line| line.bytes.len() * 2)
+            .max()
+            .unwrap_or(0);
+
+        println!(
+            "{}",
+            theme.paint(
+                &format!(
+                    "{:03X} {} ({}, {} instructions)",
+                    index, field.name, field.display_format, disassembly.line_count
+                ),
+                field_colour
+            )
+        );
+
+        for line in disassembly.lines.iter() {
+            let annotation = line
+                .branch_target
+                .and_then(|target| {
+                    field_ranges.iter().find(|(start, end, target_index)| {
+                        target_index != index && (*start..*end).contains(&(target as usize))
+                    })
+                })
+                .map(|(target_start, _, target_index)| {
+                    let target_field = &parsed_gram.fields[*target_index];
+                    let target_offset = line.branch_target.unwrap_or(0) as usize - target_start;
+                    let annotation = match target_offset {
+                        0 => format!("  ; -> {:03X} {}", target_index, target_field.name),
+                        _ => format!(
+                            "  ; -> {:03X} {}+{:#X}",
+                            target_index, target_field.name, target_offset
+                        ),
+                    };
+
+                    theme.paint(
+                        &annotation,
+                        Some(theme.field_colour(*target_index, target_field)),
+                    )
+                })
+                .unwrap_or_default();
+
+            println!(
+                "  {:08X}  {:width$}  {}{}",
+                line.address,
+                line.bytes.encode_hex_upper::<String>(),
+                line.instruction,
+                annotation,
+                width = bytes_width
+            );
+        }
+
+        println!();
+    }
+}
@@ -0,0 +1,1312 @@
+//! Module for converting a struct described by the DWARF debug information of an ELF file to the grammar format.
+//!
+//! Unlike C struct conversion, which lays types out itself, the offset of every member, the size of every type and
+//! the bits of every bitfield are read from the debug information written by the compiler.
+use crate::arg_parse;
+use crate::c_types;
+use crate::format;
+use crate::struct_convert;
+use object::{Object, ObjectSection};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::prelude::*;
+
+/// Reader of the DWARF sections of the ELF file.
+type DwarfReader<'a> = gimli::EndianSlice<'a, gimli::RunTimeEndian>;
+
+/// Types nested deeper than this are assumed to contain themselves.
+const MAX_TYPE_DEPTH: usize = 32;
+
+/// The kinds of type a member can have.
+#[derive(Clone)]
+enum TypeKinds {
+    /// An integer, float or character type shown in the display format.
+    Base(&'static str),
+    /// A pointer or reference.
+    Pointer,
+    /// An enum with the name and value of each enumerator, and whether it is signed.
+    Enum(Vec<(String, i64)>, bool),
+    /// A struct or union, referred to by the name of its `DwarfRecord`.
+    Record(String),
+    /// A type without a display format, e.g `void` or `long double`.
+    Bytes,
+}
+
+/// The type of a member.
+#[derive(Clone)]
+struct DwarfType {
+    /// The name of the type shown as the data type of a field, e.g `uint32_t` or `struct point *`.
+    name: String,
+    /// Size of a single element of the type in bytes.
+    size: usize,
+    /// The length of each array dimension, empty if the type is not an array.
+    dimensions: Vec<usize>,
+    kind: TypeKinds,
+}
+
+/// A member of a struct or union at the offset given by the debug information.
+struct DwarfMember {
+    name: String,
+    /// Offset of the member from the start of the record in bytes.
+    offset: usize,
+    member_type: DwarfType,
+    /// The offset in bits from the start of the record and the width of a bitfield.
+    bits: Option<(usize, usize)>,
+}
+
+/// A struct or union definition.
+struct DwarfRecord {
+    /// `struct` or `union`.
+    kind: &'static str,
+    name: String,
+    size: usize,
+    members: Vec<DwarfMember>,
+}
+
+/// Holds the struct read from the DWARF debug information and the converted grammar contents.
+pub struct DwarfStruct {
+    /// Name of the struct (Also name of grammar).
+    pub name: String,
+    /// The struct and every struct and union it contains, created by `parse_dwarf`. Records come after the records
+    /// they contain, so the struct is last.
+    records: Vec<DwarfRecord>,
+    /// The byte order of the ELF file.
+    endian: c_types::Endians,
+    /// String containing the resulting grammar contents.
+    pub grammar_contents: String,
+}
+
+impl DwarfStruct {
+    pub fn new() -> Self {
+        Self {
+            name: String::from(""),
+            records: Vec::new(),
+            endian: c_types::Endians::Little,
+            grammar_contents: String::from(""),
+        }
+    }
+
+    /// Reads the struct called `cmd_args.root` from the DWARF debug information of the ELF file at
+    /// `cmd_args.elf_filepath`.
+    ///
+    /// The root may be the name of a struct, union or class, or of a typedef of one. The first definition found is
+    /// read, along with every struct and union it contains. Compressed debug sections are supported.
+    pub fn parse_dwarf(&mut self, cmd_args: &arg_parse::CMDArgParse) -> Result<&mut Self, ()> {
+        let elf_filepath = &cmd_args.elf_filepath;
+
+        if cmd_args.root.is_empty() {
+            serror!(format!(
+                "The struct to convert from {} must be named with {}",
+                elf_filepath,
+                arg_parse::ROOT_FLAG
+            ));
+            return Err(());
+        }
+
+        let elf_data = fs::read(elf_filepath).map_err(|e| {
+            serror!(format!(
+                "Error opening file: {}, because:{}",
+                elf_filepath, e
+            ))
+        })?;
+
+        let elf = object::File::parse(&*elf_data).map_err(|e| {
+            serror!(format!(
+                "Invalid ELF file: {}, because: {}",
+                elf_filepath, e
+            ))
+        })?;
+
+        if elf.section_by_name(".debug_info").is_none() {
+            serror!(format!(
+                "ELF file: {}, has no DWARF debug information, it must be built with -g",
+                elf_filepath
+            ));
+            return Err(());
+        }
+
+        let (endian, runtime_endian) = if elf.is_little_endian() {
+            (c_types::Endians::Little, gimli::RunTimeEndian::Little)
+        } else {
+            (c_types::Endians::Big, gimli::RunTimeEndian::Big)
+        };
+        self.endian = endian;
+
+        let sections = gimli::DwarfSections::load(|id| -> Result<Cow<[u8]>, ()> {
+            match elf.section_by_name(id.name()) {
+                Some(section) => section.uncompressed_data().map_err(|e| {
+                    serror!(format!(
+                        "Could not read section: {}, because: {}",
+                        id.name(),
+                        e
+                    ))
+                }),
+                None => Ok(Cow::Borrowed(&[][..])),
+            }
+        })?;
+        let dwarf = sections.borrow(|section| gimli::EndianSlice::new(section, runtime_endian));
+
+        let mut units = dwarf.units();
+
+        while let Some(header) = units.next().map_err(dwarf_error)? {
+            let unit = dwarf.unit(header).map_err(dwarf_error)?;
+            let mut reader = TypeReader {
+                dwarf: &dwarf,
+                unit: &unit,
+                endian,
+                records: Vec::new(),
+                names: HashMap::new(),
+                anonymous_count: 0,
+            };
+
+            if let Some(offset) = reader.find_root(&cmd_args.root)? {
+                self.name = reader.record(offset, &cmd_args.root, 0)?;
+                self.records = reader.records;
+
+                return Ok(self);
+            }
+        }
+
+        serror!(format!(
+            "Struct: {}, is not defined in the DWARF debug information of {}",
+            cmd_args.root, elf_filepath
+        ));
+        Err(())
+    }
+
+    /// Builds the contents of the output grammar file line by line.
+    ///
+    /// The fields of the struct are written as `[[fields]]`, followed by the struct and every struct and union it
+    /// contains as `[[types]]`.
+    pub fn build_grammar_contents(&mut self) -> Result<&mut Self, ()> {
+        self.grammar_contents.push_str("[metadata]\r\n");
+        self.grammar_contents
+            .push_str(&format!("\tname = '{}'\r\n", self.name)[..]);
+        self.grammar_contents
+            .push_str("\tvariable_size_fields = [['','','','']]\r\n");
+        self.grammar_contents
+            .push_str("\tmultiply_fields = [['','']]\r\n");
+        self.grammar_contents
+            .push_str(&format!("\tendian = '{}'\r\n", self.endian.name()));
+
+        let root = match self.records.last() {
+            Some(root) => root,
+            None => {
+                serror!("No struct has been read from the DWARF debug information");
+                return Err(());
+            }
+        };
+        let mut contents = self.record_contents("fields", root)?;
+
+        for record in self.records.iter() {
+            contents.push_str("\r\n[[types]]\r\n");
+            contents.push_str(&format!("\tname = '{}'\r\n", record.name));
+            contents.push_str(&format!("\tkind = '{}'\r\n", record.kind));
+            contents.push_str(&format!("\tsize = {:#04X}\r\n", record.size));
+            contents.push_str(&self.record_contents("types.fields", record)?);
+        }
+
+        self.grammar_contents.push_str(&contents);
+
+        Ok(self)
+    }
+
+    /// Builds the grammar entries of the members of `record` in the array of tables `table`.
+    ///
+    /// Padding fields are added wherever the members of a struct leave a gap and at its end. Bitfields sharing bytes
+    /// become one field with a bit range for each.
+    fn record_contents(&self, table: &str, record: &DwarfRecord) -> Result<String, ()> {
+        let padded = record.kind == "struct";
+        let mut contents = String::new();
+        let mut padding_count = 0;
+        let mut end = 0;
+        // Bitfields waiting to be written, with the bytes they share.
+        let mut bitfields: Vec<&DwarfMember> = Vec::new();
+        let mut bitfields_start = 0;
+        let mut bitfields_end = 0;
+
+        for member in record.members.iter() {
+            if let Some((bit, width)) = member.bits {
+                if !bitfields.is_empty() && bit / 8 < bitfields_end {
+                    bitfields_end = bitfields_end.max((bit + width).div_ceil(8));
+                    bitfields.push(member);
+                    continue;
+                }
+            }
+
+            if !bitfields.is_empty() {
+                contents.push_str(&self.bitfield_contents(
+                    table,
+                    &bitfields,
+                    bitfields_start,
+                    bitfields_end,
+                )?);
+                end = end.max(bitfields_end);
+                bitfields.clear();
+            }
+
+            if padded && member.offset < end {
+                serror!(format!(
+                    "Member: {}.{}, at offset {:#X} overlaps the member before it",
+                    record.name, member.name, member.offset
+                ));
+                return Err(());
+            }
+
+            if padded && member.offset > end {
+                padding_count += 1;
+                contents.push_str(&struct_convert::padding_contents(
+                    table,
+                    padding_count,
+                    member.offset - end,
+                ));
+            }
+
+            match member.bits {
+                Some((bit, width)) => {
+                    bitfields_start = bit / 8;
+                    bitfields_end = (bit + width).div_ceil(8);
+                    bitfields.push(member);
+                }
+                None => {
+                    let (field_contents, size) = self.field_contents(table, member);
+                    contents.push_str(&field_contents);
+                    end = end.max(member.offset + size);
+                }
+            }
+        }
+
+        if !bitfields.is_empty() {
+            contents.push_str(&self.bitfield_contents(
+                table,
+                &bitfields,
+                bitfields_start,
+                bitfields_end,
+            )?);
+            end = end.max(bitfields_end);
+        }
+
+        if padded && record.size > end {
+            padding_count += 1;
+            contents.push_str(&struct_convert::padding_contents(
+                table,
+                padding_count,
+                record.size - end,
+            ));
+        }
+
+        Ok(contents)
+    }
+
+    /// Builds the grammar entry of a member in the array of tables `table`, returning it with the size of the member.
+    ///
+    /// Arrays of text or single bytes become one field, other arrays become a field with a `count`. Structs and unions
+    /// become a field with a `type`.
+    fn field_contents(&self, table: &str, member: &DwarfMember) -> (String, usize) {
+        let member_type = &member.member_type;
+        let element_count: usize = member_type.dimensions.iter().product();
+        let dimensions: String = member_type
+            .dimensions
+            .iter()
+            .map(|length| format!("[{}]", length))
+            .collect();
+
+        let (display_format, type_name) = match &member_type.kind {
+            TypeKinds::Base(display_format) => (*display_format, None),
+            TypeKinds::Pointer => (
+                c_types::native_format(format::HEXLE_TYPE, self.endian),
+                None,
+            ),
+            TypeKinds::Enum(_, signed) => (
+                c_types::native_format(
+                    if *signed {
+                        format::INT_TYPE
+                    } else {
+                        format::UINT_TYPE
+                    },
+                    self.endian,
+                ),
+                None,
+            ),
+            TypeKinds::Record(name) => (format::HEX_TYPE, Some(name)),
+            TypeKinds::Bytes => (format::HEX_TYPE, None),
+        };
+
+        let mut size = member_type.size;
+        let mut data_type = member_type.name.clone();
+        let mut count = None;
+
+        if !member_type.dimensions.is_empty() {
+            if type_name.is_none() && (format::is_text_format(display_format) || size == 1) {
+                size *= element_count;
+                data_type.push_str(&dimensions);
+            } else {
+                count = Some(element_count);
+            }
+        }
+
+        // Arrays of single bytes are shown as one hex field rather than as integers.
+        let display_format = if count.is_none()
+            && !member_type.dimensions.is_empty()
+            && !format::is_text_format(display_format)
+        {
+            format::HEX_TYPE
+        } else {
+            display_format
+        };
+
+        let mut contents = format!("\r\n[[{}]]\r\n", table);
+        contents.push_str(&format!("\tname = '{}'\r\n", member.name));
+        contents.push_str(&format!("\tsize = {:#04X}\r\n", size));
+        if let Some(count) = count {
+            contents.push_str(&format!("\tcount = {}\r\n", count));
+        }
+        if let Some(type_name) = type_name {
+            contents.push_str(&format!("\ttype = '{}'\r\n", type_name));
+        }
+        contents.push_str(&format!("\tdata_type = '{}'\r\n", data_type));
+        contents.push_str(&format!("\tdisplay_format = '{}'\r\n", display_format));
+        contents.push_str(&format!(
+            "\tdescription = '{}'\r\n",
+            description(member_type)
+        ));
+
+        (contents, size * count.unwrap_or(1))
+    }
+
+    /// Builds the grammar entry of `bitfields` sharing the bytes from `start` to `end` in the array of tables `table`.
+    ///
+    /// The bytes become one field with the `bits` display format and a bit range for each bitfield, given by its
+    /// offset in bits from the start of the record and its width.
+    fn bitfield_contents(
+        &self,
+        table: &str,
+        bitfields: &[&DwarfMember],
+        start: usize,
+        end: usize,
+    ) -> Result<String, ()> {
+        let first = bitfields[0];
+        let last = bitfields[bitfields.len() - 1];
+        let size = end - start;
+
+        if size > 8 {
+            serror!(format!(
+                "Bitfield members: {} to {}, share more than 8 bytes",
+                first.name, last.name
+            ));
+            return Err(());
+        }
+
+        let (name, description) = if bitfields.len() == 1 {
+            (first.name.clone(), description(&first.member_type))
+        } else {
+            (
+                format!("{}_to_{}", first.name, last.name),
+                String::from("N/A"),
+            )
+        };
+
+        let display_format = match self.endian {
+            c_types::Endians::Little => format::BITS_TYPE,
+            c_types::Endians::Big => format::BITSBE_TYPE,
+        };
+
+        let mut contents = format!("\r\n[[{}]]\r\n", table);
+        contents.push_str(&format!("\tname = '{}'\r\n", name));
+        contents.push_str(&format!("\tsize = {:#04X}\r\n", size));
+        contents.push_str(&format!("\tdata_type = '{}'\r\n", first.member_type.name));
+        contents.push_str(&format!("\tdisplay_format = '{}'\r\n", display_format));
+        contents.push_str(&format!("\tdescription = '{}'\r\n", description));
+
+        for member in bitfields.iter() {
+            let (bit, width) = member.bits.unwrap_or_default();
+            // Bits are counted up from the least significant bit of the field, which is the first bit allocated on a
+            // little endian target and the last on a big endian one.
+            let bit = bit - start * 8;
+            let offset = match self.endian {
+                c_types::Endians::Little => bit,
+                c_types::Endians::Big => size * 8 - bit - width,
+            };
+            let signed = match &member.member_type.kind {
+                TypeKinds::Base(display_format) => {
+                    *display_format == format::INT_TYPE || *display_format == format::INTBE_TYPE
+                }
+                TypeKinds::Enum(_, signed) => *signed,
+                _ => false,
+            };
+
+            contents.push_str(&format!("\r\n[[{}.bits]]\r\n", table));
+            contents.push_str(&format!("\tname = '{}'\r\n", member.name));
+            contents.push_str(&format!("\toffset = {}\r\n", offset));
+            contents.push_str(&format!("\twidth = {}\r\n", width));
+            if signed {
+                contents.push_str("\tsigned = true\r\n");
+            }
+        }
+
+        Ok(contents)
+    }
+
+    /// Writes the newly created grammar contents in `self.grammar_contents` to a toml file specified by `output_path`.
+    pub fn write_grammar_file(&mut self, output_path: &str) -> Result<&mut Self, ()> {
+        let mut grammar_file = fs::File::create(output_path).map_err(|e| {
+            serror!(format!(
+                "Could not create/open file {}, because {} ",
+                output_path, e
+            ))
+        })?;
+
+        grammar_file
+            .write_all(self.grammar_contents.as_bytes())
+            .map_err(|e| {
+                serror!(format!(
+                    "Could not write to file: {}, because {}",
+                    output_path, e
+                ))
+            })?;
+
+        println!(
+            "[+] Successfully converted DWARF struct {} to grammar file {}",
+            self.name, output_path
+        );
+
+        Ok(self)
+    }
+}
+
+/// Reads the types of a single compilation unit.
+struct TypeReader<'a, 'b> {
+    dwarf: &'a gimli::Dwarf<DwarfReader<'b>>,
+    unit: &'a gimli::Unit<DwarfReader<'b>>,
+    endian: c_types::Endians,
+    /// The structs and unions read so far, each after the records it contains.
+    records: Vec<DwarfRecord>,
+    /// The name given to the record at each offset, so each record is read once.
+    names: HashMap<gimli::UnitOffset, String>,
+    /// Number of anonymous struct and union members so far, used to generate unique names for them.
+    anonymous_count: usize,
+}
+
+impl TypeReader<'_, '_> {
+    /// Finds the definition of the struct, union or class called `root`, or the one named by the typedef `root`.
+    fn find_root(&self, root: &str) -> Result<Option<gimli::UnitOffset>, ()> {
+        let mut entries = self.unit.entries();
+
+        while let Some((_, entry)) = entries.next_dfs().map_err(dwarf_error)? {
+            let offset = match entry.tag() {
+                gimli::DW_TAG_structure_type
+                | gimli::DW_TAG_class_type
+                | gimli::DW_TAG_union_type => entry.offset(),
+                gimli::DW_TAG_typedef => match self.strip_qualifiers(self.type_offset(entry)?)? {
+                    Some(offset) => offset,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            if self.name(entry)?.as_deref() != Some(root) {
+                continue;
+            }
+
+            let record = self.entry(offset)?;
+            let is_record = matches!(
+                record.tag(),
+                gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type | gimli::DW_TAG_union_type
+            );
+
+            if is_record
+                && record
+                    .attr_value(gimli::DW_AT_declaration)
+                    .map_err(dwarf_error)?
+                    .is_none()
+            {
+                return Ok(Some(offset));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the struct, union or class at `offset` and the records it contains, returning the name of its record.
+    ///
+    /// `anonymous_name` is the name given to the record if it does not have one. Members are sorted by their offset,
+    /// as compilers of some languages reorder them.
+    fn record(
+        &mut self,
+        offset: gimli::UnitOffset,
+        anonymous_name: &str,
+        depth: usize,
+    ) -> Result<String, ()> {
+        if let Some(name) = self.names.get(&offset) {
+            return Ok(name.clone());
+        }
+
+        check_depth(anonymous_name, depth)?;
+
+        let entry = self.entry(offset)?;
+        let kind = match entry.tag() {
+            gimli::DW_TAG_union_type => "union",
+            _ => "struct",
+        };
+        let mut name = self
+            .name(&entry)?
+            .unwrap_or_else(|| anonymous_name.to_string());
+
+        if entry
+            .attr_value(gimli::DW_AT_declaration)
+            .map_err(dwarf_error)?
+            .is_some()
+        {
+            serror!(format!(
+                "Struct or union: {}, is only declared, its definition is not in the debug information",
+                name
+            ));
+            return Err(());
+        }
+
+        let size = match self.udata(&entry, gimli::DW_AT_byte_size)? {
+            Some(size) => size,
+            None => {
+                serror!(format!("Struct or union: {}, does not have a size", name));
+                return Err(());
+            }
+        };
+
+        // Records with the same name in different scopes, e.g C++ namespaces, are given unique names.
+        if self.names.values().any(|used_name| *used_name == name) {
+            let mut number = 2;
+            while self
+                .names
+                .values()
+                .any(|used_name| *used_name == format!("{}_{}", name, number))
+            {
+                number += 1;
+            }
+            name = format!("{}_{}", name, number);
+        }
+        self.names.insert(offset, name.clone());
+
+        let mut members: Vec<DwarfMember> = Vec::new();
+        let mut tree = self.unit.entries_tree(Some(offset)).map_err(dwarf_error)?;
+        let root = tree.root().map_err(dwarf_error)?;
+        let mut children = root.children();
+
+        while let Some(child) = children.next().map_err(dwarf_error)? {
+            let member = child.entry();
+
+            match member.tag() {
+                gimli::DW_TAG_member | gimli::DW_TAG_inheritance => (),
+                _ => continue,
+            }
+
+            // Static members are declared in the record but not stored in it.
+            if member
+                .attr_value(gimli::DW_AT_declaration)
+                .map_err(dwarf_error)?
+                .is_some()
+                || member
+                    .attr_value(gimli::DW_AT_external)
+                    .map_err(dwarf_error)?
+                    .is_some()
+            {
+                continue;
+            }
+
+            let member_name = match self.name(member)? {
+                Some(member_name) => member_name,
+                None => {
+                    self.anonymous_count += 1;
+                    format!("anonymous{}", self.anonymous_count)
+                }
+            };
+            let member_type = self.member_type(
+                self.type_offset(member)?,
+                &format!("{}_{}", name, member_name),
+                depth + 1,
+            )?;
+            // Base classes are named after their class.
+            let member_name = match (member.tag(), &member_type.kind) {
+                (gimli::DW_TAG_inheritance, TypeKinds::Record(base_name)) => base_name.clone(),
+                _ => member_name,
+            };
+
+            let member_offset = self.member_location(member)?;
+            let bits = match self.udata(member, gimli::DW_AT_bit_size)? {
+                Some(width) => Some((
+                    self.bit_offset(member, member_offset, width, member_type.size)?,
+                    width,
+                )),
+                None => None,
+            };
+
+            members.push(DwarfMember {
+                name: member_name,
+                offset: bits.map_or(member_offset, |(bit, _)| bit / 8),
+                member_type,
+                bits,
+            });
+        }
+
+        members.sort_by_key(|member| member.bits.map_or(member.offset * 8, |(bit, _)| bit));
+
+        self.records.push(DwarfRecord {
+            kind,
+            name: name.clone(),
+            size,
+            members,
+        });
+
+        Ok(name)
+    }
+
+    /// Reads the type at `offset`, or `void` if there is no type.
+    ///
+    /// Typedefs keep their name but are laid out as the type they name, and qualifiers such as `const` are skipped.
+    /// `anonymous_name` is the name given to an anonymous struct or union.
+    fn member_type(
+        &mut self,
+        offset: Option<gimli::UnitOffset>,
+        anonymous_name: &str,
+        depth: usize,
+    ) -> Result<DwarfType, ()> {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => {
+                return Ok(DwarfType {
+                    name: String::from("void"),
+                    size: 0,
+                    dimensions: Vec::new(),
+                    kind: TypeKinds::Bytes,
+                })
+            }
+        };
+
+        check_depth(anonymous_name, depth)?;
+
+        let entry = self.entry(offset)?;
+        let name = self.name(&entry)?;
+        let size = self.udata(&entry, gimli::DW_AT_byte_size)?;
+
+        match entry.tag() {
+            gimli::DW_TAG_base_type => {
+                let size = size.unwrap_or(0);
+                let encoding = match entry
+                    .attr_value(gimli::DW_AT_encoding)
+                    .map_err(dwarf_error)?
+                {
+                    Some(gimli::AttributeValue::Encoding(encoding)) => encoding,
+                    _ => gimli::DW_ATE_unsigned,
+                };
+
+                let name = name.unwrap_or_default();
+                let kind = match base_format(encoding, size, &name) {
+                    Some(display_format) => {
+                        TypeKinds::Base(c_types::native_format(display_format, self.endian))
+                    }
+                    None => TypeKinds::Bytes,
+                };
+
+                Ok(DwarfType {
+                    name,
+                    size,
+                    dimensions: Vec::new(),
+                    kind,
+                })
+            }
+            gimli::DW_TAG_typedef => {
+                let typedef_name = name.unwrap_or_default();
+                let mut member_type =
+                    self.member_type(self.type_offset(&entry)?, &typedef_name, depth + 1)?;
+
+                if member_type.dimensions.is_empty()
+                    && !matches!(member_type.kind, TypeKinds::Record(_))
+                {
+                    member_type.name = typedef_name;
+                }
+
+                Ok(member_type)
+            }
+            gimli::DW_TAG_const_type
+            | gimli::DW_TAG_volatile_type
+            | gimli::DW_TAG_restrict_type
+            | gimli::DW_TAG_atomic_type
+            | gimli::DW_TAG_immutable_type
+            | gimli::DW_TAG_packed_type
+            | gimli::DW_TAG_shared_type => {
+                self.member_type(self.type_offset(&entry)?, anonymous_name, depth + 1)
+            }
+            gimli::DW_TAG_pointer_type
+            | gimli::DW_TAG_reference_type
+            | gimli::DW_TAG_rvalue_reference_type
+            | gimli::DW_TAG_ptr_to_member_type => Ok(DwarfType {
+                name: match name {
+                    Some(name) => name,
+                    None => format!(
+                        "{} *",
+                        self.type_name(self.type_offset(&entry)?, depth + 1)?
+                    ),
+                },
+                size: size.unwrap_or(usize::from(self.unit.encoding().address_size)),
+                dimensions: Vec::new(),
+                kind: TypeKinds::Pointer,
+            }),
+            gimli::DW_TAG_array_type => {
+                let mut dimensions = self.array_dimensions(offset)?;
+                let mut element =
+                    self.member_type(self.type_offset(&entry)?, anonymous_name, depth + 1)?;
+
+                dimensions.append(&mut element.dimensions);
+                element.dimensions = dimensions;
+
+                Ok(element)
+            }
+            gimli::DW_TAG_enumeration_type => {
+                let underlying_type = self.strip_qualifiers(self.type_offset(&entry)?)?;
+                let underlying_signed = match underlying_type {
+                    Some(underlying_type) => {
+                        let underlying_type = self.entry(underlying_type)?;
+                        matches!(
+                            underlying_type
+                                .attr_value(gimli::DW_AT_encoding)
+                                .map_err(dwarf_error)?,
+                            Some(gimli::AttributeValue::Encoding(gimli::DW_ATE_signed))
+                                | Some(gimli::AttributeValue::Encoding(gimli::DW_ATE_signed_char))
+                        )
+                    }
+                    None => false,
+                };
+                let values =
+                    self.enumerators(offset, underlying_type.is_none() || underlying_signed)?;
+                let signed = underlying_signed || values.iter().any(|(_, value)| *value < 0);
+
+                let size = match size {
+                    Some(size) => size,
+                    None => {
+                        serror!(format!(
+                            "Enum: {}, does not have a size",
+                            name.as_deref().unwrap_or(anonymous_name)
+                        ));
+                        return Err(());
+                    }
+                };
+
+                Ok(DwarfType {
+                    name: match name {
+                        Some(name) => format!("enum {}", name),
+                        None => String::from("enum"),
+                    },
+                    size,
+                    dimensions: Vec::new(),
+                    kind: TypeKinds::Enum(values, signed),
+                })
+            }
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type | gimli::DW_TAG_union_type => {
+                let record_name = self.record(offset, anonymous_name, depth + 1)?;
+                let record = self
+                    .records
+                    .iter()
+                    .find(|record| record.name == record_name)
+                    .map_or((0, "struct"), |record| (record.size, record.kind));
+
+                Ok(DwarfType {
+                    name: format!("{} {}", record.1, record_name),
+                    size: record.0,
+                    dimensions: Vec::new(),
+                    kind: TypeKinds::Record(record_name),
+                })
+            }
+            gimli::DW_TAG_subroutine_type | gimli::DW_TAG_unspecified_type => Ok(DwarfType {
+                name: name.unwrap_or_else(|| String::from("fn")),
+                size: size.unwrap_or(0),
+                dimensions: Vec::new(),
+                kind: TypeKinds::Bytes,
+            }),
+            tag => {
+                serror!(format!(
+                    "Type: {}, has the unsupported DWARF tag {}",
+                    name.as_deref().unwrap_or(anonymous_name),
+                    tag
+                ));
+                Err(())
+            }
+        }
+    }
+
+    /// Get's the name of the type at `offset` without reading its layout, used for the types pointers point to.
+    fn type_name(&self, offset: Option<gimli::UnitOffset>, depth: usize) -> Result<String, ()> {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => return Ok(String::from("void")),
+        };
+
+        check_depth("pointer", depth)?;
+
+        let entry = self.entry(offset)?;
+        let name = self.name(&entry)?;
+        let keyword = match entry.tag() {
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type => "struct",
+            gimli::DW_TAG_union_type => "union",
+            gimli::DW_TAG_enumeration_type => "enum",
+            gimli::DW_TAG_subroutine_type => return Ok(String::from("fn")),
+            gimli::DW_TAG_pointer_type
+            | gimli::DW_TAG_reference_type
+            | gimli::DW_TAG_rvalue_reference_type
+            | gimli::DW_TAG_ptr_to_member_type
+                if name.is_none() =>
+            {
+                return Ok(format!(
+                    "{} *",
+                    self.type_name(self.type_offset(&entry)?, depth + 1)?
+                ));
+            }
+            gimli::DW_TAG_array_type => {
+                return Ok(format!(
+                    "{}[]",
+                    self.type_name(self.type_offset(&entry)?, depth + 1)?
+                ));
+            }
+            _ if name.is_none() => return self.type_name(self.type_offset(&entry)?, depth + 1),
+            _ => "",
+        };
+
+        Ok(format!("{} {}", keyword, name.unwrap_or_default())
+            .trim()
+            .to_string())
+    }
+
+    /// Get's the length of each dimension of the array type at `offset`, 0 for a flexible array member.
+    fn array_dimensions(&self, offset: gimli::UnitOffset) -> Result<Vec<usize>, ()> {
+        let mut dimensions: Vec<usize> = Vec::new();
+        let mut tree = self.unit.entries_tree(Some(offset)).map_err(dwarf_error)?;
+        let root = tree.root().map_err(dwarf_error)?;
+        let mut children = root.children();
+
+        while let Some(child) = children.next().map_err(dwarf_error)? {
+            let subrange = child.entry();
+
+            if subrange.tag() != gimli::DW_TAG_subrange_type {
+                continue;
+            }
+
+            let length = match self.udata(subrange, gimli::DW_AT_count)? {
+                Some(count) => count,
+                None => {
+                    let lower_bound = self.udata(subrange, gimli::DW_AT_lower_bound)?.unwrap_or(0);
+
+                    match subrange
+                        .attr_value(gimli::DW_AT_upper_bound)
+                        .map_err(dwarf_error)?
+                    {
+                        // Flexible array members have no upper bound or an upper bound of -1.
+                        Some(upper_bound) => match upper_bound.sdata_value() {
+                            Some(-1) | None => 0,
+                            Some(_) => (upper_bound.udata_value().unwrap_or(0) as usize + 1)
+                                .saturating_sub(lower_bound),
+                        },
+                        None => 0,
+                    }
+                }
+            };
+
+            dimensions.push(length);
+        }
+
+        Ok(dimensions)
+    }
+
+    /// Get's the name and value of each enumerator of the enum at `offset`.
+    fn enumerators(
+        &self,
+        offset: gimli::UnitOffset,
+        signed: bool,
+    ) -> Result<Vec<(String, i64)>, ()> {
+        let mut values: Vec<(String, i64)> = Vec::new();
+        let mut tree = self.unit.entries_tree(Some(offset)).map_err(dwarf_error)?;
+        let root = tree.root().map_err(dwarf_error)?;
+        let mut children = root.children();
+
+        while let Some(child) = children.next().map_err(dwarf_error)? {
+            let enumerator = child.entry();
+
+            if enumerator.tag() != gimli::DW_TAG_enumerator {
+                continue;
+            }
+
+            let value = enumerator
+                .attr_value(gimli::DW_AT_const_value)
+                .map_err(dwarf_error)?;
+            let value = match value {
+                Some(value) if signed => value.sdata_value(),
+                Some(value) => value.udata_value().map(|value| value as i64),
+                None => None,
+            };
+
+            values.push((
+                self.name(enumerator)?.unwrap_or_default(),
+                value.unwrap_or(0),
+            ));
+        }
+
+        Ok(values)
+    }
+
+    /// Get's the offset of a member from the start of its record in bytes, which is 0 for union members.
+    ///
+    /// The offset is either a constant or, in older DWARF versions, an expression adding a constant.
+    fn member_location(
+        &self,
+        member: &gimli::DebuggingInformationEntry<DwarfReader>,
+    ) -> Result<usize, ()> {
+        let location = match member
+            .attr_value(gimli::DW_AT_data_member_location)
+            .map_err(dwarf_error)?
+        {
+            Some(location) => location,
+            None => return Ok(0),
+        };
+
+        if let Some(offset) = location.udata_value() {
+            return Ok(offset as usize);
+        }
+
+        if let Some(mut expression) = location.exprloc_value().map(|expression| expression.0) {
+            if let Ok(gimli::Operation::PlusConstant { value }) =
+                gimli::Operation::parse(&mut expression, self.unit.encoding())
+            {
+                return Ok(value as usize);
+            }
+        }
+
+        serror!(format!(
+            "Member: {}, has a location that is not a constant offset",
+            self.name(member)?.unwrap_or_default()
+        ));
+        Err(())
+    }
+
+    /// Get's the offset in bits from the start of its record of a bitfield `width` bits wide at `offset` bytes.
+    ///
+    /// Newer DWARF versions give the offset in bits directly. Older ones give the offset of the bitfield's most
+    /// significant bit from the most significant bit of a storage unit, `storage_size` bytes unless the bitfield has
+    /// its own size, which is converted to the order bits are allocated in on the target.
+    fn bit_offset(
+        &self,
+        member: &gimli::DebuggingInformationEntry<DwarfReader>,
+        offset: usize,
+        width: usize,
+        storage_size: usize,
+    ) -> Result<usize, ()> {
+        if let Some(bit) = self.udata(member, gimli::DW_AT_data_bit_offset)? {
+            return Ok(bit);
+        }
+
+        let storage_size = self
+            .udata(member, gimli::DW_AT_byte_size)?
+            .unwrap_or(storage_size);
+
+        Ok(match self.udata(member, gimli::DW_AT_bit_offset)? {
+            Some(bit_offset) => match self.endian {
+                c_types::Endians::Little => {
+                    (offset + storage_size) * 8 - (bit_offset + width).min(storage_size * 8)
+                }
+                c_types::Endians::Big => offset * 8 + bit_offset,
+            },
+            None => offset * 8,
+        })
+    }
+
+    /// Follows qualifiers such as `const` and typedefs from the type at `offset` to the type they apply to.
+    fn strip_qualifiers(
+        &self,
+        mut offset: Option<gimli::UnitOffset>,
+    ) -> Result<Option<gimli::UnitOffset>, ()> {
+        for _ in 0..MAX_TYPE_DEPTH {
+            let entry = match offset {
+                Some(type_offset) => self.entry(type_offset)?,
+                None => return Ok(None),
+            };
+
+            match entry.tag() {
+                gimli::DW_TAG_typedef
+                | gimli::DW_TAG_const_type
+                | gimli::DW_TAG_volatile_type
+                | gimli::DW_TAG_restrict_type
+                | gimli::DW_TAG_atomic_type => offset = self.type_offset(&entry)?,
+                _ => return Ok(offset),
+            }
+        }
+
+        Ok(offset)
+    }
+
+    /// Reads the entry at `offset`.
+    fn entry(
+        &self,
+        offset: gimli::UnitOffset,
+    ) -> Result<gimli::DebuggingInformationEntry<'_, '_, DwarfReader<'_>>, ()> {
+        self.unit.entry(offset).map_err(dwarf_error)
+    }
+
+    /// Get's the name of an entry, if it has one.
+    fn name(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader>,
+    ) -> Result<Option<String>, ()> {
+        match entry.attr_value(gimli::DW_AT_name).map_err(dwarf_error)? {
+            Some(name) => Ok(Some(
+                self.dwarf
+                    .attr_string(self.unit, name)
+                    .map_err(dwarf_error)?
+                    .to_string_lossy()
+                    .into_owned(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Get's the value of an unsigned integer attribute of an entry, if it has one.
+    fn udata(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader>,
+        attribute: gimli::DwAt,
+    ) -> Result<Option<usize>, ()> {
+        Ok(entry
+            .attr_value(attribute)
+            .map_err(dwarf_error)?
+            .and_then(|value| value.udata_value())
+            .map(|value| value as usize))
+    }
+
+    /// Get's the offset of the type of an entry, or `None` if it has no type, e.g `void`.
+    ///
+    /// Types in other compilation units are not supported.
+    fn type_offset(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader>,
+    ) -> Result<Option<gimli::UnitOffset>, ()> {
+        match entry.attr_value(gimli::DW_AT_type).map_err(dwarf_error)? {
+            Some(gimli::AttributeValue::UnitRef(offset)) => Ok(Some(offset)),
+            Some(gimli::AttributeValue::DebugInfoRef(offset)) => {
+                match offset.to_unit_offset(&self.unit.header) {
+                    Some(offset) => Ok(Some(offset)),
+                    None => {
+                        serror!(format!(
+                            "Type of: {}, is in another compilation unit, which is not supported",
+                            self.name(entry)?.unwrap_or_default()
+                        ));
+                        Err(())
+                    }
+                }
+            }
+            Some(_) => {
+                serror!(format!(
+                    "Type of: {}, is not a reference to a type",
+                    self.name(entry)?.unwrap_or_default()
+                ));
+                Err(())
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Get's the little endian display format of a base type with the DWARF `encoding`, `size` bytes long and called
+/// `name`, or `None` if it has no display format.
+///
+/// Plain `char` is shown as text while `signed char` and `unsigned char` are shown as integers.
+fn base_format(encoding: gimli::DwAte, size: usize, name: &str) -> Option<&'static str> {
+    let integer_size = matches!(size, 1 | 2 | 4 | 8);
+
+    let display_format = match encoding {
+        gimli::DW_ATE_float if size == 4 || size == 8 => format::FLOAT_TYPE,
+        gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char if name == "char" => {
+            format::ASCII_TYPE
+        }
+        gimli::DW_ATE_UTF if size == 1 => format::ASCII_TYPE,
+        gimli::DW_ATE_UTF if size == 2 => format::UTF16LE_TYPE,
+        gimli::DW_ATE_signed | gimli::DW_ATE_signed_char if integer_size => format::INT_TYPE,
+        gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char | gimli::DW_ATE_boolean
+            if integer_size =>
+        {
+            format::UINT_TYPE
+        }
+        gimli::DW_ATE_signed | gimli::DW_ATE_unsigned | gimli::DW_ATE_UTF => format::HEXLE_TYPE,
+        _ => return None,
+    };
+
+    Some(display_format)
+}
+
+/// Get's the description of a member, the enumerators of an enum or `N/A` for other types.
+fn description(member_type: &DwarfType) -> String {
+    match &member_type.kind {
+        TypeKinds::Enum(values, _) if !values.is_empty() => values
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect::<Vec<String>>()
+            .join(", "),
+        _ => String::from("N/A"),
+    }
+}
+
+/// Checks that types are not nested deeper than `MAX_TYPE_DEPTH`, which happens when a type contains itself.
+fn check_depth(name: &str, depth: usize) -> Result<(), ()> {
+    if depth > MAX_TYPE_DEPTH {
+        serror!(format!(
+            "Type: {}, is nested more than {} levels deep, it may contain itself",
+            name, MAX_TYPE_DEPTH
+        ));
+        return Err(());
+    }
+
+    Ok(())
+}
+
+/// Reports an error reading the DWARF debug information.
+fn dwarf_error(error: gimli::Error) {
+    serror!(format!(
+        "Invalid DWARF debug information, because: {}",
+        error
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gram_parse;
+
+    /// ELF file built from `examples/dwarf_example.c` with debug information.
+    const EXAMPLE_ELF: &str = "./examples/dwarf_example.elf";
+
+    fn convert(elf_filepath: &str, root: &str) -> Result<String, ()> {
+        let mut cmd_args = arg_parse::CMDArgParse::new();
+        cmd_args.elf_filepath = elf_filepath.to_string();
+        cmd_args.root = root.to_string();
+
+        let mut dwarf_struct = DwarfStruct::new();
+        dwarf_struct
+            .parse_dwarf(&cmd_args)?
+            .build_grammar_contents()?;
+        Ok(dwarf_struct.grammar_contents)
+    }
+
+    /// Gets the (name, size) of each root field of `grammar`.
+    fn fields(grammar: &str) -> Vec<(String, usize)> {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(grammar).unwrap();
+
+        parsed_gram
+            .fields
+            .iter()
+            .map(|field| (field.name.clone(), field.size))
+            .collect()
+    }
+
+    #[test]
+    fn members_are_placed_at_their_compiled_offsets() {
+        let grammar = convert(EXAMPLE_ELF, "packet").unwrap();
+        let names_and_sizes: Vec<(&str, usize)> = vec![
+            ("magic", 4),
+            ("version", 1),
+            ("_padding1", 1),
+            ("port", 2),
+            ("points", 4),
+            ("value", 4),
+            ("state", 4),
+            ("ack_to_window", 1),
+            ("level", 1),
+            ("_padding2", 6),
+            ("ratio", 8),
+            ("next", 8),
+            ("anonymous1", 2),
+            ("_padding3", 6),
+        ];
+
+        assert_eq!(
+            fields(&grammar),
+            names_and_sizes
+                .iter()
+                .map(|(name, size)| (name.to_string(), *size))
+                .collect::<Vec<(String, usize)>>()
+        );
+        assert!(grammar.contains("\tendian = 'little'\r\n"));
+        assert!(grammar.contains("\tname = 'packet'\r\n\tkind = 'struct'\r\n\tsize = 0x38\r\n"));
+    }
+
+    #[test]
+    fn types_keep_their_names_and_formats() {
+        let grammar = convert(EXAMPLE_ELF, "packet").unwrap();
+
+        assert!(grammar.contains("\tdata_type = 'char[4]'\r\n\tdisplay_format = 'ascii'\r\n"));
+        assert!(grammar.contains("\tdata_type = 'port_t'\r\n\tdisplay_format = 'uint'\r\n"));
+        assert!(grammar.contains("\tdata_type = 'double'\r\n\tdisplay_format = 'float'\r\n"));
+        assert!(
+            grammar.contains("\tdata_type = 'struct packet *'\r\n\tdisplay_format = 'hexle'\r\n")
+        );
+        assert!(grammar.contains(
+            "\tdata_type = 'enum state'\r\n\tdisplay_format = 'int'\r\n\tdescription = 'IDLE = 0, BUSY = 4, FAILED = -1'\r\n"
+        ));
+    }
+
+    #[test]
+    fn structs_and_unions_become_types() {
+        let grammar = convert(EXAMPLE_ELF, "packet").unwrap();
+
+        assert!(
+            grammar.contains("\tcount = 2\r\n\ttype = 'point'\r\n\tdata_type = 'struct point'\r\n")
+        );
+        assert!(grammar.contains("\tname = 'value'\r\n\tkind = 'union'\r\n\tsize = 0x04\r\n"));
+        assert!(grammar
+            .contains("\tname = 'packet_anonymous1'\r\n\tkind = 'union'\r\n\tsize = 0x02\r\n"));
+    }
+
+    #[test]
+    fn bitfields_become_bit_ranges() {
+        let grammar = convert(EXAMPLE_ELF, "packet").unwrap();
+
+        assert!(grammar.contains(
+            "\tname = 'ack_to_window'\r\n\tsize = 0x01\r\n\tdata_type = 'unsigned int'\r\n\tdisplay_format = 'bits'\r\n"
+        ));
+        assert!(grammar
+            .contains("[[fields.bits]]\r\n\tname = 'window'\r\n\toffset = 1\r\n\twidth = 7\r\n"));
+        assert!(grammar.contains(
+            "[[fields.bits]]\r\n\tname = 'level'\r\n\toffset = 0\r\n\twidth = 4\r\n\tsigned = true\r\n"
+        ));
+    }
+
+    #[test]
+    fn typedefs_can_name_the_root() {
+        assert_eq!(
+            convert(EXAMPLE_ELF, "PACKET").unwrap(),
+            convert(EXAMPLE_ELF, "packet").unwrap()
+        );
+        assert!(convert(EXAMPLE_ELF, "point")
+            .unwrap()
+            .contains("\tname = 'point'\r\n\tkind = 'struct'\r\n\tsize = 0x04\r\n"));
+    }
+
+    #[test]
+    fn missing_structs_and_debug_information_are_an_error() {
+        assert!(convert(EXAMPLE_ELF, "").is_err());
+        assert!(convert(EXAMPLE_ELF, "missing").is_err());
+        assert!(convert("./examples/mbr.bin", "packet").is_err());
+    }
+
+    #[test]
+    fn base_types_get_display_formats() {
+        assert_eq!(
+            base_format(gimli::DW_ATE_signed_char, 1, "char"),
+            Some(format::ASCII_TYPE)
+        );
+        assert_eq!(
+            base_format(gimli::DW_ATE_signed_char, 1, "signed char"),
+            Some(format::INT_TYPE)
+        );
+        assert_eq!(
+            base_format(gimli::DW_ATE_unsigned, 16, "__int128 unsigned"),
+            Some(format::HEXLE_TYPE)
+        );
+        assert_eq!(base_format(gimli::DW_ATE_float, 16, "long double"), None);
+    }
+}
@@ -9,7 +9,7 @@ macro_rules! serror {
             $cause,
             line!(),
             file!(),
-        );
+        )
     };
 }
 
@@ -17,23 +17,45 @@ macro_rules! serror {
 const USAGE_STRING: &str = "memgram [OPTION] [VALUE]";
 
 /// Memgram CLI argument options and their corresponding descriptions.
-const OPTIONS: [&str; 9] = [
+const OPTIONS: [&str; 26] = [
     "-b     binary filepath",
-    "-c     c struct filepath",
+    "-c     c struct filepath, or rust source (.rs) with #[repr(C)] structs",
     "-d     show description table",
-    "-e     reverse the endianess for table formatted data",
-    "-E     reverse the endianess for hex view formatted data",
+    "-e     reverse the endianess of endian sensitive formats in the table",
+    "-E     show decoded words beneath the hex view, with little endian elements reversed",
     "-g     grammar filepath",
     "-h     display usage information",
-    "-o     output filepath for conversion",
+    "-o     output filepath for conversion, a grammar from -c, or a c header or rust module (.rs) from -g",
     "-s     offset into binary the file structure starts at",
+    "--format   output format: table (default), json, jsonl, csv, tsv, markdown or html",
+    "--color    when to colour output: auto (default), always or never",
+    "--theme    colour palette: default, colourblind, light or type",
+    "--row-width    number of bytes per hex view row (default 16)",
+    "--group    number of bytes per group in the hex view",
+    "--context  number of bytes shown before and after the structure in the hex view",
+    "--before   number of bytes shown before the structure in the hex view",
+    "--after    number of bytes shown after the structure in the hex view",
+    "--side     hex view side column: cp850 (default), ascii, utf16le, utf16be, ebcdic or none",
+    "--legend   print a legend mapping colours to field IDs after the hex view",
+    "--markers  print field IDs beneath the byte each field starts at in the hex view",
+    "--syntax   x86 assembly syntax: nasm (default), intel, gas or masm",
+    "--list-formats     list the supported display formats, the sizes they accept and whether -e affects them",
+    "--abi      target ABI c structs are laid out for: llp64 (default), lp64, ilp32, win32, arm, avr, msp430, m68k or ppc",
+    "--root     name of the struct converted from a c header (default is the last struct) or from --elf (required)",
+    "--elf      elf filepath built with debug information, a struct from its DWARF is converted like -c",
+    "--columns  comma separated columns for csv, tsv and markdown: id,name,offset,size,type,raw,value,description",
 ];
 
 /// Example usage strings for memgram.
-const EXAMPLES: [&str; 3] = [
+const EXAMPLES: [&str; 8] = [
     "memgram -b ./examples/test_formats.bin -g ./grammar/test_formats.toml -s 0",
     "memgram -c ./examples/COFFHeader.h -o ./grammar/COFFHeader.toml",
+    "memgram --elf ./build/disk_tool -o ./grammar/SuperBlock.toml --root SuperBlock",
+    "memgram -g ./grammar/mbr.toml -o ./mbr.h",
+    "memgram -g ./grammar/mbr.toml -o ./mbr.rs -b ./examples/mbr.bin",
     "memgram -c ./examples/COFFHeader.h -b .~/Downloads/binary.exe -o 244 -e -E -d",
+    "memgram -b ./examples/mbr.bin -g ./grammar/mbr.toml --format json",
+    "memgram -b ./examples/mbr.bin -g ./grammar/mbr.toml --format csv --columns id,name,offset,value",
 ];
 
 /// Prints memgram usage information.
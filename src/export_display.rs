@@ -0,0 +1,237 @@
+//! Module for exporting the standard table as CSV, TSV or Markdown without colour codes.
+use crate::arg_parse;
+use crate::gram_parse;
+use crate::table_display;
+use hex::ToHex;
+
+/// Columns exported when none are specified on the cmdline. These match the columns of the standard table.
+const DEFAULT_COLUMNS: [arg_parse::Columns; 7] = [
+    arg_parse::Columns::Id,
+    arg_parse::Columns::Name,
+    arg_parse::Columns::Offset,
+    arg_parse::Columns::Size,
+    arg_parse::Columns::DataType,
+    arg_parse::Columns::RawData,
+    arg_parse::Columns::FormattedData,
+];
+
+/// Returns the header text for a column.
+fn column_header(column: arg_parse::Columns) -> &'static str {
+    match column {
+        arg_parse::Columns::Id => "ID",
+        arg_parse::Columns::Name => "Field",
+        arg_parse::Columns::Offset => "Offset",
+        arg_parse::Columns::Size => "Size",
+        arg_parse::Columns::DataType => "Data Type",
+        arg_parse::Columns::RawData => "Raw Data",
+        arg_parse::Columns::FormattedData => "Formatted Data",
+        arg_parse::Columns::Description => "Description",
+    }
+}
+
+/// Builds the exported rows, header row first, from the data gathered in `table_data`.
+///
+/// If `columns` is empty, `DEFAULT_COLUMNS` are used, with the description column appended when `description` is true.
+pub fn build_export_rows(
+    parsed_gram: &gram_parse::Grammar,
+    table_data: &table_display::TableData,
+    mut struct_offset: usize,
+    columns: &[arg_parse::Columns],
+    description: bool,
+) -> Result<Vec<Vec<String>>, ()> {
+    let mut selected_columns: Vec<arg_parse::Columns> = columns.to_vec();
+
+    if selected_columns.is_empty() {
+        selected_columns.extend_from_slice(&DEFAULT_COLUMNS);
+
+        if description {
+            selected_columns.push(arg_parse::Columns::Description);
+        }
+    }
+
+    let mut rows: Vec<Vec<String>> = vec![selected_columns
+        .iter()
+        .map(|column| column_header(*column).to_string())
+        .collect()];
+
+    for (index, field) in parsed_gram.fields.iter().enumerate() {
+        let raw_field_data = table_data.field_hashmap.get(&field.name).ok_or_else(|| {
+            serror!(format!("Could not get value for field: {}", field.name));
+        })?;
+
+        let formatted_data = table_data
+            .field_fmt_hashmap
+            .get(&field.name)
+            .ok_or_else(|| {
+                serror!(format!("Could not get value for field: {}", field.name));
+            })?;
+
+        rows.push(
+            selected_columns
+                .iter()
+                .map(|column| match column {
+                    arg_parse::Columns::Id => format!("{:03X}", index),
+                    arg_parse::Columns::Name => field.name.clone(),
+                    arg_parse::Columns::Offset => format!("{:#X}", struct_offset),
+                    arg_parse::Columns::Size => format!("{:#X}", field.size),
+                    arg_parse::Columns::DataType => field.data_type.clone(),
+                    arg_parse::Columns::RawData => raw_field_data.encode_hex_upper::<String>(),
+                    arg_parse::Columns::FormattedData => formatted_data.trim_end().to_string(),
+                    arg_parse::Columns::Description => field.description.clone(),
+                })
+                .collect(),
+        );
+
+        struct_offset += field.size;
+    }
+
+    Ok(rows)
+}
+
+/// Prints rows to stdout as comma separated values.
+///
+/// Cells containing a comma, quote or line break are quoted as described in RFC 4180.
+pub fn print_csv(rows: &[Vec<String>]) {
+    for row in rows.iter() {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| {
+                if cell.contains(&[',', '"', '\n', '\r'][..]) {
+                    format!("\"{}\"", cell.replace('"', "\"\""))
+                } else {
+                    cell.clone()
+                }
+            })
+            .collect();
+
+        println!("{}", cells.join(","));
+    }
+}
+
+/// Prints rows to stdout as tab separated values.
+///
+/// Tabs and line breaks inside cells are replaced by spaces.
+pub fn print_tsv(rows: &[Vec<String>]) {
+    for row in rows.iter() {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| cell.replace(&['\t', '\n', '\r'][..], " "))
+            .collect();
+
+        println!("{}", cells.join("\t"));
+    }
+}
+
+/// Prints rows to stdout as a GitHub flavoured Markdown table, using the first row as the header.
+///
+/// Pipes inside cells are escaped and line breaks are replaced by `<br>`.
+pub fn print_markdown(rows: &[Vec<String>]) {
+    for (index, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| {
+                cell.replace('|', "\\|")
+                    .replace("\r\n", "<br>")
+                    .replace('\n', "<br>")
+            })
+            .collect();
+
+        println!("| {} |", cells.join(" | "));
+
+        if index == 0 {
+            println!("|{}", " --- |".repeat(row.len()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = r#"
+[metadata]
+name = "Header"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "Magic"
+size = 2
+data_type = "char"
+display_format = "ascii"
+description = "File magic"
+
+[[fields]]
+name = "Length"
+size = 4
+data_type = "uint32_t"
+display_format = "hex"
+description = "Length of the data"
+"#;
+
+    fn build_rows(columns: &[arg_parse::Columns], description: bool) -> Vec<Vec<String>> {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(GRAMMAR).unwrap();
+
+        let mut table_data = table_display::TableData::new();
+        table_data
+            .field_hashmap
+            .insert(String::from("Magic"), vec![0x4D, 0x5A]);
+        table_data
+            .field_hashmap
+            .insert(String::from("Length"), vec![0x00, 0x01, 0xAB, 0xFF]);
+        table_data
+            .field_fmt_hashmap
+            .insert(String::from("Magic"), String::from("MZ"));
+        table_data
+            .field_fmt_hashmap
+            .insert(String::from("Length"), String::from("0001ABFF   "));
+
+        build_export_rows(&parsed_gram, &table_data, 0x20, columns, description).unwrap()
+    }
+
+    #[test]
+    fn default_columns_match_the_standard_table() {
+        let rows = build_rows(&[], false);
+
+        assert_eq!(
+            rows[0],
+            [
+                "ID",
+                "Field",
+                "Offset",
+                "Size",
+                "Data Type",
+                "Raw Data",
+                "Formatted Data"
+            ]
+        );
+        assert_eq!(
+            rows[2],
+            ["001", "Length", "0x22", "0x4", "uint32_t", "0001ABFF", "0001ABFF"]
+        );
+    }
+
+    #[test]
+    fn description_column_is_appended_to_the_default_columns() {
+        let rows = build_rows(&[], true);
+
+        assert_eq!(rows[0].last().unwrap(), "Description");
+        assert_eq!(rows[1].last().unwrap(), "File magic");
+    }
+
+    #[test]
+    fn selected_columns_are_exported_in_order() {
+        let rows = build_rows(
+            &[
+                arg_parse::Columns::FormattedData,
+                arg_parse::Columns::Name,
+                arg_parse::Columns::Offset,
+            ],
+            true,
+        );
+
+        assert_eq!(rows[0], ["Formatted Data", "Field", "Offset"]);
+        assert_eq!(rows[1], ["MZ", "Magic", "0x20"]);
+    }
+}
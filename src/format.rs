@@ -1,22 +1,258 @@
 //! Module that deals with converting raw u8 arrays into formatted strings. e.g utf16 byte array to utf16 string.
-use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction, NasmFormatter};
-use std::net::{IpAddr, Ipv4Addr};
-use widestring::U16CString;
+use crate::arm_disass;
+use crate::riscv_disass;
+use hex::ToHex;
+use iced_x86::{
+    Decoder, DecoderOptions, FlowControl, Formatter, GasFormatter, Instruction, IntelFormatter,
+    MasmFormatter, NasmFormatter, OpKind,
+};
+use std::fmt::{Display, LowerExp};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
+/// Display data as a hex string in file order.
+pub const HEX_TYPE: &str = "hex";
 /// Display data in little endian hex string format.
 pub const HEXLE_TYPE: &str = "hexle";
 /// Display data in ASCII format.
 pub const ASCII_TYPE: &str = "ascii";
+/// Display data in UTF-8 format.
+pub const UTF8_TYPE: &str = "utf8";
+/// Display data in ISO-8859-1 (Latin-1) format.
+pub const LATIN1_TYPE: &str = "latin1";
+/// Display data in Windows-1252 format.
+pub const CP1252_TYPE: &str = "cp1252";
+/// Display data in Shift-JIS format.
+pub const SHIFT_JIS_TYPE: &str = "shift_jis";
+/// Display data in EUC-JP format.
+pub const EUC_JP_TYPE: &str = "euc_jp";
+/// Display data in GBK format.
+pub const GBK_TYPE: &str = "gbk";
+/// Display data in EBCDIC code page 037 format.
+pub const EBCDIC_TYPE: &str = "ebcdic";
 /// Display data in IPv4 big endian format.
 pub const IPV4BE_TYPE: &str = "ipv4be";
 /// Display data in IPv4 little endian format.
 pub const IPV4LE_TYPE: &str = "ipv4le";
+/// Display data in IPv6 format, 16 bytes in network order.
+pub const IPV6_TYPE: &str = "ipv6";
+/// Display a 6 byte MAC address.
+pub const MAC_TYPE: &str = "mac";
+/// Display an 8 byte EUI-64 identifier.
+pub const EUI64_TYPE: &str = "eui64";
+/// Display a 16 byte mixed endian Microsoft GUID.
+pub const GUID_TYPE: &str = "guid";
+/// Display a 16 byte big endian RFC 4122 UUID.
+pub const UUID_TYPE: &str = "uuid";
+/// Display a 2 byte big endian (network order) port number and its service name.
+pub const PORTBE_TYPE: &str = "portbe";
+/// Display a 2 byte little endian port number and its service name.
+pub const PORTLE_TYPE: &str = "portle";
+/// Display a 6 byte IPv4 address followed by a port, both in network order.
+pub const SOCKET4_TYPE: &str = "socket4";
+/// Display an 18 byte IPv6 address followed by a port, both in network order.
+pub const SOCKET6_TYPE: &str = "socket6";
+/// Display a 16 byte `sockaddr_in` structure.
+pub const SOCKADDR_IN_TYPE: &str = "sockaddr_in";
+/// Display a 28 byte `sockaddr_in6` structure.
+pub const SOCKADDR_IN6_TYPE: &str = "sockaddr_in6";
 /// Display data in UTF16 little endian format.
-pub const UTF16LE_TYPE: &str = "utf16be";
-/// Display data in UTF16  big endian format.
-pub const UTF16BE_TYPE: &str = "utf16le";
+pub const UTF16LE_TYPE: &str = "utf16le";
+/// Display data in UTF16 big endian format.
+pub const UTF16BE_TYPE: &str = "utf16be";
+/// Display x86 16-bit (real mode) assembly format.
+pub const X86_16_TYPE: &str = "x86_16";
 /// Display x86_32 assembly format.
-pub const X86_TYPE: &str = "x86_32";
+pub const X86_32_TYPE: &str = "x86_32";
+/// Display x86_64 assembly format.
+pub const X86_64_TYPE: &str = "x86_64";
+/// Display 32-bit ARM (A32) assembly format.
+pub const ARM32_TYPE: &str = "arm32";
+/// Display ARM Thumb assembly format.
+pub const THUMB_TYPE: &str = "thumb";
+/// Display 64-bit ARM (A64) assembly format.
+pub const AARCH64_TYPE: &str = "aarch64";
+/// Display RISC-V (RV64IMC) assembly format.
+pub const RISCV_TYPE: &str = "riscv";
+
+/// Well known ports and their IANA service names.
+const SERVICE_NAMES: [(u16, &str); 36] = [
+    (20, "ftp-data"),
+    (21, "ftp"),
+    (22, "ssh"),
+    (23, "telnet"),
+    (25, "smtp"),
+    (53, "domain"),
+    (67, "bootps"),
+    (68, "bootpc"),
+    (69, "tftp"),
+    (80, "http"),
+    (88, "kerberos"),
+    (110, "pop3"),
+    (123, "ntp"),
+    (135, "epmap"),
+    (137, "netbios-ns"),
+    (138, "netbios-dgm"),
+    (139, "netbios-ssn"),
+    (143, "imap"),
+    (161, "snmp"),
+    (162, "snmptrap"),
+    (389, "ldap"),
+    (443, "https"),
+    (445, "microsoft-ds"),
+    (514, "syslog"),
+    (587, "submission"),
+    (636, "ldaps"),
+    (993, "imaps"),
+    (995, "pop3s"),
+    (1433, "ms-sql-s"),
+    (1883, "mqtt"),
+    (3306, "mysql"),
+    (3389, "ms-wbt-server"),
+    (5432, "postgresql"),
+    (5900, "rfb"),
+    (6379, "redis"),
+    (8080, "http-alt"),
+];
+/// `sa_family` value of `sockaddr_in`, the same on Linux, BSD and Windows.
+const AF_INET: u16 = 2;
+
+/// Display a little endian Unix timestamp in seconds, 4 or 8 bytes.
+pub const UNIX_TYPE: &str = "unix";
+/// Display a little endian Unix timestamp in milliseconds, 4 or 8 bytes.
+pub const UNIX_MS_TYPE: &str = "unix_ms";
+/// Display a little endian Unix timestamp in nanoseconds, 4 or 8 bytes.
+pub const UNIX_NS_TYPE: &str = "unix_ns";
+/// Display a little endian Windows FILETIME, 100ns intervals since 1601-01-01.
+pub const FILETIME_TYPE: &str = "filetime";
+/// Display a DOS/FAT packed time followed by a packed date, as found in FAT directory entries.
+pub const DOS_DATETIME_TYPE: &str = "dos_datetime";
+/// Display a DOS/FAT packed date.
+pub const DOS_DATE_TYPE: &str = "dos_date";
+/// Display a DOS/FAT packed time.
+pub const DOS_TIME_TYPE: &str = "dos_time";
+/// Display a big endian Mac HFS timestamp, seconds since 1904-01-01.
+pub const HFS_TYPE: &str = "hfs";
+/// Display a little endian GPS timestamp, seconds since 1980-01-06 without leap seconds.
+pub const GPS_TYPE: &str = "gps";
+
+/// Seconds between 1601-01-01 and the Unix epoch.
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+/// Seconds between 1904-01-01 and the Unix epoch.
+const HFS_EPOCH_OFFSET: i64 = 2_082_844_800;
+/// Seconds between the Unix epoch and 1980-01-06, the GPS epoch.
+const GPS_EPOCH_OFFSET: i64 = 315_964_800;
+/// GPS seconds at which each leap second since the GPS epoch took effect. GPS time is ahead of UTC by one second
+/// for every entry that has passed.
+const GPS_LEAP_SECONDS: [i64; 18] = [
+    46_828_800,
+    78_364_801,
+    109_900_802,
+    173_059_203,
+    252_028_804,
+    315_187_205,
+    346_723_206,
+    393_984_007,
+    425_520_008,
+    457_056_009,
+    504_489_610,
+    551_750_411,
+    599_184_012,
+    820_108_813,
+    914_803_214,
+    1_025_136_015,
+    1_119_744_016,
+    1_167_264_017,
+];
+/// Timestamps outside of these years are flagged as implausible.
+const PLAUSIBLE_YEARS: std::ops::Range<i64> = 1980..2100;
+
+/// Display an unsigned LEB128 integer, the field size is determined while reading.
+pub const ULEB128_TYPE: &str = "uleb128";
+/// Display a signed LEB128 integer, the field size is determined while reading.
+pub const SLEB128_TYPE: &str = "sleb128";
+/// Display a protocol buffers varint, encoded the same as an unsigned LEB128 integer.
+pub const VARINT_TYPE: &str = "varint";
+/// Display a zigzag encoded varint, as used by protocol buffers sint32 and sint64 fields.
+pub const ZIGZAG_TYPE: &str = "zigzag";
+/// Display packed BCD, two digits per byte with the most significant digit first.
+pub const BCD_TYPE: &str = "bcd";
+/// Display a little endian IEEE 754 half precision float.
+pub const HALF_TYPE: &str = "half";
+/// Display a little endian bfloat16, the upper 16 bits of a single precision float.
+pub const BFLOAT16_TYPE: &str = "bfloat16";
+/// Prefix of the signed fixed point formats, e.g. `q16.16` or `q1.15`.
+const FIXED_POINT_PREFIX: &str = "q";
+/// Prefix of the unsigned fixed point formats, e.g. `uq8.8`.
+const UNSIGNED_FIXED_POINT_PREFIX: &str = "uq";
+/// Display a little endian unsigned integer, 1, 2, 4 or 8 bytes.
+pub const UINT_TYPE: &str = "uint";
+/// Display a little endian signed integer, 1, 2, 4 or 8 bytes.
+pub const INT_TYPE: &str = "int";
+/// Display a big endian unsigned integer, 1, 2, 4 or 8 bytes.
+pub const UINTBE_TYPE: &str = "uintbe";
+/// Display a big endian signed integer, 1, 2, 4 or 8 bytes.
+pub const INTBE_TYPE: &str = "intbe";
+/// Display a little endian IEEE 754 float or double.
+pub const FLOAT_TYPE: &str = "float";
+/// Display a big endian IEEE 754 float or double.
+pub const FLOATBE_TYPE: &str = "floatbe";
+/// The unit that makes scaled values display as human readable sizes, e.g `31.5 MiB`.
+pub const BYTES_UNIT: &str = "B";
+/// Binary prefixes of human readable sizes.
+const SIZE_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+/// Most decimal places shown for scaled values.
+const MAX_SCALED_DECIMALS: usize = 6;
+/// Display the named bit ranges, listed in the `bits` of the field, of a little endian integer up to 8 bytes.
+pub const BITS_TYPE: &str = "bits";
+/// Display the named bit ranges, listed in the `bits` of the field, of a big endian integer up to 8 bytes.
+pub const BITSBE_TYPE: &str = "bitsbe";
+/// Display the result of the Rhai script set with the `script` key of the field.
+pub const SCRIPT_TYPE: &str = "script";
+/// Largest number of bytes read for a varint field without an explicit size, enough for any 64 bit value.
+pub const MAX_VARINT_SIZE: usize = 10;
+
+/// EBCDIC code page 037 (US/Canada) mapped to Unicode, indexed by byte value.
+#[rustfmt::skip]
+pub const EBCDIC_037: [char; 256] = [
+    '\u{00}', '\u{01}', '\u{02}', '\u{03}', '\u{9C}', '\u{09}', '\u{86}', '\u{7F}', '\u{97}', '\u{8D}', '\u{8E}', '\u{0B}', '\u{0C}', '\u{0D}', '\u{0E}', '\u{0F}',
+    '\u{10}', '\u{11}', '\u{12}', '\u{13}', '\u{9D}', '\u{85}', '\u{08}', '\u{87}', '\u{18}', '\u{19}', '\u{92}', '\u{8F}', '\u{1C}', '\u{1D}', '\u{1E}', '\u{1F}',
+    '\u{80}', '\u{81}', '\u{82}', '\u{83}', '\u{84}', '\u{0A}', '\u{17}', '\u{1B}', '\u{88}', '\u{89}', '\u{8A}', '\u{8B}', '\u{8C}', '\u{05}', '\u{06}', '\u{07}',
+    '\u{90}', '\u{91}', '\u{16}', '\u{93}', '\u{94}', '\u{95}', '\u{96}', '\u{04}', '\u{98}', '\u{99}', '\u{9A}', '\u{9B}', '\u{14}', '\u{15}', '\u{9E}', '\u{1A}',
+    ' ', '\u{A0}', 'â', 'ä', 'à', 'á', 'ã', 'å', 'ç', 'ñ', '¢', '.', '<', '(', '+', '|',
+    '&', 'é', 'ê', 'ë', 'è', 'í', 'î', 'ï', 'ì', 'ß', '!', '$', '*', ')', ';', '¬',
+    '-', '/', 'Â', 'Ä', 'À', 'Á', 'Ã', 'Å', 'Ç', 'Ñ', '¦', ',', '%', '_', '>', '?',
+    'ø', 'É', 'Ê', 'Ë', 'È', 'Í', 'Î', 'Ï', 'Ì', '`', ':', '#', '@', '\'', '=', '"',
+    'Ø', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', '«', '»', 'ð', 'ý', 'þ', '±',
+    '°', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 'ª', 'º', 'æ', '¸', 'Æ', '¤',
+    'µ', '~', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '¡', '¿', 'Ð', 'Ý', 'Þ', '®',
+    '^', '£', '¥', '·', '©', '§', '¶', '¼', '½', '¾', '[', ']', '¯', '¨', '´', '×',
+    '{', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', '\u{AD}', 'ô', 'ö', 'ò', 'ó', 'õ',
+    '}', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', '¹', 'û', 'ü', 'ù', 'ú', 'ÿ',
+    '\\', '÷', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '²', 'Ô', 'Ö', 'Ò', 'Ó', 'Õ',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '³', 'Û', 'Ü', 'Ù', 'Ú', '\u{9F}',
+];
+
+/// Hex strings longer than this many characters are shortened by `hex_preview`.
+const HEX_PREVIEW_LENGTH: usize = 25;
+
+/// Converts a u8 array into an upper case hex string, shortened to `HEX_PREVIEW_LENGTH` characters followed by `...`.
+pub fn hex_preview(bytes: &[u8]) -> String {
+    let mut hex_string = bytes.encode_hex_upper::<String>();
+
+    if hex_string.len() > HEX_PREVIEW_LENGTH {
+        hex_string.truncate(HEX_PREVIEW_LENGTH);
+        hex_string.push_str("...");
+    }
+
+    hex_string
+}
+
+/// Converts a u8 array into an upper case hex string with the bytes reversed.
+pub fn hexle_string(bytes: &[u8]) -> String {
+    let mut reversed_bytes = bytes.to_vec();
+    reversed_bytes.reverse();
+    reversed_bytes.encode_hex_upper::<String>()
+}
 
 /// Converts a 4 byte u8 array into a ipv4 string
 pub fn ipv4_string(ipv4_bytes: &[u8]) -> Result<String, ()> {
@@ -37,45 +273,890 @@ pub fn ipv4_string(ipv4_bytes: &[u8]) -> Result<String, ()> {
     }
 }
 
+/// Checks that `bytes` is `size` bytes long, printing an error naming `kind` if not.
+fn check_size(bytes: &[u8], size: usize, kind: &str) -> Result<(), ()> {
+    if bytes.len() == size {
+        Ok(())
+    } else {
+        serror!(format!(
+            "Invalid {} size: {}, must be {} bytes",
+            kind,
+            bytes.len(),
+            size
+        ));
+        Err(())
+    }
+}
+
+/// Converts a 16 byte u8 array in network order into an ipv6 string.
+pub fn ipv6_string(ipv6_bytes: &[u8]) -> Result<String, ()> {
+    check_size(ipv6_bytes, 16, "IPv6 address")?;
+
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(ipv6_bytes);
+
+    Ok(Ipv6Addr::from(octets).to_string())
+}
+
+/// Converts a 6 byte MAC address or 8 byte EUI-64 into a colon separated string, e.g `00:1a:2b:3c:4d:5e`.
+pub fn mac_string(mac_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    match display_format {
+        EUI64_TYPE => check_size(mac_bytes, 8, "EUI-64")?,
+        _ => check_size(mac_bytes, 6, "MAC address")?,
+    }
+
+    Ok(mac_bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<String>>()
+        .join(":"))
+}
+
+/// Converts a 16 byte GUID or UUID into a string.
+///
+/// Microsoft GUIDs store the first three groups little endian and are displayed in braces, e.g
+/// `{C12A7328-F81F-11D2-BA4B-00A0C93EC93B}`. RFC 4122 UUIDs are big endian and displayed with their version, e.g
+/// `550e8400-e29b-41d4-a716-446655440000 (v4)`.
+pub fn guid_string(guid_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    check_size(guid_bytes, 16, "GUID")?;
+
+    let mut bytes = guid_bytes.to_vec();
+
+    if display_format == GUID_TYPE {
+        bytes[0..4].reverse();
+        bytes[4..6].reverse();
+        bytes[6..8].reverse();
+    }
+
+    let hex = |range: std::ops::Range<usize>| -> String {
+        bytes[range]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    };
+    let guid = format!(
+        "{}-{}-{}-{}-{}",
+        hex(0..4),
+        hex(4..6),
+        hex(6..8),
+        hex(8..10),
+        hex(10..16)
+    );
+
+    if display_format == GUID_TYPE {
+        return Ok(format!("{{{}}}", guid.to_uppercase()));
+    }
+
+    // The version is only meaningful for the RFC 4122 variant.
+    match (bytes[6] >> 4, bytes[8] >> 6) {
+        (version @ 1..=8, 0b10) => Ok(format!("{} (v{})", guid, version)),
+        _ => Ok(guid),
+    }
+}
+
+/// Appends the service name of `port` to `text` if the port is well known, e.g `443 (https)`.
+fn with_service_name(text: String, port: u16) -> String {
+    match SERVICE_NAMES.iter().find(|(number, _)| *number == port) {
+        Some((_, service)) => format!("{} ({})", text, service),
+        None => text,
+    }
+}
+
+/// Converts a 2 byte port number into a string containing its service name if it is well known.
+pub fn port_string(port_bytes: &[u8], little_endian: bool) -> Result<String, ()> {
+    check_size(port_bytes, 2, "port")?;
+
+    let port = if little_endian {
+        u16::from_le_bytes([port_bytes[0], port_bytes[1]])
+    } else {
+        u16::from_be_bytes([port_bytes[0], port_bytes[1]])
+    };
+
+    Ok(with_service_name(port.to_string(), port))
+}
+
+/// Converts an IPv4 or IPv6 address followed by a port, all in network order, into a socket address string,
+/// e.g `127.0.0.1:80 (http)` or `[::1]:443 (https)`.
+pub fn socket_string(socket_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    let address = match display_format {
+        SOCKET4_TYPE => {
+            check_size(socket_bytes, 6, "IPv4 socket address")?;
+            IpAddr::V4(Ipv4Addr::new(
+                socket_bytes[0],
+                socket_bytes[1],
+                socket_bytes[2],
+                socket_bytes[3],
+            ))
+        }
+        _ => {
+            check_size(socket_bytes, 18, "IPv6 socket address")?;
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&socket_bytes[..16]);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    };
+    let port_offset = socket_bytes.len() - 2;
+    let port = u16::from_be_bytes([socket_bytes[port_offset], socket_bytes[port_offset + 1]]);
+
+    Ok(with_service_name(
+        SocketAddr::new(address, port).to_string(),
+        port,
+    ))
+}
+
+/// Converts a `sockaddr_in` or `sockaddr_in6` structure into a socket address string.
+///
+/// The address family is read in little endian host order, the port and address in network order. IPv6 socket
+/// addresses include the scope ID if it is non zero. The family is flagged if it is not the expected value.
+pub fn sockaddr_string(sockaddr_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    let (socket_address, expected_families): (String, &[u16]) = match display_format {
+        SOCKADDR_IN_TYPE => {
+            check_size(sockaddr_bytes, 16, "sockaddr_in")?;
+            (
+                socket_string(
+                    &[&sockaddr_bytes[4..8], &sockaddr_bytes[2..4]].concat(),
+                    SOCKET4_TYPE,
+                )?,
+                &[AF_INET],
+            )
+        }
+        _ => {
+            check_size(sockaddr_bytes, 28, "sockaddr_in6")?;
+            let mut socket_address = socket_string(
+                &[&sockaddr_bytes[8..24], &sockaddr_bytes[2..4]].concat(),
+                SOCKET6_TYPE,
+            )?;
+            let scope_id = u32::from_le_bytes([
+                sockaddr_bytes[24],
+                sockaddr_bytes[25],
+                sockaddr_bytes[26],
+                sockaddr_bytes[27],
+            ]);
+
+            if scope_id != 0 {
+                socket_address.push_str(&format!(" scope {}", scope_id));
+            }
+            // AF_INET6 is 10 on Linux, 23 on Windows, 28 on FreeBSD and 30 on macOS.
+            (socket_address, &[10, 23, 28, 30])
+        }
+    };
+
+    let family = u16::from_le_bytes([sockaddr_bytes[0], sockaddr_bytes[1]]);
+
+    if expected_families.contains(&family) {
+        Ok(socket_address)
+    } else {
+        Ok(format!("{} (unexpected family {})", socket_address, family))
+    }
+}
+
 /// Converts a utf16 byte array into a utf16 string.
 ///
 /// If little_endian is set to true, the utf16 byte array will be converted to a utf16_le string.
 ///
 /// If little endian is set to false, the utf16 byte array will be converted to a utf16_be string.
-pub fn utf16_string(utf16_bytes: &[u8], little_endian: bool) -> Result<String, ()> {
-    let raw_iter = utf16_bytes.chunks_exact(2);
+///
+/// Trailing NUL padding is removed, invalid surrogates are replaced and non-printable characters are escaped.
+/// A trailing odd byte is ignored.
+pub fn utf16_string(utf16_bytes: &[u8], little_endian: bool) -> String {
+    let mut code_units: Vec<u16> = utf16_bytes
+        .chunks_exact(2)
+        .map(|word| {
+            if little_endian {
+                u16::from_le_bytes([word[0], word[1]])
+            } else {
+                u16::from_be_bytes([word[0], word[1]])
+            }
+        })
+        .collect();
 
-    if little_endian {
-        let le_raw_field_data: Vec<u16> = raw_iter
-            .map(|word| u16::from_le_bytes([word[0], word[1]]))
-            .collect();
-
-        match U16CString::from_vec_with_nul(le_raw_field_data) {
-            Ok(le_data) => Ok(le_data.to_string_lossy()),
-            Err(_) => {
-                serror!("Error constructing UTF16_LE string");
-                Err(())
+    while code_units.last() == Some(&0) {
+        code_units.pop();
+    }
+
+    escape_non_printable(&String::from_utf16_lossy(&code_units))
+}
+
+/// Decodes a byte array in the text encoding named by `display_format` into a string.
+///
+/// Decoding is lossy, bytes that are invalid in the encoding are replaced with U+FFFD, except for ASCII where bytes
+/// above 0x7F are escaped as `\xNN`. Trailing NUL padding is removed before decoding and non-printable characters are
+/// escaped.
+pub fn text_string(text_bytes: &[u8], display_format: &str) -> String {
+    let end = text_bytes
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |position| position + 1);
+    let text_bytes = &text_bytes[..end];
+
+    let text: String = match display_format {
+        ASCII_TYPE => text_bytes
+            .iter()
+            .map(|byte| match byte {
+                0x00..=0x7F => (*byte as char).to_string(),
+                _ => format!("\\x{:02X}", byte),
+            })
+            .collect(),
+        LATIN1_TYPE => text_bytes.iter().map(|byte| *byte as char).collect(),
+        EBCDIC_TYPE => text_bytes
+            .iter()
+            .map(|byte| EBCDIC_037[*byte as usize])
+            .collect(),
+        _ => {
+            let encoding = match display_format {
+                CP1252_TYPE => encoding_rs::WINDOWS_1252,
+                SHIFT_JIS_TYPE => encoding_rs::SHIFT_JIS,
+                EUC_JP_TYPE => encoding_rs::EUC_JP,
+                GBK_TYPE => encoding_rs::GBK,
+                _ => encoding_rs::UTF_8,
+            };
+            encoding
+                .decode_without_bom_handling(text_bytes)
+                .0
+                .into_owned()
+        }
+    };
+
+    escape_non_printable(&text)
+}
+
+/// Returns true if `display_format` stores multi-byte values big endian.
+pub fn is_big_endian_format(display_format: &str) -> bool {
+    matches!(
+        display_format,
+        UINTBE_TYPE
+            | INTBE_TYPE
+            | FLOATBE_TYPE
+            | BITSBE_TYPE
+            | UTF16BE_TYPE
+            | IPV4BE_TYPE
+            | PORTBE_TYPE
+            | HFS_TYPE
+    )
+}
+
+/// Returns true if `display_format` is one of the text formats.
+pub fn is_text_format(display_format: &str) -> bool {
+    matches!(
+        display_format,
+        ASCII_TYPE
+            | UTF8_TYPE
+            | LATIN1_TYPE
+            | CP1252_TYPE
+            | SHIFT_JIS_TYPE
+            | EUC_JP_TYPE
+            | GBK_TYPE
+            | EBCDIC_TYPE
+            | UTF16LE_TYPE
+            | UTF16BE_TYPE
+    )
+}
+
+/// Escapes control characters so they are not written raw to the terminal, e.g a newline becomes `\n`.
+fn escape_non_printable(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => escaped.push_str("\\0"),
+            '\u{0}'..='\u{FF}' if character.is_control() => {
+                escaped.push_str(&format!("\\x{:02X}", character as u32))
             }
+            _ if character.is_control() => {
+                escaped.push_str(&format!("\\u{{{:X}}}", character as u32))
+            }
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Reads a 4 or 8 byte little endian integer, 4 byte integers are unsigned.
+fn le_integer(bytes: &[u8]) -> Result<i64, ()> {
+    match bytes.len() {
+        4 => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64),
+        8 => Ok(i64::from_le_bytes([
+            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+        ])),
+        size => {
+            serror!(format!(
+                "Invalid timestamp size: {}, must be 4 or 8 bytes",
+                size
+            ));
+            Err(())
         }
+    }
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
     } else {
-        let le_raw_field_data: Vec<u16> = raw_iter
-            .map(|word| u16::from_be_bytes([word[0], word[1]]))
-            .collect();
-
-        match U16CString::from_vec_with_nul(le_raw_field_data) {
-            Ok(le_data) => Ok(le_data.to_string_lossy()),
-            Err(_) => {
-                serror!("Error constructing UTF16_BE string");
-                Err(())
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Formats seconds since the Unix epoch as an ISO-8601 UTC string, e.g `2020-03-14T15:09:26Z`.
+///
+/// `fraction` is written after the seconds, padded to `fraction_digits` digits, if `fraction_digits` is non zero.
+/// Dates outside of `PLAUSIBLE_YEARS` are flagged as implausible.
+fn utc_string(seconds: i64, fraction: u64, fraction_digits: usize) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let second_of_day = seconds.rem_euclid(86400);
+
+    let mut utc = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60
+    );
+
+    if fraction_digits != 0 {
+        utc.push_str(&format!(".{:0width$}", fraction, width = fraction_digits));
+    }
+    utc.push('Z');
+
+    if !PLAUSIBLE_YEARS.contains(&year) {
+        utc.push_str(" (implausible)");
+    }
+    utc
+}
+
+/// Converts a 4 or 8 byte little endian Unix timestamp into an ISO-8601 UTC string.
+///
+/// `units_per_second` is 1 for seconds, 1000 for milliseconds and 1000000000 for nanoseconds.
+pub fn unix_time_string(time_bytes: &[u8], units_per_second: i64) -> Result<String, ()> {
+    let timestamp = le_integer(time_bytes)?;
+    let fraction_digits = units_per_second.to_string().len() - 1;
+
+    Ok(utc_string(
+        timestamp.div_euclid(units_per_second),
+        timestamp.rem_euclid(units_per_second) as u64,
+        fraction_digits,
+    ))
+}
+
+/// Converts an 8 byte little endian Windows FILETIME into an ISO-8601 UTC string.
+pub fn filetime_string(time_bytes: &[u8]) -> Result<String, ()> {
+    match time_bytes.len() {
+        8 => {
+            let intervals = u64::from_le_bytes([
+                time_bytes[0],
+                time_bytes[1],
+                time_bytes[2],
+                time_bytes[3],
+                time_bytes[4],
+                time_bytes[5],
+                time_bytes[6],
+                time_bytes[7],
+            ]);
+
+            Ok(utc_string(
+                (intervals / 10_000_000) as i64 - FILETIME_EPOCH_OFFSET,
+                intervals % 10_000_000,
+                7,
+            ))
+        }
+        size => {
+            serror!(format!("Invalid FILETIME size: {}, must be 8 bytes", size));
+            Err(())
+        }
+    }
+}
+
+/// Converts a 4 byte big endian Mac HFS timestamp into an ISO-8601 UTC string.
+pub fn hfs_time_string(time_bytes: &[u8]) -> Result<String, ()> {
+    match time_bytes.len() {
+        4 => Ok(utc_string(
+            u32::from_be_bytes([time_bytes[0], time_bytes[1], time_bytes[2], time_bytes[3]]) as i64
+                - HFS_EPOCH_OFFSET,
+            0,
+            0,
+        )),
+        size => {
+            serror!(format!(
+                "Invalid HFS timestamp size: {}, must be 4 bytes",
+                size
+            ));
+            Err(())
+        }
+    }
+}
+
+/// Converts a 4 or 8 byte little endian GPS timestamp into an ISO-8601 UTC string, accounting for leap seconds.
+pub fn gps_time_string(time_bytes: &[u8]) -> Result<String, ()> {
+    let gps_seconds = le_integer(time_bytes)?;
+    let leap_seconds = GPS_LEAP_SECONDS
+        .iter()
+        .filter(|leap_second| gps_seconds >= **leap_second)
+        .count() as i64;
+
+    match gps_seconds
+        .checked_add(GPS_EPOCH_OFFSET)
+        .and_then(|seconds| seconds.checked_sub(leap_seconds))
+    {
+        Some(seconds) => Ok(utc_string(seconds, 0, 0)),
+        None => Ok(format!(
+            "{} seconds after 1980-01-06 (implausible)",
+            gps_seconds
+        )),
+    }
+}
+
+/// Converts a DOS/FAT packed time and/or date into a string, a 2 byte field is either a date or a time.
+///
+/// 4 byte fields hold the time followed by the date, both little endian, and are converted to an ISO-8601 string.
+/// Fields with out of range components are flagged as invalid.
+pub fn dos_datetime_string(time_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    let word = |index: usize| u16::from_le_bytes([time_bytes[index], time_bytes[index + 1]]);
+
+    let date = |date: u16| {
+        let (year, month, day) = (1980 + (date >> 9), (date >> 5) & 0xF, date & 0x1F);
+        (
+            format!("{:04}-{:02}-{:02}", year, month, day),
+            (1..=12).contains(&month) && day != 0,
+        )
+    };
+    let time = |time: u16| {
+        let (hour, minute, second) = (time >> 11, (time >> 5) & 0x3F, (time & 0x1F) * 2);
+        (
+            format!("{:02}:{:02}:{:02}", hour, minute, second),
+            hour < 24 && minute < 60 && second < 60,
+        )
+    };
+
+    let (formatted, valid) = match (display_format, time_bytes.len()) {
+        (DOS_DATE_TYPE, 2) => date(word(0)),
+        (DOS_TIME_TYPE, 2) => time(word(0)),
+        (DOS_DATETIME_TYPE, 4) => {
+            let (time, valid_time) = time(word(0));
+            let (date, valid_date) = date(word(2));
+            (format!("{}T{}", date, time), valid_time && valid_date)
+        }
+        (_, size) => {
+            serror!(format!(
+                "Invalid {} size: {}, dos_date and dos_time must be 2 bytes, dos_datetime must be 4 bytes",
+                display_format, size
+            ));
+            return Err(());
+        }
+    };
+
+    if valid {
+        Ok(formatted)
+    } else {
+        Ok(format!("{} (invalid)", formatted))
+    }
+}
+
+/// Converts a 1, 2, 4 or 8 byte integer into a decimal string.
+pub fn integer_string(int_bytes: &[u8], signed: bool, little_endian: bool) -> Result<String, ()> {
+    if ![1, 2, 4, 8].contains(&int_bytes.len()) {
+        serror!(format!(
+            "Invalid integer size: {}, must be 1, 2, 4 or 8 bytes",
+            int_bytes.len()
+        ));
+        return Err(());
+    }
+
+    let mut be_bytes = int_bytes.to_vec();
+    if little_endian {
+        be_bytes.reverse();
+    }
+
+    let value = be_bytes
+        .iter()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64);
+
+    if signed {
+        let unused_bits = 64 - be_bytes.len() as u32 * 8;
+        Ok((((value << unused_bits) as i64) >> unused_bits).to_string())
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Converts `width` bits, starting `offset` bits above the least significant bit of an integer of up to 8 bytes, into
+/// a decimal string.
+///
+/// `None` is returned if the bits do not fit in the integer.
+pub fn bit_range_string(
+    int_bytes: &[u8],
+    little_endian: bool,
+    offset: u32,
+    width: u32,
+    signed: bool,
+) -> Option<String> {
+    if int_bytes.len() > 8 || width == 0 || offset + width > int_bytes.len() as u32 * 8 {
+        return None;
+    }
+
+    let mut be_bytes = int_bytes.to_vec();
+    if little_endian {
+        be_bytes.reverse();
+    }
+
+    let value = be_bytes
+        .iter()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64);
+
+    // Shift the range up to the top of the value and back down again, sign extending it if it is signed.
+    let unused_bits = 64 - width;
+    let range = value << (unused_bits - offset);
+
+    if signed {
+        Some(((range as i64) >> unused_bits).to_string())
+    } else {
+        Some((range >> unused_bits).to_string())
+    }
+}
+
+/// Converts a 4 byte float or 8 byte double into a decimal string, extreme magnitudes are shown in scientific notation.
+pub fn float_string(float_bytes: &[u8], little_endian: bool) -> Result<String, ()> {
+    let mut be_bytes = float_bytes.to_vec();
+    if little_endian {
+        be_bytes.reverse();
+    }
+
+    match be_bytes.len() {
+        4 => Ok(float_to_string(f32::from_be_bytes([
+            be_bytes[0],
+            be_bytes[1],
+            be_bytes[2],
+            be_bytes[3],
+        ]))),
+        8 => Ok(float_to_string(f64::from_be_bytes([
+            be_bytes[0],
+            be_bytes[1],
+            be_bytes[2],
+            be_bytes[3],
+            be_bytes[4],
+            be_bytes[5],
+            be_bytes[6],
+            be_bytes[7],
+        ]))),
+        size => {
+            serror!(format!(
+                "Invalid float size: {}, must be 4 or 8 bytes",
+                size
+            ));
+            Err(())
+        }
+    }
+}
+
+/// Scales a number by `scale` and `offset` and appends `unit`, e.g `raw * 0.01 - 40` with the unit `°C`.
+///
+/// Values in the unit `B` are shown as human readable sizes. Scaled values are shown with as many decimal places as
+/// `scale` and `offset` have. `None` is returned if `number` is not a plain number, e.g a flagged varint.
+pub fn scaled_string(number: &str, scale: f64, offset: f64, unit: &str) -> Option<String> {
+    let value = number.parse::<f64>().ok()?;
+
+    if unit == BYTES_UNIT {
+        return Some(human_size(value * scale + offset));
+    }
+
+    let scaled = if scale == 1.0 && offset == 0.0 {
+        number.to_string()
+    } else {
+        let decimals = decimal_places(scale).max(decimal_places(offset));
+        let scaled = format!("{:.*}", decimals, value * scale + offset);
+
+        match decimals {
+            0 => scaled,
+            _ => scaled
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string(),
+        }
+    };
+
+    match unit {
+        "" => Some(scaled),
+        _ => Some(format!("{} {}", scaled, unit)),
+    }
+}
+
+/// Gets the number of decimal places needed to show `number`, up to `MAX_SCALED_DECIMALS`.
+pub fn decimal_places(number: f64) -> usize {
+    let number = number.to_string();
+
+    number
+        .find('.')
+        .map_or(0, |point| number.len() - point - 1)
+        .min(MAX_SCALED_DECIMALS)
+}
+
+/// Converts a number of bytes into a human readable size using binary prefixes, e.g `512 B` or `31.5 MiB`.
+pub fn human_size(bytes: f64) -> String {
+    let mut size = bytes;
+    let mut unit = 0;
+
+    while size.abs() >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} {}", size.round(), SIZE_UNITS[unit]),
+        _ => format!(
+            "{} {}",
+            format!("{:.1}", size).trim_end_matches(".0"),
+            SIZE_UNITS[unit]
+        ),
+    }
+}
+
+/// Checks if the display format is a variable length integer whose size is only known once it has been read.
+pub fn is_varint_format(display_format: &str) -> bool {
+    matches!(
+        display_format,
+        ULEB128_TYPE | SLEB128_TYPE | VARINT_TYPE | ZIGZAG_TYPE
+    )
+}
+
+/// Gets the number of bytes the varint at the start of `varint_bytes` occupies.
+///
+/// If no byte without the continuation bit is found the whole slice is used.
+pub fn varint_size(varint_bytes: &[u8]) -> usize {
+    varint_bytes
+        .iter()
+        .position(|byte| byte & 0x80 == 0)
+        .map_or(varint_bytes.len(), |index| index + 1)
+}
+
+/// Decodes a LEB128 or protocol buffers varint into a decimal string.
+///
+/// Varints whose last byte still has the continuation bit set are flagged as unterminated.
+pub fn varint_string(varint_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    if varint_bytes.is_empty() || varint_bytes.len() > MAX_VARINT_SIZE {
+        serror!(format!(
+            "Invalid {} size: {}, must be between 1 and {} bytes",
+            display_format,
+            varint_bytes.len(),
+            MAX_VARINT_SIZE
+        ));
+        return Err(());
+    }
+
+    let mut value: u128 = 0;
+    let mut shift = 0;
+
+    for byte in varint_bytes {
+        value |= ((byte & 0x7F) as u128) << shift;
+        shift += 7;
+    }
+
+    let last_byte = varint_bytes[varint_bytes.len() - 1];
+
+    let formatted = match display_format {
+        SLEB128_TYPE if last_byte & 0x40 != 0 => ((value | (!0u128 << shift)) as i128).to_string(),
+        ZIGZAG_TYPE => ((value >> 1) as i128 ^ -((value & 1) as i128)).to_string(),
+        _ => value.to_string(),
+    };
+
+    if last_byte & 0x80 != 0 {
+        Ok(format!("{} (unterminated)", formatted))
+    } else {
+        Ok(formatted)
+    }
+}
+
+/// Converts packed BCD into a string of digits.
+///
+/// A final nibble of 0xA, 0xC, 0xE or 0xF is treated as a positive sign and 0xB or 0xD as a negative sign, as in
+/// COBOL packed decimals. Any other nibble above 9 is shown as `?` and the field is flagged as invalid.
+pub fn bcd_string(bcd_bytes: &[u8]) -> String {
+    let nibbles: Vec<u8> = bcd_bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0F])
+        .collect();
+
+    let (sign, digits) = match nibbles.split_last() {
+        Some((0xB, digits)) | Some((0xD, digits)) => ("-", digits),
+        Some((0xA, digits)) | Some((0xC, digits)) | Some((0xE, digits)) | Some((0xF, digits)) => {
+            ("", digits)
+        }
+        _ => ("", &nibbles[..]),
+    };
+
+    let formatted: String = digits
+        .iter()
+        .map(|digit| std::char::from_digit(*digit as u32, 10).unwrap_or('?'))
+        .collect();
+
+    if formatted.contains('?') {
+        format!("{}{} (invalid)", sign, formatted)
+    } else {
+        format!("{}{}", sign, formatted)
+    }
+}
+
+/// Converts a float into a decimal string, magnitudes that would print a long run of zeros are written in scientific
+/// notation instead, e.g `1e-300` rather than 300 decimal places.
+fn float_to_string<T: Copy + Display + LowerExp + Into<f64>>(value: T) -> String {
+    let magnitude = value.into().abs();
+
+    if magnitude.is_finite() && magnitude != 0.0 && !(1e-6..1e15).contains(&magnitude) {
+        format!("{:e}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parses a fixed point display format such as `q16.16` or `uq8.8` into whether it is signed, the number of integer
+/// bits and the number of fraction bits.
+///
+/// The integer bits include the sign bit, so `q1.15` is a 16 bit format.
+pub fn parse_fixed_point(display_format: &str) -> Option<(bool, u32, u32)> {
+    let (signed, bits) = match display_format.strip_prefix(UNSIGNED_FIXED_POINT_PREFIX) {
+        Some(bits) => (false, bits),
+        None => (true, display_format.strip_prefix(FIXED_POINT_PREFIX)?),
+    };
+
+    let (integer_bits, fraction_bits) = bits.split_once('.')?;
+
+    Some((
+        signed,
+        integer_bits.parse().ok()?,
+        fraction_bits.parse().ok()?,
+    ))
+}
+
+/// Converts a little endian Qm.n fixed point number into a decimal string.
+pub fn fixed_point_string(fixed_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    let (signed, integer_bits, fraction_bits) =
+        parse_fixed_point(display_format).ok_or_else(|| {
+            serror!(format!("Invalid fixed point format: {}", display_format));
+        })?;
+
+    let total_bits = integer_bits.checked_add(fraction_bits).ok_or_else(|| {
+        serror!(format!(
+            "Invalid fixed point format: {}, the integer and fraction bits must add up to at most 64",
+            display_format
+        ));
+    })?;
+
+    if fixed_bytes.is_empty() || fixed_bytes.len() > 8 || total_bits != fixed_bytes.len() as u32 * 8
+    {
+        serror!(format!(
+            "Invalid {} size: {}, the integer and fraction bits must add up to the field size of at most 8 bytes",
+            display_format,
+            fixed_bytes.len()
+        ));
+        return Err(());
+    }
+
+    let mut le_bytes = [0u8; 8];
+    le_bytes[..fixed_bytes.len()].copy_from_slice(fixed_bytes);
+    let raw = u64::from_le_bytes(le_bytes);
+
+    let value = if signed {
+        // Shift the sign bit up to bit 63 and back down again to sign extend it.
+        ((raw << (64 - total_bits)) as i64 >> (64 - total_bits)) as f64
+    } else {
+        raw as f64
+    };
+
+    Ok(float_to_string(value / 2f64.powi(fraction_bits as i32)))
+}
+
+/// Converts a 2 byte little endian half precision float or bfloat16 into a decimal string.
+pub fn half_float_string(float_bytes: &[u8], display_format: &str) -> Result<String, ()> {
+    check_size(float_bytes, 2, display_format)?;
+
+    let bits = u16::from_le_bytes([float_bytes[0], float_bytes[1]]) as u32;
+
+    let value = match display_format {
+        BFLOAT16_TYPE => f32::from_bits(bits << 16),
+        _ => {
+            let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+            let exponent = (bits >> 10) & 0x1F;
+            let mantissa = bits & 0x3FF;
+
+            match exponent {
+                0 => sign * mantissa as f32 * 2f32.powi(-24),
+                0x1F if mantissa == 0 => sign * f32::INFINITY,
+                0x1F => f32::NAN,
+                _ => f32::from_bits(
+                    ((bits & 0x8000) << 16) | ((exponent + 112) << 23) | (mantissa << 13),
+                ),
             }
         }
+    };
+
+    Ok(float_to_string(value))
+}
+
+/// Assembly syntaxes disassembled code can be displayed in.
+#[derive(Clone, Copy)]
+pub enum AsmSyntaxes {
+    Nasm,
+    Intel,
+    Gas,
+    Masm,
+}
+
+/// Returns the bitness of an x86 display format, or None if `display_format` is not an x86 format.
+pub fn x86_bitness(display_format: &str) -> Option<u32> {
+    match display_format {
+        X86_16_TYPE => Some(16),
+        X86_32_TYPE => Some(32),
+        X86_64_TYPE => Some(64),
+        _ => None,
     }
 }
 
+/// Returns true if `display_format` is one of the assembly formats.
+pub fn is_code_format(display_format: &str) -> bool {
+    matches!(
+        display_format,
+        X86_16_TYPE
+            | X86_32_TYPE
+            | X86_64_TYPE
+            | ARM32_TYPE
+            | THUMB_TYPE
+            | AARCH64_TYPE
+            | RISCV_TYPE
+    )
+}
+
+/// Holds a single disassembled instruction.
+pub struct DissassLine {
+    /// Address of the instruction, the offset into the binary file it was read from.
+    pub address: u64,
+    /// The machine code of the instruction.
+    pub bytes: Vec<u8>,
+    /// The instruction in the selected assembly syntax.
+    pub instruction: String,
+    /// The address a jump or call instruction branches to.
+    pub branch_target: Option<u64>,
+}
+
 /// Holds the outputted assembly as well as the line count.
 pub struct DissassOutput {
     pub output: String,
     pub line_count: u32,
+    /// Every disassembled instruction, used to print the code listing.
+    pub lines: Vec<DissassLine>,
 }
 
 impl DissassOutput {
@@ -83,21 +1164,519 @@ impl DissassOutput {
         Self {
             output: String::from(""),
             line_count: 0,
+            lines: Vec::new(),
         }
     }
 
-    /// Converts u8 array into x86 assembly string and populates `self.output` and `self.line_count`.
-    pub fn format_x86(&mut self, bitness: u32, machine_code: &[u8]) {
-        let mut decoder = Decoder::new(bitness, machine_code, DecoderOptions::NONE);
-        let mut formatter = NasmFormatter::new();
+    /// Converts u8 array into an assembly string based on `display_format`, which must be one of the assembly formats.
+    ///
+    /// `address` is the address of the first instruction, which relative branch targets are calculated from.
+    /// `syntax` only applies to x86 formats.
+    pub fn format_code(
+        &mut self,
+        display_format: &str,
+        machine_code: &[u8],
+        address: u64,
+        syntax: AsmSyntaxes,
+    ) {
+        match display_format {
+            ARM32_TYPE => self.format_risc(arm_disass::decode_arm32, machine_code, address),
+            THUMB_TYPE => self.format_risc(arm_disass::decode_thumb, machine_code, address),
+            AARCH64_TYPE => self.format_risc(arm_disass::decode_aarch64, machine_code, address),
+            RISCV_TYPE => self.format_risc(riscv_disass::decode_riscv, machine_code, address),
+            _ => self.format_x86(
+                x86_bitness(display_format).unwrap_or(32),
+                machine_code,
+                address,
+                syntax,
+            ),
+        }
+    }
+
+    /// Converts u8 array into assembly using `decode`, which decodes the instruction at the start of a slice.
+    fn format_risc(
+        &mut self,
+        decode: fn(&[u8], u64) -> DissassLine,
+        machine_code: &[u8],
+        address: u64,
+    ) {
+        let mut position = 0;
+
+        while position < machine_code.len() {
+            let line = decode(&machine_code[position..], address + position as u64);
+
+            position += line.bytes.len().max(1);
+            self.output.push_str(&line.instruction);
+            self.output.push('\n');
+            self.line_count += 1;
+            self.lines.push(line);
+        }
+    }
+
+    /// Converts u8 array into x86 assembly string and populates `self.output`, `self.line_count` and `self.lines`.
+    ///
+    /// `address` is the address of the first instruction, which relative branch targets are calculated from.
+    pub fn format_x86(
+        &mut self,
+        bitness: u32,
+        machine_code: &[u8],
+        address: u64,
+        syntax: AsmSyntaxes,
+    ) {
+        let mut decoder = Decoder::with_ip(bitness, machine_code, address, DecoderOptions::NONE);
+        let mut formatter: Box<dyn Formatter> = match syntax {
+            AsmSyntaxes::Nasm => Box::new(NasmFormatter::new()),
+            AsmSyntaxes::Intel => Box::new(IntelFormatter::new()),
+            AsmSyntaxes::Gas => Box::new(GasFormatter::new()),
+            AsmSyntaxes::Masm => Box::new(MasmFormatter::new()),
+        };
         let mut instruction = Instruction::default();
 
         while decoder.can_decode() {
             decoder.decode_out(&mut instruction);
 
-            formatter.format(&instruction, &mut self.output);
-            self.output.push_str("\n");
+            let mut instruction_string = String::new();
+            formatter.format(&instruction, &mut instruction_string);
+
+            let start = (instruction.ip() - address) as usize;
+            let end = (start + instruction.len()).min(machine_code.len());
+
+            let branch_target = match instruction.flow_control() {
+                FlowControl::UnconditionalBranch
+                | FlowControl::ConditionalBranch
+                | FlowControl::Call => match instruction.op0_kind() {
+                    OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                        Some(instruction.near_branch_target())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            self.output.push_str(&instruction_string);
+            self.output.push('\n');
             self.line_count += 1;
+
+            self.lines.push(DissassLine {
+                address: instruction.ip(),
+                bytes: machine_code[start..end].to_vec(),
+                instruction: instruction_string,
+                branch_target,
+            });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn x86_formats_select_the_bitness() {
+        assert_eq!(x86_bitness(X86_16_TYPE), Some(16));
+        assert_eq!(x86_bitness(X86_32_TYPE), Some(32));
+        assert_eq!(x86_bitness(X86_64_TYPE), Some(64));
+        assert_eq!(x86_bitness(HEXLE_TYPE), None);
+    }
+
+    #[test]
+    fn x86_is_decoded_with_the_selected_bitness() {
+        let mut disassembly = DissassOutput::new();
+        disassembly.format_x86(16, &[0xB8, 0x01, 0x00], 0, AsmSyntaxes::Nasm);
+        assert_eq!(disassembly.output, "mov ax,1\n");
+
+        let mut disassembly = DissassOutput::new();
+        disassembly.format_x86(32, &[0xB8, 0x01, 0x00, 0x00, 0x00], 0, AsmSyntaxes::Nasm);
+        assert_eq!(disassembly.output, "mov eax,1\n");
+    }
+
+    #[test]
+    fn x86_is_formatted_in_the_selected_syntax() {
+        let mut disassembly = DissassOutput::new();
+        disassembly.format_x86(32, &[0xB8, 0x01, 0x00, 0x00, 0x00], 0, AsmSyntaxes::Gas);
+        assert_eq!(disassembly.output, "mov $1,%eax\n");
+    }
+
+    #[test]
+    fn x86_lines_hold_addresses_bytes_and_branch_targets() {
+        let mut disassembly = DissassOutput::new();
+        disassembly.format_x86(16, &[0x90, 0xEB, 0xFD], 0x7C00, AsmSyntaxes::Nasm);
+
+        assert_eq!(disassembly.line_count, 2);
+        assert_eq!(disassembly.lines[1].address, 0x7C01);
+        assert_eq!(disassembly.lines[1].bytes, [0xEB, 0xFD]);
+        assert_eq!(disassembly.lines[0].branch_target, None);
+        assert_eq!(disassembly.lines[1].branch_target, Some(0x7C00));
+    }
+
+    #[test]
+    fn arm_code_is_decoded_in_the_code_listing() {
+        let mut disassembly = DissassOutput::new();
+        disassembly.format_code(
+            ARM32_TYPE,
+            &[0x2E, 0x00, 0x00, 0xEA, 0x1E, 0xFF, 0x2F, 0xE1],
+            0x0800_0000,
+            AsmSyntaxes::Nasm,
+        );
+
+        assert_eq!(disassembly.line_count, 2);
+        assert_eq!(disassembly.lines[0].branch_target, Some(0x0800_00C0));
+        assert_eq!(disassembly.lines[1].address, 0x0800_0004);
+        assert_eq!(disassembly.lines[1].instruction, "bx lr");
+    }
+
+    #[test]
+    fn code_formats_are_recognised() {
+        assert!(is_code_format(X86_64_TYPE));
+        assert!(is_code_format(THUMB_TYPE));
+        assert!(is_code_format(RISCV_TYPE));
+        assert!(!is_code_format(ASCII_TYPE));
+    }
+
+    #[test]
+    fn unix_timestamps_are_converted_to_utc() {
+        assert_eq!(
+            unix_time_string(&[0xA6, 0xF3, 0x6C, 0x5E], 1).unwrap(),
+            "2020-03-14T15:09:26Z"
+        );
+        assert_eq!(
+            unix_time_string(&[0xEB, 0xC0, 0x97, 0xD9, 0x70, 0x01, 0x00, 0x00], 1_000).unwrap(),
+            "2020-03-14T15:09:26.123Z"
+        );
+        assert_eq!(
+            unix_time_string(&[0x00, 0x00, 0x00, 0x00], 1).unwrap(),
+            "1970-01-01T00:00:00Z (implausible)"
+        );
+        assert!(unix_time_string(&[0x00, 0x00], 1).is_err());
+    }
+
+    #[test]
+    fn filetime_and_hfs_timestamps_are_converted_to_utc() {
+        assert_eq!(
+            filetime_string(&[0x87, 0x6D, 0x1E, 0x8D, 0x12, 0xFA, 0xD5, 0x01]).unwrap(),
+            "2020-03-14T15:09:26.1234567Z"
+        );
+        assert_eq!(
+            hfs_time_string(&[0xDA, 0x92, 0xA4, 0x26]).unwrap(),
+            "2020-03-14T15:09:26Z"
+        );
+    }
+
+    #[test]
+    fn dos_dates_and_times_are_unpacked() {
+        assert_eq!(
+            dos_datetime_string(&[0x2D, 0x79, 0x6E, 0x50], DOS_DATETIME_TYPE).unwrap(),
+            "2020-03-14T15:09:26"
+        );
+        assert_eq!(
+            dos_datetime_string(&[0x6E, 0x50], DOS_DATE_TYPE).unwrap(),
+            "2020-03-14"
+        );
+        assert_eq!(
+            dos_datetime_string(&[0x00, 0x00], DOS_DATE_TYPE).unwrap(),
+            "1980-00-00 (invalid)"
+        );
+        assert!(dos_datetime_string(&[0x00, 0x00], DOS_DATETIME_TYPE).is_err());
+    }
+
+    #[test]
+    fn gps_timestamps_account_for_leap_seconds() {
+        assert_eq!(
+            gps_time_string(&[0x00, 0x00, 0x00, 0x00]).unwrap(),
+            "1980-01-06T00:00:00Z"
+        );
+        assert_eq!(
+            gps_time_string(&[0x00, 0xCA, 0x9A, 0x3B]).unwrap(),
+            "2011-09-14T01:46:25Z"
+        );
+    }
+
+    #[test]
+    fn gps_timestamps_that_overflow_are_implausible() {
+        assert_eq!(
+            gps_time_string(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]).unwrap(),
+            "9223372036854775807 seconds after 1980-01-06 (implausible)"
+        );
+    }
+
+    #[test]
+    fn ipv6_and_mac_addresses_are_formatted() {
+        let mut loopback = [0u8; 16];
+        loopback[15] = 1;
+        assert_eq!(ipv6_string(&loopback).unwrap(), "::1");
+        assert!(ipv6_string(&loopback[..4]).is_err());
+
+        assert_eq!(
+            mac_string(&[0x00, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E], MAC_TYPE).unwrap(),
+            "00:1a:2b:3c:4d:5e"
+        );
+        assert!(mac_string(&[0x00, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E], EUI64_TYPE).is_err());
+    }
+
+    #[test]
+    fn guids_are_mixed_endian_and_uuids_big_endian() {
+        let efi_system_partition = [
+            0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E,
+            0xC9, 0x3B,
+        ];
+        assert_eq!(
+            guid_string(&efi_system_partition, GUID_TYPE).unwrap(),
+            "{C12A7328-F81F-11D2-BA4B-00A0C93EC93B}"
+        );
+
+        let uuid = [
+            0x55, 0x0E, 0x84, 0x00, 0xE2, 0x9B, 0x41, 0xD4, 0xA7, 0x16, 0x44, 0x66, 0x55, 0x44,
+            0x00, 0x00,
+        ];
+        assert_eq!(
+            guid_string(&uuid, UUID_TYPE).unwrap(),
+            "550e8400-e29b-41d4-a716-446655440000 (v4)"
+        );
+    }
+
+    #[test]
+    fn ports_include_well_known_service_names() {
+        assert_eq!(port_string(&[0x01, 0xBB], false).unwrap(), "443 (https)");
+        assert_eq!(port_string(&[0xBB, 0x01], true).unwrap(), "443 (https)");
+        assert_eq!(port_string(&[0x30, 0x39], false).unwrap(), "12345");
+    }
+
+    #[test]
+    fn socket_addresses_are_formatted() {
+        assert_eq!(
+            socket_string(&[127, 0, 0, 1, 0x00, 0x50], SOCKET4_TYPE).unwrap(),
+            "127.0.0.1:80 (http)"
+        );
+
+        let mut sockaddr_in = [0u8; 16];
+        sockaddr_in[..8].copy_from_slice(&[0x02, 0x00, 0x00, 0x16, 10, 0, 0, 1]);
+        assert_eq!(
+            sockaddr_string(&sockaddr_in, SOCKADDR_IN_TYPE).unwrap(),
+            "10.0.0.1:22 (ssh)"
+        );
+
+        sockaddr_in[0] = 0x0A;
+        assert_eq!(
+            sockaddr_string(&sockaddr_in, SOCKADDR_IN_TYPE).unwrap(),
+            "10.0.0.1:22 (ssh) (unexpected family 10)"
+        );
+    }
+
+    #[test]
+    fn sockaddr_in6_includes_the_scope_id() {
+        let mut sockaddr_in6 = [0u8; 28];
+        sockaddr_in6[..4].copy_from_slice(&[0x0A, 0x00, 0x01, 0xBB]);
+        sockaddr_in6[8..10].copy_from_slice(&[0xFE, 0x80]);
+        sockaddr_in6[23] = 1;
+        sockaddr_in6[24] = 3;
+
+        assert_eq!(
+            sockaddr_string(&sockaddr_in6, SOCKADDR_IN6_TYPE).unwrap(),
+            "[fe80::1]:443 (https) scope 3"
+        );
+    }
+
+    #[test]
+    fn ascii_escapes_control_characters_and_bytes_above_0x7f() {
+        assert_eq!(
+            text_string(b"MZ\x90\x00\n\x1B", ASCII_TYPE),
+            "MZ\\x90\\0\\n\\x1B"
+        );
+    }
+
+    #[test]
+    fn trailing_nul_padding_is_trimmed() {
+        assert_eq!(text_string(b"NAME\0\0\0\0", ASCII_TYPE), "NAME");
+        assert_eq!(text_string(&[0u8; 8], UTF8_TYPE), "");
+        assert_eq!(
+            utf16_string(&[0x41, 0x00, 0x42, 0x00, 0x00, 0x00, 0x00], true),
+            "AB"
+        );
+    }
+
+    #[test]
+    fn text_encodings_are_decoded() {
+        assert_eq!(text_string("caf\u{E9}".as_bytes(), UTF8_TYPE), "caf\u{E9}");
+        assert_eq!(text_string(b"caf\xE9", LATIN1_TYPE), "caf\u{E9}");
+        assert_eq!(text_string(b"\x80 5", CP1252_TYPE), "\u{20AC} 5");
+        assert_eq!(
+            text_string(&[0x82, 0xA0, 0x88, 0xEA], SHIFT_JIS_TYPE),
+            "\u{3042}\u{4E00}"
+        );
+        assert_eq!(text_string(&[0xA4, 0xA2], EUC_JP_TYPE), "\u{3042}");
+        assert_eq!(text_string(&[0xC4, 0xE3], GBK_TYPE), "\u{4F60}");
+        assert_eq!(text_string(&[0xC8, 0x89], EBCDIC_TYPE), "Hi");
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced_instead_of_failing() {
+        assert_eq!(text_string(b"a\xFFb", UTF8_TYPE), "a\u{FFFD}b");
+        assert_eq!(utf16_string(&[0x00, 0xD8, 0x41, 0x00], true), "\u{FFFD}A");
+    }
+
+    #[test]
+    fn utf16_formats_match_their_byte_order() {
+        assert_eq!(utf16_string(&[0x00, 0x41, 0x00, 0x42], false), "AB");
+        assert_eq!(utf16_string(&[0x41, 0x00, 0x42, 0x00], true), "AB");
+        assert_eq!(UTF16LE_TYPE, "utf16le");
+        assert_eq!(UTF16BE_TYPE, "utf16be");
+    }
+
+    #[test]
+    fn fixed_point_bits_that_overflow_are_an_error() {
+        assert_eq!(
+            fixed_point_string(&[0x80, 0x01], "uq8.8"),
+            Ok(String::from("1.5"))
+        );
+        assert!(fixed_point_string(&[0; 4], "q4294967295.1").is_err());
+    }
+
+    #[test]
+    fn varints_are_decoded() {
+        assert_eq!(varint_size(&[0xE5, 0x8E, 0x26, 0xFF]), 3);
+        assert_eq!(
+            varint_string(&[0xE5, 0x8E, 0x26], ULEB128_TYPE).unwrap(),
+            "624485"
+        );
+        assert_eq!(
+            varint_string(&[0xC0, 0xBB, 0x78], SLEB128_TYPE).unwrap(),
+            "-123456"
+        );
+        assert_eq!(varint_string(&[0x03], ZIGZAG_TYPE).unwrap(), "-2");
+        assert_eq!(varint_string(&[0x04], ZIGZAG_TYPE).unwrap(), "2");
+        assert_eq!(
+            varint_string(&[0x80, 0x80], VARINT_TYPE).unwrap(),
+            "0 (unterminated)"
+        );
+        assert!(varint_string(&[0x80; 11], VARINT_TYPE).is_err());
+    }
+
+    #[test]
+    fn packed_bcd_is_decoded() {
+        assert_eq!(bcd_string(&[0x20, 0x24]), "2024");
+        assert_eq!(bcd_string(&[0x12, 0x3D]), "-123");
+        assert_eq!(bcd_string(&[0x1A, 0x2F]), "1?2 (invalid)");
+    }
+
+    #[test]
+    fn fixed_point_formats_are_parsed() {
+        assert_eq!(parse_fixed_point("q1.15"), Some((true, 1, 15)));
+        assert_eq!(parse_fixed_point("uq8.8"), Some((false, 8, 8)));
+        assert_eq!(parse_fixed_point("quad"), None);
+        assert_eq!(
+            fixed_point_string(&[0x00, 0xC0], "q1.15"),
+            Ok(String::from("-0.5"))
+        );
+        assert!(fixed_point_string(&[0x00, 0xC0], "q8.16").is_err());
+    }
+
+    #[test]
+    fn half_floats_are_decoded() {
+        assert_eq!(half_float_string(&[0x00, 0x3C], HALF_TYPE).unwrap(), "1");
+        assert_eq!(half_float_string(&[0x00, 0xC1], HALF_TYPE).unwrap(), "-2.5");
+        assert_eq!(half_float_string(&[0x00, 0x7C], HALF_TYPE).unwrap(), "inf");
+        assert_eq!(
+            half_float_string(&[0x80, 0x3F], BFLOAT16_TYPE).unwrap(),
+            "1"
+        );
+    }
+
+    #[test]
+    fn extreme_magnitudes_are_shown_in_scientific_notation() {
+        assert_eq!(
+            half_float_string(&[0x01, 0x00], HALF_TYPE).unwrap(),
+            "5.9604645e-8"
+        );
+        assert_eq!(
+            half_float_string(&[0x7F, 0x7F], BFLOAT16_TYPE).unwrap(),
+            "3.3895314e38"
+        );
+        assert_eq!(
+            fixed_point_string(&[0x01, 0, 0, 0, 0, 0, 0, 0], "uq1.63").unwrap(),
+            "1.0842021724855044e-19"
+        );
+        assert_eq!(float_to_string(1e-300), "1e-300");
+        assert_eq!(float_to_string(f64::MAX), "1.7976931348623157e308");
+        assert_eq!(float_to_string(0.0), "0");
+        assert_eq!(float_to_string(123.25), "123.25");
+    }
+
+    #[test]
+    fn integers_are_decoded() {
+        assert_eq!(integer_string(&[0xFF, 0xFF], true, true).unwrap(), "-1");
+        assert_eq!(integer_string(&[0xFF, 0xFF], false, true).unwrap(), "65535");
+        assert_eq!(integer_string(&[0x12, 0x34], false, false).unwrap(), "4660");
+        assert_eq!(
+            integer_string(&[0x80, 0, 0, 0, 0, 0, 0, 0], true, false).unwrap(),
+            "-9223372036854775808"
+        );
+        assert!(integer_string(&[0, 0, 0], false, true).is_err());
+    }
+
+    #[test]
+    fn floats_are_decoded() {
+        assert_eq!(float_string(&1.5f32.to_le_bytes(), true).unwrap(), "1.5");
+        assert_eq!(
+            float_string(&(-0.25f64).to_be_bytes(), false).unwrap(),
+            "-0.25"
+        );
+        assert_eq!(
+            float_string(&1e-300f64.to_le_bytes(), true).unwrap(),
+            "1e-300"
+        );
+        assert_eq!(
+            float_string(&f64::MAX.to_be_bytes(), false).unwrap(),
+            "1.7976931348623157e308"
+        );
+        assert!(float_string(&[0, 0], true).is_err());
+    }
+
+    #[test]
+    fn scale_offset_and_unit_are_applied() {
+        assert_eq!(
+            scaled_string("2650", 0.01, -40.0, "°C"),
+            Some(String::from("-13.5 °C"))
+        );
+        assert_eq!(
+            scaled_string("7", 1.0, 0.0, "ms"),
+            Some(String::from("7 ms"))
+        );
+        assert_eq!(scaled_string("7", 2.0, 1.0, ""), Some(String::from("15")));
+        assert_eq!(scaled_string("7 (flagged)", 2.0, 0.0, ""), None);
+    }
+
+    #[test]
+    fn byte_units_are_human_readable() {
+        assert_eq!(
+            scaled_string("512", 1.0, 0.0, BYTES_UNIT),
+            Some(String::from("512 B"))
+        );
+        assert_eq!(
+            scaled_string("33030144", 1.0, 0.0, BYTES_UNIT),
+            Some(String::from("31.5 MiB"))
+        );
+        assert_eq!(human_size(1024.0), "1 KiB");
+    }
+
+    #[test]
+    fn bit_ranges_are_extracted() {
+        assert_eq!(
+            bit_range_string(&[0xAB, 0x0F], true, 4, 8, false),
+            Some(String::from("250"))
+        );
+        assert_eq!(
+            bit_range_string(&[0xAB, 0x0F], true, 4, 8, true),
+            Some(String::from("-6"))
+        );
+        assert_eq!(
+            bit_range_string(&[0x0F, 0xAB], false, 4, 8, false),
+            Some(String::from("250"))
+        );
+        assert_eq!(
+            bit_range_string(&[0xFF; 8], true, 0, 64, true),
+            Some(String::from("-1"))
+        );
+        assert_eq!(bit_range_string(&[0xAB, 0x0F], true, 12, 5, false), None);
+        assert_eq!(bit_range_string(&[0xAB], true, 0, 0, false), None);
+    }
+}
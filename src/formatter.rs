@@ -0,0 +1,1060 @@
+//! Module containing the registry of display formats a grammar field can use.
+//!
+//! Each format is a `Formatter` that declares its name, the field sizes it accepts, whether the `-e` flag reverses
+//! its bytes and how the raw bytes of a field are rendered. Adding a format only requires adding it to `FORMATTERS`.
+use crate::format;
+use crate::gram_parse;
+use crate::script;
+use prettytable::Table;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Disassembled code fields longer than this many instructions are only shown in the code listing, not in the standard table.
+const MAX_DISASSEMBLY_LINES: u32 = 5;
+
+/// The field sizes, in bytes, a format can be used with.
+#[derive(Clone, Copy)]
+pub enum FieldSizes {
+    /// Any size.
+    Any,
+    /// One of the listed sizes.
+    OneOf(&'static [usize]),
+    /// Any size from 1 up to and including the given size.
+    UpTo(usize),
+}
+
+impl FieldSizes {
+    /// Checks if a field of `size` bytes can use the format.
+    pub fn accepts(self, size: usize) -> bool {
+        match self {
+            FieldSizes::Any => true,
+            FieldSizes::OneOf(sizes) => sizes.contains(&size),
+            FieldSizes::UpTo(max_size) => (1..=max_size).contains(&size),
+        }
+    }
+}
+
+impl fmt::Display for FieldSizes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldSizes::Any => write!(f, "any"),
+            FieldSizes::OneOf(sizes) => write!(
+                f,
+                "{}",
+                sizes
+                    .iter()
+                    .map(|size| size.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            FieldSizes::UpTo(max_size) => write!(f, "1-{}", max_size),
+        }
+    }
+}
+
+/// Information about the field being rendered besides its bytes.
+pub struct FormatContext<'a> {
+    /// The display format of the field, for formatters that handle several formats.
+    pub display_format: &'a str,
+    /// The offset of the field in the binary, used as the address of disassembled code.
+    pub field_offset: usize,
+    /// The syntax x86 code is disassembled in.
+    pub asm_syntax: format::AsmSyntaxes,
+    /// The name of the field, used when reporting script errors.
+    pub field_name: &'a str,
+    /// The filepath of the script used by `script` fields.
+    pub script_path: &'a str,
+    /// The engine `script` fields are run in.
+    pub scripts: &'a script::ScriptEngine,
+    /// The raw bytes of every field, passed to scripts.
+    pub sibling_bytes: &'a HashMap<String, Vec<u8>>,
+    /// The formatted values of the fields formatted so far, passed to scripts.
+    pub sibling_values: &'a HashMap<String, String>,
+    /// The named bit ranges of `bits` fields.
+    pub bit_ranges: &'a [gram_parse::GrammarBits],
+}
+
+/// A display format that converts the raw bytes of a field into the string shown in the "Formatted Data" column.
+pub trait Formatter: Sync {
+    /// The name of the format, as used for `display_format` in grammar files.
+    fn name(&self) -> &'static str;
+
+    /// A short description of the format, shown by `--list-formats`.
+    fn description(&self) -> &'static str;
+
+    /// The field sizes the format can be used with.
+    fn sizes(&self) -> FieldSizes;
+
+    /// Whether the bytes of the field are reversed before rendering when the `-e` flag is used.
+    fn endian_sensitive(&self) -> bool;
+
+    /// Whether the format renders a plain number, which the `scale`, `offset` and `unit` of a field can be applied to.
+    fn numeric(&self) -> bool;
+
+    /// Checks if `display_format` refers to this format.
+    fn matches(&self, display_format: &str) -> bool {
+        display_format == self.name()
+    }
+
+    /// Converts the raw bytes of a field into a formatted string.
+    fn render(&self, field_bytes: &[u8], context: &FormatContext) -> Result<String, ()>;
+}
+
+/// A format with a fixed name whose rendering is done by a plain function.
+struct SimpleFormatter {
+    name: &'static str,
+    description: &'static str,
+    sizes: FieldSizes,
+    endian_sensitive: bool,
+    numeric: bool,
+    render: fn(&[u8], &FormatContext) -> Result<String, ()>,
+}
+
+impl Formatter for SimpleFormatter {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn sizes(&self) -> FieldSizes {
+        self.sizes
+    }
+
+    fn endian_sensitive(&self) -> bool {
+        self.endian_sensitive
+    }
+
+    fn numeric(&self) -> bool {
+        self.numeric
+    }
+
+    fn render(&self, field_bytes: &[u8], context: &FormatContext) -> Result<String, ()> {
+        (self.render)(field_bytes, context)
+    }
+}
+
+/// The `qM.N` and `uqM.N` fixed point formats, whose names contain the number of integer and fraction bits.
+struct FixedPointFormatter;
+
+impl Formatter for FixedPointFormatter {
+    fn name(&self) -> &'static str {
+        "qM.N, uqM.N"
+    }
+
+    fn description(&self) -> &'static str {
+        "Signed or unsigned little endian fixed point, M integer and N fraction bits, e.g q16.16"
+    }
+
+    fn sizes(&self) -> FieldSizes {
+        FieldSizes::UpTo(8)
+    }
+
+    fn endian_sensitive(&self) -> bool {
+        true
+    }
+
+    fn numeric(&self) -> bool {
+        true
+    }
+
+    fn matches(&self, display_format: &str) -> bool {
+        format::parse_fixed_point(display_format).is_some()
+    }
+
+    fn render(&self, field_bytes: &[u8], context: &FormatContext) -> Result<String, ()> {
+        format::fixed_point_string(field_bytes, context.display_format)
+    }
+}
+
+/// Shorthand for registering a `SimpleFormatter`.
+const fn simple(
+    name: &'static str,
+    description: &'static str,
+    sizes: FieldSizes,
+    endian_sensitive: bool,
+    numeric: bool,
+    render: fn(&[u8], &FormatContext) -> Result<String, ()>,
+) -> SimpleFormatter {
+    SimpleFormatter {
+        name,
+        description,
+        sizes,
+        endian_sensitive,
+        numeric,
+        render,
+    }
+}
+
+/// Every format a grammar field can be displayed in, in the order they are listed by `--list-formats`.
+static FORMATTERS: &[&dyn Formatter] = &[
+    &simple(
+        format::HEX_TYPE,
+        "Hex string in file order, shortened like the Raw Data column",
+        FieldSizes::Any,
+        true,
+        false,
+        |bytes, _| Ok(format::hex_preview(bytes)),
+    ),
+    &simple(
+        format::HEXLE_TYPE,
+        "Little endian hex string",
+        FieldSizes::Any,
+        false,
+        false,
+        |bytes, _| Ok(format::hexle_string(bytes)),
+    ),
+    &simple(
+        format::UINT_TYPE,
+        "Little endian unsigned integer",
+        FieldSizes::OneOf(&[1, 2, 4, 8]),
+        true,
+        true,
+        |bytes, _| format::integer_string(bytes, false, true),
+    ),
+    &simple(
+        format::INT_TYPE,
+        "Little endian signed integer",
+        FieldSizes::OneOf(&[1, 2, 4, 8]),
+        true,
+        true,
+        |bytes, _| format::integer_string(bytes, true, true),
+    ),
+    &simple(
+        format::UINTBE_TYPE,
+        "Big endian unsigned integer",
+        FieldSizes::OneOf(&[1, 2, 4, 8]),
+        false,
+        true,
+        |bytes, _| format::integer_string(bytes, false, false),
+    ),
+    &simple(
+        format::INTBE_TYPE,
+        "Big endian signed integer",
+        FieldSizes::OneOf(&[1, 2, 4, 8]),
+        false,
+        true,
+        |bytes, _| format::integer_string(bytes, true, false),
+    ),
+    &simple(
+        format::BITS_TYPE,
+        "Named bit ranges of a little endian integer, e.g C bitfields",
+        FieldSizes::UpTo(8),
+        true,
+        false,
+        |bytes, context| render_bits(bytes, context, true),
+    ),
+    &simple(
+        format::BITSBE_TYPE,
+        "Named bit ranges of a big endian integer, e.g C bitfields",
+        FieldSizes::UpTo(8),
+        false,
+        false,
+        |bytes, context| render_bits(bytes, context, false),
+    ),
+    &simple(
+        format::FLOAT_TYPE,
+        "Little endian IEEE 754 float or double",
+        FieldSizes::OneOf(&[4, 8]),
+        true,
+        true,
+        |bytes, _| format::float_string(bytes, true),
+    ),
+    &simple(
+        format::FLOATBE_TYPE,
+        "Big endian IEEE 754 float or double",
+        FieldSizes::OneOf(&[4, 8]),
+        false,
+        true,
+        |bytes, _| format::float_string(bytes, false),
+    ),
+    &simple(
+        format::ASCII_TYPE,
+        "ASCII text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::UTF8_TYPE,
+        "UTF-8 text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::UTF16LE_TYPE,
+        "UTF-16 little endian text",
+        FieldSizes::Any,
+        false,
+        false,
+        |bytes, _| Ok(format::utf16_string(bytes, true)),
+    ),
+    &simple(
+        format::UTF16BE_TYPE,
+        "UTF-16 big endian text",
+        FieldSizes::Any,
+        false,
+        false,
+        |bytes, _| Ok(format::utf16_string(bytes, false)),
+    ),
+    &simple(
+        format::LATIN1_TYPE,
+        "ISO-8859-1 (Latin-1) text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::CP1252_TYPE,
+        "Windows-1252 text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::SHIFT_JIS_TYPE,
+        "Shift-JIS text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::EUC_JP_TYPE,
+        "EUC-JP text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::GBK_TYPE,
+        "GBK text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::EBCDIC_TYPE,
+        "EBCDIC (code page 037) text",
+        FieldSizes::Any,
+        false,
+        false,
+        render_text,
+    ),
+    &simple(
+        format::IPV4BE_TYPE,
+        "IPv4 address in network order",
+        FieldSizes::OneOf(&[4]),
+        false,
+        false,
+        |bytes, _| format::ipv4_string(bytes),
+    ),
+    &simple(
+        format::IPV4LE_TYPE,
+        "Little endian IPv4 address",
+        FieldSizes::OneOf(&[4]),
+        false,
+        false,
+        |bytes, _| {
+            let mut reversed_bytes = bytes.to_vec();
+            reversed_bytes.reverse();
+            format::ipv4_string(&reversed_bytes)
+        },
+    ),
+    &simple(
+        format::IPV6_TYPE,
+        "IPv6 address in network order",
+        FieldSizes::OneOf(&[16]),
+        false,
+        false,
+        |bytes, _| format::ipv6_string(bytes),
+    ),
+    &simple(
+        format::MAC_TYPE,
+        "MAC address",
+        FieldSizes::OneOf(&[6]),
+        false,
+        false,
+        |bytes, context| format::mac_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::EUI64_TYPE,
+        "EUI-64 identifier",
+        FieldSizes::OneOf(&[8]),
+        false,
+        false,
+        |bytes, context| format::mac_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::GUID_TYPE,
+        "Mixed endian Microsoft GUID",
+        FieldSizes::OneOf(&[16]),
+        false,
+        false,
+        |bytes, context| format::guid_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::UUID_TYPE,
+        "Big endian RFC 4122 UUID and its version",
+        FieldSizes::OneOf(&[16]),
+        false,
+        false,
+        |bytes, context| format::guid_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::PORTBE_TYPE,
+        "Big endian port number and service name",
+        FieldSizes::OneOf(&[2]),
+        false,
+        false,
+        |bytes, _| format::port_string(bytes, false),
+    ),
+    &simple(
+        format::PORTLE_TYPE,
+        "Little endian port number and service name",
+        FieldSizes::OneOf(&[2]),
+        false,
+        false,
+        |bytes, _| format::port_string(bytes, true),
+    ),
+    &simple(
+        format::SOCKET4_TYPE,
+        "IPv4 address and port in network order",
+        FieldSizes::OneOf(&[6]),
+        false,
+        false,
+        |bytes, context| format::socket_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::SOCKET6_TYPE,
+        "IPv6 address and port in network order",
+        FieldSizes::OneOf(&[18]),
+        false,
+        false,
+        |bytes, context| format::socket_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::SOCKADDR_IN_TYPE,
+        "sockaddr_in structure as found in memory",
+        FieldSizes::OneOf(&[16]),
+        false,
+        false,
+        |bytes, context| format::sockaddr_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::SOCKADDR_IN6_TYPE,
+        "sockaddr_in6 structure as found in memory",
+        FieldSizes::OneOf(&[28]),
+        false,
+        false,
+        |bytes, context| format::sockaddr_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::X86_16_TYPE,
+        "x86 16-bit (real mode) assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::X86_32_TYPE,
+        "x86 32-bit assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::X86_64_TYPE,
+        "x86 64-bit assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::ARM32_TYPE,
+        "32-bit ARM (A32) assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::THUMB_TYPE,
+        "ARM Thumb assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::AARCH64_TYPE,
+        "64-bit ARM (A64) assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::RISCV_TYPE,
+        "RISC-V (RV64IMC) assembly",
+        FieldSizes::Any,
+        false,
+        false,
+        render_code,
+    ),
+    &simple(
+        format::UNIX_TYPE,
+        "Unix timestamp in seconds",
+        FieldSizes::OneOf(&[4, 8]),
+        true,
+        false,
+        |bytes, _| format::unix_time_string(bytes, 1),
+    ),
+    &simple(
+        format::UNIX_MS_TYPE,
+        "Unix timestamp in milliseconds",
+        FieldSizes::OneOf(&[4, 8]),
+        true,
+        false,
+        |bytes, _| format::unix_time_string(bytes, 1_000),
+    ),
+    &simple(
+        format::UNIX_NS_TYPE,
+        "Unix timestamp in nanoseconds",
+        FieldSizes::OneOf(&[4, 8]),
+        true,
+        false,
+        |bytes, _| format::unix_time_string(bytes, 1_000_000_000),
+    ),
+    &simple(
+        format::FILETIME_TYPE,
+        "Windows FILETIME, 100ns intervals since 1601",
+        FieldSizes::OneOf(&[8]),
+        true,
+        false,
+        |bytes, _| format::filetime_string(bytes),
+    ),
+    &simple(
+        format::DOS_DATE_TYPE,
+        "DOS/FAT packed date",
+        FieldSizes::OneOf(&[2]),
+        false,
+        false,
+        |bytes, context| format::dos_datetime_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::DOS_TIME_TYPE,
+        "DOS/FAT packed time",
+        FieldSizes::OneOf(&[2]),
+        false,
+        false,
+        |bytes, context| format::dos_datetime_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::DOS_DATETIME_TYPE,
+        "DOS/FAT packed time followed by date",
+        FieldSizes::OneOf(&[4]),
+        false,
+        false,
+        |bytes, context| format::dos_datetime_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::HFS_TYPE,
+        "Big endian Mac HFS timestamp",
+        FieldSizes::OneOf(&[4]),
+        false,
+        false,
+        |bytes, _| format::hfs_time_string(bytes),
+    ),
+    &simple(
+        format::GPS_TYPE,
+        "GPS timestamp, converted to UTC",
+        FieldSizes::OneOf(&[4, 8]),
+        true,
+        false,
+        |bytes, _| format::gps_time_string(bytes),
+    ),
+    &simple(
+        format::ULEB128_TYPE,
+        "Unsigned LEB128 integer, sized while reading",
+        FieldSizes::UpTo(format::MAX_VARINT_SIZE),
+        false,
+        true,
+        render_varint,
+    ),
+    &simple(
+        format::SLEB128_TYPE,
+        "Signed LEB128 integer, sized while reading",
+        FieldSizes::UpTo(format::MAX_VARINT_SIZE),
+        false,
+        true,
+        render_varint,
+    ),
+    &simple(
+        format::VARINT_TYPE,
+        "Protocol buffers varint, sized while reading",
+        FieldSizes::UpTo(format::MAX_VARINT_SIZE),
+        false,
+        true,
+        render_varint,
+    ),
+    &simple(
+        format::ZIGZAG_TYPE,
+        "Zigzag encoded signed varint, sized while reading",
+        FieldSizes::UpTo(format::MAX_VARINT_SIZE),
+        false,
+        true,
+        render_varint,
+    ),
+    &simple(
+        format::BCD_TYPE,
+        "Packed BCD with an optional trailing sign nibble",
+        FieldSizes::Any,
+        false,
+        false,
+        |bytes, _| Ok(format::bcd_string(bytes)),
+    ),
+    &FixedPointFormatter,
+    &simple(
+        format::HALF_TYPE,
+        "IEEE 754 half precision float",
+        FieldSizes::OneOf(&[2]),
+        true,
+        true,
+        |bytes, context| format::half_float_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::BFLOAT16_TYPE,
+        "bfloat16 float",
+        FieldSizes::OneOf(&[2]),
+        true,
+        true,
+        |bytes, context| format::half_float_string(bytes, context.display_format),
+    ),
+    &simple(
+        format::SCRIPT_TYPE,
+        "Formatted by the Rhai script set with the field's script key",
+        FieldSizes::Any,
+        false,
+        true,
+        render_script,
+    ),
+];
+
+/// Renders the text formats.
+fn render_text(field_bytes: &[u8], context: &FormatContext) -> Result<String, ()> {
+    Ok(format::text_string(field_bytes, context.display_format))
+}
+
+/// Renders the variable length integer formats.
+fn render_varint(field_bytes: &[u8], context: &FormatContext) -> Result<String, ()> {
+    format::varint_string(field_bytes, context.display_format)
+}
+
+/// Renders the code formats, long disassemblies are left to the code listing.
+fn render_code(field_bytes: &[u8], context: &FormatContext) -> Result<String, ()> {
+    let mut disassembly = format::DissassOutput::new();
+    disassembly.format_code(
+        context.display_format,
+        field_bytes,
+        context.field_offset as u64,
+        context.asm_syntax,
+    );
+
+    if disassembly.line_count > MAX_DISASSEMBLY_LINES {
+        Ok(format!(
+            "{} instructions, see code listing",
+            disassembly.line_count
+        ))
+    } else {
+        Ok(disassembly.output)
+    }
+}
+
+/// Renders the bit ranges of `bits` fields as `name=value` pairs.
+fn render_bits(
+    field_bytes: &[u8],
+    context: &FormatContext,
+    little_endian: bool,
+) -> Result<String, ()> {
+    if context.bit_ranges.is_empty() {
+        serror!(format!(
+            "Field: {} uses the {} display format but has no bits",
+            context.field_name, context.display_format
+        ));
+        return Err(());
+    }
+
+    let mut ranges: Vec<String> = Vec::new();
+
+    for bit_range in context.bit_ranges.iter() {
+        let value = format::bit_range_string(
+            field_bytes,
+            little_endian,
+            bit_range.offset,
+            bit_range.width,
+            bit_range.signed,
+        )
+        .ok_or_else(|| {
+            serror!(format!(
+                "Bits: {} of field: {} must be at least 1 bit wide and fit in the {} bits of the field",
+                bit_range.name,
+                context.field_name,
+                field_bytes.len() * 8
+            ))
+        })?;
+
+        ranges.push(format!("{}={}", bit_range.name, value));
+    }
+
+    Ok(ranges.join(", "))
+}
+
+/// Renders fields with a user-defined script.
+///
+/// Script errors are reported for the field and shown as its formatted value, rather than stopping the other fields
+/// from being formatted.
+fn render_script(field_bytes: &[u8], context: &FormatContext) -> Result<String, ()> {
+    let script_input = script::ScriptInput {
+        field_name: context.field_name,
+        field_offset: context.field_offset,
+        sibling_bytes: context.sibling_bytes,
+        sibling_values: context.sibling_values,
+    };
+
+    match context
+        .scripts
+        .run(context.script_path, field_bytes, &script_input)
+    {
+        Ok(formatted) => Ok(formatted),
+        Err(e) => {
+            serror!(format!(
+                "Script: {} failed for field: {}, because {}",
+                context.script_path, context.field_name, e
+            ));
+            Ok(format!("script error: {}", e))
+        }
+    }
+}
+
+/// Finds the formatter for `display_format`.
+pub fn find_formatter(display_format: &str) -> Option<&'static dyn Formatter> {
+    FORMATTERS
+        .iter()
+        .find(|formatter| formatter.matches(display_format))
+        .copied()
+}
+
+/// Converts the raw bytes of `field` into a formatted string using the formatter for its `display_format`.
+///
+/// The bytes are reversed first if `fmt_endian_flag` is set and the format is endian sensitive. The `scale`, `offset`
+/// and `unit` of the field are applied to the formatted number, values that are not plain numbers are left unscaled.
+/// An `Err(())` is returned if the format is unknown, does not accept the size of the field or is scaled without
+/// being numeric.
+pub fn format_field(
+    field: &gram_parse::GrammarFields,
+    field_bytes: &[u8],
+    fmt_endian_flag: bool,
+    context: &FormatContext,
+) -> Result<String, ()> {
+    let formatter = find_formatter(&field.display_format).ok_or_else(|| {
+        serror!(format!(
+            "Unknown display format: {} for field: {}, use --list-formats to see the supported formats",
+            field.display_format, field.name
+        ))
+    })?;
+
+    if !formatter.sizes().accepts(field_bytes.len()) {
+        serror!(format!(
+            "Invalid size: {} for field: {}, display format: {} accepts sizes: {}",
+            field_bytes.len(),
+            field.name,
+            field.display_format,
+            formatter.sizes()
+        ));
+        return Err(());
+    }
+
+    if !field.bits.is_empty()
+        && field.display_format != format::BITS_TYPE
+        && field.display_format != format::BITSBE_TYPE
+    {
+        serror!(format!(
+            "Field: {} has bits but its display format: {} is not {} or {}",
+            field.name,
+            field.display_format,
+            format::BITS_TYPE,
+            format::BITSBE_TYPE
+        ));
+        return Err(());
+    }
+
+    let scaled = field.scale.is_some() || field.offset.is_some() || !field.unit.is_empty();
+
+    if scaled && !formatter.numeric() {
+        serror!(format!(
+            "Field: {} has a scale, offset or unit but its display format: {} is not numeric",
+            field.name, field.display_format
+        ));
+        return Err(());
+    }
+
+    let formatted = if fmt_endian_flag && formatter.endian_sensitive() {
+        let mut reversed_bytes = field_bytes.to_vec();
+        reversed_bytes.reverse();
+        formatter.render(&reversed_bytes, context)?
+    } else {
+        formatter.render(field_bytes, context)?
+    };
+
+    if !scaled {
+        return Ok(formatted);
+    }
+
+    Ok(format::scaled_string(
+        &formatted,
+        field.scale.unwrap_or(1.0),
+        field.offset.unwrap_or(0.0),
+        &field.unit,
+    )
+    .unwrap_or(formatted))
+}
+
+/// Prints every supported display format with the sizes it accepts, whether `-e` affects it, whether it can be scaled
+/// and a description.
+pub fn print_formats() {
+    let mut formats_table = Table::new();
+
+    formats_table.add_row(row!["Format", "Sizes", "-e", "Scalable", "Description"]);
+
+    for formatter in FORMATTERS.iter() {
+        formats_table.add_row(row![
+            formatter.name(),
+            formatter.sizes(),
+            if formatter.endian_sensitive() {
+                "yes"
+            } else {
+                "no"
+            },
+            if formatter.numeric() { "yes" } else { "no" },
+            formatter.description(),
+        ]);
+    }
+
+    formats_table.printstd();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(display_format: &str, size: usize) -> gram_parse::GrammarFields {
+        gram_parse::GrammarFields {
+            name: String::from("Field"),
+            size,
+            data_type: String::from("data"),
+            display_format: String::from(display_format),
+            description: String::new(),
+            scale: None,
+            offset: None,
+            unit: String::new(),
+            script: String::new(),
+            bits: Vec::new(),
+            count: None,
+            type_name: String::new(),
+        }
+    }
+
+    /// Holds the script engine and sibling fields a `FormatContext` borrows.
+    struct Siblings {
+        scripts: script::ScriptEngine,
+        bytes: HashMap<String, Vec<u8>>,
+        values: HashMap<String, String>,
+    }
+
+    impl Siblings {
+        fn new() -> Self {
+            Self {
+                scripts: script::ScriptEngine::new(),
+                bytes: HashMap::new(),
+                values: HashMap::new(),
+            }
+        }
+
+        fn context<'a>(&'a self, display_format: &'a str) -> FormatContext<'a> {
+            FormatContext {
+                display_format,
+                field_offset: 0,
+                asm_syntax: format::AsmSyntaxes::Nasm,
+                field_name: "Field",
+                script_path: "",
+                scripts: &self.scripts,
+                sibling_bytes: &self.bytes,
+                sibling_values: &self.values,
+                bit_ranges: &[],
+            }
+        }
+    }
+
+    #[test]
+    fn field_sizes_are_checked() {
+        assert!(FieldSizes::Any.accepts(3));
+        assert!(FieldSizes::OneOf(&[4, 8]).accepts(8));
+        assert!(!FieldSizes::OneOf(&[4, 8]).accepts(6));
+        assert!(FieldSizes::UpTo(8).accepts(1));
+        assert!(!FieldSizes::UpTo(8).accepts(0));
+        assert_eq!(FieldSizes::OneOf(&[4, 8]).to_string(), "4, 8");
+        assert_eq!(FieldSizes::UpTo(8).to_string(), "1-8");
+    }
+
+    #[test]
+    fn format_names_are_unique() {
+        for (index, formatter) in FORMATTERS.iter().enumerate() {
+            assert!(
+                FORMATTERS[index + 1..]
+                    .iter()
+                    .all(|other| other.name() != formatter.name()),
+                "{} is registered twice",
+                formatter.name()
+            );
+        }
+    }
+
+    #[test]
+    fn formatters_are_found_by_display_format() {
+        assert_eq!(
+            find_formatter(format::UTF16BE_TYPE).unwrap().name(),
+            "utf16be"
+        );
+        assert!(find_formatter("q16.16").is_some());
+        assert!(find_formatter("hexadecimal").is_none());
+    }
+
+    #[test]
+    fn unknown_formats_and_sizes_are_an_error() {
+        let siblings = Siblings::new();
+        assert!(format_field(
+            &field("hexadecimal", 2),
+            &[0, 1],
+            false,
+            &siblings.context("")
+        )
+        .is_err());
+        assert!(format_field(
+            &field(format::IPV4BE_TYPE, 2),
+            &[0, 1],
+            false,
+            &siblings.context(format::IPV4BE_TYPE)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn endian_flag_only_reverses_endian_sensitive_formats() {
+        let siblings = Siblings::new();
+        let bytes = [0x12, 0x34];
+
+        assert_eq!(
+            format_field(
+                &field(format::HEX_TYPE, 2),
+                &bytes,
+                true,
+                &siblings.context(format::HEX_TYPE)
+            ),
+            Ok(String::from("3412"))
+        );
+        assert_eq!(
+            format_field(
+                &field(format::HEXLE_TYPE, 2),
+                &bytes,
+                true,
+                &siblings.context(format::HEXLE_TYPE)
+            ),
+            Ok(String::from("3412"))
+        );
+        assert_eq!(
+            format_field(
+                &field(format::HEXLE_TYPE, 2),
+                &bytes,
+                false,
+                &siblings.context(format::HEXLE_TYPE)
+            ),
+            Ok(String::from("3412"))
+        );
+    }
+
+    #[test]
+    fn scale_offset_and_unit_are_applied_to_numeric_formats() {
+        let siblings = Siblings::new();
+        let mut temperature = field(format::UINT_TYPE, 2);
+        temperature.scale = Some(0.01);
+        temperature.offset = Some(-40.0);
+        temperature.unit = String::from("°C");
+
+        assert_eq!(
+            format_field(
+                &temperature,
+                &2650u16.to_le_bytes(),
+                false,
+                &siblings.context(format::UINT_TYPE)
+            ),
+            Ok(String::from("-13.5 °C"))
+        );
+    }
+
+    #[test]
+    fn scaling_a_non_numeric_format_is_an_error() {
+        let siblings = Siblings::new();
+        let mut magic = field(format::ASCII_TYPE, 2);
+        magic.unit = String::from("B");
+
+        assert!(format_field(&magic, b"MZ", false, &siblings.context(format::ASCII_TYPE)).is_err());
+    }
+
+    #[test]
+    fn bit_ranges_are_shown_as_name_value_pairs() {
+        let siblings = Siblings::new();
+        let bit_ranges = [
+            gram_parse::GrammarBits {
+                name: String::from("low"),
+                offset: 0,
+                width: 4,
+                signed: false,
+            },
+            gram_parse::GrammarBits {
+                name: String::from("high"),
+                offset: 4,
+                width: 4,
+                signed: true,
+            },
+        ];
+        let context = FormatContext {
+            bit_ranges: &bit_ranges,
+            ..siblings.context(format::BITS_TYPE)
+        };
+
+        assert_eq!(
+            format_field(&field(format::BITS_TYPE, 1), &[0x9A], false, &context),
+            Ok(String::from("low=10, high=-7"))
+        );
+        assert!(format_field(
+            &field(format::BITS_TYPE, 1),
+            &[0x9A],
+            false,
+            &siblings.context(format::BITS_TYPE)
+        )
+        .is_err());
+    }
+}
@@ -1,7 +1,16 @@
 //! Module that deals with parsing a grammar file into a `Grammar` data structure.
+use crate::format;
 use serde::Deserialize;
 use serde::Serialize;
 use std::convert::TryInto;
+use std::path::Path;
+
+/// The kind of a grammar type whose fields follow each other.
+pub const STRUCT_KIND: &str = "struct";
+/// The kind of a grammar type whose fields overlap.
+pub const UNION_KIND: &str = "union";
+/// Types nested deeper than this are assumed to contain themselves.
+const MAX_TYPE_DEPTH: usize = 32;
 
 /// Parent structure which holds the metadata and fields of the grammar.
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -10,6 +19,22 @@ pub struct Grammar {
     pub metadata: GrammarMetadata,
     /// Each GrammarField entry corrosponds to a [[fields]] entry in the grammar file.
     pub fields: Vec<GrammarFields>,
+    /// Named struct and union types that fields can be instances of, each corrosponds to a [[types]] entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<GrammarType>,
+}
+
+/// A named struct or union type (`[[types]]`) made up of its own fields (`[[types.fields]]`).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GrammarType {
+    /// The name fields use to refer to the type.
+    pub name: String,
+    /// Either `struct`, whose fields follow each other, or `union`, whose fields overlap.
+    pub kind: String,
+    /// How large the type is in bytes.
+    pub size: usize,
+    /// The fields of the type.
+    pub fields: Vec<GrammarFields>,
 }
 
 /// Holds metadata (`[metadata]`) portion of the grammar file.
@@ -21,6 +46,26 @@ pub struct GrammarMetadata {
     pub variable_size_fields: Vec<(String, String, String, String)>,
     /// Specifies which fields if any should be multiplied/repeated.
     pub multiply_fields: Vec<(String, String)>,
+    /// The target ABI a converted C struct was laid out for.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub abi: String,
+    /// The byte order of the target a converted C struct was laid out for, `little` or `big`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub endian: String,
+}
+
+/// A named range of bits in an integer field, e.g a C bitfield, corrosponding to a `[[fields.bits]]` entry.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GrammarBits {
+    /// The name of the bit range.
+    pub name: String,
+    /// The number of bits between the least significant bit of the field and the first bit of the range.
+    pub offset: u32,
+    /// The number of bits in the range.
+    pub width: u32,
+    /// Whether the range is a two's complement signed number.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub signed: bool,
 }
 
 /// Each `GrammarField` entry corrosponds to a `[[fields]]` entry in the grammar file.
@@ -36,6 +81,27 @@ pub struct GrammarFields {
     pub display_format: String,
     /// The description of the field.
     pub description: String,
+    /// The number of consecutive elements of `size` bytes the field is made up of, each shown as its own field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// The name of the `[[types]]` entry the field is an instance of.
+    #[serde(default, rename = "type", skip_serializing_if = "String::is_empty")]
+    pub type_name: String,
+    /// The number the formatted value of a numeric field is multiplied by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// The number added to the formatted value of a numeric field after scaling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<f64>,
+    /// The unit shown after the formatted value of a numeric field, `B` shows human readable sizes.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub unit: String,
+    /// The filepath of the Rhai script used to format the field, required by the `script` display format.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub script: String,
+    /// The named bit ranges of the field, required by the `bits` and `bitsbe` display formats.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bits: Vec<GrammarBits>,
 }
 
 impl GrammarMetadata {
@@ -44,6 +110,8 @@ impl GrammarMetadata {
             name: String::from(""),
             variable_size_fields: Vec::new(),
             multiply_fields: Vec::new(),
+            abi: String::from(""),
+            endian: String::from(""),
         }
     }
 }
@@ -53,6 +121,7 @@ impl Grammar {
         Self {
             metadata: GrammarMetadata::new(),
             fields: Vec::new(),
+            types: Vec::new(),
         }
     }
 
@@ -84,8 +153,24 @@ impl Grammar {
 
     /// Further parses the grammar in the Grammar structure.
     ///
-    /// `multiply_fields` is run here if mulitplying fields was specified in the grammar file.
+    /// `multiply_fields` is run here if mulitplying fields was specified in the grammar file. Fields with a `count`
+    /// or a `type` are then expanded into the fields they are made up of.
     pub fn post_parse_toml(&mut self) -> Result<&mut Self, ()> {
+        self.apply_multiply_fields()?;
+
+        let mut expanded_fields: Vec<GrammarFields> = Vec::new();
+
+        for field in self.fields.iter() {
+            self.expand_field(field, "", &mut expanded_fields, 0)?;
+        }
+
+        self.fields = expanded_fields;
+
+        Ok(self)
+    }
+
+    /// Runs `multiply_fields` if mulitplying fields was specified in the grammar file.
+    pub fn apply_multiply_fields(&mut self) -> Result<&mut Self, ()> {
         if !self.metadata.multiply_fields[0].0.is_empty()
             && !self.metadata.multiply_fields[0].1.is_empty()
         {
@@ -95,6 +180,272 @@ impl Grammar {
         Ok(self)
     }
 
+    /// Expands a field into `expanded_fields`, prefixing the names of the expanded fields with `prefix`.
+    ///
+    /// A field with a `count` becomes one field per element named `name[index]`. A field whose `type` is a struct
+    /// becomes the fields of the struct named `name.field`, while a union stays a single field as its fields overlap.
+    fn expand_field(
+        &self,
+        field: &GrammarFields,
+        prefix: &str,
+        expanded_fields: &mut Vec<GrammarFields>,
+        depth: usize,
+    ) -> Result<(), ()> {
+        if depth > MAX_TYPE_DEPTH {
+            serror!(format!(
+                "Type: {} of field: {} is nested more than {} levels deep, it may contain itself",
+                field.type_name, field.name, MAX_TYPE_DEPTH
+            ));
+            return Err(());
+        }
+
+        let names: Vec<String> = match field.count {
+            Some(count) => (0..count)
+                .map(|index| format!("{}{}[{}]", prefix, field.name, index))
+                .collect(),
+            None => vec![format!("{}{}", prefix, field.name)],
+        };
+
+        for name in names {
+            if field.type_name.is_empty() {
+                let mut element = field.clone();
+                element.name = name;
+                element.count = None;
+                expanded_fields.push(element);
+                continue;
+            }
+
+            let field_type = self.find_type(field)?;
+
+            match &field_type.kind[..] {
+                STRUCT_KIND => {
+                    for type_field in field_type.fields.iter() {
+                        self.expand_field(
+                            type_field,
+                            &format!("{}.", name),
+                            expanded_fields,
+                            depth + 1,
+                        )?;
+                    }
+                }
+                UNION_KIND => {
+                    let mut union_field = field.clone();
+                    union_field.name = name;
+                    union_field.count = None;
+                    union_field.type_name = String::new();
+                    union_field.size = field_type.size;
+                    union_field.data_type = format!("union {}", field_type.name);
+                    union_field.display_format = format::HEX_TYPE.to_string();
+                    expanded_fields.push(union_field);
+                }
+                kind => {
+                    serror!(format!(
+                        "Invalid kind: {} for type: {}, must be struct or union",
+                        kind, field_type.name
+                    ));
+                    return Err(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the type `field` is an instance of.
+    pub fn find_type(&self, field: &GrammarFields) -> Result<&GrammarType, ()> {
+        self.types
+            .iter()
+            .find(|field_type| field_type.name == field.type_name)
+            .ok_or_else(|| {
+                serror!(format!(
+                    "Type: {} of field: {} is not in the grammar types",
+                    field.type_name, field.name
+                ))
+            })
+    }
+
+    /// Get's the type with the name and fields of the grammar, which C struct conversion writes for the root struct.
+    pub fn root_type(&self) -> Option<&GrammarType> {
+        self.types.iter().find(|field_type| {
+            field_type.name == self.metadata.name
+                && field_type.kind == STRUCT_KIND
+                && field_type.fields.len() == self.fields.len()
+                && field_type
+                    .fields
+                    .iter()
+                    .zip(self.fields.iter())
+                    .all(|(type_field, field)| {
+                        type_field.name == field.name
+                            && type_field.size == field.size
+                            && type_field.count == field.count
+                            && type_field.type_name == field.type_name
+                    })
+        })
+    }
+
+    /// Get's every type ordered so each type comes after the types its fields are made of.
+    ///
+    /// An `Err(())` is returned if a type contains itself or a field refers to a type that does not exist.
+    pub fn ordered_types(&self) -> Result<Vec<&GrammarType>, ()> {
+        let mut ordered_types: Vec<&GrammarType> = Vec::new();
+
+        for field_type in self.types.iter() {
+            self.order_type(field_type, &mut ordered_types, &mut Vec::new())?;
+        }
+
+        Ok(ordered_types)
+    }
+
+    /// Adds `field_type` to `ordered_types` after the types its fields are made of.
+    ///
+    /// `path` holds the types being ordered that contain `field_type`.
+    fn order_type<'a>(
+        &'a self,
+        field_type: &'a GrammarType,
+        ordered_types: &mut Vec<&'a GrammarType>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), ()> {
+        if ordered_types
+            .iter()
+            .any(|ordered| ordered.name == field_type.name)
+        {
+            return Ok(());
+        }
+
+        if path.contains(&&field_type.name[..]) || path.len() > MAX_TYPE_DEPTH {
+            serror!(format!("Type: {}, contains itself", field_type.name));
+            return Err(());
+        }
+
+        path.push(&field_type.name);
+        for field in field_type.fields.iter() {
+            if !field.type_name.is_empty() {
+                self.order_type(self.find_type(field)?, ordered_types, path)?;
+            }
+        }
+        path.pop();
+
+        ordered_types.push(field_type);
+
+        Ok(())
+    }
+
+    /// Get's the size in bytes of `field_type`.
+    ///
+    /// An `Err(())` is returned if the fields of a struct do not add up to its size or a field of a union is larger
+    /// than it.
+    pub fn type_size(&self, field_type: &GrammarType) -> Result<usize, ()> {
+        match &field_type.kind[..] {
+            STRUCT_KIND => {
+                let fields_size = self.fields_size(&field_type.fields, 0)?;
+
+                if fields_size != field_type.size {
+                    serror!(format!(
+                        "Struct type: {}, is {} bytes but its fields add up to {} bytes",
+                        field_type.name, field_type.size, fields_size
+                    ));
+                    return Err(());
+                }
+            }
+            UNION_KIND => {
+                for field in field_type.fields.iter() {
+                    let field_size = self.field_size(field, 0)?;
+
+                    if field_size > field_type.size {
+                        serror!(format!(
+                            "Union type: {}, is {} bytes but its field: {} is {} bytes",
+                            field_type.name, field_type.size, field.name, field_size
+                        ));
+                        return Err(());
+                    }
+                }
+            }
+            kind => {
+                serror!(format!(
+                    "Invalid kind: {} for type: {}, must be struct or union",
+                    kind, field_type.name
+                ));
+                return Err(());
+            }
+        }
+
+        Ok(field_type.size)
+    }
+
+    /// Get's the size in bytes of `field` including every element, where a struct type is the size of its fields.
+    ///
+    /// `depth` is how deeply nested the field is in types.
+    pub fn field_size(&self, field: &GrammarFields, depth: usize) -> Result<usize, ()> {
+        if field.type_name.is_empty() {
+            return Ok(field.size * field.count.unwrap_or(1));
+        }
+
+        if depth > MAX_TYPE_DEPTH {
+            serror!(format!(
+                "Type: {} of field: {} is nested more than {} levels deep, it may contain itself",
+                field.type_name, field.name, MAX_TYPE_DEPTH
+            ));
+            return Err(());
+        }
+
+        let field_type = self.find_type(field)?;
+        let element_size = match &field_type.kind[..] {
+            STRUCT_KIND => self.fields_size(&field_type.fields, depth + 1)?,
+            _ => field_type.size,
+        };
+
+        Ok(element_size * field.count.unwrap_or(1))
+    }
+
+    /// Get's the total size in bytes of `fields` placed one after another.
+    pub fn fields_size(&self, fields: &[GrammarFields], depth: usize) -> Result<usize, ()> {
+        let mut size = 0;
+
+        for field in fields.iter() {
+            size += self.field_size(field, depth)?;
+        }
+
+        Ok(size)
+    }
+
+    /// Makes the script filepaths of fields relative to the directory of the grammar file at `grammar_filepath`.
+    ///
+    /// An `Err(())` is returned if a field uses the `script` display format without a script, or has a script but
+    /// uses another display format.
+    pub fn resolve_script_paths(&mut self, grammar_filepath: &str) -> Result<&mut Self, ()> {
+        let grammar_dir = Path::new(grammar_filepath)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+
+        for field in self.fields.iter_mut() {
+            match (&field.display_format[..], field.script.is_empty()) {
+                (format::SCRIPT_TYPE, true) => {
+                    serror!(format!(
+                        "Field: {} uses the script display format but has no script",
+                        field.name
+                    ));
+                    return Err(());
+                }
+                (format::SCRIPT_TYPE, false) => {
+                    field.script = grammar_dir
+                        .join(&field.script)
+                        .to_string_lossy()
+                        .to_string();
+                }
+                (_, true) => (),
+                (display_format, false) => {
+                    serror!(format!(
+                        "Field: {} has a script but uses the display format: {}, scripts need the script display format",
+                        field.name, display_format
+                    ));
+                    return Err(());
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
     /// Populates a `Vec<VariableSizeEntry>`.
     pub fn create_var_size_entry_vector(
         &mut self,
@@ -444,3 +795,161 @@ impl FieldMultiply {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = r#"
+[metadata]
+name = "Outer"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "points"
+size = 4
+count = 2
+type = "Point"
+data_type = "struct Point"
+display_format = "hex"
+description = "N/A"
+
+[[fields]]
+name = "value"
+size = 4
+type = "Value"
+data_type = "union Value"
+display_format = "hex"
+description = "N/A"
+
+[[fields]]
+name = "flags"
+size = 1
+count = 2
+data_type = "uint8_t"
+display_format = "uint"
+description = "N/A"
+
+[[types]]
+name = "Point"
+kind = "struct"
+size = 4
+
+[[types.fields]]
+name = "x"
+size = 2
+data_type = "short"
+display_format = "int"
+description = "N/A"
+
+[[types.fields]]
+name = "y"
+size = 2
+data_type = "short"
+display_format = "int"
+description = "N/A"
+
+[[types]]
+name = "Value"
+kind = "union"
+size = 4
+
+[[types.fields]]
+name = "number"
+size = 4
+data_type = "int"
+display_format = "int"
+description = "N/A"
+"#;
+
+    fn parse(grammar: &str) -> Result<Grammar, ()> {
+        let mut parsed_gram = Grammar::new();
+        parsed_gram.parse_toml(grammar)?.post_parse_toml()?;
+        Ok(parsed_gram)
+    }
+
+    #[test]
+    fn counts_and_struct_types_are_expanded() {
+        let parsed_gram = parse(GRAMMAR).unwrap();
+        let names: Vec<&str> = parsed_gram
+            .fields
+            .iter()
+            .map(|field| &field.name[..])
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "points[0].x",
+                "points[0].y",
+                "points[1].x",
+                "points[1].y",
+                "value",
+                "flags[0]",
+                "flags[1]"
+            ]
+        );
+        assert_eq!(parsed_gram.get_struct_size(), 14);
+    }
+
+    #[test]
+    fn unions_stay_a_single_hex_field() {
+        let parsed_gram = parse(GRAMMAR).unwrap();
+        let value = &parsed_gram.fields[4];
+
+        assert_eq!(value.size, 4);
+        assert_eq!(value.data_type, "union Value");
+        assert_eq!(value.display_format, format::HEX_TYPE);
+        assert!(value.type_name.is_empty());
+    }
+
+    #[test]
+    fn unknown_types_are_an_error() {
+        assert!(parse(&GRAMMAR.replace("type = \"Point\"", "type = \"Missing\"")).is_err());
+    }
+
+    #[test]
+    fn bit_ranges_are_parsed() {
+        let parsed_gram = parse(
+            r#"
+[metadata]
+name = "Flags"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "flags"
+size = 1
+data_type = "uint8_t"
+display_format = "bits"
+description = "N/A"
+
+[[fields.bits]]
+name = "low"
+offset = 0
+width = 3
+
+[[fields.bits]]
+name = "high"
+offset = 3
+width = 5
+signed = true
+"#,
+        )
+        .unwrap();
+        let bits = &parsed_gram.fields[0].bits;
+
+        assert_eq!(bits.len(), 2);
+        assert_eq!(
+            (&bits[0].name[..], bits[0].offset, bits[0].width),
+            ("low", 0, 3)
+        );
+        assert!(!bits[0].signed);
+        assert_eq!(
+            (&bits[1].name[..], bits[1].offset, bits[1].width),
+            ("high", 3, 5)
+        );
+        assert!(bits[1].signed);
+    }
+}
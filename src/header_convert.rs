@@ -0,0 +1,613 @@
+//! Module for converting a grammar to a C header.
+//!
+//! Every struct and union type of the grammar becomes a C struct or union and the fields of the grammar become the
+//! root struct. Members are packed, so the offset of every member is the offset of its field in the grammar, and
+//! each offset and size is checked with `_Static_assert` when the header is compiled.
+use crate::format;
+use crate::gram_parse;
+use std::collections::HashSet;
+use std::fs;
+use std::io::prelude::*;
+
+/// C keywords and names used by the header, which are renamed when used as the name of a field or type.
+const RESERVED_NAMES: [&str; 45] = [
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "_Bool",
+    "_Static_assert",
+    "offsetof",
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+];
+
+/// Holds the grammar being converted and the resulting C header contents.
+pub struct CHeaderFile {
+    /// Name of the root struct (Also name of grammar).
+    pub name: String,
+    /// The grammar being converted, set by `build_header_contents`.
+    grammar: gram_parse::Grammar,
+    /// String containing the resulting C header contents.
+    pub header_contents: String,
+}
+
+impl CHeaderFile {
+    pub fn new() -> Self {
+        Self {
+            name: String::from(""),
+            grammar: gram_parse::Grammar::new(),
+            header_contents: String::from(""),
+        }
+    }
+
+    /// Builds the contents of the C header from `grammar`, which has not been post parsed.
+    ///
+    /// Types are written before the types and root struct that use them. The root struct is named after the grammar,
+    /// unless a struct type with the same name and fields exists, in which case that type is the root.
+    pub fn build_header_contents(
+        &mut self,
+        grammar: &gram_parse::Grammar,
+    ) -> Result<&mut Self, ()> {
+        self.grammar = grammar.clone();
+        self.grammar.apply_multiply_fields()?;
+        self.name = c_identifier(&self.grammar.metadata.name);
+
+        let ordered_types = self.grammar.ordered_types()?;
+
+        let guard = format!("{}_H", self.name.to_uppercase());
+        let mut contents = format!(
+            "/* {} converted from a memgram grammar. */\n",
+            self.grammar.metadata.name
+        );
+        contents.push_str("/* Members are packed so their offsets match the grammar, integers are in host byte order. */\n");
+        if !self.grammar.metadata.abi.is_empty() {
+            contents.push_str(&format!(
+                "/* The grammar was converted from a C struct laid out for the {} ABI ({} endian). */\n",
+                self.grammar.metadata.abi, self.grammar.metadata.endian
+            ));
+        }
+        contents.push_str(&format!("#ifndef {}\n#define {}\n\n", guard, guard));
+        contents.push_str("#include <stddef.h>\n#include <stdint.h>\n\n");
+        contents.push_str("#pragma pack(push, 1)\n");
+
+        for field_type in ordered_types.iter() {
+            contents.push_str(&self.record_contents(
+                &field_type.kind,
+                &field_type.name,
+                &field_type.fields,
+                Some(self.grammar.type_size(field_type)?),
+            )?);
+        }
+
+        if self.grammar.root_type().is_none() {
+            let mut root_name = self.name.clone();
+            if ordered_types
+                .iter()
+                .any(|field_type| c_identifier(&field_type.name) == root_name)
+            {
+                root_name.push_str("_fields");
+                self.name = root_name.clone();
+            }
+
+            contents.push_str(&self.record_contents(
+                gram_parse::STRUCT_KIND,
+                &root_name,
+                &self.grammar.fields,
+                None,
+            )?);
+        }
+
+        contents.push_str("\n#pragma pack(pop)\n\n");
+        contents.push_str(&format!("#endif /* {} */\n", guard));
+
+        self.header_contents = contents;
+
+        Ok(self)
+    }
+
+    /// Builds the C definition of the struct or union `name` made of `fields`, followed by the bit range macros of its
+    /// members and the assertions checking its layout.
+    ///
+    /// `size` is the size of a type, which a union is made at least as large as.
+    fn record_contents(
+        &self,
+        kind: &str,
+        name: &str,
+        fields: &[gram_parse::GrammarFields],
+        size: Option<usize>,
+    ) -> Result<String, ()> {
+        let record_name = c_identifier(name);
+        let keyword = match kind {
+            gram_parse::STRUCT_KIND | gram_parse::UNION_KIND => kind,
+            _ => {
+                serror!(format!(
+                    "Invalid kind: {} for type: {}, must be struct or union",
+                    kind, name
+                ));
+                return Err(());
+            }
+        };
+
+        let mut members = String::new();
+        let mut macros = String::new();
+        let mut asserts = String::new();
+        let mut member_names: HashSet<String> = HashSet::new();
+        let mut offset = 0;
+        let mut largest = 0;
+
+        for field in fields.iter() {
+            let member_size = self.grammar.field_size(field, 0)?;
+            let mut member_name = c_identifier(&field.name);
+
+            if member_size == 0 {
+                members.push_str(&format!(
+                    "    /* {}: Zero size field, not included */\n",
+                    comment_text(&field.name)
+                ));
+                continue;
+            }
+
+            if member_names.contains(&member_name) {
+                let mut number = 2;
+                while member_names.contains(&format!("{}_{}", member_name, number)) {
+                    number += 1;
+                }
+                member_name = format!("{}_{}", member_name, number);
+            }
+            member_names.insert(member_name.clone());
+
+            let (c_type, mut dimensions) = if field.type_name.is_empty() {
+                element_type(field)
+            } else {
+                let field_type = self.grammar.find_type(field)?;
+                (
+                    format!("{} {}", field_type.kind, c_identifier(&field_type.name)),
+                    String::new(),
+                )
+            };
+            if let Some(count) = field.count {
+                dimensions = format!("[{}]{}", count, dimensions);
+            }
+
+            members.push_str(&format!("    {} {}{};", c_type, member_name, dimensions));
+            let comment = self.member_comment(field);
+            if !comment.is_empty() {
+                members.push_str(&format!(" /* {} */", comment));
+            }
+            members.push('\n');
+
+            for bits in field.bits.iter() {
+                let prefix = format!(
+                    "{}_{}_{}",
+                    record_name,
+                    member_name,
+                    c_identifier(&bits.name)
+                )
+                .to_uppercase();
+                let mask = if bits.width >= 64 {
+                    u64::MAX
+                } else {
+                    (1u64 << bits.width) - 1
+                };
+
+                macros.push_str(&format!("#define {}_SHIFT {}\n", prefix, bits.offset));
+                macros.push_str(&format!("#define {}_MASK {:#X}u\n", prefix, mask));
+            }
+
+            if keyword == gram_parse::STRUCT_KIND {
+                asserts.push_str(&format!(
+                    "_Static_assert(offsetof({} {}, {}) == {:#04X}, \"Offset of {}.{}\");\n",
+                    keyword, record_name, member_name, offset, record_name, member_name
+                ));
+                offset += member_size;
+            }
+            largest = largest.max(member_size);
+        }
+
+        let record_size = match (keyword, size) {
+            (gram_parse::UNION_KIND, Some(size)) if size > largest => {
+                members.push_str(&format!("    uint8_t _size[{}];\n", size));
+                size
+            }
+            (_, Some(size)) => size,
+            (gram_parse::UNION_KIND, None) => largest,
+            (_, None) => offset,
+        };
+
+        if members.is_empty() || record_size == 0 {
+            serror!(format!(
+                "Type: {}, has no fields with a size so can not be converted to C",
+                name
+            ));
+            return Err(());
+        }
+
+        let mut contents = format!("\n{} {} {{\n{}}};\n", keyword, record_name, members);
+        if !macros.is_empty() {
+            contents.push('\n');
+            contents.push_str(&macros);
+        }
+        contents.push('\n');
+        contents.push_str(&asserts);
+        contents.push_str(&format!(
+            "_Static_assert(sizeof({} {}) == {:#04X}, \"Size of {}\");\n",
+            keyword, record_name, record_size, record_name
+        ));
+
+        Ok(contents)
+    }
+
+    /// Get's the comment written after a member, made of its original name if it was renamed, its description and
+    /// notes on its byte order or variable size.
+    fn member_comment(&self, field: &gram_parse::GrammarFields) -> String {
+        let mut notes: Vec<String> = Vec::new();
+
+        if c_identifier(&field.name) != field.name {
+            notes.push(field.name.clone());
+        }
+        if !field.description.is_empty() && field.description != "N/A" {
+            notes.push(field.description.clone());
+        }
+        if field.type_name.is_empty()
+            && field.size > 1
+            && format::is_big_endian_format(&field.display_format)
+        {
+            notes.push(String::from("Big endian"));
+        }
+        if self
+            .grammar
+            .metadata
+            .variable_size_fields
+            .iter()
+            .any(|entry| entry.3 == field.name)
+        {
+            notes.push(format!("Variable size, at most {} bytes", field.size));
+        }
+
+        comment_text(&notes.join(". "))
+    }
+
+    /// Writes the newly created header contents in `self.header_contents` to a file specified by `output_path`.
+    pub fn write_header_file(&mut self, output_path: &str) -> Result<&mut Self, ()> {
+        let mut header_file = fs::File::create(output_path).map_err(|e| {
+            serror!(format!(
+                "Could not create/open file {}, because {} ",
+                output_path, e
+            ))
+        })?;
+
+        header_file
+            .write_all(self.header_contents.as_bytes())
+            .map_err(|e| {
+                serror!(format!(
+                    "Could not write to file {}, because {} ",
+                    output_path, e
+                ))
+            })?;
+
+        println!(
+            "[+] Successfully converted grammar {} to C header file {}",
+            self.name, output_path
+        );
+
+        Ok(self)
+    }
+}
+
+/// Get's the C type of one element of a field without a type, along with any array dimensions it needs.
+///
+/// Integer, float and text formats become the matching `<stdint.h>`, floating point or character types. Other
+/// fields of 1, 2, 4 or 8 bytes become unsigned integers, except `hex` fields which, like all other sizes, become
+/// byte arrays.
+fn element_type(field: &gram_parse::GrammarFields) -> (String, String) {
+    let size = field.size;
+    let display_format = &field.display_format[..];
+    let integer_size = matches!(size, 1 | 2 | 4 | 8);
+    let signed = matches!(display_format, format::INT_TYPE | format::INTBE_TYPE)
+        || matches!(
+            format::parse_fixed_point(display_format),
+            Some((true, _, _))
+        );
+
+    let (c_type, length) = match display_format {
+        format::FLOAT_TYPE | format::FLOATBE_TYPE if size == 4 => ("float", None),
+        format::FLOAT_TYPE | format::FLOATBE_TYPE if size == 8 => ("double", None),
+        format::UTF16LE_TYPE | format::UTF16BE_TYPE if size.is_multiple_of(2) => {
+            ("uint16_t", Some(size / 2))
+        }
+        _ if format::is_text_format(display_format) && size == 1 => ("char", None),
+        _ if format::is_text_format(display_format) => ("char", Some(size)),
+        format::HEX_TYPE if size > 1 => ("uint8_t", Some(size)),
+        _ if integer_size && signed => (
+            match size {
+                1 => "int8_t",
+                2 => "int16_t",
+                4 => "int32_t",
+                _ => "int64_t",
+            },
+            None,
+        ),
+        _ if integer_size => (
+            match size {
+                1 => "uint8_t",
+                2 => "uint16_t",
+                4 => "uint32_t",
+                _ => "uint64_t",
+            },
+            None,
+        ),
+        _ => ("uint8_t", Some(size)),
+    };
+
+    (
+        c_type.to_string(),
+        length
+            .map(|length| format!("[{}]", length))
+            .unwrap_or_default(),
+    )
+}
+
+/// Converts `name` into a valid C identifier by replacing invalid characters with `_`.
+///
+/// Names starting with a digit are prefixed with `_` and C keywords are suffixed with `_`.
+fn c_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+
+    if RESERVED_NAMES.contains(&&identifier[..]) {
+        identifier.push('_');
+    }
+
+    identifier
+}
+
+/// Removes characters from `text` that would end or break a C comment.
+fn comment_text(text: &str) -> String {
+    text.replace("*/", "* /")
+        .replace(['\r', '\n'], " ")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = r#"
+[metadata]
+name = "Packet"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "header"
+size = 6
+type = "Header"
+data_type = "struct Header"
+display_format = "hex"
+description = "N/A"
+
+[[fields]]
+name = "length"
+size = 4
+data_type = "uint32_t"
+display_format = "uintbe"
+description = "Payload length"
+
+[[fields]]
+name = "flags"
+size = 1
+data_type = "uint8_t"
+display_format = "bits"
+description = "N/A"
+
+[[fields.bits]]
+name = "ack"
+offset = 0
+width = 1
+
+[[fields.bits]]
+name = "window"
+offset = 1
+width = 7
+
+[[fields]]
+name = "default"
+size = 2
+count = 3
+data_type = "int16_t"
+display_format = "int"
+description = "N/A"
+
+[[types]]
+name = "Header"
+kind = "struct"
+size = 6
+
+[[types.fields]]
+name = "magic"
+size = 4
+data_type = "char"
+display_format = "ascii"
+description = "N/A"
+
+[[types.fields]]
+name = "value"
+size = 2
+type = "Value"
+data_type = "union Value"
+display_format = "hex"
+description = "N/A"
+
+[[types]]
+name = "Value"
+kind = "union"
+size = 2
+
+[[types.fields]]
+name = "number"
+size = 2
+data_type = "uint16_t"
+display_format = "uint"
+description = "N/A"
+
+[[types.fields]]
+name = "bytes"
+size = 1
+count = 2
+data_type = "uint8_t"
+display_format = "hex"
+description = "N/A"
+"#;
+
+    fn convert(grammar: &str) -> Result<String, ()> {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(grammar)?;
+
+        let mut header = CHeaderFile::new();
+        header.build_header_contents(&parsed_gram)?;
+        Ok(header.header_contents)
+    }
+
+    #[test]
+    fn types_come_before_the_records_that_use_them() {
+        let contents = convert(GRAMMAR).unwrap();
+        let value = contents.find("union Value {").unwrap();
+        let header = contents.find("struct Header {").unwrap();
+        let packet = contents.find("struct Packet {").unwrap();
+
+        assert!(value < header && header < packet);
+        assert!(contents.contains("    union Value value;\n"));
+        assert!(contents.contains("    struct Header header;\n"));
+    }
+
+    #[test]
+    fn members_are_asserted_at_their_grammar_offsets() {
+        let contents = convert(GRAMMAR).unwrap();
+
+        assert!(contents.contains("#pragma pack(push, 1)\n"));
+        assert!(contents.contains(
+            "_Static_assert(offsetof(struct Packet, length) == 0x06, \"Offset of Packet.length\");\n"
+        ));
+        assert!(contents.contains(
+            "_Static_assert(offsetof(struct Packet, default_) == 0x0B, \"Offset of Packet.default_\");\n"
+        ));
+        assert!(contents
+            .contains("_Static_assert(sizeof(struct Packet) == 0x11, \"Size of Packet\");\n"));
+        assert!(
+            contents.contains("_Static_assert(sizeof(union Value) == 0x02, \"Size of Value\");\n")
+        );
+    }
+
+    #[test]
+    fn members_get_c_types_and_comments() {
+        let contents = convert(GRAMMAR).unwrap();
+
+        assert!(contents.contains("    char magic[4];\n"));
+        assert!(contents.contains("    uint8_t bytes[2];\n"));
+        assert!(contents.contains("    uint32_t length; /* Payload length. Big endian */\n"));
+        assert!(contents.contains("    int16_t default_[3]; /* default */\n"));
+    }
+
+    #[test]
+    fn bit_ranges_become_shift_and_mask_macros() {
+        let contents = convert(GRAMMAR).unwrap();
+
+        assert!(contents
+            .contains("#define PACKET_FLAGS_ACK_SHIFT 0\n#define PACKET_FLAGS_ACK_MASK 0x1u\n"));
+        assert!(contents.contains(
+            "#define PACKET_FLAGS_WINDOW_SHIFT 1\n#define PACKET_FLAGS_WINDOW_MASK 0x7Fu\n"
+        ));
+    }
+
+    #[test]
+    fn unions_are_padded_to_their_grammar_size() {
+        let contents =
+            convert(&GRAMMAR.replace("kind = \"union\"\nsize = 2", "kind = \"union\"\nsize = 4"));
+
+        assert!(contents.is_err());
+
+        let contents = convert(
+            &GRAMMAR
+                .replace("kind = \"union\"\nsize = 2", "kind = \"union\"\nsize = 4")
+                .replace("kind = \"struct\"\nsize = 6", "kind = \"struct\"\nsize = 8")
+                .replace("size = 6\ntype = \"Header\"", "size = 8\ntype = \"Header\""),
+        )
+        .unwrap();
+
+        assert!(contents.contains("    uint8_t _size[4];\n"));
+        assert!(
+            contents.contains("_Static_assert(sizeof(union Value) == 0x04, \"Size of Value\");\n")
+        );
+    }
+
+    #[test]
+    fn struct_sizes_that_do_not_match_their_fields_are_an_error() {
+        assert!(convert(
+            &GRAMMAR.replace("kind = \"struct\"\nsize = 6", "kind = \"struct\"\nsize = 7")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn types_that_contain_themselves_are_an_error() {
+        assert!(convert(&GRAMMAR.replace(
+            "data_type = \"uint16_t\"",
+            "type = \"Header\"\ndata_type = \"struct Header\""
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn names_become_c_identifiers() {
+        assert_eq!(c_identifier("points[0].x"), "points_0__x");
+        assert_eq!(c_identifier("2nd field"), "_2nd_field");
+        assert_eq!(c_identifier("int"), "int_");
+        assert_eq!(c_identifier(""), "_");
+        assert_eq!(comment_text("a */ b\nc"), "a * / b c");
+    }
+}
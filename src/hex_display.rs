@@ -1,21 +1,94 @@
 //! Module for printing a colored hextable.
 use crate::format;
 use crate::gram_parse;
-use hexplay::HexViewBuilder;
+use crate::theme;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+
+/// Character sets the side column of the hex view can be displayed in.
+#[derive(Clone, Copy)]
+pub enum SideColumns {
+    /// Code page 850, the hexplay default.
+    Cp850,
+    /// Printable 7-bit ASCII.
+    Ascii,
+    /// UTF-16 little endian, one character per two bytes.
+    Utf16Le,
+    /// UTF-16 big endian, one character per two bytes.
+    Utf16Be,
+    /// EBCDIC code page 037.
+    Ebcdic,
+    /// No side column.
+    Hidden,
+}
+
+/// Holds the layout options of the hex view.
+pub struct HexViewOptions {
+    /// Number of bytes displayed per row.
+    pub row_width: usize,
+    /// Number of bytes in a group, groups are separated by an extra space. 0 disables grouping.
+    pub group_size: usize,
+    /// Number of bytes of the binary file shown before the structure.
+    pub context_before: usize,
+    /// Number of bytes of the binary file shown after the structure.
+    pub context_after: usize,
+    /// The character set of the side column.
+    pub side_column: SideColumns,
+    /// Determines whether a legend mapping colours to field IDs is printed after the hex view.
+    pub legend: bool,
+    /// Determines whether the ID of each field is printed beneath the byte it starts at.
+    pub markers: bool,
+}
+
+impl HexViewOptions {
+    pub fn new() -> Self {
+        Self {
+            row_width: 0x10,
+            group_size: 0,
+            context_before: 0,
+            context_after: 0,
+            side_column: SideColumns::Cp850,
+            legend: false,
+            markers: false,
+        }
+    }
+}
 
 /// Prints a colored hextable containing data extracted from a binary file based on the grammar file definition.
+///
+/// Bytes are always shown in file order. If `decode_words` is true, a line beneath each row shows every element of a
+/// field, grouped by the element width of its type, with its bytes most significant first so multi-byte values read
+/// naturally. The bytes of little endian elements are reversed, while those of big endian formats, e.g `uintbe`, are
+/// already in that order.
+///
+/// Bytes of the binary file surrounding the structure are read from `binary_filepath` if context was requested in `options`.
 pub fn print_hex_table(
     parsed_gram: &gram_parse::Grammar,
     field_hashmap: &HashMap<String, Vec<u8>>,
     field_offset: usize,
-    hex_endianess: bool,
+    decode_words: bool,
+    theme: &theme::Theme,
+    options: &HexViewOptions,
+    binary_filepath: &str,
 ) -> Result<(), ()> {
-    let struct_size = parsed_gram.get_struct_size();
-
     let mut hex_data: Vec<u8> = Vec::new();
+    // Index of the field each byte in `hex_data` belongs to, None for context bytes.
+    let mut field_indexes: Vec<Option<usize>> = Vec::new();
+    let mut field_starts: Vec<(usize, usize)> = Vec::new();
+    // Position of each byte in `hex_data` mirrored within the element it belongs to, used for the decoded word line.
+    let mut decoded_positions: Vec<usize> = Vec::new();
 
-    for field in parsed_gram.fields.iter() {
+    let context_start = field_offset.saturating_sub(options.context_before);
+
+    let mut context_before =
+        read_context(binary_filepath, context_start, field_offset - context_start)?;
+    field_indexes.extend(context_before.iter().map(|_| None));
+    decoded_positions.extend(0..context_before.len());
+    hex_data.append(&mut context_before);
+
+    for (index, field) in parsed_gram.fields.iter().enumerate() {
         let mut data: Vec<u8> = field_hashmap
             .get(&field.name)
             .ok_or_else(|| {
@@ -23,39 +96,390 @@ pub fn print_hex_table(
             })?
             .clone();
 
-        if hex_endianess && &field.display_format[..] != format::ASCII_TYPE {
-            data.reverse()
-        }
+        data.truncate(field.size);
+
+        let field_start = hex_data.len();
+        let element_width = element_width(field);
+        let big_endian = format::is_big_endian_format(&field.display_format);
+
+        decoded_positions.extend(
+            element_positions(data.len(), element_width, big_endian)
+                .into_iter()
+                .map(|p| field_start + p),
+        );
 
+        field_starts.push((context_start + hex_data.len(), index));
+        field_indexes.extend(data.iter().map(|_| Some(index)));
         hex_data.append(&mut data);
     }
 
-    let mut color_vector = Vec::new();
+    let mut context_after = read_context(
+        binary_filepath,
+        context_start + hex_data.len(),
+        options.context_after,
+    )?;
+    field_indexes.extend(context_after.iter().map(|_| None));
+    decoded_positions.extend(hex_data.len()..hex_data.len() + context_after.len());
+    hex_data.append(&mut context_after);
 
-    let mut color_offset: usize = 0;
+    let row_width = options.row_width.max(1);
+    let colour_at = |position: usize| {
+        field_indexes[position].map(|index| theme.field_colour(index, &parsed_gram.fields[index]))
+    };
+
+    // Character offset of each byte column from the start of the hex area, including group separators.
+    let column_positions: Vec<usize> = (0..row_width)
+        .map(|column| {
+            column * 3
+                + match options.group_size {
+                    0 => 0,
+                    group_size => column / group_size,
+                }
+        })
+        .collect();
+    let hex_area_width = column_positions[row_width - 1] + 2;
+
+    let data_end = context_start + hex_data.len();
+    let mut row_address = context_start - context_start % row_width;
+
+    while row_address < data_end {
+        let mut hex_column = String::new();
+        let mut side_column = String::new();
+        let mut decoded_column = String::new();
+        let mut marker_column = String::new();
+        // Width of `marker_column` in characters, excluding colour codes.
+        let mut marker_width: usize = 0;
+        let mut marker_names: Vec<String> = Vec::new();
+
+        for (column, column_position) in column_positions.iter().enumerate() {
+            let address = row_address + column;
+
+            if column != 0 {
+                hex_column.push(' ');
+                decoded_column.push(' ');
+            }
+            if options.group_size != 0 && column != 0 && column % options.group_size == 0 {
+                hex_column.push(' ');
+                decoded_column.push(' ');
+            }
+
+            if address < context_start || address >= data_end {
+                hex_column.push_str("  ");
+                decoded_column.push_str("  ");
+                continue;
+            }
+
+            let position = address - context_start;
+
+            hex_column.push_str(
+                &theme.paint(&format!("{:02X}", hex_data[position]), colour_at(position)),
+            );
+
+            match field_indexes[position] {
+                Some(_) => decoded_column.push_str(&theme.paint(
+                    &format!("{:02X}", hex_data[decoded_positions[position]]),
+                    colour_at(position),
+                )),
+                None => decoded_column.push_str("  "),
+            }
+
+            if options.markers {
+                if let Some((_, index)) = field_starts.iter().find(|(start, _)| *start == address) {
+                    let field_colour =
+                        Some(theme.field_colour(*index, &parsed_gram.fields[*index]));
+
+                    if marker_width < *column_position {
+                        marker_column.push_str(&" ".repeat(column_position - marker_width));
+                    }
+                    marker_column.push_str(&theme.paint(&format!("{:03X}", index), field_colour));
+                    marker_width = column_position + 3;
+                    marker_names.push(theme.paint(
+                        &format!("{:03X} {}", index, parsed_gram.fields[*index].name),
+                        field_colour,
+                    ));
+                }
+            }
+        }
+
+        let mut previous_wide = false;
+
+        for column in 0..row_width {
+            let address = row_address + column;
+
+            if address < context_start || address >= data_end {
+                side_column.push(' ');
+                continue;
+            }
+
+            let position = address - context_start;
+            let side_char = match options.side_column {
+                SideColumns::Cp850 => {
+                    printable(hexplay::CODEPAGE_0850[hex_data[position] as usize])
+                }
+                SideColumns::Ascii => {
+                    printable(hexplay::CODEPAGE_ASCII[hex_data[position] as usize])
+                }
+                SideColumns::Ebcdic => printable(format::EBCDIC_037[hex_data[position] as usize]),
+                SideColumns::Utf16Le | SideColumns::Utf16Be => {
+                    // Each UTF-16 code unit occupies the column of its first byte, the second column is left blank
+                    // unless the character is double width.
+                    if column % 2 == 1 {
+                        if previous_wide {
+                            continue;
+                        }
+                        ' '
+                    } else if position + 1 < hex_data.len() {
+                        let code_unit = match options.side_column {
+                            SideColumns::Utf16Le => {
+                                u16::from_le_bytes([hex_data[position], hex_data[position + 1]])
+                            }
+                            _ => u16::from_be_bytes([hex_data[position], hex_data[position + 1]]),
+                        };
+                        printable(std::char::from_u32(code_unit as u32).unwrap_or('.'))
+                    } else {
+                        '.'
+                    }
+                }
+                SideColumns::Hidden => break,
+            };
+
+            previous_wide = is_wide(side_char);
+            side_column.push_str(&theme.paint(&side_char.to_string(), colour_at(position)));
+        }
+
+        match options.side_column {
+            SideColumns::Hidden => println!("{:08X}  {}", row_address, hex_column),
+            _ => println!("{:08X}  {}  | {} |", row_address, hex_column, side_column),
+        }
+
+        if decode_words {
+            println!("{:>8}  {}", theme.paint("decoded", None), decoded_column);
+        }
+
+        if !marker_names.is_empty() {
+            println!(
+                "{:10}{}{}  {}",
+                "",
+                marker_column,
+                " ".repeat(hex_area_width.saturating_sub(marker_width)),
+                marker_names.join(", ")
+            );
+        }
+
+        row_address += row_width;
+    }
+
+    if options.legend {
+        println!("\nLegend:");
+
+        let mut legend_offset = field_offset;
+
+        for (index, field) in parsed_gram.fields.iter().enumerate() {
+            let field_colour = Some(theme.field_colour(index, field));
+
+            println!(
+                "  {} {} {} {:#X}-{:#X}",
+                theme.paint("\u{2588}\u{2588}", field_colour),
+                theme.paint(&format!("{:03X}", index), field_colour),
+                field.name,
+                legend_offset,
+                (legend_offset + field.size).saturating_sub(1)
+            );
+
+            legend_offset += field.size;
+        }
+    }
 
-    for (index, field) in parsed_gram.fields.iter().enumerate() {
-        match index % 2 {
-            0 => color_vector.append(&mut vec![(
-                hexplay::color::green_bold(),
-                color_offset..color_offset + field.size,
-            )]),
-            _ => color_vector.append(&mut vec![(
-                hexplay::color::magenta_bold(),
-                color_offset..color_offset + field.size,
-            )]),
-        };
-
-        color_offset += field.size;
-    }
-
-    let hex_view = HexViewBuilder::new(&hex_data[..struct_size])
-        .address_offset(field_offset)
-        .row_width(0x10)
-        .add_colors(color_vector)
-        .finish();
-
-    hex_view.print().unwrap();
     println!();
     Ok(())
 }
+
+/// Returns the width in bytes of a single element of `field`, based on its data type and display format.
+///
+/// e.g a `short` field of 0x10 bytes is made up of 8 elements of 2 bytes. Fields of an unknown type that are the size
+/// of an integer are treated as a single element, anything else is treated as individual bytes.
+fn element_width(field: &gram_parse::GrammarFields) -> usize {
+    let data_type = field.data_type.to_lowercase();
+    let data_type = data_type
+        .trim_start_matches("unsigned ")
+        .trim_start_matches("signed ")
+        .trim_end_matches(" int");
+
+    let width = match &field.display_format[..] {
+        format::UTF16LE_TYPE
+        | format::UTF16BE_TYPE
+        | format::THUMB_TYPE
+        | format::RISCV_TYPE
+        | format::DOS_DATETIME_TYPE
+        | format::HALF_TYPE
+        | format::BFLOAT16_TYPE => 2,
+        format::X86_16_TYPE | format::X86_32_TYPE | format::X86_64_TYPE => 1,
+        format::BCD_TYPE => 1,
+        display_format if format::is_varint_format(display_format) => 1,
+        display_format if format::is_text_format(display_format) => 1,
+        format::ARM32_TYPE | format::AARCH64_TYPE => 4,
+        format::IPV4LE_TYPE | format::IPV4BE_TYPE => 4,
+        format::HEXLE_TYPE | format::BITS_TYPE | format::BITSBE_TYPE => field.size,
+        display_format if format::parse_fixed_point(display_format).is_some() => field.size,
+        _ => match data_type {
+            "char" | "byte" | "u8" | "i8" | "uint8_t" | "int8_t" | "uchar" | "bool" => 1,
+            "short" | "word" | "u16" | "i16" | "uint16_t" | "int16_t" | "wchar_t" => 2,
+            "int" | "long" | "float" | "dword" | "u32" | "i32" | "f32" | "uint32_t" | "int32_t" => {
+                4
+            }
+            "long long" | "double" | "qword" | "u64" | "i64" | "f64" | "uint64_t" | "int64_t" => 8,
+            _ => match field.size {
+                2 | 4 | 8 => field.size,
+                _ => 1,
+            },
+        },
+    };
+
+    width.max(1)
+}
+
+/// Returns the position of each of `data_len` bytes in the decoded word line, most significant byte of each element first.
+///
+/// The bytes of little endian elements are reversed, while those of big endian elements are already in that order.
+fn element_positions(data_len: usize, element_width: usize, big_endian: bool) -> Vec<usize> {
+    let mut positions: Vec<usize> = Vec::new();
+
+    for element_start in (0..data_len).step_by(element_width) {
+        let element_end = (element_start + element_width).min(data_len);
+
+        if big_endian {
+            positions.extend(element_start..element_end);
+        } else {
+            positions.extend((element_start..element_end).rev());
+        }
+    }
+
+    positions
+}
+
+/// Replaces control and unmappable characters with `.` so they are not written raw to the terminal.
+fn printable(character: char) -> char {
+    if character.is_control() || character == std::char::REPLACEMENT_CHARACTER {
+        '.'
+    } else {
+        character
+    }
+}
+
+/// Returns true if `character` is displayed two columns wide in a terminal, e.g CJK ideographs.
+fn is_wide(character: char) -> bool {
+    matches!(
+        character as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+    )
+}
+
+/// Reads up to `size` bytes from `binary_filepath` starting at `offset`, stopping early at the end of the file.
+fn read_context(binary_filepath: &str, offset: usize, size: usize) -> Result<Vec<u8>, ()> {
+    let mut context: Vec<u8> = Vec::new();
+
+    if size == 0 {
+        return Ok(context);
+    }
+
+    let mut binary_file = File::open(binary_filepath)
+        .map_err(|_| serror!(format!("Could not open file: {}", binary_filepath)))?;
+
+    binary_file
+        .seek(SeekFrom::Start(offset as u64))
+        .map_err(|e| {
+            serror!(format!(
+                "Could not seek to offset: {}, because {}",
+                offset, e
+            ))
+        })?;
+
+    binary_file
+        .take(size as u64)
+        .read_to_end(&mut context)
+        .map_err(|e| serror!(format!("Could not read context bytes, because {}", e)))?;
+
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_is_read_from_the_binary_file() {
+        assert_eq!(
+            read_context("./examples/mbr.bin", 0x10, 4).unwrap(),
+            [0xBF, 0x00, 0x06, 0xB9]
+        );
+        assert!(read_context("./examples/mbr.bin", 0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn context_stops_at_the_end_of_the_file() {
+        assert_eq!(
+            read_context("./examples/mbr.bin", 0x1FE, 0x10).unwrap(),
+            [0x55, 0xAA]
+        );
+    }
+
+    #[test]
+    fn control_characters_are_not_printed() {
+        assert_eq!(printable('\u{1B}'), '.');
+        assert_eq!(printable(std::char::REPLACEMENT_CHARACTER), '.');
+        assert_eq!(printable('A'), 'A');
+        assert_eq!(printable(format::EBCDIC_037[0xC1]), 'A');
+    }
+
+    #[test]
+    fn wide_characters_are_detected() {
+        assert!(is_wide('\u{65E5}'));
+        assert!(is_wide('\u{FF21}'));
+        assert!(!is_wide('A'));
+        assert!(!is_wide('\u{E9}'));
+    }
+
+    fn field(data_type: &str, display_format: &str, size: usize) -> gram_parse::GrammarFields {
+        gram_parse::GrammarFields {
+            name: String::from("Field"),
+            size,
+            data_type: String::from(data_type),
+            display_format: String::from(display_format),
+            description: String::new(),
+            scale: None,
+            offset: None,
+            unit: String::new(),
+            script: String::new(),
+            bits: Vec::new(),
+            count: None,
+            type_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn elements_are_as_wide_as_the_field_type() {
+        assert_eq!(element_width(&field("unsigned short", "hex", 0x10)), 2);
+        assert_eq!(element_width(&field("uint32_t", "hex", 0x10)), 4);
+        assert_eq!(element_width(&field("char", format::ASCII_TYPE, 0x10)), 1);
+        assert_eq!(element_width(&field("blob", format::HEXLE_TYPE, 6)), 6);
+        assert_eq!(element_width(&field("blob", "hex", 8)), 8);
+        assert_eq!(element_width(&field("blob", "hex", 3)), 1);
+    }
+
+    #[test]
+    fn little_endian_elements_are_reversed_individually() {
+        assert_eq!(element_positions(6, 2, false), [1, 0, 3, 2, 5, 4]);
+        assert_eq!(element_positions(5, 4, false), [3, 2, 1, 0, 4]);
+    }
+
+    #[test]
+    fn big_endian_elements_stay_in_file_order() {
+        assert_eq!(element_positions(8, 4, true), [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+}
@@ -0,0 +1,281 @@
+//! Module for creating a self-contained HTML report of data extracted from a binary file.
+//!
+//! The report contains the standard table, description table and a hex view. Hovering over a table row highlights
+//! the bytes of that field in the hex view and vice versa, clicking pins the highlight. No network resources are referenced.
+use crate::gram_parse;
+use crate::table_display;
+use crate::theme;
+use hex::ToHex;
+
+/// Number of bytes shown per row of the hex view.
+const HEX_ROW_WIDTH: usize = 0x10;
+
+/// Styling for the report, field colours are set per element from the selected `theme::Theme`.
+const REPORT_CSS: &str = "
+body { font-family: sans-serif; margin: 2em; background: #ffffff; color: #202020; }
+h1, h2 { font-weight: normal; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #c0c0c0; padding: 0.25em 0.5em; text-align: left; vertical-align: top; }
+td.raw { font-family: monospace; word-break: break-all; max-width: 24em; }
+td.fmt { font-family: monospace; white-space: pre-wrap; }
+[data-field] { font-weight: bold; }
+.hover { background: #fff3a0; }
+.selected { background: #ffd24d; }
+#hex { font-family: monospace; white-space: pre; line-height: 1.4; }
+#hex .addr { color: #808080; }
+#hex span[data-field] { cursor: pointer; }
+tr[data-field] { cursor: pointer; }
+";
+
+/// Links table rows with hex view bytes by their `data-field` attribute.
+const REPORT_JS: &str = "
+function fieldElements(id) {
+    return document.querySelectorAll('[data-field=\"' + id + '\"]');
+}
+document.querySelectorAll('[data-field]').forEach(function (el) {
+    var id = el.getAttribute('data-field');
+    el.addEventListener('mouseenter', function () {
+        fieldElements(id).forEach(function (e) { e.classList.add('hover'); });
+    });
+    el.addEventListener('mouseleave', function () {
+        fieldElements(id).forEach(function (e) { e.classList.remove('hover'); });
+    });
+    el.addEventListener('click', function () {
+        var selected = !el.classList.contains('selected');
+        document.querySelectorAll('.selected').forEach(function (e) { e.classList.remove('selected'); });
+        if (!selected) { return; }
+        var counterpart = null;
+        fieldElements(id).forEach(function (e) {
+            e.classList.add('selected');
+            if (counterpart === null && (e.tagName === 'TR') !== (el.tagName === 'TR')) { counterpart = e; }
+        });
+        if (counterpart !== null) { counterpart.scrollIntoView({ block: 'center' }); }
+    });
+});
+";
+
+/// Escapes the characters that have a special meaning in HTML.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Returns the inline CSS colour style for `field`, which is at `index` in the grammar.
+fn colour_style(theme: &theme::Theme, index: usize, field: &gram_parse::GrammarFields) -> String {
+    format!("color: {}", theme.field_colour(index, field).css_colour())
+}
+
+/// Builds the contents of the HTML report from the data gathered in `table_data`.
+///
+/// `format_fields` must have been run on `table_data` before this is called.
+pub fn build_html_report(
+    parsed_gram: &gram_parse::Grammar,
+    table_data: &table_display::TableData,
+    struct_offset: usize,
+    theme: &theme::Theme,
+) -> Result<String, ()> {
+    let title = html_escape(&parsed_gram.metadata.name);
+    let mut standard_rows = String::new();
+    let mut description_rows = String::new();
+    let mut hex_bytes: Vec<(u8, usize, String)> = Vec::new();
+    let mut field_offset = struct_offset;
+
+    for (index, field) in parsed_gram.fields.iter().enumerate() {
+        let raw_field_data = table_data.field_hashmap.get(&field.name).ok_or_else(|| {
+            serror!(format!("Could not get value for field: {}", field.name));
+        })?;
+
+        let formatted_data = table_data
+            .field_fmt_hashmap
+            .get(&field.name)
+            .ok_or_else(|| {
+                serror!(format!("Could not get value for field: {}", field.name));
+            })?;
+
+        let field_style = colour_style(theme, index, field);
+
+        standard_rows.push_str(&format!(
+            "<tr style=\"{}\" data-field=\"{}\"><td>{:03X}</td><td>{}</td><td>{:#X}</td><td>{:#X}</td><td>{}</td><td class=\"raw\">{}</td><td class=\"fmt\">{}</td></tr>\n",
+            field_style,
+            index,
+            index,
+            html_escape(&field.name),
+            field_offset,
+            field.size,
+            html_escape(&field.data_type),
+            raw_field_data.encode_hex_upper::<String>(),
+            html_escape(formatted_data.trim_end()),
+        ));
+
+        description_rows.push_str(&format!(
+            "<tr style=\"{}\" data-field=\"{}\"><td>{:03X}</td><td>{}</td><td>{}</td></tr>\n",
+            field_style,
+            index,
+            index,
+            html_escape(&field.name),
+            html_escape(&field.description),
+        ));
+
+        hex_bytes.extend(
+            raw_field_data
+                .iter()
+                .map(|byte| (*byte, index, field_style.clone())),
+        );
+        field_offset += field.size;
+    }
+
+    let mut hex_view = String::new();
+
+    for (row_index, row) in hex_bytes.chunks(HEX_ROW_WIDTH).enumerate() {
+        let mut hex_column = String::new();
+        let mut ascii_column = String::new();
+
+        for (byte, index, field_style) in row.iter() {
+            let ascii_char = match *byte {
+                0x20..=0x7E => html_escape(&(*byte as char).to_string()),
+                _ => String::from("."),
+            };
+
+            hex_column.push_str(&format!(
+                "<span style=\"{}\" data-field=\"{}\">{:02X}</span> ",
+                field_style, index, byte
+            ));
+            ascii_column.push_str(&format!(
+                "<span style=\"{}\" data-field=\"{}\">{}</span>",
+                field_style, index, ascii_char
+            ));
+        }
+
+        hex_column.push_str(&"   ".repeat(HEX_ROW_WIDTH - row.len()));
+        ascii_column.push_str(&" ".repeat(HEX_ROW_WIDTH - row.len()));
+
+        hex_view.push_str(&format!(
+            "<span class=\"addr\">{:08X}</span>  {} | {} |\n",
+            struct_offset + row_index * HEX_ROW_WIDTH,
+            hex_column,
+            ascii_column
+        ));
+    }
+
+    Ok(format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>memgram - {title}</title>
+<style>{css}</style>
+</head>
+<body>
+<h1>{title}</h1>
+<h2>Fields</h2>
+<table>
+<tr><th>ID</th><th>Field</th><th>Offset</th><th>Size</th><th>Data Type</th><th>Raw Data</th><th>Formatted Data</th></tr>
+{standard_rows}</table>
+<h2>Descriptions</h2>
+<table>
+<tr><th>ID</th><th>Field</th><th>Description</th></tr>
+{description_rows}</table>
+<h2>Hex View</h2>
+<div id=\"hex\">{hex_view}</div>
+<script>{js}</script>
+</body>
+</html>
+",
+        title = title,
+        css = REPORT_CSS,
+        standard_rows = standard_rows,
+        description_rows = description_rows,
+        hex_view = hex_view,
+        js = REPORT_JS,
+    ))
+}
+
+/// Prints the HTML report to stdout.
+pub fn print_html(html_report: &str) {
+    print!("{}", html_report);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = r#"
+[metadata]
+name = "Header <v1>"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "Magic"
+size = 2
+data_type = "char"
+display_format = "ascii"
+description = "File magic & version"
+
+[[fields]]
+name = "Length"
+size = 4
+data_type = "uint32_t"
+display_format = "hex"
+description = "Length of the data"
+"#;
+
+    fn build_report() -> String {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(GRAMMAR).unwrap();
+
+        let mut table_data = table_display::TableData::new();
+        table_data
+            .field_hashmap
+            .insert(String::from("Magic"), vec![0x4D, 0x3C]);
+        table_data
+            .field_hashmap
+            .insert(String::from("Length"), vec![0x00, 0x01, 0xAB, 0xFF]);
+        table_data
+            .field_fmt_hashmap
+            .insert(String::from("Magic"), String::from("M<"));
+        table_data
+            .field_fmt_hashmap
+            .insert(String::from("Length"), String::from("0001ABFF"));
+
+        let theme = theme::Theme {
+            colour: true,
+            palette: theme::Palettes::Default,
+        };
+
+        build_html_report(&parsed_gram, &table_data, 0x100, &theme).unwrap()
+    }
+
+    #[test]
+    fn text_is_html_escaped() {
+        let html_report = build_report();
+
+        assert!(html_report.contains("<title>memgram - Header &lt;v1&gt;</title>"));
+        assert!(html_report.contains("<td>File magic &amp; version</td>"));
+        assert!(html_report.contains("<td class=\"fmt\">M&lt;</td>"));
+    }
+
+    #[test]
+    fn table_rows_and_hex_bytes_are_linked_by_field() {
+        let html_report = build_report();
+
+        assert!(html_report.contains(
+            "<tr style=\"color: #a4249c\" data-field=\"1\"><td>001</td><td>Length</td><td>0x102</td>"
+        ));
+        assert!(html_report.contains("<span style=\"color: #138a36\" data-field=\"0\">4D</span>"));
+        assert!(html_report.contains("<span style=\"color: #a4249c\" data-field=\"1\">AB</span>"));
+        assert!(html_report.contains("<span class=\"addr\">00000100</span>"));
+    }
+
+    #[test]
+    fn no_network_resources_are_referenced() {
+        let html_report = build_report();
+
+        assert!(!html_report.contains("http"));
+        assert!(!html_report.contains(" src="));
+        assert!(!html_report.contains(" href="));
+    }
+}
@@ -0,0 +1,204 @@
+//! Module for printing data extracted from a binary file as JSON or JSON Lines.
+use crate::gram_parse;
+use crate::table_display;
+use hex::ToHex;
+use serde::Serialize;
+
+/// Holds a parsed structure in the form it is serialized to JSON.
+#[derive(Serialize)]
+pub struct JsonStruct<'a> {
+    /// The name of the data structure from the grammar metadata.
+    pub name: &'a str,
+    /// Absolute offset into the binary file the structure starts at.
+    pub offset: usize,
+    /// Total size of the structure in bytes.
+    pub size: usize,
+    /// Every field of the structure in grammar order.
+    pub fields: Vec<JsonField<'a>>,
+}
+
+/// Holds a single field of a parsed structure in the form it is serialized to JSON.
+#[derive(Serialize)]
+pub struct JsonField<'a> {
+    /// The field ID, matching the "ID" column of the standard table.
+    pub id: usize,
+    /// The name of the field.
+    pub name: &'a str,
+    /// The name of the field qualified by the name of the structure, e.g `MBR.Boot Signature`.
+    pub path: String,
+    /// Absolute offset into the binary file the field starts at.
+    pub offset: usize,
+    /// How large the field is in bytes.
+    pub size: usize,
+    /// The data type of the field.
+    pub data_type: &'a str,
+    /// The display format of the field.
+    pub display_format: &'a str,
+    /// The description of the field.
+    pub description: &'a str,
+    /// The untruncated raw data of the field as an uppercase hex string.
+    pub raw_data: String,
+    /// The data formatted based on `display_format`, as shown in the "Formatted Data" column.
+    pub formatted_data: &'a str,
+}
+
+/// Builds a `JsonStruct` from the raw and formatted data gathered in `table_data`.
+///
+/// `format_fields` must have been run on `table_data` before this is called.
+pub fn build_json_struct<'a>(
+    parsed_gram: &'a gram_parse::Grammar,
+    table_data: &'a table_display::TableData,
+    struct_offset: usize,
+) -> Result<JsonStruct<'a>, ()> {
+    let mut fields: Vec<JsonField> = Vec::new();
+    let mut field_offset = struct_offset;
+
+    for (index, field) in parsed_gram.fields.iter().enumerate() {
+        let raw_field_data = table_data.field_hashmap.get(&field.name).ok_or_else(|| {
+            serror!(format!("Could not get value for field: {}", field.name));
+        })?;
+
+        let formatted_data = table_data
+            .field_fmt_hashmap
+            .get(&field.name)
+            .ok_or_else(|| {
+                serror!(format!("Could not get value for field: {}", field.name));
+            })?;
+
+        fields.push(JsonField {
+            id: index,
+            name: &field.name,
+            path: format!("{}.{}", parsed_gram.metadata.name, field.name),
+            offset: field_offset,
+            size: field.size,
+            data_type: &field.data_type,
+            display_format: &field.display_format,
+            description: &field.description,
+            raw_data: raw_field_data.encode_hex_upper::<String>(),
+            formatted_data,
+        });
+
+        field_offset += field.size;
+    }
+
+    Ok(JsonStruct {
+        name: &parsed_gram.metadata.name,
+        offset: struct_offset,
+        size: parsed_gram.get_struct_size(),
+        fields,
+    })
+}
+
+/// Prints the parsed structure to stdout as a single pretty printed JSON document.
+pub fn print_json(json_struct: &JsonStruct) -> Result<(), ()> {
+    let json_string = serde_json::to_string_pretty(json_struct).map_err(|e| {
+        serror!(format!(
+            "Could not serialize structure to JSON, because {}",
+            e
+        ))
+    })?;
+
+    println!("{}", json_string);
+    Ok(())
+}
+
+/// Prints the parsed structure to stdout as JSON Lines, with one JSON object per field.
+pub fn print_json_lines(json_struct: &JsonStruct) -> Result<(), ()> {
+    for field in json_struct.fields.iter() {
+        let json_string = serde_json::to_string(field).map_err(|e| {
+            serror!(format!(
+                "Could not serialize field: {} to JSON, because {}",
+                field.name, e
+            ))
+        })?;
+
+        println!("{}", json_string);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = r#"
+[metadata]
+name = "Header"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "Magic"
+size = 2
+data_type = "char"
+display_format = "ascii"
+description = "File magic"
+
+[[fields]]
+name = "Length"
+size = 4
+data_type = "uint32_t"
+display_format = "hex"
+description = "Length of the data"
+"#;
+
+    fn table_data() -> table_display::TableData {
+        let mut table_data = table_display::TableData::new();
+        table_data
+            .field_hashmap
+            .insert(String::from("Magic"), vec![0x4D, 0x5A]);
+        table_data
+            .field_hashmap
+            .insert(String::from("Length"), vec![0x00, 0x01, 0xAB, 0xFF]);
+        table_data
+            .field_fmt_hashmap
+            .insert(String::from("Magic"), String::from("MZ"));
+        table_data
+            .field_fmt_hashmap
+            .insert(String::from("Length"), String::from("0001ABFF"));
+        table_data
+    }
+
+    #[test]
+    fn fields_have_absolute_offsets_and_paths() {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(GRAMMAR).unwrap();
+        let table_data = table_data();
+
+        let json_struct = build_json_struct(&parsed_gram, &table_data, 0x10).unwrap();
+
+        assert_eq!(json_struct.name, "Header");
+        assert_eq!(json_struct.offset, 0x10);
+        assert_eq!(json_struct.size, 6);
+        assert_eq!(json_struct.fields[1].id, 1);
+        assert_eq!(json_struct.fields[1].path, "Header.Length");
+        assert_eq!(json_struct.fields[1].offset, 0x12);
+        assert_eq!(json_struct.fields[1].raw_data, "0001ABFF");
+        assert_eq!(json_struct.fields[0].formatted_data, "MZ");
+    }
+
+    #[test]
+    fn json_lines_serialize_one_object_per_field() {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(GRAMMAR).unwrap();
+        let table_data = table_data();
+
+        let json_struct = build_json_struct(&parsed_gram, &table_data, 0).unwrap();
+        let line = serde_json::to_string(&json_struct.fields[0]).unwrap();
+
+        assert_eq!(
+            line,
+            r#"{"id":0,"name":"Magic","path":"Header.Magic","offset":0,"size":2,"data_type":"char","display_format":"ascii","description":"File magic","raw_data":"4D5A","formatted_data":"MZ"}"#
+        );
+    }
+
+    #[test]
+    fn missing_field_data_is_an_error() {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(GRAMMAR).unwrap();
+        let mut table_data = table_data();
+        table_data.field_hashmap.remove("Length");
+
+        assert!(build_json_struct(&parsed_gram, &table_data, 0).is_err());
+    }
+}
@@ -4,11 +4,26 @@
 #[macro_use]
 mod errors;
 mod arg_parse;
+mod arm_disass;
+mod c_parse;
+mod c_types;
+mod code_display;
+mod dwarf_convert;
+mod export_display;
 mod format;
+mod formatter;
 mod gram_parse;
+mod header_convert;
 mod hex_display;
+mod html_display;
+mod json_display;
+mod riscv_disass;
+mod rust_convert;
+mod rust_parse;
+mod script;
 mod struct_convert;
 mod table_display;
+mod theme;
 use std::fs;
 
 #[macro_use]
@@ -32,15 +47,22 @@ fn run() -> Result<(), ()> {
 
     match cmd_args.run_cmds() {
         Ok(r) => match r {
+            arg_parse::RunOptions::ListFormats => {
+                formatter::print_formats();
+
+                Ok(())
+            }
             arg_parse::RunOptions::CStructConvertWrite => {
                 let mut c_struct = struct_convert::CStruct::new();
 
                 cmd_args
                     .parse_file_arg(arg_parse::OUTPUT_FILE_FLAG)?
-                    .parse_file_arg(arg_parse::CSTRUCT_FILE_FLAG)?;
+                    .parse_file_arg(arg_parse::CSTRUCT_FILE_FLAG)?
+                    .parse_abi_flag(arg_parse::ABI_FLAG)?
+                    .parse_root_flag(arg_parse::ROOT_FLAG)?;
 
                 c_struct
-                    .parse_c_struct(&cmd_args.cstruct_filepath)?
+                    .parse_c_struct(&cmd_args)?
                     .build_grammar_contents()?
                     .write_grammar_file(&cmd_args.output_filepath)?;
 
@@ -57,30 +79,160 @@ fn run() -> Result<(), ()> {
                         arg_parse::FMT_ENDIAN_FLAG,
                         arg_parse::HEX_ENDIAN_FLAG,
                         arg_parse::DESCRIPTION_FLAG,
-                    );
+                    )
+                    .parse_output_format_flag(arg_parse::OUTPUT_FORMAT_FLAG)?
+                    .parse_columns_flag(arg_parse::COLUMNS_FLAG)?
+                    .parse_colour_flags(arg_parse::COLOUR_FLAG, arg_parse::THEME_FLAG)?
+                    .parse_hex_view_flags()?
+                    .parse_syntax_flag(arg_parse::SYNTAX_FLAG)?
+                    .parse_abi_flag(arg_parse::ABI_FLAG)?
+                    .parse_root_flag(arg_parse::ROOT_FLAG)?;
 
                 c_struct
-                    .parse_c_struct(&cmd_args.cstruct_filepath)?
+                    .parse_c_struct(&cmd_args)?
                     .build_grammar_contents()?;
 
                 let mut parsed_gram = gram_parse::Grammar::new();
 
-                parsed_gram.parse_toml(&c_struct.grammar_contents)?;
+                parsed_gram
+                    .parse_toml(&c_struct.grammar_contents)?
+                    .post_parse_toml()?;
 
                 let mut table_data = table_display::TableData::new();
 
                 table_data
                     .create_field_hashmap(&mut parsed_gram, &cmd_args)?
-                    .format_fields(&parsed_gram, cmd_args.fmt_endian)?
-                    .fill_standard_table(&parsed_gram, cmd_args.struct_offset as usize)?
-                    .print_table(table_display::Tables::Standard);
+                    .format_fields(
+                        &parsed_gram,
+                        cmd_args.fmt_endian,
+                        cmd_args.struct_offset as usize,
+                        cmd_args.asm_syntax,
+                    )?;
+
+                print_output(&parsed_gram, &mut table_data, &cmd_args)
+            }
+            arg_parse::RunOptions::DwarfConvertWrite => {
+                let mut dwarf_struct = dwarf_convert::DwarfStruct::new();
 
-                hex_display::print_hex_table(
-                    &parsed_gram,
-                    &table_data.field_hashmap,
-                    cmd_args.struct_offset as usize,
-                    cmd_args.hex_endian,
-                )?;
+                cmd_args
+                    .parse_file_arg(arg_parse::OUTPUT_FILE_FLAG)?
+                    .parse_file_arg(arg_parse::ELF_FILE_FLAG)?
+                    .parse_root_flag(arg_parse::ROOT_FLAG)?;
+
+                dwarf_struct
+                    .parse_dwarf(&cmd_args)?
+                    .build_grammar_contents()?
+                    .write_grammar_file(&cmd_args.output_filepath)?;
+
+                Ok(())
+            }
+            arg_parse::RunOptions::DwarfConvertDisplay => {
+                let mut dwarf_struct = dwarf_convert::DwarfStruct::new();
+
+                cmd_args
+                    .parse_file_arg(arg_parse::ELF_FILE_FLAG)?
+                    .parse_file_arg(arg_parse::BINARY_FILE_FLAG)?
+                    .parse_offset_flag(arg_parse::STRUCT_OFFSET_FLAG)?
+                    .parse_bool_flags(
+                        arg_parse::FMT_ENDIAN_FLAG,
+                        arg_parse::HEX_ENDIAN_FLAG,
+                        arg_parse::DESCRIPTION_FLAG,
+                    )
+                    .parse_output_format_flag(arg_parse::OUTPUT_FORMAT_FLAG)?
+                    .parse_columns_flag(arg_parse::COLUMNS_FLAG)?
+                    .parse_colour_flags(arg_parse::COLOUR_FLAG, arg_parse::THEME_FLAG)?
+                    .parse_hex_view_flags()?
+                    .parse_syntax_flag(arg_parse::SYNTAX_FLAG)?
+                    .parse_root_flag(arg_parse::ROOT_FLAG)?;
+
+                dwarf_struct
+                    .parse_dwarf(&cmd_args)?
+                    .build_grammar_contents()?;
+
+                let mut parsed_gram = gram_parse::Grammar::new();
+
+                parsed_gram
+                    .parse_toml(&dwarf_struct.grammar_contents)?
+                    .post_parse_toml()?;
+
+                let mut table_data = table_display::TableData::new();
+
+                table_data
+                    .create_field_hashmap(&mut parsed_gram, &cmd_args)?
+                    .format_fields(
+                        &parsed_gram,
+                        cmd_args.fmt_endian,
+                        cmd_args.struct_offset as usize,
+                        cmd_args.asm_syntax,
+                    )?;
+
+                print_output(&parsed_gram, &mut table_data, &cmd_args)
+            }
+            arg_parse::RunOptions::GrammarConvertWrite => {
+                cmd_args
+                    .parse_file_arg(arg_parse::OUTPUT_FILE_FLAG)?
+                    .parse_file_arg(arg_parse::GRAMMER_FILE_FLAG)?;
+
+                let file_contents =
+                    fs::read_to_string(&cmd_args.grammar_filepath).map_err(|e| {
+                        serror!(format!(
+                            "Error opening file: {}, because:{}",
+                            &cmd_args.grammar_filepath, e
+                        ))
+                    })?;
+
+                let mut parsed_gram = gram_parse::Grammar::new();
+                parsed_gram.parse_toml(&file_contents)?;
+
+                header_convert::CHeaderFile::new()
+                    .build_header_contents(&parsed_gram)?
+                    .write_header_file(&cmd_args.output_filepath)?;
+
+                Ok(())
+            }
+            arg_parse::RunOptions::GrammarConvertRust => {
+                cmd_args
+                    .parse_file_arg(arg_parse::OUTPUT_FILE_FLAG)?
+                    .parse_file_arg(arg_parse::GRAMMER_FILE_FLAG)?;
+
+                let file_contents =
+                    fs::read_to_string(&cmd_args.grammar_filepath).map_err(|e| {
+                        serror!(format!(
+                            "Error opening file: {}, because:{}",
+                            &cmd_args.grammar_filepath, e
+                        ))
+                    })?;
+
+                let mut parsed_gram = gram_parse::Grammar::new();
+                parsed_gram.parse_toml(&file_contents)?;
+
+                let mut rust_module = rust_convert::RustModule::new();
+                rust_module.build_module_contents(&parsed_gram)?;
+
+                if cmd_args.arg_map.contains_key(arg_parse::BINARY_FILE_FLAG) {
+                    cmd_args
+                        .parse_file_arg(arg_parse::BINARY_FILE_FLAG)?
+                        .parse_offset_flag(arg_parse::STRUCT_OFFSET_FLAG)?;
+
+                    parsed_gram
+                        .post_parse_toml()?
+                        .resolve_script_paths(&cmd_args.grammar_filepath)?;
+
+                    let mut table_data = table_display::TableData::new();
+
+                    table_data
+                        .create_field_hashmap(&mut parsed_gram, &cmd_args)?
+                        .format_fields(
+                            &parsed_gram,
+                            false,
+                            cmd_args.struct_offset as usize,
+                            cmd_args.asm_syntax,
+                        )?;
+
+                    rust_module.build_sample_test(&cmd_args, &table_data)?;
+                }
+
+                rust_module.write_module_file(&cmd_args.output_filepath)?;
 
                 Ok(())
             }
@@ -93,7 +245,12 @@ fn run() -> Result<(), ()> {
                         arg_parse::FMT_ENDIAN_FLAG,
                         arg_parse::HEX_ENDIAN_FLAG,
                         arg_parse::DESCRIPTION_FLAG,
-                    );
+                    )
+                    .parse_output_format_flag(arg_parse::OUTPUT_FORMAT_FLAG)?
+                    .parse_columns_flag(arg_parse::COLUMNS_FLAG)?
+                    .parse_colour_flags(arg_parse::COLOUR_FLAG, arg_parse::THEME_FLAG)?
+                    .parse_hex_view_flags()?
+                    .parse_syntax_flag(arg_parse::SYNTAX_FLAG)?;
 
                 let file_contents =
                     fs::read_to_string(&cmd_args.grammar_filepath).map_err(|e| {
@@ -105,32 +262,123 @@ fn run() -> Result<(), ()> {
 
                 let mut parsed_gram = gram_parse::Grammar::new();
 
-                parsed_gram.parse_toml(&file_contents)?.post_parse_toml()?;
+                parsed_gram
+                    .parse_toml(&file_contents)?
+                    .post_parse_toml()?
+                    .resolve_script_paths(&cmd_args.grammar_filepath)?;
 
                 let mut table_data = table_display::TableData::new();
 
                 if cmd_args.description {
-                    table_data
-                        .fill_description_table(&parsed_gram)
-                        .print_table(table_display::Tables::Description)
+                    if let arg_parse::OutputFormats::Table = cmd_args.output_format {
+                        let theme = theme::Theme::from_args(&cmd_args);
+
+                        table_data
+                            .fill_description_table(&parsed_gram, &theme)
+                            .print_table(table_display::Tables::Description, &theme)
+                    }
                 }
 
                 table_data
                     .create_field_hashmap(&mut parsed_gram, &cmd_args)?
-                    .format_fields(&parsed_gram, cmd_args.fmt_endian)?
-                    .fill_standard_table(&parsed_gram, cmd_args.struct_offset as usize)?
-                    .print_table(table_display::Tables::Standard);
-
-                hex_display::print_hex_table(
-                    &parsed_gram,
-                    &table_data.field_hashmap,
-                    cmd_args.struct_offset as usize,
-                    cmd_args.hex_endian,
-                )?;
+                    .format_fields(
+                        &parsed_gram,
+                        cmd_args.fmt_endian,
+                        cmd_args.struct_offset as usize,
+                        cmd_args.asm_syntax,
+                    )?;
 
-                Ok(())
+                print_output(&parsed_gram, &mut table_data, &cmd_args)
             }
         },
         Err(_) => Err(()),
     }
 }
+
+/// Prints the formatted data in `table_data` to stdout in the output format selected on the cmdline.
+fn print_output(
+    parsed_gram: &gram_parse::Grammar,
+    table_data: &mut table_display::TableData,
+    cmd_args: &arg_parse::CMDArgParse,
+) -> Result<(), ()> {
+    let theme = theme::Theme::from_args(cmd_args);
+
+    match cmd_args.output_format {
+        arg_parse::OutputFormats::Table => {
+            table_data
+                .fill_standard_table(parsed_gram, cmd_args.struct_offset as usize, &theme)?
+                .print_table(table_display::Tables::Standard, &theme);
+
+            code_display::print_code_listings(
+                parsed_gram,
+                &table_data.field_hashmap,
+                cmd_args.struct_offset as usize,
+                cmd_args.asm_syntax,
+                &theme,
+            );
+
+            hex_display::print_hex_table(
+                parsed_gram,
+                &table_data.field_hashmap,
+                cmd_args.struct_offset as usize,
+                cmd_args.hex_endian,
+                &theme,
+                &cmd_args.hex_view_options,
+                &cmd_args.binary_filepath,
+            )
+        }
+        arg_parse::OutputFormats::Json => {
+            json_display::print_json(&json_display::build_json_struct(
+                parsed_gram,
+                table_data,
+                cmd_args.struct_offset as usize,
+            )?)
+        }
+        arg_parse::OutputFormats::JsonLines => {
+            json_display::print_json_lines(&json_display::build_json_struct(
+                parsed_gram,
+                table_data,
+                cmd_args.struct_offset as usize,
+            )?)
+        }
+        arg_parse::OutputFormats::Csv => {
+            export_display::print_csv(&export_display::build_export_rows(
+                parsed_gram,
+                table_data,
+                cmd_args.struct_offset as usize,
+                &cmd_args.columns,
+                cmd_args.description,
+            )?);
+            Ok(())
+        }
+        arg_parse::OutputFormats::Tsv => {
+            export_display::print_tsv(&export_display::build_export_rows(
+                parsed_gram,
+                table_data,
+                cmd_args.struct_offset as usize,
+                &cmd_args.columns,
+                cmd_args.description,
+            )?);
+            Ok(())
+        }
+        arg_parse::OutputFormats::Markdown => {
+            export_display::print_markdown(&export_display::build_export_rows(
+                parsed_gram,
+                table_data,
+                cmd_args.struct_offset as usize,
+                &cmd_args.columns,
+                cmd_args.description,
+            )?);
+            Ok(())
+        }
+        arg_parse::OutputFormats::Html => {
+            html_display::print_html(&html_display::build_html_report(
+                parsed_gram,
+                table_data,
+                cmd_args.struct_offset as usize,
+                &theme,
+            )?);
+            Ok(())
+        }
+    }
+}
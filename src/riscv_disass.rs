@@ -0,0 +1,435 @@
+//! Module for disassembling little endian RISC-V machine code.
+//!
+//! The RV64I base integer instruction set, the M extension and the integer instructions of the C (compressed)
+//! extension are decoded. Compressed instructions are displayed as the base instruction they expand to. Anything
+//! else is displayed as a `.word` or `.hword` directive.
+use crate::format::DissassLine;
+
+/// ABI names of the integer registers.
+const REGISTERS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Returns the ABI name of an integer register.
+fn reg(register: u32) -> &'static str {
+    REGISTERS[(register & 0x1F) as usize]
+}
+
+/// Returns the ABI name of a register encoded in the 3 bit field of a compressed instruction, i.e x8 - x15.
+fn creg(register: u32) -> &'static str {
+    REGISTERS[(8 + (register & 0x7)) as usize]
+}
+
+/// Sign extends the lowest `bits` bits of `value`.
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as i64
+}
+
+/// Decodes the RISC-V instruction at the start of `machine_code`, which is located at `address`.
+pub fn decode_riscv(machine_code: &[u8], address: u64) -> DissassLine {
+    if machine_code.len() < 2 {
+        return data_line(machine_code, address);
+    }
+
+    let half = u16::from_le_bytes([machine_code[0], machine_code[1]]) as u32;
+
+    let (size, decoded) = if half & 0x3 != 0x3 {
+        (2, compressed_instruction(half, address))
+    } else if machine_code.len() >= 4 {
+        let word = half | (u16::from_le_bytes([machine_code[2], machine_code[3]]) as u32) << 16;
+        (4, instruction(word, address))
+    } else {
+        return data_line(machine_code, address);
+    };
+
+    match decoded {
+        Some((instruction, branch_target)) => DissassLine {
+            address,
+            bytes: machine_code[..size].to_vec(),
+            instruction,
+            branch_target,
+        },
+        None => data_line(&machine_code[..size], address),
+    }
+}
+
+/// Creates a line for machine code that could not be decoded.
+fn data_line(machine_code: &[u8], address: u64) -> DissassLine {
+    let instruction = match machine_code.len() {
+        4 => format!(
+            ".word {:#010x}",
+            u32::from_le_bytes([
+                machine_code[0],
+                machine_code[1],
+                machine_code[2],
+                machine_code[3]
+            ])
+        ),
+        2 => format!(
+            ".hword {:#06x}",
+            u16::from_le_bytes([machine_code[0], machine_code[1]])
+        ),
+        _ => format!(".byte {:#04x}", machine_code.first().copied().unwrap_or(0)),
+    };
+
+    DissassLine {
+        address,
+        bytes: machine_code.to_vec(),
+        instruction,
+        branch_target: None,
+    }
+}
+
+/// Returns the assembly of the 32-bit instruction `word` and its branch target, or None if it is not supported.
+fn instruction(word: u32, address: u64) -> Option<(String, Option<u64>)> {
+    let bits = |shift: u32, mask: u32| (word >> shift) & mask;
+    let rd = bits(7, 0x1F);
+    let rs1 = bits(15, 0x1F);
+    let rs2 = bits(20, 0x1F);
+    let funct3 = bits(12, 0x7);
+    let funct7 = bits(25, 0x7F);
+    let imm_i = sign_extend(bits(20, 0xFFF), 12);
+    let imm_s = sign_extend((bits(25, 0x7F) << 5) | bits(7, 0x1F), 12);
+
+    let instruction = match word & 0x7F {
+        0x37 => format!("lui {}, {:#x}", reg(rd), bits(12, 0xF_FFFF)),
+        0x17 => format!("auipc {}, {:#x}", reg(rd), bits(12, 0xF_FFFF)),
+        0x6F => {
+            let offset = sign_extend(
+                (bits(31, 1) << 20)
+                    | (bits(12, 0xFF) << 12)
+                    | (bits(20, 1) << 11)
+                    | (bits(21, 0x3FF) << 1),
+                21,
+            );
+            let target = (address as i64 + offset) as u64;
+            return Some(match rd {
+                0 => (format!("j {:#x}", target), Some(target)),
+                _ => (format!("jal {}, {:#x}", reg(rd), target), Some(target)),
+            });
+        }
+        0x67 if funct3 == 0 => match (rd, rs1, imm_i) {
+            (0, 1, 0) => String::from("ret"),
+            (0, _, 0) => format!("jr {}", reg(rs1)),
+            _ => format!("jalr {}, {}({})", reg(rd), imm_i, reg(rs1)),
+        },
+        0x63 => {
+            let mnemonic = match funct3 {
+                0 => "beq",
+                1 => "bne",
+                4 => "blt",
+                5 => "bge",
+                6 => "bltu",
+                7 => "bgeu",
+                _ => return None,
+            };
+            let offset = sign_extend(
+                (bits(31, 1) << 12)
+                    | (bits(7, 1) << 11)
+                    | (bits(25, 0x3F) << 5)
+                    | (bits(8, 0xF) << 1),
+                13,
+            );
+            let target = (address as i64 + offset) as u64;
+            return Some((
+                format!("{} {}, {}, {:#x}", mnemonic, reg(rs1), reg(rs2), target),
+                Some(target),
+            ));
+        }
+        0x03 => {
+            let mnemonic = ["lb", "lh", "lw", "ld", "lbu", "lhu", "lwu"].get(funct3 as usize)?;
+            format!("{} {}, {}({})", mnemonic, reg(rd), imm_i, reg(rs1))
+        }
+        0x23 => {
+            let mnemonic = ["sb", "sh", "sw", "sd"].get(funct3 as usize)?;
+            format!("{} {}, {}({})", mnemonic, reg(rs2), imm_s, reg(rs1))
+        }
+        0x13 => match (funct3, rd, rs1, imm_i) {
+            (0, 0, 0, 0) => String::from("nop"),
+            (0, _, 0, _) => format!("li {}, {}", reg(rd), imm_i),
+            (0, _, _, 0) => format!("mv {}, {}", reg(rd), reg(rs1)),
+            (1, _, _, _) => format!("slli {}, {}, {}", reg(rd), reg(rs1), bits(20, 0x3F)),
+            (5, _, _, _) => format!(
+                "{} {}, {}, {}",
+                if bits(30, 1) == 1 { "srai" } else { "srli" },
+                reg(rd),
+                reg(rs1),
+                bits(20, 0x3F)
+            ),
+            _ => format!(
+                "{} {}, {}, {}",
+                ["addi", "", "slti", "sltiu", "xori", "", "ori", "andi"][funct3 as usize],
+                reg(rd),
+                reg(rs1),
+                imm_i
+            ),
+        },
+        0x1B => match funct3 {
+            0 => format!("addiw {}, {}, {}", reg(rd), reg(rs1), imm_i),
+            1 => format!("slliw {}, {}, {}", reg(rd), reg(rs1), rs2),
+            5 => format!(
+                "{} {}, {}, {}",
+                if bits(30, 1) == 1 { "sraiw" } else { "srliw" },
+                reg(rd),
+                reg(rs1),
+                rs2
+            ),
+            _ => return None,
+        },
+        0x33 => {
+            let mnemonic = match (funct7, funct3) {
+                (0x00, _) => {
+                    ["add", "sll", "slt", "sltu", "xor", "srl", "or", "and"][funct3 as usize]
+                }
+                (0x20, 0) => "sub",
+                (0x20, 5) => "sra",
+                (0x01, _) => [
+                    "mul", "mulh", "mulhsu", "mulhu", "div", "divu", "rem", "remu",
+                ][funct3 as usize],
+                _ => return None,
+            };
+            format!("{} {}, {}, {}", mnemonic, reg(rd), reg(rs1), reg(rs2))
+        }
+        0x3B => {
+            let mnemonic = match (funct7, funct3) {
+                (0x00, 0) => "addw",
+                (0x00, 1) => "sllw",
+                (0x00, 5) => "srlw",
+                (0x20, 0) => "subw",
+                (0x20, 5) => "sraw",
+                (0x01, 0) => "mulw",
+                (0x01, 4) => "divw",
+                (0x01, 5) => "divuw",
+                (0x01, 6) => "remw",
+                (0x01, 7) => "remuw",
+                _ => return None,
+            };
+            format!("{} {}, {}, {}", mnemonic, reg(rd), reg(rs1), reg(rs2))
+        }
+        0x0F => String::from("fence"),
+        0x73 => match (word, funct3) {
+            (0x0000_0073, _) => String::from("ecall"),
+            (0x0010_0073, _) => String::from("ebreak"),
+            (0x1020_0073, _) => String::from("sret"),
+            (0x3020_0073, _) => String::from("mret"),
+            (0x1050_0073, _) => String::from("wfi"),
+            (_, 1..=3) => format!(
+                "{} {}, {:#x}, {}",
+                ["", "csrrw", "csrrs", "csrrc"][funct3 as usize],
+                reg(rd),
+                bits(20, 0xFFF),
+                reg(rs1)
+            ),
+            (_, 5..=7) => format!(
+                "{} {}, {:#x}, {}",
+                ["", "", "", "", "", "csrrwi", "csrrsi", "csrrci"][funct3 as usize],
+                reg(rd),
+                bits(20, 0xFFF),
+                rs1
+            ),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some((instruction, None))
+}
+
+/// Returns the assembly of the compressed instruction `half` and its branch target, or None if it is not supported.
+///
+/// RV64C encodings are used where they differ from RV32C.
+fn compressed_instruction(half: u32, address: u64) -> Option<(String, Option<u64>)> {
+    let bits = |shift: u32, mask: u32| (half >> shift) & mask;
+    let funct3 = bits(13, 0x7);
+    let rd = bits(7, 0x1F);
+    let rs2 = bits(2, 0x1F);
+    let imm6 = sign_extend((bits(12, 1) << 5) | bits(2, 0x1F), 6);
+    // Offsets of compressed loads and stores of words and double words.
+    let word_offset = (bits(5, 1) << 6) | (bits(10, 0x7) << 3) | (bits(6, 1) << 2);
+    let double_offset = (bits(5, 0x3) << 6) | (bits(10, 0x7) << 3);
+
+    if half == 0 {
+        return None;
+    }
+
+    let instruction = match (half & 0x3, funct3) {
+        (0, 0) => {
+            let imm =
+                (bits(7, 0xF) << 6) | (bits(11, 0x3) << 4) | (bits(5, 1) << 3) | (bits(6, 1) << 2);
+            format!("addi {}, sp, {}", creg(bits(2, 0x7)), imm)
+        }
+        (0, 2) => format!(
+            "lw {}, {}({})",
+            creg(bits(2, 0x7)),
+            word_offset,
+            creg(bits(7, 0x7))
+        ),
+        (0, 3) => format!(
+            "ld {}, {}({})",
+            creg(bits(2, 0x7)),
+            double_offset,
+            creg(bits(7, 0x7))
+        ),
+        (0, 6) => format!(
+            "sw {}, {}({})",
+            creg(bits(2, 0x7)),
+            word_offset,
+            creg(bits(7, 0x7))
+        ),
+        (0, 7) => format!(
+            "sd {}, {}({})",
+            creg(bits(2, 0x7)),
+            double_offset,
+            creg(bits(7, 0x7))
+        ),
+        (1, 0) => match rd {
+            0 => String::from("nop"),
+            _ => format!("addi {}, {}, {}", reg(rd), reg(rd), imm6),
+        },
+        (1, 1) => format!("addiw {}, {}, {}", reg(rd), reg(rd), imm6),
+        (1, 2) => format!("li {}, {}", reg(rd), imm6),
+        (1, 3) if rd == 2 => {
+            let imm = sign_extend(
+                (bits(12, 1) << 9)
+                    | (bits(3, 0x3) << 7)
+                    | (bits(5, 1) << 6)
+                    | (bits(2, 1) << 5)
+                    | (bits(6, 1) << 4),
+                10,
+            );
+            format!("addi sp, sp, {}", imm)
+        }
+        (1, 3) => format!("lui {}, {:#x}", reg(rd), (imm6 as u32) & 0xF_FFFF),
+        (1, 4) => {
+            let rd = creg(bits(7, 0x7));
+            match (bits(10, 0x3), bits(12, 1), bits(5, 0x3)) {
+                (0, _, _) => format!("srli {}, {}, {}", rd, rd, (bits(12, 1) << 5) | rs2),
+                (1, _, _) => format!("srai {}, {}, {}", rd, rd, (bits(12, 1) << 5) | rs2),
+                (2, _, _) => format!("andi {}, {}, {}", rd, rd, imm6),
+                (_, 0, operation) => format!(
+                    "{} {}, {}, {}",
+                    ["sub", "xor", "or", "and"][operation as usize],
+                    rd,
+                    rd,
+                    creg(bits(2, 0x7))
+                ),
+                (_, _, 0) => format!("subw {}, {}, {}", rd, rd, creg(bits(2, 0x7))),
+                (_, _, 1) => format!("addw {}, {}, {}", rd, rd, creg(bits(2, 0x7))),
+                _ => return None,
+            }
+        }
+        (1, 5) => {
+            let offset = sign_extend(
+                (bits(12, 1) << 11)
+                    | (bits(8, 1) << 10)
+                    | (bits(9, 0x3) << 8)
+                    | (bits(6, 1) << 7)
+                    | (bits(7, 1) << 6)
+                    | (bits(2, 1) << 5)
+                    | (bits(11, 1) << 4)
+                    | (bits(3, 0x7) << 1),
+                12,
+            );
+            let target = (address as i64 + offset) as u64;
+            return Some((format!("j {:#x}", target), Some(target)));
+        }
+        (1, 6) | (1, 7) => {
+            let offset = sign_extend(
+                (bits(12, 1) << 8)
+                    | (bits(5, 0x3) << 6)
+                    | (bits(2, 1) << 5)
+                    | (bits(10, 0x3) << 3)
+                    | (bits(3, 0x3) << 1),
+                9,
+            );
+            let target = (address as i64 + offset) as u64;
+            let mnemonic = if funct3 == 6 { "beqz" } else { "bnez" };
+            return Some((
+                format!("{} {}, {:#x}", mnemonic, creg(bits(7, 0x7)), target),
+                Some(target),
+            ));
+        }
+        (2, 0) => format!(
+            "slli {}, {}, {}",
+            reg(rd),
+            reg(rd),
+            (bits(12, 1) << 5) | rs2
+        ),
+        (2, 2) => {
+            let offset = (bits(2, 0x3) << 6) | (bits(12, 1) << 5) | (bits(4, 0x7) << 2);
+            format!("lw {}, {}(sp)", reg(rd), offset)
+        }
+        (2, 3) => {
+            let offset = (bits(2, 0x7) << 6) | (bits(12, 1) << 5) | (bits(5, 0x3) << 3);
+            format!("ld {}, {}(sp)", reg(rd), offset)
+        }
+        (2, 4) => match (bits(12, 1), rd, rs2) {
+            (0, 1, 0) => String::from("ret"),
+            (0, _, 0) => format!("jr {}", reg(rd)),
+            (0, _, _) => format!("mv {}, {}", reg(rd), reg(rs2)),
+            (_, 0, 0) => String::from("ebreak"),
+            (_, _, 0) => format!("jalr {}", reg(rd)),
+            _ => format!("add {}, {}, {}", reg(rd), reg(rd), reg(rs2)),
+        },
+        (2, 6) => {
+            let offset = (bits(7, 0x3) << 6) | (bits(9, 0xF) << 2);
+            format!("sw {}, {}(sp)", reg(rs2), offset)
+        }
+        (2, 7) => {
+            let offset = (bits(7, 0x7) << 6) | (bits(10, 0x7) << 3);
+            format!("sd {}, {}(sp)", reg(rs2), offset)
+        }
+        _ => return None,
+    };
+
+    Some((instruction, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_base_instructions() {
+        let cases: [(u32, &str, Option<u64>); 8] = [
+            (0x0015_0513, "addi a0, a0, 1", None),
+            (0xFE01_0113, "addi sp, sp, -32", None),
+            (0x1234_5537, "lui a0, 0x12345", None),
+            (0x0011_3423, "sd ra, 8(sp)", None),
+            (0x02B5_0533, "mul a0, a0, a1", None),
+            (0x0000_8067, "ret", None),
+            (0x0080_00EF, "jal ra, 0x1008", Some(0x1008)),
+            (0x0005_0463, "beq a0, zero, 0x1008", Some(0x1008)),
+        ];
+
+        for (word, instruction, branch_target) in cases {
+            let line = decode_riscv(&word.to_le_bytes(), 0x1000);
+            assert_eq!(line.instruction, instruction, "{:#010x}", word);
+            assert_eq!(line.branch_target, branch_target, "{:#010x}", word);
+            assert_eq!(line.bytes.len(), 4);
+        }
+    }
+
+    #[test]
+    fn decodes_compressed_instructions() {
+        let cases: [(u16, &str, Option<u64>); 7] = [
+            (0x4515, "li a0, 5", None),
+            (0x0505, "addi a0, a0, 1", None),
+            (0x1141, "addi sp, sp, -16", None),
+            (0xE406, "sd ra, 8(sp)", None),
+            (0x852E, "mv a0, a1", None),
+            (0x8082, "ret", None),
+            (0xA001, "j 0x1000", Some(0x1000)),
+        ];
+
+        for (half, instruction, branch_target) in cases {
+            let line = decode_riscv(&half.to_le_bytes(), 0x1000);
+            assert_eq!(line.instruction, instruction, "{:#06x}", half);
+            assert_eq!(line.branch_target, branch_target, "{:#06x}", half);
+            assert_eq!(line.bytes.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,1630 @@
+//! Module for converting a grammar to a Rust module that parses it.
+//!
+//! Every struct type of the grammar becomes a Rust struct and the fields of the grammar become the root struct, each
+//! with a `parse` function reading it from the start of a byte slice. Union types become a struct holding their bytes
+//! with a method reading each member. Integer fields whose description lists C enumerators, as written by C struct
+//! conversion, get an enum type and each bit range of a `bits` field gets a method reading it.
+use crate::arg_parse;
+use crate::format;
+use crate::gram_parse;
+use crate::table_display;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Component, Path, PathBuf};
+
+/// Extension of the output filepath that selects Rust code rather than a C header.
+pub const RUST_EXTENSION: &str = "rs";
+
+/// Rust keywords, which are suffixed with `_` when used as a name.
+const KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while",
+];
+
+/// Number of bytes written on each line of the byte arrays in the sample test.
+const BYTES_PER_LINE: usize = 16;
+/// Names of the functions every generated struct has, which members and bit ranges can not use as method names.
+const METHOD_NAMES: [&str; 2] = ["parse", "read"];
+
+/// How the value of a field without a type is read from its bytes.
+enum FieldKinds {
+    /// A primitive integer or float, e.g `u16`, read with `from_le_bytes` or `from_be_bytes`.
+    Number {
+        rust_type: &'static str,
+        little_endian: bool,
+    },
+    /// An unsigned integer of 3, 5, 6 or 7 bytes read into a `u64`.
+    Uint { size: usize, little_endian: bool },
+    /// Bytes kept as they are, e.g text and hex fields.
+    Bytes(usize),
+    /// An integer wrapped in an enum type named after the C enum, with its enumerators.
+    Enum {
+        name: String,
+        rust_type: &'static str,
+        little_endian: bool,
+        enumerators: Vec<(String, i64)>,
+    },
+}
+
+/// An enum type generated from the enumerators of a field.
+struct RustEnum {
+    /// The name of the C enum the type was generated from, which is numbered in `name` if it is used more than once.
+    enum_name: String,
+    name: String,
+    rust_type: &'static str,
+    description: String,
+    enumerators: Vec<(String, i64)>,
+}
+
+/// Holds the grammar being converted and the resulting Rust module contents.
+pub struct RustModule {
+    /// Name of the root struct in the module.
+    pub name: String,
+    /// The grammar being converted, set by `build_module_contents`.
+    grammar: gram_parse::Grammar,
+    /// Enums generated so far, used so fields of the same C enum share a type.
+    enums: Vec<RustEnum>,
+    /// Whether the generated code uses the `array` and `read_uint` helper functions.
+    uses_array: bool,
+    uses_read_uint: bool,
+    /// String containing the resulting Rust module contents.
+    pub module_contents: String,
+}
+
+impl RustModule {
+    pub fn new() -> Self {
+        Self {
+            name: String::from(""),
+            grammar: gram_parse::Grammar::new(),
+            enums: Vec::new(),
+            uses_array: false,
+            uses_read_uint: false,
+            module_contents: String::from(""),
+        }
+    }
+
+    /// Builds the contents of the Rust module from `grammar`, which has not been post parsed.
+    ///
+    /// Types are written before the root struct, which is named after the grammar unless a struct type with the same
+    /// name and fields exists, in which case that type is the root.
+    pub fn build_module_contents(
+        &mut self,
+        grammar: &gram_parse::Grammar,
+    ) -> Result<&mut Self, ()> {
+        self.grammar = grammar.clone();
+        self.grammar.apply_multiply_fields()?;
+        self.name = type_identifier(&self.grammar.metadata.name);
+
+        let grammar = self.grammar.clone();
+        let ordered_types = grammar.ordered_types()?;
+        let mut items = String::new();
+
+        for field_type in ordered_types.iter() {
+            let size = grammar.type_size(field_type)?;
+
+            items.push_str(&match &field_type.kind[..] {
+                gram_parse::UNION_KIND => self.union_contents(field_type, size)?,
+                _ => self.struct_contents(&field_type.name, &field_type.fields, size)?,
+            });
+        }
+
+        if grammar.root_type().is_none() {
+            if ordered_types
+                .iter()
+                .any(|field_type| type_identifier(&field_type.name) == self.name)
+            {
+                self.name.push_str("Fields");
+            }
+
+            let size = grammar.fields_size(&grammar.fields, 0)?;
+            items.push_str(&self.struct_contents(&self.name.clone(), &grammar.fields, size)?);
+        }
+
+        let mut contents = format!(
+            "//! Parser for {}, generated by memgram from a grammar.\n",
+            grammar.metadata.name
+        );
+        contents.push_str("//!\n");
+        contents.push_str(&format!(
+            "//! [`parse`] reads the root struct [`{}`] from the start of a byte slice. Multi-byte values are read in the\n",
+            self.name
+        ));
+        contents.push_str(
+            "//! byte order of their display format, which is little endian unless the format is big endian.\n",
+        );
+        if !grammar.metadata.abi.is_empty() {
+            contents.push_str(&format!(
+                "//! The grammar was converted from a C struct laid out for the {} ABI ({} endian).\n",
+                grammar.metadata.abi, grammar.metadata.endian
+            ));
+        }
+
+        contents.push_str(
+            "
+/// Error returned when there are too few bytes to parse a struct from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    /// The number of bytes the struct needs.
+    pub needed: usize,
+    /// The number of bytes that were given.
+    pub available: usize,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            \"needed {} bytes but only {} are available\",
+            self.needed, self.available
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+",
+        );
+
+        contents.push_str(&format!(
+            "
+/// Parses the root struct from the start of `bytes`.
+pub fn parse(bytes: &[u8]) -> Result<{}, ParseError> {{
+    {}::parse(bytes)
+}}
+",
+            self.name, self.name
+        ));
+
+        for rust_enum in self.enums.iter() {
+            contents.push_str(&enum_contents(rust_enum));
+        }
+
+        contents.push_str(&items);
+
+        contents.push_str(
+            "
+/// Checks `bytes` holds at least `size` bytes.
+fn check_size(bytes: &[u8], size: usize) -> Result<(), ParseError> {
+    if bytes.len() < size {
+        return Err(ParseError {
+            needed: size,
+            available: bytes.len(),
+        });
+    }
+
+    Ok(())
+}
+",
+        );
+
+        if self.uses_array {
+            contents.push_str(
+                "
+/// Copies the first `N` bytes of `bytes` into an array.
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(&bytes[..N]);
+    array
+}
+",
+            );
+        }
+
+        if self.uses_read_uint {
+            contents.push_str(
+                "
+/// Reads an unsigned integer of up to 8 bytes.
+fn read_uint(bytes: &[u8], little_endian: bool) -> u64 {
+    let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+
+    if little_endian {
+        bytes.iter().rev().fold(0, fold)
+    } else {
+        bytes.iter().fold(0, fold)
+    }
+}
+",
+            );
+        }
+
+        self.module_contents = contents;
+
+        Ok(self)
+    }
+
+    /// Builds a test checking the generated parser reads the same values from the binary at `cmd_args.binary_filepath`
+    /// as memgram, whose parse of it is in `table_data`.
+    ///
+    /// Integer, float and enum fields are compared with the value memgram displayed, scaled by their `scale` and
+    /// `offset` with their `unit` appended, and enums are also checked for the name of their enumerator. Bit ranges are
+    /// compared with the values memgram displayed for each range, other fields are compared with the bytes memgram
+    /// read. The test stops at the first variable size field, as the generated parser reads it at its largest size.
+    pub fn build_sample_test(
+        &mut self,
+        cmd_args: &arg_parse::CMDArgParse,
+        table_data: &table_display::TableData,
+    ) -> Result<&mut Self, ()> {
+        let mut expanded_grammar = self.grammar.clone();
+        expanded_grammar.post_parse_toml()?;
+
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        for field in expanded_grammar.fields.iter() {
+            *name_counts.entry(&field.name).or_insert(0) += 1;
+        }
+
+        let mut checks = SampleChecks {
+            grammar: &self.grammar,
+            table_data,
+            name_counts,
+            variable_size_reached: false,
+            uses_scaled: false,
+            asserts: String::new(),
+        };
+
+        let root_fields = match self.grammar.root_type() {
+            Some(root_type) => &root_type.fields,
+            None => &self.grammar.fields,
+        };
+        checks.add_fields(root_fields, false, "", "value")?;
+
+        let helpers = if checks.uses_scaled {
+            "
+    /// Scales `value` the way memgram displays it, with at most `decimals` decimal places.
+    fn scaled(value: f64, scale: f64, offset: f64, decimals: usize) -> String {
+        let scaled = format!(\"{:.*}\", decimals, value * scale + offset);
+
+        match decimals {
+            0 => scaled,
+            _ => scaled
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string(),
+        }
+    }
+"
+        } else {
+            ""
+        };
+
+        let output_dir = Path::new(&cmd_args.output_filepath)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+
+        self.module_contents.push_str(&format!(
+            "
+#[cfg(test)]
+mod tests {{
+    use super::*;
+
+    /// Checks the values parsed from the sample file match the values memgram read from it.
+    #[test]
+    fn parse_sample() {{
+        let sample = include_bytes!({:?});
+        let value = parse(&sample[{:#X}..]).unwrap();
+{}    }}
+{}}}
+",
+            relative_path(output_dir, Path::new(&cmd_args.binary_filepath)),
+            cmd_args.struct_offset,
+            checks.asserts,
+            helpers
+        ));
+
+        Ok(self)
+    }
+
+    /// Builds the Rust struct `name` made up of `fields`, which are `size` bytes long.
+    fn struct_contents(
+        &mut self,
+        name: &str,
+        fields: &[gram_parse::GrammarFields],
+        size: usize,
+    ) -> Result<String, ()> {
+        let struct_name = type_identifier(name);
+        let names = member_names(fields, false);
+        let mut members = String::new();
+        let mut reads = String::new();
+        let mut methods = String::new();
+        let mut offset = 0;
+
+        for (field, (member_name, bits_names)) in fields.iter().zip(names.iter()) {
+            let field_size = self.grammar.field_size(field, 0)?;
+
+            if field_size == 0 {
+                continue;
+            }
+
+            let element_size = field_size / field.count.unwrap_or(1);
+            let (rust_type, read) = self.member_read(field, offset, element_size)?;
+
+            members.push_str(&doc_comment("    ", &member_doc(field)));
+            members.push_str(&format!("    pub {}: {},\n", member_name, rust_type));
+            reads.push_str(&format!("            {}: {},\n", member_name, read));
+
+            for (bits, bits_name) in field.bits.iter().zip(bits_names.iter()) {
+                methods.push_str(&bits_method(field, member_name, bits, bits_name));
+            }
+
+            offset += field_size;
+        }
+
+        if members.is_empty() {
+            serror!(format!(
+                "Type: {}, has no fields with a size so can not be converted to Rust",
+                name
+            ));
+            return Err(());
+        }
+
+        Ok(format!(
+            "
+/// The {} struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct {} {{
+{}}}
+
+impl {} {{
+    /// The size of the struct in bytes.
+    pub const SIZE: usize = {:#X};
+
+    /// Parses the struct from the start of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ParseError> {{
+        check_size(bytes, Self::SIZE)?;
+        Ok(Self::read(bytes))
+    }}
+
+    /// Reads the struct from `bytes`, which holds at least `SIZE` bytes.
+    fn read(bytes: &[u8]) -> Self {{
+        Self {{
+{}        }}
+    }}
+{}}}
+",
+            name, struct_name, members, struct_name, size, reads, methods
+        ))
+    }
+
+    /// Builds the Rust struct holding the bytes of the union type `field_type`, which is `size` bytes long, with a
+    /// method reading each member.
+    fn union_contents(
+        &mut self,
+        field_type: &gram_parse::GrammarType,
+        size: usize,
+    ) -> Result<String, ()> {
+        let union_name = type_identifier(&field_type.name);
+        let names = member_names(&field_type.fields, true);
+        let mut methods = String::new();
+        self.uses_array = true;
+
+        for (field, (member_name, _)) in field_type.fields.iter().zip(names.iter()) {
+            let field_size = self.grammar.field_size(field, 0)?;
+
+            if field_size == 0 {
+                continue;
+            }
+
+            let element_size = field_size / field.count.unwrap_or(1);
+            let (rust_type, read) = self.member_read(field, 0, element_size)?;
+
+            methods.push('\n');
+            methods.push_str(&doc_comment("    ", &member_doc(field)));
+            methods.push_str(&format!(
+                "    pub fn {}(&self) -> {} {{\n        let bytes = &self.bytes[..];\n        {}\n    }}\n",
+                member_name, rust_type, read
+            ));
+        }
+
+        Ok(format!(
+            "
+/// The {} union.
+#[derive(Debug, Clone, PartialEq)]
+pub struct {} {{
+    /// The bytes shared by the members of the union.
+    pub bytes: [u8; {}],
+}}
+
+impl {} {{
+    /// The size of the union in bytes.
+    pub const SIZE: usize = {:#X};
+
+    /// Parses the union from the start of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ParseError> {{
+        check_size(bytes, Self::SIZE)?;
+        Ok(Self::read(bytes))
+    }}
+
+    /// Reads the union from `bytes`, which holds at least `SIZE` bytes.
+    fn read(bytes: &[u8]) -> Self {{
+        Self {{
+            bytes: array(bytes),
+        }}
+    }}
+{}}}
+",
+            field_type.name, union_name, size, union_name, size, methods
+        ))
+    }
+
+    /// Get's the Rust type of `field`, which starts at `offset` in `bytes`, and the expression reading it.
+    fn member_read(
+        &mut self,
+        field: &gram_parse::GrammarFields,
+        offset: usize,
+        element_size: usize,
+    ) -> Result<(String, String), ()> {
+        let (element_type, element_read) = if field.type_name.is_empty() {
+            let kind = field_kind(field);
+            let rust_type = self.kind_type(&kind, field);
+            let read = self.kind_read(
+                &kind,
+                &rust_type,
+                offset,
+                element_size,
+                field.count.is_some(),
+            );
+            (rust_type, read)
+        } else {
+            let type_name = type_identifier(&self.grammar.find_type(field)?.name);
+            let read = format!(
+                "{}::read(&bytes[{}..])",
+                type_name,
+                offset_expression(offset, element_size, field.count.is_some())
+            );
+            (type_name, read)
+        };
+
+        Ok(match field.count {
+            Some(count) => (
+                format!("[{}; {}]", element_type, count),
+                format!("std::array::from_fn(|index| {})", element_read),
+            ),
+            None => (element_type, element_read),
+        })
+    }
+
+    /// Get's the Rust type of a field of `kind`, adding the enum type of enum fields if it has not been added yet.
+    fn kind_type(&mut self, kind: &FieldKinds, field: &gram_parse::GrammarFields) -> String {
+        match kind {
+            FieldKinds::Number { rust_type, .. } => rust_type.to_string(),
+            FieldKinds::Uint { .. } => String::from("u64"),
+            FieldKinds::Bytes(size) => format!("[u8; {}]", size),
+            FieldKinds::Enum {
+                name,
+                rust_type,
+                enumerators,
+                ..
+            } => {
+                if let Some(rust_enum) = self.enums.iter().find(|rust_enum| {
+                    rust_enum.enum_name == *name
+                        && rust_enum.rust_type == *rust_type
+                        && rust_enum.enumerators == *enumerators
+                }) {
+                    return rust_enum.name.clone();
+                }
+
+                let mut enum_name = name.clone();
+                let mut number = 2;
+                while self
+                    .enums
+                    .iter()
+                    .any(|rust_enum| rust_enum.name == enum_name)
+                {
+                    enum_name = format!("{}{}", name, number);
+                    number += 1;
+                }
+
+                self.enums.push(RustEnum {
+                    enum_name: name.clone(),
+                    name: enum_name.clone(),
+                    rust_type,
+                    description: field.data_type.clone(),
+                    enumerators: enumerators.clone(),
+                });
+
+                enum_name
+            }
+        }
+    }
+
+    /// Get's the expression reading one element of a field of `kind` and `size` bytes from `bytes`.
+    ///
+    /// The field starts at `offset`, or is an array whose elements start there if `indexed` is set.
+    fn kind_read(
+        &mut self,
+        kind: &FieldKinds,
+        rust_type: &str,
+        offset: usize,
+        size: usize,
+        indexed: bool,
+    ) -> String {
+        let start = offset_expression(offset, size, indexed);
+
+        match kind {
+            FieldKinds::Number {
+                rust_type: "u8", ..
+            } => format!("bytes[{}]", start),
+            FieldKinds::Number {
+                rust_type: "i8", ..
+            } => format!("bytes[{}] as i8", start),
+            FieldKinds::Number {
+                rust_type,
+                little_endian,
+            } => {
+                self.uses_array = true;
+                format!(
+                    "{}::{}(array(&bytes[{}..]))",
+                    rust_type,
+                    from_bytes(*little_endian),
+                    start
+                )
+            }
+            FieldKinds::Uint {
+                size,
+                little_endian,
+            } => {
+                self.uses_read_uint = true;
+                format!(
+                    "read_uint(&bytes[{}..{} + {}], {})",
+                    start, start, size, little_endian
+                )
+            }
+            FieldKinds::Bytes(_) => {
+                self.uses_array = true;
+                format!("array(&bytes[{}..])", start)
+            }
+            FieldKinds::Enum {
+                rust_type: enum_type,
+                little_endian,
+                ..
+            } => {
+                let value = match *enum_type {
+                    "u8" => format!("bytes[{}]", start),
+                    "i8" => format!("bytes[{}] as i8", start),
+                    _ => {
+                        self.uses_array = true;
+                        format!(
+                            "{}::{}(array(&bytes[{}..]))",
+                            enum_type,
+                            from_bytes(*little_endian),
+                            start
+                        )
+                    }
+                };
+                format!("{}({})", rust_type, value)
+            }
+        }
+    }
+
+    /// Writes the newly created module contents in `self.module_contents` to a file specified by `output_path`.
+    pub fn write_module_file(&mut self, output_path: &str) -> Result<&mut Self, ()> {
+        let mut module_file = fs::File::create(output_path).map_err(|e| {
+            serror!(format!(
+                "Could not create/open file {}, because {} ",
+                output_path, e
+            ))
+        })?;
+
+        module_file
+            .write_all(self.module_contents.as_bytes())
+            .map_err(|e| {
+                serror!(format!(
+                    "Could not write to file {}, because {} ",
+                    output_path, e
+                ))
+            })?;
+
+        println!(
+            "[+] Successfully converted grammar {} to Rust module {}",
+            self.name, output_path
+        );
+
+        Ok(self)
+    }
+}
+
+/// Builds the assertions of the sample test, comparing the values the generated parser reads with memgram's parse.
+struct SampleChecks<'a> {
+    grammar: &'a gram_parse::Grammar,
+    table_data: &'a table_display::TableData,
+    /// How many times each expanded field name is used, fields with repeated names are not checked.
+    name_counts: HashMap<&'a str, usize>,
+    /// Set once a variable size field is reached, after which fields are not checked.
+    variable_size_reached: bool,
+    /// Whether the assertions use the `scaled` helper function of the test.
+    uses_scaled: bool,
+    asserts: String,
+}
+
+impl SampleChecks<'_> {
+    /// Adds the assertions for `fields` of a struct, or of a union if `union` is set.
+    ///
+    /// `prefix` is the prefix of the expanded memgram names of the fields and `path` is the Rust expression of the
+    /// struct or union holding them.
+    fn add_fields(
+        &mut self,
+        fields: &[gram_parse::GrammarFields],
+        union: bool,
+        prefix: &str,
+        path: &str,
+    ) -> Result<(), ()> {
+        let names = member_names(fields, union);
+
+        for (field, (member_name, bits_names)) in fields.iter().zip(names.iter()) {
+            if self
+                .grammar
+                .metadata
+                .variable_size_fields
+                .iter()
+                .any(|entry| entry.3 == field.name)
+            {
+                self.variable_size_reached = true;
+            }
+
+            if self.variable_size_reached {
+                return Ok(());
+            }
+
+            if self.grammar.field_size(field, 0)? == 0 {
+                continue;
+            }
+
+            let member_path = if union {
+                format!("{}.{}()", path, member_name)
+            } else {
+                format!("{}.{}", path, member_name)
+            };
+
+            let elements: Vec<(String, String)> = match field.count {
+                Some(count) => (0..count)
+                    .map(|index| {
+                        (
+                            format!("{}{}[{}]", prefix, field.name, index),
+                            format!("{}[{}]", member_path, index),
+                        )
+                    })
+                    .collect(),
+                None => vec![(format!("{}{}", prefix, field.name), member_path.clone())],
+            };
+
+            for (name, element_path) in elements {
+                if field.type_name.is_empty() {
+                    let bits_methods = if field.count.is_none() && !union {
+                        Some((path, &bits_names[..]))
+                    } else {
+                        None
+                    };
+                    self.add_field(field, &name, &element_path, bits_methods);
+                    continue;
+                }
+
+                let field_type = self.grammar.find_type(field)?;
+                match &field_type.kind[..] {
+                    gram_parse::UNION_KIND => {
+                        self.add_bytes(&name, &format!("{}.bytes", element_path))
+                    }
+                    _ => self.add_fields(
+                        &field_type.fields,
+                        false,
+                        &format!("{}.", name),
+                        &element_path,
+                    )?,
+                }
+
+                if self.variable_size_reached {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the assertion for a field without a type, memgram's field `name` read by the Rust expression `path`.
+    ///
+    /// `bits_methods` is the path of the struct holding the field and the names of its bit range methods, if the
+    /// field has them.
+    fn add_field(
+        &mut self,
+        field: &gram_parse::GrammarFields,
+        name: &str,
+        path: &str,
+        bits_methods: Option<(&str, &[String])>,
+    ) {
+        let formatted = match (
+            self.name_counts.get(name),
+            self.table_data.field_fmt_hashmap.get(name),
+        ) {
+            (Some(1), Some(formatted)) => formatted,
+            _ => return,
+        };
+
+        let unscaled = field.scale.is_none() && field.offset.is_none() && field.unit.is_empty();
+        let plain_number = matches!(
+            &field.display_format[..],
+            format::UINT_TYPE
+                | format::INT_TYPE
+                | format::UINTBE_TYPE
+                | format::INTBE_TYPE
+                | format::FLOAT_TYPE
+                | format::FLOATBE_TYPE
+        );
+        let scaled_number = plain_number && !unscaled && field.unit != format::BYTES_UNIT;
+
+        match (field_kind(field), bits_methods) {
+            (FieldKinds::Number { .. }, Some((struct_path, bits_names)))
+            | (FieldKinds::Uint { .. }, Some((struct_path, bits_names)))
+                if !field.bits.is_empty() =>
+            {
+                let ranges: Vec<String> = field
+                    .bits
+                    .iter()
+                    .map(|bits| format!("{}={{}}", bits.name))
+                    .collect();
+                let methods: Vec<String> = bits_names
+                    .iter()
+                    .map(|bits_name| format!("{}.{}()", struct_path, bits_name))
+                    .collect();
+
+                self.asserts.push_str(&format!(
+                    "        assert_eq!(format!({:?}, {}), {:?});\n",
+                    ranges.join(", "),
+                    methods.join(", "),
+                    formatted
+                ));
+            }
+            (FieldKinds::Number { .. }, _) if plain_number && unscaled => {
+                self.asserts.push_str(&format!(
+                    "        assert_eq!({}.to_string(), {:?});\n",
+                    path, formatted
+                ))
+            }
+            (FieldKinds::Enum { enumerators, .. }, _) if plain_number && unscaled => {
+                let enumerator = formatted.parse::<i64>().ok().and_then(|value| {
+                    enumerators
+                        .iter()
+                        .find(|(_, enumerator_value)| *enumerator_value == value)
+                });
+
+                self.asserts.push_str(&format!(
+                    "        assert_eq!({}.0.to_string(), {:?});\n",
+                    path, formatted
+                ));
+                self.asserts.push_str(&format!(
+                    "        assert_eq!({}.name(), {:?});\n",
+                    path,
+                    enumerator.map(|(name, _)| &name[..])
+                ));
+            }
+            (FieldKinds::Number { .. }, _) if scaled_number => {
+                self.add_scaled(field, path, formatted)
+            }
+            (FieldKinds::Enum { .. }, _) if scaled_number => {
+                self.add_scaled(field, &format!("{}.0", path), formatted)
+            }
+            (FieldKinds::Number { little_endian, .. }, _) => {
+                self.add_bytes(name, &format!("{}.{}()", path, to_bytes(little_endian)))
+            }
+            (FieldKinds::Enum { little_endian, .. }, _) => {
+                self.add_bytes(name, &format!("{}.0.{}()", path, to_bytes(little_endian)))
+            }
+            (
+                FieldKinds::Uint {
+                    size,
+                    little_endian: true,
+                },
+                _,
+            ) => self.add_bytes(name, &format!("{}.to_le_bytes()[..{}]", path, size)),
+            (
+                FieldKinds::Uint {
+                    size,
+                    little_endian: false,
+                },
+                _,
+            ) => self.add_bytes(name, &format!("{}.to_be_bytes()[{}..]", path, 8 - size)),
+            (FieldKinds::Bytes(_), _) => self.add_bytes(name, path),
+        }
+    }
+
+    /// Adds the assertion comparing the number read by the Rust expression `path`, scaled by the `scale` and `offset`
+    /// of `field` with its `unit` appended, with the value memgram displayed.
+    fn add_scaled(&mut self, field: &gram_parse::GrammarFields, path: &str, formatted: &str) {
+        let scale = field.scale.unwrap_or(1.0);
+        let offset = field.offset.unwrap_or(0.0);
+        let unit = match &field.unit[..] {
+            "" => String::new(),
+            unit => format!(" + {:?}", format!(" {}", unit)),
+        };
+
+        if scale == 1.0 && offset == 0.0 {
+            self.asserts.push_str(&format!(
+                "        assert_eq!({}.to_string(){}, {:?});\n",
+                path, unit, formatted
+            ));
+            return;
+        }
+
+        self.uses_scaled = true;
+        self.asserts.push_str(&format!(
+            "        assert_eq!(scaled({} as f64, {:?}, {:?}, {}){}, {:?});\n",
+            path,
+            scale,
+            offset,
+            format::decimal_places(scale).max(format::decimal_places(offset)),
+            unit,
+            formatted
+        ));
+    }
+
+    /// Adds the assertion comparing the bytes of the Rust expression `path` with the bytes memgram read for `name`.
+    fn add_bytes(&mut self, name: &str, path: &str) {
+        if let (Some(1), Some(bytes)) = (
+            self.name_counts.get(name),
+            self.table_data.field_hashmap.get(name),
+        ) {
+            let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:#04X}", byte)).collect();
+
+            if bytes.len() <= BYTES_PER_LINE {
+                self.asserts.push_str(&format!(
+                    "        assert_eq!({}, [{}]);\n",
+                    path,
+                    bytes.join(", ")
+                ));
+                return;
+            }
+
+            self.asserts.push_str(&format!(
+                "        assert_eq!(\n            {},\n            [\n",
+                path
+            ));
+            for line in bytes.chunks(BYTES_PER_LINE) {
+                self.asserts
+                    .push_str(&format!("                {},\n", line.join(", ")));
+            }
+            self.asserts.push_str("            ]\n        );\n");
+        }
+    }
+}
+
+/// Get's how a field without a type is read, from its display format and size.
+///
+/// Integer and float formats of 1, 2, 4 or 8 bytes become primitive numbers, as do other formats of those sizes
+/// except `hex`. Text, `hex` and fields of other sizes are kept as bytes, apart from `bits` fields of up to 8 bytes.
+/// Integers whose data type is a C enum and whose description lists its enumerators, e.g `RED = 0, GREEN = 1`,
+/// become enums.
+fn field_kind(field: &gram_parse::GrammarFields) -> FieldKinds {
+    let size = field.size;
+    let display_format = &field.display_format[..];
+    let little_endian = !format::is_big_endian_format(display_format);
+    let signed = matches!(display_format, format::INT_TYPE | format::INTBE_TYPE)
+        || matches!(
+            format::parse_fixed_point(display_format),
+            Some((true, _, _))
+        );
+    let bits = matches!(display_format, format::BITS_TYPE | format::BITSBE_TYPE);
+
+    let rust_type = match (size, signed) {
+        (1, false) => "u8",
+        (2, false) => "u16",
+        (4, false) => "u32",
+        (8, false) => "u64",
+        (1, true) => "i8",
+        (2, true) => "i16",
+        (4, true) => "i32",
+        (8, true) => "i64",
+        _ if bits && size <= 8 => {
+            return FieldKinds::Uint {
+                size,
+                little_endian,
+            }
+        }
+        _ => return FieldKinds::Bytes(size),
+    };
+
+    match display_format {
+        format::FLOAT_TYPE | format::FLOATBE_TYPE if size == 4 || size == 8 => {
+            return FieldKinds::Number {
+                rust_type: if size == 4 { "f32" } else { "f64" },
+                little_endian,
+            }
+        }
+        format::HEX_TYPE if size > 1 => return FieldKinds::Bytes(size),
+        _ if format::is_text_format(display_format) && size > 1 => return FieldKinds::Bytes(size),
+        _ => (),
+    }
+
+    if let Some(enum_name) = field.data_type.strip_prefix("enum") {
+        let enumerators = parse_enumerators(&field.description);
+
+        if !enumerators.is_empty()
+            && !bits
+            && enumerators
+                .iter()
+                .all(|(_, value)| fits_in(*value, size, signed))
+        {
+            let enum_name = enum_name.trim();
+
+            return FieldKinds::Enum {
+                name: type_identifier(if enum_name.is_empty() {
+                    &field.name
+                } else {
+                    enum_name
+                }),
+                rust_type,
+                little_endian,
+                enumerators,
+            };
+        }
+    }
+
+    FieldKinds::Number {
+        rust_type,
+        little_endian,
+    }
+}
+
+/// Parses enumerators written as `NAME = value`, separated by commas, returning none if `description` is not a list
+/// of them.
+fn parse_enumerators(description: &str) -> Vec<(String, i64)> {
+    let mut enumerators = Vec::new();
+
+    for enumerator in description.split(',') {
+        let mut parts = enumerator.splitn(2, '=');
+
+        match (
+            parts.next().map(str::trim),
+            parts.next().map(|value| value.trim().parse::<i64>()),
+        ) {
+            (Some(name), Some(Ok(value)))
+                if !name.is_empty()
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                enumerators.push((name.to_string(), value))
+            }
+            _ => return Vec::new(),
+        }
+    }
+
+    enumerators
+}
+
+/// Checks if `value` fits in an integer of `size` bytes.
+fn fits_in(value: i64, size: usize, signed: bool) -> bool {
+    let bits = size as u32 * 8;
+
+    match (signed, bits >= 64) {
+        (true, true) => true,
+        (false, true) => value >= 0,
+        (true, false) => value >= -(1i64 << (bits - 1)) && value < (1i64 << (bits - 1)),
+        (false, false) => value >= 0 && value < (1i64 << bits),
+    }
+}
+
+/// Get's the Rust names of `fields`, along with the names of the methods reading each of their bit ranges.
+///
+/// Members of a union are methods, so they can not use the names of the functions every struct has. Repeated names
+/// are numbered and fields with a type or a `count` have no bit range methods.
+fn member_names(fields: &[gram_parse::GrammarFields], union: bool) -> Vec<(String, Vec<String>)> {
+    let mut used_members: HashSet<String> = HashSet::new();
+    let mut used_methods: HashSet<String> =
+        METHOD_NAMES.iter().map(|name| name.to_string()).collect();
+
+    fields
+        .iter()
+        .map(|field| {
+            let member_name = if union {
+                unique_name(field_identifier(&field.name), &mut used_methods)
+            } else {
+                unique_name(field_identifier(&field.name), &mut used_members)
+            };
+
+            let bits_names = if union || field.count.is_some() || !field.type_name.is_empty() {
+                Vec::new()
+            } else {
+                field
+                    .bits
+                    .iter()
+                    .map(|bits| unique_name(field_identifier(&bits.name), &mut used_methods))
+                    .collect()
+            };
+
+            (member_name, bits_names)
+        })
+        .collect()
+}
+
+/// Numbers `name` if it has already been used, then marks it as used.
+fn unique_name(name: String, used_names: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut number = 2;
+
+    while used_names.contains(&unique) {
+        unique = format!("{}_{}", name, number);
+        number += 1;
+    }
+
+    used_names.insert(unique.clone());
+    unique
+}
+
+/// Builds the method of a struct reading the bit range `bits` of its member `member_name`.
+fn bits_method(
+    field: &gram_parse::GrammarFields,
+    member_name: &str,
+    bits: &gram_parse::GrammarBits,
+    method_name: &str,
+) -> String {
+    let (storage_bits, unsigned_type, signed_type) = match field_kind(field) {
+        FieldKinds::Number { rust_type, .. } => match &rust_type[1..] {
+            "8" => (8, "u8", "i8"),
+            "16" => (16, "u16", "i16"),
+            "32" => (32, "u32", "i32"),
+            _ => (64, "u64", "i64"),
+        },
+        _ => (64, "u64", "i64"),
+    };
+
+    let (return_type, read) = if bits.signed {
+        let mut value = format!("self.{}", member_name);
+        let unused_bits = storage_bits - bits.offset - bits.width;
+        if unused_bits > 0 {
+            value = format!("({} << {})", value, unused_bits);
+        }
+        value = format!("({} as {})", value, signed_type);
+        if bits.width < storage_bits {
+            value = format!("{} >> {}", value, storage_bits - bits.width);
+        }
+        (signed_type, value)
+    } else {
+        let mut value = format!("self.{}", member_name);
+        if bits.offset > 0 {
+            value = format!("({} >> {})", value, bits.offset);
+        }
+        if bits.width < storage_bits {
+            value = format!("{} & {:#X}", value, (1u64 << bits.width) - 1);
+        }
+        (unsigned_type, value)
+    };
+
+    format!(
+        "
+    /// Reads the `{}` bits of `{}`.
+    pub fn {}(&self) -> {} {{
+        {}
+    }}
+",
+        comment_text(&bits.name),
+        member_name,
+        method_name,
+        return_type,
+        strip_outer_parentheses(&read)
+    )
+}
+
+/// Removes the parentheses around `expression` if they enclose all of it.
+fn strip_outer_parentheses(expression: &str) -> &str {
+    if !expression.starts_with('(') || !expression.ends_with(')') {
+        return expression;
+    }
+
+    let mut depth = 0;
+    for (index, c) in expression.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+
+        if depth == 0 && index < expression.len() - 1 {
+            return expression;
+        }
+    }
+
+    &expression[1..expression.len() - 1]
+}
+
+/// Builds the enum type `rust_enum`, a wrapper of its integer with a constant for each enumerator.
+fn enum_contents(rust_enum: &RustEnum) -> String {
+    let mut used_names: HashSet<String> = ["SIZE".to_string()].iter().cloned().collect();
+    let mut used_values: HashSet<i64> = HashSet::new();
+    let mut constants = String::new();
+    let mut arms = String::new();
+
+    for (name, value) in rust_enum.enumerators.iter() {
+        let constant = unique_name(constant_identifier(name), &mut used_names);
+        constants.push_str(&format!(
+            "    pub const {}: Self = Self({});\n",
+            constant, value
+        ));
+
+        if used_values.insert(*value) {
+            arms.push_str(&format!("            {} => Some({:?}),\n", value, name));
+        }
+    }
+
+    format!(
+        "
+/// The {} enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct {}(pub {});
+
+impl {} {{
+{}
+    /// Get's the name of the enumerator with this value, or `None` if there is not one.
+    pub fn name(self) -> Option<&'static str> {{
+        match self.0 {{
+{}            _ => None,
+        }}
+    }}
+}}
+",
+        comment_text(&rust_enum.description),
+        rust_enum.name,
+        rust_enum.rust_type,
+        rust_enum.name,
+        constants,
+        arms
+    )
+}
+
+/// Get's the documentation of a member, its original name if it was renamed and its description.
+fn member_doc(field: &gram_parse::GrammarFields) -> String {
+    let mut notes: Vec<String> = Vec::new();
+
+    if field_identifier(&field.name) != field.name {
+        notes.push(format!("{}.", field.name));
+    }
+    if !field.description.is_empty() && field.description != "N/A" {
+        notes.push(field.description.clone());
+    }
+
+    comment_text(&notes.join(" "))
+}
+
+/// Builds a doc comment of `text` indented by `indent`, or nothing if `text` is empty.
+fn doc_comment(indent: &str, text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}/// {}\n", indent, text)
+    }
+}
+
+/// Get's the expression of the offset of a field at `offset`, or of its element at `index` if `indexed` is set.
+fn offset_expression(offset: usize, size: usize, indexed: bool) -> String {
+    match (indexed, offset) {
+        (false, _) => format!("{:#X}", offset),
+        (true, 0) => format!("index * {:#X}", size),
+        (true, _) => format!("{:#X} + index * {:#X}", offset, size),
+    }
+}
+
+/// Get's the name of the function converting bytes in the byte order of a field to a number.
+fn from_bytes(little_endian: bool) -> &'static str {
+    if little_endian {
+        "from_le_bytes"
+    } else {
+        "from_be_bytes"
+    }
+}
+
+/// Get's the name of the function converting a number to bytes in the byte order of a field.
+fn to_bytes(little_endian: bool) -> &'static str {
+    if little_endian {
+        "to_le_bytes"
+    } else {
+        "to_be_bytes"
+    }
+}
+
+/// Converts `name` into a Rust type name in upper camel case, e.g `FAT16-bootsector` into `FAT16Bootsector`.
+fn type_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            // Acronyms such as `MBR` are written as `Mbr`, as Rust type names are.
+            let rest = if part.chars().any(|c| c.is_ascii_lowercase()) {
+                part[1..].to_string()
+            } else {
+                part[1..].to_ascii_lowercase()
+            };
+            part[..1].to_ascii_uppercase() + rest.as_str()
+        })
+        .collect();
+
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, 'T');
+    }
+
+    if KEYWORDS.contains(&&identifier[..]) {
+        identifier.push('_');
+    }
+
+    identifier
+}
+
+/// Converts `name` into a Rust field name in snake case, e.g `SizeOfRawData` into `size_of_raw_data`.
+fn field_identifier(name: &str) -> String {
+    let chars: Vec<char> = name.trim().chars().collect();
+    let mut identifier = String::new();
+
+    for (index, c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            identifier.push('_');
+            continue;
+        }
+
+        if c.is_ascii_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next_lowercase = chars
+                .get(index + 1)
+                .is_some_and(|next| next.is_ascii_lowercase());
+
+            if previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_lowercase)
+            {
+                identifier.push('_');
+            }
+        }
+
+        identifier.push(c.to_ascii_lowercase());
+    }
+
+    while identifier.contains("__") {
+        identifier = identifier.replace("__", "_");
+    }
+
+    let mut identifier = identifier.trim_end_matches('_').to_string();
+
+    if identifier.is_empty()
+        || identifier == "_"
+        || identifier.starts_with(|c: char| c.is_ascii_digit())
+    {
+        identifier.insert_str(0, "field_");
+    }
+
+    if KEYWORDS.contains(&&identifier[..]) {
+        identifier.push('_');
+    }
+
+    identifier
+}
+
+/// Converts `name` into a Rust constant name in upper case.
+fn constant_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+
+    if KEYWORDS.contains(&&identifier[..]) {
+        identifier.push('_');
+    }
+
+    identifier
+}
+
+/// Removes newlines from `text` so it fits on one comment line.
+fn comment_text(text: &str) -> String {
+    text.replace(['\r', '\n'], " ").trim().to_string()
+}
+
+/// Get's the path of `target` relative to the directory `base`, for `include_bytes!` in a module written to `base`.
+///
+/// The absolute path of `target` is used if either path can not be resolved.
+fn relative_path(base: &Path, target: &Path) -> String {
+    let base = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+
+    let (base, target) = match (base.canonicalize(), target.canonicalize()) {
+        (Ok(base), Ok(target)) => (base, target),
+        _ => return target.to_string_lossy().to_string(),
+    };
+
+    let base_components: Vec<Component> = base.components().collect();
+    let target_components: Vec<Component> = target.components().collect();
+    let common = base_components
+        .iter()
+        .zip(target_components.iter())
+        .take_while(|(base_component, target_component)| base_component == target_component)
+        .count();
+
+    if common == 0 {
+        return target.to_string_lossy().to_string();
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in target_components[common..].iter() {
+        relative.push(component);
+    }
+
+    relative.to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const GRAMMAR: &str = r#"
+[metadata]
+name = "Sensor"
+variable_size_fields = [["", "", "", ""]]
+multiply_fields = [["", ""]]
+
+[[fields]]
+name = "temperature"
+size = 2
+data_type = "uint16_t"
+display_format = "uint"
+description = "N/A"
+scale = 0.01
+offset = -40
+unit = "°C"
+
+[[fields]]
+name = "delay"
+size = 4
+data_type = "int32_t"
+display_format = "int"
+description = "N/A"
+unit = "ms"
+
+[[fields]]
+name = "state"
+size = 4
+data_type = "enum state"
+display_format = "int"
+description = "IDLE = 0, BUSY = 4"
+
+[[fields]]
+name = "mode"
+size = 1
+data_type = "enum state"
+display_format = "uint"
+description = "IDLE = 0, BUSY = 4"
+
+[[fields]]
+name = "flags"
+size = 2
+data_type = "uint16_t"
+display_format = "bits"
+description = "N/A"
+
+[[fields.bits]]
+name = "ack"
+offset = 0
+width = 1
+
+[[fields.bits]]
+name = "level"
+offset = 1
+width = 5
+signed = true
+
+[[fields]]
+name = "value"
+size = 4
+type = "Value"
+data_type = "union Value"
+display_format = "hex"
+description = "N/A"
+
+[[fields]]
+name = "ratio"
+size = 4
+data_type = "float"
+display_format = "float"
+description = "N/A"
+scale = 2.5
+
+[[types]]
+name = "Value"
+kind = "union"
+size = 4
+
+[[types.fields]]
+name = "number"
+size = 4
+data_type = "uint32_t"
+display_format = "uint"
+description = "N/A"
+
+[[types.fields]]
+name = "parse"
+size = 2
+data_type = "uint16_t"
+display_format = "uintbe"
+description = "N/A"
+"#;
+
+    fn convert(grammar: &str) -> Result<RustModule, ()> {
+        let mut parsed_gram = gram_parse::Grammar::new();
+        parsed_gram.parse_toml(grammar)?;
+
+        let mut rust_module = RustModule::new();
+        rust_module.build_module_contents(&parsed_gram)?;
+        Ok(rust_module)
+    }
+
+    /// Builds the sample test with memgram's parse of the sample given as the bytes and displayed value of each field.
+    fn sample_test(rust_module: &mut RustModule, fields: &[(&str, &[u8], &str)]) -> String {
+        let sample_dir = env::temp_dir().join("memgram_rust_convert_samples");
+        fs::create_dir_all(&sample_dir).unwrap();
+        let sample_path = sample_dir.join("sensor.bin");
+        fs::write(&sample_path, b"").unwrap();
+
+        let mut cmd_args = arg_parse::CMDArgParse::new();
+        cmd_args.output_filepath = env::temp_dir()
+            .join("sensor.rs")
+            .to_string_lossy()
+            .to_string();
+        cmd_args.binary_filepath = sample_path.to_string_lossy().to_string();
+
+        let mut table_data = table_display::TableData::new();
+        for (name, bytes, formatted) in fields {
+            table_data
+                .field_hashmap
+                .insert(name.to_string(), bytes.to_vec());
+            table_data
+                .field_fmt_hashmap
+                .insert(name.to_string(), formatted.to_string());
+        }
+
+        let contents = rust_module.module_contents.clone();
+        rust_module
+            .build_sample_test(&cmd_args, &table_data)
+            .unwrap();
+        rust_module.module_contents[contents.len()..].to_string()
+    }
+
+    #[test]
+    fn fields_become_members_read_in_their_byte_order() {
+        let contents = convert(GRAMMAR).unwrap().module_contents;
+
+        assert!(contents.contains("pub struct Sensor {"));
+        assert!(contents.contains("    pub const SIZE: usize = 0x15;\n"));
+        assert!(contents
+            .contains("            temperature: u16::from_le_bytes(array(&bytes[0x0..])),\n"));
+        assert!(
+            contents.contains("            ratio: f32::from_le_bytes(array(&bytes[0x11..])),\n")
+        );
+        assert!(contents.contains("            value: Value::read(&bytes[0xD..]),\n"));
+    }
+
+    #[test]
+    fn union_members_become_methods() {
+        let contents = convert(GRAMMAR).unwrap().module_contents;
+
+        assert!(contents.contains("    pub bytes: [u8; 4],\n"));
+        assert!(contents.contains(
+            "    pub fn number(&self) -> u32 {\n        let bytes = &self.bytes[..];\n        u32::from_le_bytes(array(&bytes[0x0..]))\n    }\n"
+        ));
+        assert!(contents.contains(
+            "    pub fn parse_2(&self) -> u16 {\n        let bytes = &self.bytes[..];\n        u16::from_be_bytes(array(&bytes[0x0..]))\n    }\n"
+        ));
+    }
+
+    #[test]
+    fn enumerators_become_enum_types() {
+        let contents = convert(GRAMMAR).unwrap().module_contents;
+
+        assert!(contents.contains("pub struct State(pub i32);\n"));
+        assert!(contents.contains("pub struct State2(pub u8);\n"));
+        assert!(contents.contains("    pub const BUSY: Self = Self(4);\n"));
+        assert!(contents.contains("            4 => Some(\"BUSY\"),\n"));
+        assert!(contents
+            .contains("            state: State(i32::from_le_bytes(array(&bytes[0x6..]))),\n"));
+        assert!(contents.contains("            mode: State2(bytes[0xA]),\n"));
+    }
+
+    #[test]
+    fn bit_ranges_become_methods() {
+        let contents = convert(GRAMMAR).unwrap().module_contents;
+
+        assert!(
+            contents.contains("    pub fn ack(&self) -> u16 {\n        self.flags & 0x1\n    }\n")
+        );
+        assert!(contents.contains(
+            "    pub fn level(&self) -> i16 {\n        ((self.flags << 10) as i16) >> 11\n    }\n"
+        ));
+    }
+
+    #[test]
+    fn sample_test_checks_displayed_values() {
+        let mut rust_module = convert(GRAMMAR).unwrap();
+        let test = sample_test(
+            &mut rust_module,
+            &[
+                ("temperature", &[0xFA, 0x19], "26.5 °C"),
+                ("delay", &[0xF9, 0xFF, 0xFF, 0xFF], "-7 ms"),
+                ("state", &[0x04, 0x00, 0x00, 0x00], "4"),
+                ("mode", &[0x03], "3"),
+                ("flags", &[0x6B, 0x00], "ack=1, level=-11"),
+                ("value", &[0x01, 0x02, 0x03, 0x04], "0x01020304"),
+                ("ratio", &[0x00, 0x00, 0xC0, 0x3F], "3.8"),
+            ],
+        );
+
+        assert!(test.contains(
+            "        let sample = include_bytes!(\"memgram_rust_convert_samples/sensor.bin\");\n"
+        ));
+        assert!(test.contains(
+            "        assert_eq!(scaled(value.temperature as f64, 0.01, -40.0, 2) + \" °C\", \"26.5 °C\");\n"
+        ));
+        assert!(
+            test.contains("        assert_eq!(value.delay.to_string() + \" ms\", \"-7 ms\");\n")
+        );
+        assert!(test.contains("        assert_eq!(value.state.name(), Some(\"BUSY\"));\n"));
+        assert!(test.contains("        assert_eq!(value.mode.name(), None);\n"));
+        assert!(test.contains(
+            "        assert_eq!(format!(\"ack={}, level={}\", value.ack(), value.level()), \"ack=1, level=-11\");\n"
+        ));
+        assert!(test.contains("        assert_eq!(value.value.bytes, [0x01, 0x02, 0x03, 0x04]);\n"));
+        assert!(test
+            .contains("        assert_eq!(scaled(value.ratio as f64, 2.5, 0.0, 1), \"3.8\");\n"));
+        assert!(test.contains(
+            "    fn scaled(value: f64, scale: f64, offset: f64, decimals: usize) -> String {\n"
+        ));
+    }
+
+    #[test]
+    fn sample_test_stops_at_the_first_variable_size_field() {
+        let mut rust_module = convert(&GRAMMAR.replace(
+            "variable_size_fields = [[\"\", \"\", \"\", \"\"]]",
+            "variable_size_fields = [[\"temperature\", \"+\", \"0\", \"state\"]]",
+        ))
+        .unwrap();
+        let test = sample_test(
+            &mut rust_module,
+            &[
+                ("delay", &[0xF9, 0xFF, 0xFF, 0xFF], "-7"),
+                ("state", &[0x04, 0x00, 0x00, 0x00], "4"),
+            ],
+        );
+
+        assert!(test.contains("value.delay"));
+        assert!(!test.contains("value.state"));
+        assert!(!test.contains("fn scaled"));
+    }
+
+    #[test]
+    fn enumerators_are_parsed_from_descriptions() {
+        assert_eq!(
+            parse_enumerators("IDLE = 0, BUSY = -4"),
+            vec![(String::from("IDLE"), 0), (String::from("BUSY"), -4)]
+        );
+        assert!(parse_enumerators("N/A").is_empty());
+        assert!(parse_enumerators("IDLE = 0, the rest").is_empty());
+        assert!(fits_in(255, 1, false) && !fits_in(256, 1, false) && !fits_in(128, 1, true));
+    }
+
+    #[test]
+    fn names_become_rust_identifiers() {
+        assert_eq!(type_identifier("my_header"), "MyHeader");
+        assert_eq!(field_identifier("Sector Count"), "sector_count");
+        assert_eq!(field_identifier("type"), "type_");
+        assert_eq!(constant_identifier("busy-state"), "BUSY_STATE");
+    }
+}
@@ -0,0 +1,1101 @@
+//! Module for parsing the `#[repr(C)]` struct and union declarations of Rust source, used by the C struct converter.
+//!
+//! `#[repr(C)]` types are laid out by the rules of the target's C compiler, so they are parsed into the same
+//! `c_parse::CHeader` as a C header, with each Rust primitive type written as a typedef of the matching C type.
+use crate::c_parse;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// Rust primitive and `core::ffi` types as (Rust type, C type).
+#[rustfmt::skip]
+const PRIMITIVES: [(&str, &str); 29] = [
+    ("u8", "uint8_t"), ("u16", "uint16_t"), ("u32", "uint32_t"), ("u64", "uint64_t"), ("u128", "__uint128_t"),
+    ("usize", "size_t"),
+    ("i8", "int8_t"), ("i16", "int16_t"), ("i32", "int32_t"), ("i64", "int64_t"), ("i128", "__int128_t"),
+    ("isize", "ssize_t"),
+    ("f32", "float"), ("f64", "double"), ("bool", "_Bool"), ("char", "char32_t"),
+    // `CHAR` is the one byte character type, as `char` is the Rust type.
+    ("c_char", "CHAR"), ("c_schar", "signed char"), ("c_uchar", "unsigned char"),
+    ("c_short", "short"), ("c_ushort", "unsigned short"), ("c_int", "int"), ("c_uint", "unsigned int"),
+    ("c_long", "long"), ("c_ulong", "unsigned long"), ("c_longlong", "long long"),
+    ("c_ulonglong", "unsigned long long"), ("c_float", "float"), ("c_double", "double"),
+];
+/// Types without a size, which become empty arrays of bytes.
+const ZERO_SIZED_TYPES: [&str; 3] = ["()", "PhantomData", "PhantomPinned"];
+/// Generic types laid out the same as their type argument, e.g `ManuallyDrop<u32>` is laid out as `u32`.
+const TRANSPARENT_WRAPPERS: [&str; 6] = [
+    "ManuallyDrop",
+    "MaybeUninit",
+    "Cell",
+    "UnsafeCell",
+    "Wrapping",
+    "Saturating",
+];
+/// Generic types holding a pointer to their type argument that is never null.
+const POINTER_WRAPPERS: [&str; 2] = ["NonNull", "Box"];
+/// Type aliases of type aliases nested deeper than this are assumed to refer to themselves.
+const MAX_ALIAS_DEPTH: usize = 32;
+
+/// The layout given to a struct or union by its `#[repr(...)]` attributes.
+#[derive(Default)]
+struct Repr {
+    /// Whether the layout is defined by `repr(C)` or `repr(transparent)`, rather than left to the compiler.
+    defined: bool,
+    /// Packing given by `repr(packed)` and the alignment given by `repr(align(n))`.
+    attributes: c_parse::Attributes,
+    /// The largest member alignment set by `repr(packed(n))`.
+    pack: Option<usize>,
+}
+
+/// Parses the `#[repr(C)]` and `#[repr(transparent)]` struct and union definitions of Rust source.
+///
+/// Fields may be primitives, `core::ffi` types, arrays, other structs and unions, type aliases, raw pointers,
+/// references and the wrappers with a defined layout such as `Option<&T>` or `ManuallyDrop<T>`. `const` integers can
+/// be used as array lengths. Structs and unions without a defined layout are skipped, as is everything else.
+pub fn parse_rust_source(source: &str) -> Result<c_parse::CHeader, ()> {
+    let mut parser = Parser {
+        tokens: tokenize(source),
+        position: 0,
+        header: c_parse::CHeader::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        unsupported: HashMap::new(),
+    };
+
+    for (rust_type, c_type) in PRIMITIVES.iter() {
+        parser.header.typedefs.push(named_member(rust_type, c_type));
+    }
+    for zero_sized_type in ZERO_SIZED_TYPES.iter() {
+        parser
+            .header
+            .typedefs
+            .push(named_member(zero_sized_type, "uint8_t"));
+    }
+
+    parser.parse_constants();
+    parser.parse_items()?;
+    parser.resolve_members()?;
+
+    Ok(parser.header)
+}
+
+/// Splits Rust source into tokens, removing comments, lifetimes, casts and the contents of string and character
+/// literals.
+///
+/// Raw identifiers such as `r#type` become the identifier without `r#`, and literals that are not integers, such as
+/// floats, become `Token::Literal` so they are never taken as a constant.
+fn tokenize(source: &str) -> Vec<(c_parse::Token, usize)> {
+    let characters: Vec<char> = source.chars().collect();
+    let mut tokens: Vec<(c_parse::Token, usize)> = Vec::new();
+    let mut line = 1;
+    let mut index = 0;
+
+    let is_ident = |character: char| character.is_ascii_alphanumeric() || character == '_';
+
+    while index < characters.len() {
+        let character = characters[index];
+        let next = characters.get(index + 1).copied();
+
+        match character {
+            '\n' => {
+                line += 1;
+                index += 1;
+            }
+            _ if character.is_whitespace() => index += 1,
+            '/' if next == Some('/') => {
+                while index < characters.len() && characters[index] != '\n' {
+                    index += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                // Block comments can be nested.
+                let mut depth = 0;
+
+                while index < characters.len() {
+                    match (characters[index], characters.get(index + 1)) {
+                        ('/', Some('*')) => {
+                            depth += 1;
+                            index += 2;
+                        }
+                        ('*', Some('/')) => {
+                            depth -= 1;
+                            index += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        ('\n', _) => {
+                            line += 1;
+                            index += 1;
+                        }
+                        _ => index += 1,
+                    }
+                }
+            }
+            'b' if next == Some('"') || next == Some('\'') => index += 1,
+            'b' if next == Some('r')
+                && matches!(characters.get(index + 2), Some('"') | Some('#')) =>
+            {
+                index += 1
+            }
+            'r' if matches!(next, Some('"') | Some('#'))
+                && !(next == Some('#')
+                    && characters.get(index + 2).is_some_and(|c| is_ident(*c))) =>
+            {
+                let hashes = characters[index + 1..]
+                    .iter()
+                    .take_while(|character| **character == '#')
+                    .count();
+                let terminator: Vec<char> = std::iter::once('"')
+                    .chain(std::iter::repeat_n('#', hashes))
+                    .collect();
+
+                index += hashes + 2;
+                while index < characters.len() && !characters[index..].starts_with(&terminator) {
+                    if characters[index] == '\n' {
+                        line += 1;
+                    }
+                    index += 1;
+                }
+                index += terminator.len();
+                tokens.push((c_parse::Token::Punct('"'), line));
+            }
+            '"' => {
+                index += 1;
+                while index < characters.len() && characters[index] != '"' {
+                    match characters[index] {
+                        '\\' => index += 1,
+                        '\n' => line += 1,
+                        _ => (),
+                    }
+                    index += 1;
+                }
+                index += 1;
+                tokens.push((c_parse::Token::Punct('"'), line));
+            }
+            // A character literal such as 'a' or '\n', otherwise a lifetime such as 'a.
+            '\'' if next == Some('\\') || characters.get(index + 2) == Some(&'\'') => {
+                index += 2;
+                while index < characters.len() && characters[index] != '\'' {
+                    if characters[index] == '\\' {
+                        index += 1;
+                    }
+                    index += 1;
+                }
+                index += 1;
+                tokens.push((c_parse::Token::Punct('\''), line));
+            }
+            '\'' => {
+                index += 1;
+                while index < characters.len() && is_ident(characters[index]) {
+                    index += 1;
+                }
+            }
+            _ if character.is_ascii_alphabetic() || character == '_' => {
+                if character == 'r' && next == Some('#') {
+                    index += 2;
+                }
+
+                let start = index;
+                while index < characters.len() && is_ident(characters[index]) {
+                    index += 1;
+                }
+                tokens.push((
+                    c_parse::Token::Ident(characters[start..index].iter().collect()),
+                    line,
+                ));
+            }
+            _ if character.is_ascii_digit() => {
+                let start = index;
+                while index < characters.len() && is_ident(characters[index]) {
+                    index += 1;
+                }
+
+                let literal: String = characters[start..index].iter().collect();
+                tokens.push((
+                    match integer_literal(&literal) {
+                        Some(number) => c_parse::Token::Number(number),
+                        None => c_parse::Token::Literal(literal),
+                    },
+                    line,
+                ));
+            }
+            _ => {
+                tokens.push((c_parse::Token::Punct(character), line));
+                index += 1;
+            }
+        }
+    }
+
+    remove_casts(tokens)
+}
+
+/// Removes casts such as `as usize` or `as core::ffi::c_int` from `tokens`, so constant expressions using them can
+/// be evaluated.
+fn remove_casts(tokens: Vec<(c_parse::Token, usize)>) -> Vec<(c_parse::Token, usize)> {
+    let mut without_casts: Vec<(c_parse::Token, usize)> = Vec::new();
+    let mut in_cast = false;
+
+    for (token, line) in tokens.into_iter() {
+        match &token {
+            c_parse::Token::Ident(ident) if ident == "as" && !in_cast => {
+                in_cast = true;
+                continue;
+            }
+            c_parse::Token::Ident(_) | c_parse::Token::Punct(':') if in_cast => continue,
+            _ => in_cast = false,
+        }
+
+        without_casts.push((token, line));
+    }
+
+    without_casts
+}
+
+/// Converts a decimal, hex, octal or binary Rust integer literal into a number, ignoring `_` separators and any
+/// integer type suffix, e.g `0x1000_u32`.
+fn integer_literal(literal: &str) -> Option<u64> {
+    let digits = literal.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") | Some("0X") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, &digits[..]),
+    };
+
+    let digits = match digits.find(['u', 'i']) {
+        Some(suffix_start)
+            if PRIMITIVES
+                .iter()
+                .any(|(name, _)| *name == &digits[suffix_start..]) =>
+        {
+            &digits[..suffix_start]
+        }
+        _ => digits,
+    };
+
+    u64::from_str_radix(digits, radix).ok()
+}
+
+/// Creates a plain member called `name` whose type is the basic type, typedef or alias `type_name`.
+fn named_member(name: &str, type_name: &str) -> c_parse::CMember {
+    c_parse::CMember {
+        type_spec: c_parse::TypeSpecs::Named(type_name.to_string()),
+        name: name.to_string(),
+        pointer_depth: 0,
+        dimensions: Vec::new(),
+        bit_width: None,
+        attributes: c_parse::Attributes::default(),
+    }
+}
+
+/// Gets the layout given by the tokens of an outer attribute, e.g `[repr(C, align(8))]`.
+///
+/// Attributes other than `repr` give the default layout.
+fn repr_attribute(tokens: &[(c_parse::Token, usize)]) -> Repr {
+    let mut repr = Repr::default();
+    let tokens: Vec<&c_parse::Token> = tokens.iter().map(|(token, _)| token).collect();
+
+    match tokens.get(1) {
+        Some(c_parse::Token::Ident(ident)) if ident == "repr" => (),
+        _ => return repr,
+    }
+
+    for (index, token) in tokens.iter().enumerate().skip(2) {
+        let argument = match tokens.get(index + 1..index + 4) {
+            Some(
+                [c_parse::Token::Punct('('), c_parse::Token::Number(argument), c_parse::Token::Punct(')')],
+            ) => Some(*argument as usize),
+            _ => None,
+        };
+
+        match token {
+            c_parse::Token::Ident(ident) if ident == "C" || ident == "transparent" => {
+                repr.defined = true;
+            }
+            c_parse::Token::Ident(ident) if ident == "packed" => match argument {
+                Some(pack) => repr.pack = Some(pack.max(1)),
+                None => repr.attributes.packed = true,
+            },
+            c_parse::Token::Ident(ident) if ident == "align" => {
+                repr.attributes.align = repr.attributes.align.max(argument);
+            }
+            _ => (),
+        }
+    }
+
+    repr
+}
+
+/// Recursive descent parser over the tokens of Rust source.
+struct Parser {
+    tokens: Vec<(c_parse::Token, usize)>,
+    position: usize,
+    header: c_parse::CHeader,
+    /// Values of the `const` integers.
+    constants: HashMap<String, i64>,
+    /// The position of the type of each type alias, parsed when a field uses the alias.
+    aliases: HashMap<String, usize>,
+    /// Structs and unions that can not be converted, with the reason why.
+    unsupported: HashMap<String, &'static str>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&c_parse::Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_ident(&self) -> Option<&str> {
+        match self.peek() {
+            Some(c_parse::Token::Ident(ident)) => Some(ident),
+            _ => None,
+        }
+    }
+
+    fn is_punct(&self, punct: char) -> bool {
+        self.peek() == Some(&c_parse::Token::Punct(punct))
+    }
+
+    /// Whether the current and next tokens are the two characters of `punct`, e.g `::`.
+    fn is_punct_pair(&self, punct: [char; 2]) -> bool {
+        self.is_punct(punct[0])
+            && self.tokens.get(self.position + 1).map(|(token, _)| token)
+                == Some(&c_parse::Token::Punct(punct[1]))
+    }
+
+    /// The line of the current token, used in error messages.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |(_, line)| *line)
+    }
+
+    fn advance(&mut self) -> Option<c_parse::Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Reports a parse error at the current token.
+    fn error<T>(&self, message: &str) -> Result<T, ()> {
+        serror!(format!(
+            "Invalid Rust declaration on line {}: {}, found: {}",
+            self.line(),
+            message,
+            match self.peek() {
+                Some(c_parse::Token::Ident(ident)) => ident.clone(),
+                Some(c_parse::Token::Number(number)) => number.to_string(),
+                Some(c_parse::Token::Literal(literal)) => literal.clone(),
+                Some(c_parse::Token::Punct(punct)) => punct.to_string(),
+                Some(c_parse::Token::Directive(directive)) => directive.clone(),
+                None => String::from("end of file"),
+            }
+        ));
+        Err(())
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<(), ()> {
+        if self.is_punct(punct) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", punct))
+        }
+    }
+
+    fn expect_ident(&mut self, message: &str) -> Result<String, ()> {
+        match self.peek_ident() {
+            Some(ident) => {
+                let ident = ident.to_string();
+                self.position += 1;
+                Ok(ident)
+            }
+            None => self.error(message),
+        }
+    }
+
+    /// Skips a balanced `open`/`close` block starting at the current token.
+    fn skip_balanced(&mut self, open: char, close: char) -> Result<(), ()> {
+        let mut depth = 0;
+
+        loop {
+            match self.advance() {
+                Some(c_parse::Token::Punct(punct)) if punct == open => depth += 1,
+                Some(c_parse::Token::Punct(punct)) if punct == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Some(_) => (),
+                None => return self.error(&format!("expected '{}'", close)),
+            }
+        }
+    }
+
+    /// Skips the rest of an item, up to and including its `;` or its body between braces.
+    fn skip_item(&mut self) -> Result<(), ()> {
+        while let Some(token) = self.peek() {
+            match token {
+                c_parse::Token::Punct(';') => {
+                    self.position += 1;
+                    break;
+                }
+                c_parse::Token::Punct('{') => {
+                    self.skip_balanced('{', '}')?;
+                    break;
+                }
+                c_parse::Token::Punct('(') => self.skip_balanced('(', ')')?,
+                _ => self.position += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates every `const NAME: type = value;` whose value is an integer constant expression.
+    ///
+    /// Constants can be used before they are defined, so the values are evaluated until no more can be.
+    fn parse_constants(&mut self) {
+        let mut definitions: Vec<(String, usize)> = Vec::new();
+
+        for (index, window) in self.tokens.windows(3).enumerate() {
+            if let [(c_parse::Token::Ident(keyword), _), (c_parse::Token::Ident(name), _), (c_parse::Token::Punct(':'), _)] =
+                window
+            {
+                if keyword != "const" {
+                    continue;
+                }
+
+                let value_start = self.tokens[index..]
+                    .iter()
+                    .position(|(token, _)| *token == c_parse::Token::Punct('='))
+                    .map(|offset| index + offset + 1);
+
+                if let Some(value_start) = value_start {
+                    definitions.push((name.clone(), value_start));
+                }
+            }
+        }
+
+        loop {
+            let mut evaluated = false;
+
+            for (name, value_start) in definitions.iter() {
+                if self.constants.contains_key(name) {
+                    continue;
+                }
+
+                self.position = *value_start;
+
+                if let Some(value) = self.evaluate_constant() {
+                    if self.is_punct(';') {
+                        self.constants.insert(name.clone(), value);
+                        evaluated = true;
+                    }
+                }
+            }
+
+            if !evaluated {
+                break;
+            }
+        }
+
+        self.position = 0;
+    }
+
+    /// Evaluates an integer constant expression, e.g `SECTOR_SIZE * 2`.
+    fn evaluate_constant(&mut self) -> Option<i64> {
+        c_parse::evaluate(&self.tokens, &mut self.position, &self.constants)
+    }
+
+    /// Parses every struct, union and type alias, skipping everything else.
+    ///
+    /// The layout given by `#[repr(...)]` attributes in front of a struct or union is given to it. Items inside inline
+    /// modules are parsed, items inside function bodies and `impl` blocks are not.
+    fn parse_items(&mut self) -> Result<(), ()> {
+        let mut repr = Repr::default();
+
+        while let Some(token) = self.peek() {
+            match token {
+                c_parse::Token::Punct('#') => {
+                    self.position += 1;
+                    let inner = self.is_punct('!');
+                    if inner {
+                        self.position += 1;
+                    }
+
+                    if self.is_punct('[') {
+                        let start = self.position;
+                        self.skip_balanced('[', ']')?;
+
+                        if !inner {
+                            let attribute = repr_attribute(&self.tokens[start..self.position]);
+                            repr.defined |= attribute.defined;
+                            repr.attributes.packed |= attribute.attributes.packed;
+                            repr.attributes.align =
+                                repr.attributes.align.max(attribute.attributes.align);
+                            repr.pack = repr.pack.or(attribute.pack);
+                        }
+                    }
+                }
+                c_parse::Token::Ident(ident)
+                    if ident == "struct"
+                        || (ident == "union"
+                            && matches!(
+                                self.tokens.get(self.position + 1),
+                                Some((c_parse::Token::Ident(_), _))
+                            )) =>
+                {
+                    self.parse_record(std::mem::take(&mut repr))?;
+                }
+                c_parse::Token::Ident(ident) if ident == "type" => {
+                    self.position += 1;
+                    let name = self.expect_ident("expected a type alias name")?;
+
+                    if self.is_punct('=') {
+                        self.aliases.insert(name, self.position + 1);
+                    }
+                    self.skip_item()?;
+                    repr = Repr::default();
+                }
+                c_parse::Token::Ident(ident) if ident == "mod" => {
+                    self.position += 1;
+                    self.expect_ident("expected a module name")?;
+
+                    // The items of an inline module are parsed like top level items and its closing brace skipped.
+                    if self.is_punct('{') {
+                        self.position += 1;
+                    }
+                    repr = Repr::default();
+                }
+                c_parse::Token::Punct('{') => {
+                    self.skip_balanced('{', '}')?;
+                    repr = Repr::default();
+                }
+                c_parse::Token::Punct(';') => {
+                    self.position += 1;
+                    repr = Repr::default();
+                }
+                _ => self.position += 1,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a struct or union definition with the layout `repr`, adding it to the header if its layout is defined.
+    ///
+    /// Fields of tuple structs are named after their index, e.g `0`.
+    fn parse_record(&mut self, repr: Repr) -> Result<(), ()> {
+        let kind = match self.advance() {
+            Some(c_parse::Token::Ident(ident)) if ident == "union" => c_parse::RecordKinds::Union,
+            _ => c_parse::RecordKinds::Struct,
+        };
+        let name = self.expect_ident("expected a struct or union name")?;
+
+        // Generic parameters that are only lifetimes are removed along with the lifetimes, leaving `<>`.
+        if self.is_punct_pair(['<', '>']) {
+            self.position += 2;
+        }
+
+        let reason = if self.is_punct('<') {
+            Some("it is generic")
+        } else if !repr.defined {
+            Some("it is not #[repr(C)]")
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            self.unsupported.insert(name, reason);
+            return self.skip_item();
+        }
+
+        let mut members: Vec<c_parse::CMember> = Vec::new();
+        let close = match self.advance() {
+            Some(c_parse::Token::Punct('{')) => '}',
+            Some(c_parse::Token::Punct('(')) => ')',
+            Some(c_parse::Token::Punct(';')) => ';',
+            _ => {
+                self.position -= 1;
+                return self.error("expected '{', '(' or ';'");
+            }
+        };
+
+        if close != ';' {
+            while !self.is_punct(close) {
+                self.skip_field_prefix()?;
+
+                let field_name = if close == '}' {
+                    let field_name = self.expect_ident("expected a field name")?;
+                    self.expect_punct(':')?;
+                    field_name
+                } else {
+                    members.len().to_string()
+                };
+
+                let (mut member, _) = self.parse_type()?;
+                member.name = field_name;
+                members.push(member);
+
+                if self.is_punct(',') {
+                    self.position += 1;
+                } else if !self.is_punct(close) {
+                    return self.error(&format!("expected ',' or '{}'", close));
+                }
+            }
+
+            self.expect_punct(close)?;
+            if close == ')' {
+                self.expect_punct(';')?;
+            }
+        }
+
+        self.header.records.push(c_parse::CRecord {
+            kind,
+            name,
+            members,
+            attributes: repr.attributes,
+            pack: repr.pack,
+        });
+
+        Ok(())
+    }
+
+    /// Skips the attributes and visibility in front of a field, e.g `#[doc(hidden)] pub(crate)`.
+    fn skip_field_prefix(&mut self) -> Result<(), ()> {
+        loop {
+            if self.is_punct('#') {
+                self.position += 1;
+                self.skip_balanced('[', ']')?;
+            } else if self.peek_ident() == Some("pub") {
+                self.position += 1;
+                if self.is_punct('(') {
+                    self.skip_balanced('(', ')')?;
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parses a type as an unnamed member, e.g `[*const u8; 4]` is an array of 4 pointers to `u8`.
+    ///
+    /// Returns whether the type can never be zero, so an `Option` of it has the same layout.
+    fn parse_type(&mut self) -> Result<(c_parse::CMember, bool), ()> {
+        let start = self.position;
+
+        match self.peek() {
+            Some(c_parse::Token::Punct('[')) => {
+                self.position += 1;
+                let (mut element, _) = self.parse_type()?;
+
+                if !self.is_punct(';') {
+                    return self.error("slices do not have a fixed size, expected ';'");
+                }
+                self.position += 1;
+
+                let length = self.parse_length()?;
+                self.expect_punct(']')?;
+                element.dimensions.insert(0, length);
+
+                Ok((element, false))
+            }
+            Some(c_parse::Token::Punct('*')) | Some(c_parse::Token::Punct('&')) => {
+                let reference = self.is_punct('&');
+                self.position += 1;
+
+                match self.peek_ident() {
+                    Some("const") | Some("mut") => self.position += 1,
+                    _ if !reference => return self.error("expected 'const' or 'mut'"),
+                    _ => (),
+                }
+
+                let (mut pointee, _) = self.parse_type()?;
+                // A pointer to an array is a single pointer, not an array of pointers.
+                pointee.dimensions.clear();
+                pointee.pointer_depth += 1;
+
+                Ok((pointee, reference))
+            }
+            Some(c_parse::Token::Punct('(')) => {
+                self.position += 1;
+
+                if !self.is_punct(')') {
+                    return self.error("tuples do not have a defined layout");
+                }
+                self.position += 1;
+
+                let mut unit = named_member("", "()");
+                unit.dimensions.push(0);
+
+                Ok((unit, false))
+            }
+            Some(c_parse::Token::Ident(ident))
+                if matches!(&ident[..], "fn" | "unsafe" | "extern") =>
+            {
+                while let Some("unsafe") | Some("extern") = self.peek_ident() {
+                    self.position += 1;
+                    if self.is_punct('"') {
+                        self.position += 1;
+                    }
+                }
+
+                if self.peek_ident() != Some("fn") {
+                    return self.error("expected 'fn'");
+                }
+                self.position += 1;
+                self.skip_balanced('(', ')')?;
+
+                if self.is_punct_pair(['-', '>']) {
+                    self.position += 2;
+
+                    if self.is_punct('!') {
+                        self.position += 1;
+                    } else {
+                        self.parse_type()?;
+                    }
+                }
+
+                let mut function = named_member("", "fn");
+                function.pointer_depth = 1;
+
+                Ok((function, true))
+            }
+            Some(c_parse::Token::Ident(ident)) if ident == "dyn" || ident == "impl" => {
+                self.error("trait objects do not have a fixed size")
+            }
+            _ => self.parse_path_type(start),
+        }
+    }
+
+    /// Parses a type given by a path, e.g `core::ffi::c_int` or `Option<NonNull<Header>>`, starting at `start`.
+    ///
+    /// Only the last segment of the path is used. Wrappers with a defined layout are replaced with the type they wrap
+    /// and `NonZero` and atomic integers with the integer type.
+    fn parse_path_type(&mut self, start: usize) -> Result<(c_parse::CMember, bool), ()> {
+        if self.is_punct_pair([':', ':']) {
+            self.position += 2;
+        }
+
+        let mut name = self.expect_ident("expected a type")?;
+
+        while self.is_punct_pair([':', ':']) {
+            self.position += 2;
+            name = self.expect_ident("expected a type")?;
+        }
+
+        let argument = if self.is_punct_pair(['<', '>']) {
+            self.position += 2;
+            None
+        } else if self.is_punct('<') {
+            if ZERO_SIZED_TYPES.contains(&&name[..]) {
+                self.skip_balanced('<', '>')?;
+                None
+            } else {
+                self.position += 1;
+                let argument = self.parse_type()?;
+                self.expect_punct('>')?;
+                Some(argument)
+            }
+        } else {
+            None
+        };
+
+        let integer_name = name
+            .strip_prefix("NonZero")
+            .or_else(|| name.strip_prefix("Atomic"))
+            .map(str::to_lowercase)
+            .filter(|integer_name| {
+                PRIMITIVES
+                    .iter()
+                    .any(|(primitive, _)| *primitive == integer_name)
+            });
+
+        match (&name[..], argument) {
+            ("Option", Some((wrapped, true))) => Ok((wrapped, false)),
+            ("Option", _) => {
+                self.position = start;
+                self.error(
+                    "Option only has a defined layout for references, NonNull, Box, function pointers and NonZero \
+                     integers",
+                )
+            }
+            ("NonZero", Some((wrapped, _))) => Ok((wrapped, true)),
+            (wrapper, Some((mut pointee, _))) if POINTER_WRAPPERS.contains(&wrapper) => {
+                pointee.dimensions.clear();
+                pointee.pointer_depth += 1;
+                Ok((pointee, true))
+            }
+            (wrapper, Some(wrapped)) if TRANSPARENT_WRAPPERS.contains(&wrapper) => Ok(wrapped),
+            (_, Some(_)) => {
+                self.position = start;
+                self.error("generic types are not supported")
+            }
+            (zero_sized_type, None) if ZERO_SIZED_TYPES.contains(&zero_sized_type) => {
+                let mut member = named_member("", zero_sized_type);
+                member.dimensions.push(0);
+                Ok((member, false))
+            }
+            (_, None) => match integer_name {
+                Some(integer_name) => {
+                    Ok((named_member("", &integer_name), name.starts_with("NonZero")))
+                }
+                None => Ok((named_member("", &name), false)),
+            },
+        }
+    }
+
+    /// Parses an array length, an integer constant expression that can not be negative, e.g `SECTOR_SIZE * 2`.
+    fn parse_length(&mut self) -> Result<usize, ()> {
+        let start = self.position;
+
+        match self.evaluate_constant().map(usize::try_from) {
+            Some(Ok(length)) => Ok(length),
+            _ => {
+                self.position = start;
+                self.error("expected an array length that is an integer constant and not negative")
+            }
+        }
+    }
+
+    /// Replaces the type aliases used by the fields of each struct and union with the types they name, and refers to
+    /// structs and unions by their record.
+    fn resolve_members(&mut self) -> Result<(), ()> {
+        let mut records = std::mem::take(&mut self.header.records);
+        let record_names: HashSet<String> =
+            records.iter().map(|record| record.name.clone()).collect();
+
+        for record in records.iter_mut() {
+            for member in record.members.iter_mut() {
+                self.resolve_member(&record.name, member, &record_names)?;
+            }
+        }
+
+        self.header.records = records;
+
+        Ok(())
+    }
+
+    /// Replaces a type alias used as the type of `member` with the type it names, following aliases of aliases.
+    ///
+    /// Aliases of primitive types are kept and added to the header as typedefs, so their name is shown. A field whose
+    /// type is a struct or union that can not be converted is an error, unless it is a pointer to it.
+    fn resolve_member(
+        &mut self,
+        record_name: &str,
+        member: &mut c_parse::CMember,
+        record_names: &HashSet<String>,
+    ) -> Result<(), ()> {
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let name = match &member.type_spec {
+                c_parse::TypeSpecs::Named(name) => name.clone(),
+                _ => return Ok(()),
+            };
+
+            if record_names.contains(&name) {
+                member.type_spec = c_parse::TypeSpecs::Record(name);
+                return Ok(());
+            }
+
+            if let Some(reason) = self.unsupported.get(&name) {
+                if member.pointer_depth > 0 {
+                    return Ok(());
+                }
+
+                serror!(format!(
+                    "Field: {}.{}, has the type {}, which can not be converted because {}",
+                    record_name, member.name, name, reason
+                ));
+                return Err(());
+            }
+
+            self.position = match self.aliases.get(&name) {
+                Some(type_start) => *type_start,
+                None => return Ok(()),
+            };
+            let (alias, _) = self.parse_type()?;
+
+            if let (c_parse::TypeSpecs::Named(target), 0, true) = (
+                &alias.type_spec,
+                alias.pointer_depth,
+                alias.dimensions.is_empty(),
+            ) {
+                if PRIMITIVES.iter().any(|(primitive, _)| primitive == target) {
+                    if self.header.typedef(&name).is_none() {
+                        self.header.typedefs.push(named_member(&name, target));
+                    }
+                    return Ok(());
+                }
+            }
+
+            member.type_spec = alias.type_spec;
+            if member.pointer_depth == 0 {
+                member.dimensions.extend(alias.dimensions);
+            }
+            member.pointer_depth += alias.pointer_depth;
+        }
+
+        serror!(format!(
+            "Field: {}.{}, uses type aliases nested more than {} levels deep, an alias may refer to itself",
+            record_name, member.name, MAX_ALIAS_DEPTH
+        ));
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the (name, type, pointer depth, dimensions) of each member of the record `name`.
+    fn members(header: &c_parse::CHeader, name: &str) -> Vec<(String, String, usize, Vec<usize>)> {
+        header
+            .record(name)
+            .unwrap()
+            .members
+            .iter()
+            .map(|member| {
+                let type_name = match &member.type_spec {
+                    c_parse::TypeSpecs::Named(name) => name.clone(),
+                    c_parse::TypeSpecs::Record(name) => format!("record {}", name),
+                    c_parse::TypeSpecs::Enum(name) => format!("enum {}", name),
+                };
+                (
+                    member.name.clone(),
+                    type_name,
+                    member.pointer_depth,
+                    member.dimensions.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_records_with_a_defined_layout_are_parsed() {
+        let header = parse_rust_source(
+            "#[derive(Debug)]\n\
+             #[repr(C)]\n\
+             pub struct Header { pub magic: u32 }\n\
+             struct Native { value: u8 }\n\
+             #[repr(transparent)] struct Id(u64);\n\
+             #[repr(C)] union Value { number: u32, bytes: [u8; 4] }\n\
+             impl Header { fn new() -> Self { Header { magic: 0 } } }\n\
+             fn union() -> u8 { let union = 1; union }\n",
+        )
+        .unwrap();
+        let names: Vec<&str> = header
+            .records
+            .iter()
+            .map(|record| &record.name[..])
+            .collect();
+
+        assert_eq!(names, vec!["Header", "Id", "Value"]);
+        assert_eq!(
+            header.record("Value").unwrap().kind,
+            c_parse::RecordKinds::Union
+        );
+        assert_eq!(members(&header, "Id")[0].0, "0");
+    }
+
+    #[test]
+    fn arrays_pointers_and_wrappers_are_parsed() {
+        let header = parse_rust_source(
+            "const SECTOR_SIZE: usize = 0x200;\n\
+             const COUNT: usize = SECTOR_SIZE / 128 + 1;\n\
+             const RATIO: f32 = 1.5;\n\
+             #[repr(C)] struct Node {\n\
+                 data: [[u8; 2]; COUNT],\n\
+                 next: Option<NonNull<Node>>,\n\
+                 name: *const [u8; 4],\n\
+                 callback: Option<unsafe extern \"C\" fn(u32) -> u32>,\n\
+                 r#type: ManuallyDrop<core::ffi::c_int>,\n\
+                 id: NonZeroU16,\n\
+                 marker: PhantomData<&'static u8>,\n\
+             }\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            members(&header, "Node"),
+            vec![
+                (String::from("data"), String::from("u8"), 0, vec![5, 2]),
+                (String::from("next"), String::from("record Node"), 1, vec![]),
+                (String::from("name"), String::from("u8"), 1, vec![]),
+                (String::from("callback"), String::from("fn"), 1, vec![]),
+                (String::from("type"), String::from("c_int"), 0, vec![]),
+                (String::from("id"), String::from("u16"), 0, vec![]),
+                (
+                    String::from("marker"),
+                    String::from("PhantomData"),
+                    0,
+                    vec![0]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn repr_attributes_set_the_layout() {
+        let header = parse_rust_source(
+            "#[repr(C, packed(2))] struct Packed { a: u8, b: u32 }\n\
+             #[repr(C)]\n\
+             #[repr(align(16))]\n\
+             struct Aligned { a: u8 }\n\
+             #[repr(C, packed)] struct Tight { a: u8, b: u64 }\n",
+        )
+        .unwrap();
+
+        assert_eq!(header.record("Packed").unwrap().pack, Some(2));
+        assert_eq!(header.record("Aligned").unwrap().attributes.align, Some(16));
+        assert!(header.record("Tight").unwrap().attributes.packed);
+    }
+
+    #[test]
+    fn type_aliases_are_resolved() {
+        let header = parse_rust_source(
+            "type Sector = [u8; 512];\n\
+             type Handle = *mut Inner;\n\
+             type Lba = u64;\n\
+             #[repr(C)] struct Inner { a: u8 }\n\
+             #[repr(C)] struct Disk { boot: Sector, handle: Handle, start: Lba, inner: Inner }\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            members(&header, "Disk"),
+            vec![
+                (String::from("boot"), String::from("u8"), 0, vec![512]),
+                (
+                    String::from("handle"),
+                    String::from("record Inner"),
+                    1,
+                    vec![]
+                ),
+                (String::from("start"), String::from("Lba"), 0, vec![]),
+                (
+                    String::from("inner"),
+                    String::from("record Inner"),
+                    0,
+                    vec![]
+                ),
+            ]
+        );
+        assert!(header.typedef("Lba").is_some());
+    }
+
+    #[test]
+    fn types_without_a_defined_layout_are_an_error() {
+        assert!(parse_rust_source("#[repr(C)] struct A { b: Vec<u8> }").is_err());
+        assert!(parse_rust_source("#[repr(C)] struct A { b: (u8, u16) }").is_err());
+        assert!(parse_rust_source("#[repr(C)] struct A { b: Option<u32> }").is_err());
+        assert!(parse_rust_source("#[repr(C)] struct A { b: [u8] }").is_err());
+        assert!(parse_rust_source("#[repr(C)] struct A { b: [u8; 1.5] }").is_err());
+        assert!(parse_rust_source("struct B { c: u8 }\n#[repr(C)] struct A { b: B }").is_err());
+        assert!(
+            parse_rust_source("struct B { c: u8 }\n#[repr(C)] struct A { b: *const B }").is_ok()
+        );
+    }
+}
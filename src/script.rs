@@ -0,0 +1,262 @@
+//! Module for running user-defined formatter scripts written in Rhai.
+//!
+//! Scripts run in a sandboxed engine: there are no file, network or module imports and every script is limited in
+//! how many operations it can run and how much memory its strings, arrays and maps can use.
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Blob, Dynamic, Engine, Map, Scope, AST};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+
+/// Largest number of operations a script can run for a single field.
+const MAX_OPERATIONS: u64 = 1_000_000;
+/// Deepest function call nesting allowed in a script.
+const MAX_CALL_LEVELS: usize = 32;
+/// Largest string, in bytes, a script can create.
+const MAX_STRING_SIZE: usize = 64 * 1024;
+/// Largest array, blob or map a script can create.
+const MAX_COLLECTION_SIZE: usize = 64 * 1024;
+
+/// A sandboxed script engine along with the scripts compiled so far.
+pub struct ScriptEngine {
+    engine: Engine,
+    /// Compiled scripts keyed by filepath, so a script used by several fields is only compiled once.
+    compiled_scripts: RefCell<HashMap<String, AST>>,
+}
+
+/// The values a script can read besides the bytes of its own field.
+pub struct ScriptInput<'a> {
+    /// The name of the field being formatted.
+    pub field_name: &'a str,
+    /// The offset of the field in the binary.
+    pub field_offset: usize,
+    /// The raw bytes of every field in the structure.
+    pub sibling_bytes: &'a HashMap<String, Vec<u8>>,
+    /// The formatted values of the fields formatted before this one.
+    pub sibling_values: &'a HashMap<String, String>,
+}
+
+impl ScriptEngine {
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_string_size(MAX_STRING_SIZE)
+            .set_max_array_size(MAX_COLLECTION_SIZE)
+            .set_max_map_size(MAX_COLLECTION_SIZE)
+            .disable_symbol("eval")
+            .on_print(|text| eprintln!("[script] {}", text))
+            .on_debug(|text, _, _| eprintln!("[script] {}", text));
+
+        Self {
+            engine,
+            compiled_scripts: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Runs the script at `script_path` on the raw bytes of a field and converts its result into a display string.
+    ///
+    /// The script can read the following variables:
+    /// * `bytes` - the raw bytes of the field as a blob
+    /// * `name` and `offset` - the name and offset of the field
+    /// * `fields` - a map from every field name to a map holding its `bytes` and, if it has been formatted already,
+    ///   its formatted `value`
+    ///
+    /// A string result is displayed as is and a boolean result is displayed as `valid` or `invalid`. A map result is
+    /// displayed as its `value` entry, followed by `(invalid)` if its `valid` entry is false. The error message is
+    /// returned as `Err` if the script could not be read, compiled or run.
+    pub fn run(
+        &self,
+        script_path: &str,
+        field_bytes: &[u8],
+        input: &ScriptInput,
+    ) -> Result<String, String> {
+        let ast = self.compiled_script(script_path)?;
+
+        let mut fields = Map::new();
+
+        for (name, bytes) in input.sibling_bytes.iter() {
+            let mut field = Map::new();
+            field.insert("bytes".into(), Dynamic::from_blob(bytes.clone()));
+
+            if let Some(value) = input.sibling_values.get(name) {
+                field.insert("value".into(), value.clone().into());
+            }
+
+            fields.insert(name.into(), field.into());
+        }
+
+        let mut scope = Scope::new();
+        scope
+            .push_constant("bytes", Dynamic::from_blob(Blob::from(field_bytes)))
+            .push_constant("name", input.field_name.to_string())
+            .push_constant("offset", input.field_offset as i64)
+            .push_constant("fields", fields);
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &ast)
+            .map_err(|e| e.to_string())?;
+
+        Ok(display_result(result))
+    }
+
+    /// Gets the compiled script at `script_path`, reading and compiling it the first time it is used.
+    fn compiled_script(&self, script_path: &str) -> Result<AST, String> {
+        if let Some(ast) = self.compiled_scripts.borrow().get(script_path) {
+            return Ok(ast.clone());
+        }
+
+        let script = fs::read_to_string(script_path)
+            .map_err(|e| format!("Could not read script: {}, because {}", script_path, e))?;
+
+        let ast = self
+            .engine
+            .compile(&script)
+            .map_err(|e| format!("Could not compile script: {}, because {}", script_path, e))?;
+
+        self.compiled_scripts
+            .borrow_mut()
+            .insert(script_path.to_string(), ast.clone());
+
+        Ok(ast)
+    }
+}
+
+/// Converts the value returned by a script into a display string.
+fn display_result(result: Dynamic) -> String {
+    if result.is_bool() {
+        return match result.as_bool() {
+            Ok(true) => String::from("valid"),
+            _ => String::from("invalid"),
+        };
+    }
+
+    if result.is_map() {
+        let map = result.cast::<Map>();
+        let value = map
+            .get("value")
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+
+        return match map.get("valid").map(|valid| valid.as_bool()) {
+            Some(Ok(false)) => format!("{} (invalid)", value),
+            _ => value,
+        };
+    }
+
+    if result.is_unit() {
+        return String::from("");
+    }
+
+    result.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `script` to a file in the temp directory and returns its filepath.
+    fn script_file(name: &str, script: &str) -> String {
+        let script_path =
+            std::env::temp_dir().join(format!("memgram_{}_{}.rhai", std::process::id(), name));
+        fs::write(&script_path, script).unwrap();
+        script_path.to_string_lossy().into_owned()
+    }
+
+    fn run(name: &str, script: &str, field_bytes: &[u8]) -> Result<String, String> {
+        let mut sibling_bytes = HashMap::new();
+        sibling_bytes.insert(String::from("Length"), vec![0x03, 0x00]);
+        let mut sibling_values = HashMap::new();
+        sibling_values.insert(String::from("Length"), String::from("3"));
+
+        let input = ScriptInput {
+            field_name: "Name",
+            field_offset: 0x10,
+            sibling_bytes: &sibling_bytes,
+            sibling_values: &sibling_values,
+        };
+
+        let script_path = script_file(name, script);
+        let result = ScriptEngine::new().run(&script_path, field_bytes, &input);
+        fs::remove_file(&script_path).unwrap();
+        result
+    }
+
+    #[test]
+    fn scripts_read_the_field_and_its_siblings() {
+        assert_eq!(
+            run(
+                "siblings",
+                r#"`${name}@${offset}: ${bytes.len()} of ${fields.Length.value}`"#,
+                &[0x41, 0x42, 0x43]
+            ),
+            Ok(String::from("Name@16: 3 of 3"))
+        );
+    }
+
+    #[test]
+    fn readme_example_decodes_xored_bytes() {
+        let mut sibling_bytes = HashMap::new();
+        sibling_bytes.insert(String::from("Key"), vec![0x20]);
+
+        let input = ScriptInput {
+            field_name: "Name",
+            field_offset: 0,
+            sibling_bytes: &sibling_bytes,
+            sibling_values: &HashMap::new(),
+        };
+
+        let script_path = script_file(
+            "xor",
+            "let key = fields[\"Key\"].bytes[0];
+let decoded = blob();
+for i in 0..bytes.len() { decoded.push(bytes[i] ^ key); }
+decoded.as_string()",
+        );
+        let result = ScriptEngine::new().run(&script_path, &[0x61, 0x62], &input);
+        fs::remove_file(&script_path).unwrap();
+
+        assert_eq!(result, Ok(String::from("AB")));
+    }
+
+    #[test]
+    fn validation_results_are_displayed() {
+        assert_eq!(
+            run("bool", "bytes[0] == 0x4D", &[0x4D]),
+            Ok(String::from("valid"))
+        );
+        assert_eq!(
+            run("map", "#{ value: \"MZ\", valid: false }", &[0x00]),
+            Ok(String::from("MZ (invalid)"))
+        );
+    }
+
+    #[test]
+    fn scripts_are_sandboxed() {
+        assert!(run("loop", "loop { }", &[]).is_err());
+        assert!(run("import", "import \"std\" as std; 1", &[]).is_err());
+        assert!(run("eval", "eval(\"1\")", &[]).is_err());
+    }
+
+    #[test]
+    fn script_errors_are_returned() {
+        assert!(run("syntax", "let = ;", &[]).is_err());
+        assert!(ScriptEngine::new()
+            .run(
+                "./missing_script.rhai",
+                &[],
+                &ScriptInput {
+                    field_name: "Name",
+                    field_offset: 0,
+                    sibling_bytes: &HashMap::new(),
+                    sibling_values: &HashMap::new(),
+                }
+            )
+            .unwrap_err()
+            .starts_with("Could not read script"));
+    }
+}
@@ -1,13 +1,49 @@
-//! Module for converting a C struct to the grammar format.
+//! Module for converting a C struct, or a `#[repr(C)]` Rust struct, to the grammar format.
+use crate::arg_parse;
+use crate::c_parse;
+use crate::c_types;
+use crate::format;
+use crate::rust_convert;
+use crate::rust_parse;
 use std::fs;
 use std::io::prelude::*;
+use std::path::Path;
+
+/// Structs and unions nested deeper than this are assumed to contain themselves.
+const MAX_RECORD_DEPTH: usize = 32;
+/// Typedefs of typedefs nested deeper than this are assumed to refer to themselves.
+const MAX_TYPEDEF_DEPTH: usize = 32;
+/// Data type of the padding fields added between members to align them.
+const PADDING_TYPE: &str = "padding";
+
+/// The size and alignment of a type in bytes.
+struct Layout {
+    size: usize,
+    align: usize,
+    /// Where each member of a struct or union is placed, empty for other types.
+    members: Vec<Placement>,
+}
+
+/// The bytes a member of a struct or union covers.
+struct Placement {
+    offset: usize,
+    size: usize,
+    /// The offset in bits from the start of the record and the width of a bitfield.
+    bits: Option<(usize, usize)>,
+}
 
 /// Holds the contents of the parsed C struct fields and converted grammar contents.
 pub struct CStruct {
     /// Name of the C struct (Also name of grammar).
     pub name: String,
-    /// Vector of (C type, field_name) created by `parse_c_struct`.
-    pub fields: Vec<(String, String)>,
+    /// Every struct, union, enum and typedef in the C header, created by `parse_c_struct`.
+    header: c_parse::CHeader,
+    /// The target ABI giving the sizes and alignments of C types.
+    abi: &'static c_types::Abi,
+    /// Whether only the root and the records it uses are written as types, which is the case when `--root` is given.
+    only_reachable: bool,
+    /// Whether the struct was parsed from Rust source rather than a C header.
+    rust_source: bool,
     /// String containing the resulting grammar contents.
     pub grammar_contents: String,
 }
@@ -16,18 +52,22 @@ impl CStruct {
     pub fn new() -> Self {
         Self {
             name: String::from(""),
-            fields: Vec::new(),
+            header: c_parse::CHeader::new(),
+            abi: c_types::default_abi(),
+            only_reachable: false,
+            rust_source: false,
             grammar_contents: String::from(""),
         }
     }
 
-    /// Parses the contents of a file containing a C struct.
+    /// Parses the contents of the C header at `cmd_args.cstruct_filepath`, or of the Rust source if it is a `.rs` file.
     ///
-    /// A `Vector` of `(String, String)` is built in `self.fields` in the format (c type, field_name)
-    pub fn parse_c_struct(&mut self, struct_filepath: &str) -> Result<&mut Self, ()> {
-        let mut prev_index: usize = 0;
-        let mut next_index: usize = 0;
-
+    /// Every struct, union, enum and typedef in the file is parsed into `self.header` and types are laid out for the
+    /// target `cmd_args.abi`. Only the `#[repr(C)]` structs and unions of Rust source are parsed, as the layout of
+    /// other Rust types is left to the compiler. The struct converted is `cmd_args.root`, a struct or a typedef of one,
+    /// or the last top level struct if no root was given.
+    pub fn parse_c_struct(&mut self, cmd_args: &arg_parse::CMDArgParse) -> Result<&mut Self, ()> {
+        let struct_filepath = &cmd_args.cstruct_filepath;
         let struct_string: String = fs::read_to_string(struct_filepath).map_err(|e| {
             serror!(format!(
                 "Error opening file: {}, because:{}",
@@ -35,69 +75,69 @@ impl CStruct {
             ))
         })?;
 
-        prev_index += match struct_string[prev_index..].find("struct ") {
-            Some(matched_index) => matched_index + 7,
-            None => {
-                serror!("Invalid C struct: could not find 'struct' keyword");
-                return Err(());
-            }
-        };
-
-        next_index += match struct_string[prev_index..].find('{') {
-            Some(matched_index) => matched_index + prev_index,
-            None => {
-                serror!("Invalid C struct: could not find opening '{'");
-                return Err(());
-            }
+        self.rust_source = is_rust_source(struct_filepath);
+        self.header = if self.rust_source {
+            rust_parse::parse_rust_source(&struct_string)?
+        } else {
+            c_parse::parse_header(&struct_string)?
         };
+        self.abi = cmd_args.abi;
+        self.only_reachable = !cmd_args.root.is_empty();
 
-        let last_index: usize = match struct_string[next_index..].find("};") {
-            Some(matched_index) => matched_index + next_index,
-            None => {
-                serror!("Invalid C struct: could not find closing '};'");
-                return Err(());
+        self.name = if cmd_args.root.is_empty() {
+            match self
+                .header
+                .records
+                .iter()
+                .rev()
+                .find(|record| record.kind == c_parse::RecordKinds::Struct)
+            {
+                Some(record) => record.name.clone(),
+                None => {
+                    serror!("Invalid C struct: could not find a struct definition");
+                    return Err(());
+                }
             }
+        } else {
+            self.root_name(&cmd_args.root)?
         };
 
-        let struct_name: &str = &struct_string[prev_index..next_index];
-
-        self.name = struct_name.trim().to_string();
-
-        let words = struct_string[next_index + 1..last_index].split_ascii_whitespace();
+        Ok(self)
+    }
 
-        let mut entry_num = 0;
+    /// Get's the name of the record called `root`, which may be the name of a struct or of a typedef of one.
+    fn root_name(&self, root: &str) -> Result<String, ()> {
+        if self.header.record(root).is_some() {
+            return Ok(root.to_string());
+        }
 
-        for word in words {
-            if word.ends_with(';') {
-                match self.fields.get_mut(entry_num) {
-                    Some(value) => {
-                        value.1.push_str(&word[..word.len() - 1]);
-                        entry_num += 1;
-                    }
-                    None => {
-                        serror!(format!(
-                            "Type must be specified in C struct before field name: {}",
-                            word
-                        ));
-                        return Err(());
-                    }
-                }
-            } else {
-                match self.fields.get_mut(entry_num) {
-                    Some(value) => {
-                        value.0.push_str(" ");
-                        value.0.push_str(word)
-                    }
-                    None => self
-                        .fields
-                        .insert(entry_num, (word.to_string(), "".to_string())),
-                }
+        if let Some(typedef) = self.header.typedef(root) {
+            if let (c_parse::TypeSpecs::Record(name), 0, true) = (
+                &typedef.type_spec,
+                typedef.pointer_depth,
+                typedef.dimensions.is_empty(),
+            ) {
+                return Ok(name.clone());
             }
         }
-        Ok(self)
+
+        serror!(format!(
+            "Root: {}, is not a struct or union in the header, must be one of the following ({})",
+            root,
+            self.header
+                .records
+                .iter()
+                .map(|record| &record.name[..])
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ));
+        Err(())
     }
 
     /// Builds the contents of the output grammar file line by line.
+    ///
+    /// The fields of the root struct are written as `[[fields]]`, followed by every struct and union in the header as
+    /// `[[types]]`. When a root was picked with `--root`, only the root and the structs and unions it uses are written.
     pub fn build_grammar_contents(&mut self) -> Result<&mut Self, ()> {
         self.grammar_contents.push_str("[metadata]\r\n");
         self.grammar_contents
@@ -106,22 +146,554 @@ impl CStruct {
             .push_str("\tvariable_size_fields = [['','','','']]\r\n");
         self.grammar_contents
             .push_str("\tmultiply_fields = [['','']]\r\n");
+        self.grammar_contents
+            .push_str(&format!("\tabi = '{}'\r\n", self.abi.name));
+        self.grammar_contents
+            .push_str(&format!("\tendian = '{}'\r\n", self.abi.endian.name()));
+
+        let root = self.record(&self.name)?;
+        let mut contents = self.record_contents("fields", root)?;
+
+        let mut used_records: Vec<&str> = vec![&root.name];
+        self.collect_used_records(root, &mut used_records, 0)?;
 
-        for field in self.fields.iter() {
-            self.grammar_contents.push_str("\r\n[[fields]]\r\n");
-            self.grammar_contents
-                .push_str(&format!("\tname = '{}'\r\n", field.1));
-            self.grammar_contents
-                .push_str(&format!("\tsize = {}\r\n", get_field_size(&field.0)?));
-            self.grammar_contents
-                .push_str(&format!("\tdata_type = '{}'\r\n", field.0));
-            self.grammar_contents
-                .push_str("\tdisplay_format = 'hex'\r\n");
-            self.grammar_contents.push_str("\tdescription = 'N/A'\r\n");
+        for record in self.header.records.iter() {
+            if self.only_reachable && !used_records.contains(&&record.name[..]) {
+                continue;
+            }
+
+            contents.push_str("\r\n[[types]]\r\n");
+            contents.push_str(&format!("\tname = '{}'\r\n", record.name));
+            contents.push_str(&format!("\tkind = '{}'\r\n", record.kind.keyword()));
+            contents.push_str(&format!(
+                "\tsize = {:#04X}\r\n",
+                self.record_layout(&record.name, 0)?.size
+            ));
+            contents.push_str(&self.record_contents("types.fields", record)?);
         }
+
+        self.grammar_contents.push_str(&contents);
+
         Ok(self)
     }
 
+    /// Finds the struct or union called `name`.
+    fn record(&self, name: &str) -> Result<&c_parse::CRecord, ()> {
+        self.header.record(name).ok_or_else(|| {
+            serror!(format!("Struct or union: {}, is not defined", name));
+        })
+    }
+
+    /// Adds the names of the structs and unions used by `record`, directly or through other records, to `used_records`.
+    fn collect_used_records<'a>(
+        &'a self,
+        record: &'a c_parse::CRecord,
+        used_records: &mut Vec<&'a str>,
+        depth: usize,
+    ) -> Result<(), ()> {
+        check_depth(&record.name, depth)?;
+
+        for member in record.members.iter() {
+            if let (c_parse::TypeSpecs::Record(name), false) =
+                (&member.type_spec, member.pointer_depth > 0)
+            {
+                if !used_records.contains(&&name[..]) {
+                    used_records.push(name);
+                    self.collect_used_records(self.record(name)?, used_records, depth + 1)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the grammar entries of the members of `record` in the array of tables `table`.
+    ///
+    /// Padding fields are added between the members of a struct and at its end, so the offset of each field and the
+    /// size of the struct match the compiler. Bitfields sharing bytes become one field with a bit range for each.
+    fn record_contents(&self, table: &str, record: &c_parse::CRecord) -> Result<String, ()> {
+        let layout = self.record_layout(&record.name, 0)?;
+        let padded = record.kind == c_parse::RecordKinds::Struct;
+        let mut contents = String::new();
+        let mut padding_count = 0;
+        let mut end = 0;
+        // Bitfields waiting to be written, with the bytes they share.
+        let mut bitfields: Vec<(&c_parse::CMember, usize, usize)> = Vec::new();
+        let mut bitfields_start = 0;
+        let mut bitfields_end = 0;
+
+        for (member, placement) in record.members.iter().zip(layout.members.iter()) {
+            // Unnamed bitfields only take up space, which is covered by padding.
+            if placement.bits.is_some() && member.name.is_empty() {
+                continue;
+            }
+
+            if let Some((bit, width)) = placement.bits {
+                if !bitfields.is_empty() && placement.offset < bitfields_end {
+                    bitfields_end = bitfields_end.max(placement.offset + placement.size);
+                    bitfields.push((member, bit, width));
+                    continue;
+                }
+            }
+
+            if !bitfields.is_empty() {
+                contents.push_str(&self.bitfield_contents(
+                    table,
+                    &bitfields,
+                    bitfields_start,
+                    bitfields_end,
+                )?);
+                end = end.max(bitfields_end);
+                bitfields.clear();
+            }
+
+            if padded && placement.offset > end {
+                padding_count += 1;
+                contents.push_str(&padding_contents(
+                    table,
+                    padding_count,
+                    placement.offset - end,
+                ));
+            }
+
+            match placement.bits {
+                Some((bit, width)) => {
+                    bitfields_start = placement.offset;
+                    bitfields_end = placement.offset + placement.size;
+                    bitfields.push((member, bit, width));
+                }
+                None => {
+                    contents.push_str(&self.field_contents(table, member)?);
+                    end = end.max(placement.offset + placement.size);
+                }
+            }
+        }
+
+        if !bitfields.is_empty() {
+            contents.push_str(&self.bitfield_contents(
+                table,
+                &bitfields,
+                bitfields_start,
+                bitfields_end,
+            )?);
+            end = end.max(bitfields_end);
+        }
+
+        if padded && layout.size > end {
+            padding_count += 1;
+            contents.push_str(&padding_contents(table, padding_count, layout.size - end));
+        }
+
+        Ok(contents)
+    }
+
+    /// Get's the layout of the struct or union called `name`.
+    ///
+    /// Each member is placed at the next offset that is a multiple of its alignment, or at offset 0 in a union. The
+    /// alignment of the record is the largest alignment of its members and its size is rounded up to a multiple of it.
+    ///
+    /// Bitfields are allocated following the rules of the ABI. By default, as in GCC, a bitfield is placed at the next
+    /// free bit unless it would span more units of its type's alignment than its type does, in which case it moves to
+    /// the next unit. With Microsoft bitfields, consecutive bitfields share a unit of their type's size as long as
+    /// they have the same size and fit, otherwise a new unit is started.
+    fn record_layout(&self, name: &str, depth: usize) -> Result<Layout, ()> {
+        check_depth(name, depth)?;
+
+        let record = self.record(name)?;
+        let mut layout = Layout {
+            size: 0,
+            align: 1,
+            members: Vec::new(),
+        };
+        // The next free bit for a bitfield, and the offset and size of the current Microsoft bitfield unit.
+        let mut end_bits = 0;
+        let mut unit: Option<(usize, usize)> = None;
+
+        for member in record.members.iter() {
+            let element = self.element_layout(member, depth)?;
+            let align = member_alignment(record, member, element.align);
+
+            let placement = match (member.bit_width, &record.kind) {
+                (Some(width), _) => {
+                    check_bitfield(member, width, element.size)?;
+
+                    if width > 0 && (!member.name.is_empty() || self.abi.ms_bitfields) {
+                        layout.align = layout.align.max(align);
+                    }
+
+                    match &record.kind {
+                        c_parse::RecordKinds::Union => Placement {
+                            offset: 0,
+                            size: if self.abi.ms_bitfields {
+                                element.size
+                            } else {
+                                width.div_ceil(8)
+                            },
+                            bits: Some((0, width)),
+                        },
+                        c_parse::RecordKinds::Struct if self.abi.ms_bitfields => {
+                            let fits = match unit {
+                                Some((offset, size)) => {
+                                    size == element.size && end_bits + width <= (offset + size) * 8
+                                }
+                                None => false,
+                            };
+
+                            if width == 0 {
+                                unit = None;
+                                Placement {
+                                    offset: layout.size,
+                                    size: 0,
+                                    bits: Some((layout.size * 8, 0)),
+                                }
+                            } else {
+                                if !fits {
+                                    let offset = align_up(layout.size, align);
+                                    unit = Some((offset, element.size));
+                                    end_bits = offset * 8;
+                                }
+
+                                let (offset, size) = unit.unwrap_or_default();
+                                let bit = end_bits;
+                                end_bits += width;
+
+                                Placement {
+                                    offset,
+                                    size,
+                                    bits: Some((bit, width)),
+                                }
+                            }
+                        }
+                        c_parse::RecordKinds::Struct => {
+                            let unit_bits = element.align * 8;
+                            let packed = record.attributes.packed
+                                || member.attributes.packed
+                                || record.pack.is_some();
+
+                            let straddles = !packed
+                                && (end_bits % unit_bits + width).div_ceil(unit_bits)
+                                    > (element.size * 8).div_ceil(unit_bits);
+
+                            if width == 0 || straddles {
+                                end_bits = align_up(end_bits, unit_bits);
+                            }
+
+                            let bit = end_bits;
+                            end_bits += width;
+
+                            Placement {
+                                offset: bit / 8,
+                                size: end_bits.div_ceil(8) - bit / 8,
+                                bits: Some((bit, width)),
+                            }
+                        }
+                    }
+                }
+                (None, kind) => {
+                    let offset = match kind {
+                        c_parse::RecordKinds::Struct => align_up(layout.size, align),
+                        c_parse::RecordKinds::Union => 0,
+                    };
+                    unit = None;
+                    layout.align = layout.align.max(align);
+
+                    Placement {
+                        offset,
+                        size: element.size * member.dimensions.iter().product::<usize>(),
+                        bits: None,
+                    }
+                }
+            };
+
+            layout.size = layout.size.max(placement.offset + placement.size);
+            if placement.bits.is_none() {
+                end_bits = layout.size * 8;
+            }
+            layout.members.push(placement);
+        }
+
+        layout.align = layout.align.max(record.attributes.align.unwrap_or(1));
+        layout.size = align_up(layout.size, layout.align);
+
+        Ok(layout)
+    }
+
+    /// Get's the layout of a single array element of a member.
+    fn element_layout(&self, member: &c_parse::CMember, depth: usize) -> Result<Layout, ()> {
+        let (size, align) = if member.pointer_depth > 0 {
+            (
+                self.abi.pointer_size,
+                self.abi.pointer_size.min(self.abi.max_align),
+            )
+        } else {
+            match &member.type_spec {
+                c_parse::TypeSpecs::Named(type_name) => {
+                    let field_type = self.get_field_type(type_name)?;
+                    (field_type.size, field_type.align)
+                }
+                c_parse::TypeSpecs::Record(name) => return self.record_layout(name, depth + 1),
+                c_parse::TypeSpecs::Enum(_) => (
+                    self.abi.enum_size,
+                    self.abi.enum_size.min(self.abi.max_align),
+                ),
+            }
+        };
+
+        Ok(Layout {
+            size,
+            align,
+            members: Vec::new(),
+        })
+    }
+
+    /// Builds the grammar entry of a member in the array of tables `table`.
+    ///
+    /// Arrays of text or single bytes become one field, other arrays become a field with a `count`. Structs and unions
+    /// become a field with a `type`.
+    fn field_contents(&self, table: &str, member: &c_parse::CMember) -> Result<String, ()> {
+        let element_count: usize = member.dimensions.iter().product();
+        let dimensions: String = member
+            .dimensions
+            .iter()
+            .map(|length| format!("[{}]", length))
+            .collect();
+
+        let pointers = "*".repeat(member.pointer_depth);
+
+        let (mut size, mut data_type, display_format, type_name) =
+            match (&member.type_spec, member.pointer_depth > 0) {
+                (c_parse::TypeSpecs::Named(type_name), true) => (
+                    self.abi.pointer_size,
+                    format!("{} {}", type_name, pointers),
+                    c_types::native_format(format::HEXLE_TYPE, self.abi.endian),
+                    None,
+                ),
+                (c_parse::TypeSpecs::Named(type_name), false) => {
+                    let field_type = self.get_field_type(type_name)?;
+                    (
+                        field_type.size,
+                        type_name.clone(),
+                        field_type.display_format,
+                        None,
+                    )
+                }
+                // A pointer may be to an opaque record that is only declared, e.g `struct Fwd *p;`.
+                (c_parse::TypeSpecs::Record(name), true) => (
+                    self.abi.pointer_size,
+                    format!(
+                        "{} {} {}",
+                        self.header
+                            .record(name)
+                            .map_or("struct", |record| record.kind.keyword()),
+                        name,
+                        pointers
+                    ),
+                    c_types::native_format(format::HEXLE_TYPE, self.abi.endian),
+                    None,
+                ),
+                (c_parse::TypeSpecs::Record(name), false) => (
+                    self.record_layout(name, 0)?.size,
+                    format!("{} {}", self.record(name)?.kind.keyword(), name),
+                    format::HEX_TYPE,
+                    Some(name),
+                ),
+                (c_parse::TypeSpecs::Enum(tag), pointer) => (
+                    if pointer {
+                        self.abi.pointer_size
+                    } else {
+                        self.abi.enum_size
+                    },
+                    format!("enum {} {}", tag, pointers)
+                        .split_ascii_whitespace()
+                        .collect::<Vec<&str>>()
+                        .join(" "),
+                    c_types::native_format(
+                        if pointer {
+                            format::HEXLE_TYPE
+                        } else {
+                            format::INT_TYPE
+                        },
+                        self.abi.endian,
+                    ),
+                    None,
+                ),
+            };
+
+        let mut count = None;
+
+        if !member.dimensions.is_empty() {
+            if type_name.is_none() && (format::is_text_format(display_format) || size == 1) {
+                size *= element_count;
+                data_type.push_str(&dimensions);
+            } else {
+                count = Some(element_count);
+            }
+        }
+
+        // Arrays of single bytes are shown as one hex field rather than as integers.
+        let display_format = if count.is_none()
+            && !member.dimensions.is_empty()
+            && !format::is_text_format(display_format)
+        {
+            format::HEX_TYPE
+        } else {
+            display_format
+        };
+
+        let mut contents = format!("\r\n[[{}]]\r\n", table);
+        contents.push_str(&format!("\tname = '{}'\r\n", member.name));
+        contents.push_str(&format!("\tsize = {:#04X}\r\n", size));
+        if let Some(count) = count {
+            contents.push_str(&format!("\tcount = {}\r\n", count));
+        }
+        if let Some(type_name) = type_name {
+            contents.push_str(&format!("\ttype = '{}'\r\n", type_name));
+        }
+        contents.push_str(&format!("\tdata_type = '{}'\r\n", data_type));
+        contents.push_str(&format!("\tdisplay_format = '{}'\r\n", display_format));
+        contents.push_str(&format!(
+            "\tdescription = '{}'\r\n",
+            self.description(member)
+        ));
+
+        Ok(contents)
+    }
+
+    /// Builds the grammar entry of `bitfields` sharing the bytes from `start` to `end` in the array of tables `table`.
+    ///
+    /// The bytes become one field with the `bits` display format and a bit range for each bitfield, given by its
+    /// offset in bits from the start of the record and its width.
+    fn bitfield_contents(
+        &self,
+        table: &str,
+        bitfields: &[(&c_parse::CMember, usize, usize)],
+        start: usize,
+        end: usize,
+    ) -> Result<String, ()> {
+        let (first, _, _) = bitfields[0];
+        let (last, _, _) = bitfields[bitfields.len() - 1];
+        let size = end - start;
+
+        if size > 8 {
+            serror!(format!(
+                "Bitfield members: {} to {}, share more than 8 bytes",
+                first.name, last.name
+            ));
+            return Err(());
+        }
+
+        let (name, description) = if bitfields.len() == 1 {
+            (first.name.clone(), self.description(first))
+        } else {
+            (
+                format!("{}_to_{}", first.name, last.name),
+                String::from("N/A"),
+            )
+        };
+
+        let data_type = match &first.type_spec {
+            c_parse::TypeSpecs::Named(type_name) => type_name.clone(),
+            c_parse::TypeSpecs::Enum(tag) => format!("enum {}", tag).trim_end().to_string(),
+            c_parse::TypeSpecs::Record(name) => name.clone(),
+        };
+
+        let display_format = match self.abi.endian {
+            c_types::Endians::Little => format::BITS_TYPE,
+            c_types::Endians::Big => format::BITSBE_TYPE,
+        };
+
+        let mut contents = format!("\r\n[[{}]]\r\n", table);
+        contents.push_str(&format!("\tname = '{}'\r\n", name));
+        contents.push_str(&format!("\tsize = {:#04X}\r\n", size));
+        contents.push_str(&format!("\tdata_type = '{}'\r\n", data_type));
+        contents.push_str(&format!("\tdisplay_format = '{}'\r\n", display_format));
+        contents.push_str(&format!("\tdescription = '{}'\r\n", description));
+
+        for (member, bit, width) in bitfields.iter() {
+            // Bits are counted up from the least significant bit of the field, which is the first bit allocated on a
+            // little endian ABI and the last on a big endian one.
+            let bit = bit - start * 8;
+            let offset = match self.abi.endian {
+                c_types::Endians::Little => bit,
+                c_types::Endians::Big => size * 8 - bit - width,
+            };
+
+            contents.push_str(&format!("\r\n[[{}.bits]]\r\n", table));
+            contents.push_str(&format!("\tname = '{}'\r\n", member.name));
+            contents.push_str(&format!("\toffset = {}\r\n", offset));
+            contents.push_str(&format!("\twidth = {}\r\n", width));
+            if self.is_signed(member)? {
+                contents.push_str("\tsigned = true\r\n");
+            }
+        }
+
+        Ok(contents)
+    }
+
+    /// Get's whether the integer or enum type of a bitfield is signed.
+    ///
+    /// Enums are signed with Microsoft bitfields, otherwise only if they have a negative enumerator.
+    fn is_signed(&self, member: &c_parse::CMember) -> Result<bool, ()> {
+        Ok(match &member.type_spec {
+            c_parse::TypeSpecs::Named(type_name) => {
+                let display_format = self.get_field_type(type_name)?.display_format;
+                display_format == format::INT_TYPE || display_format == format::INTBE_TYPE
+            }
+            c_parse::TypeSpecs::Enum(tag) => {
+                self.abi.ms_bitfields
+                    || self
+                        .header
+                        .enum_definition(tag)
+                        .is_some_and(|enum_definition| {
+                            enum_definition.values.iter().any(|(_, value)| *value < 0)
+                        })
+            }
+            c_parse::TypeSpecs::Record(_) => false,
+        })
+    }
+
+    /// Get's the field size, alignment and default display format based on the C basic type or typedef.
+    ///
+    /// Typedefs in the header are followed to the type they name, otherwise the type is looked up in the known
+    /// typedefs.
+    fn get_field_type(&self, field_type: &str) -> Result<c_types::CType, ()> {
+        let mut type_name = field_type;
+
+        for _ in 0..MAX_TYPEDEF_DEPTH {
+            match self.header.typedef(type_name) {
+                Some(typedef) => match &typedef.type_spec {
+                    c_parse::TypeSpecs::Named(named) if named != type_name => type_name = named,
+                    _ => break,
+                },
+                None => break,
+            }
+        }
+
+        c_types::lookup(type_name, self.abi).ok_or_else(|| {
+            serror!(format!("Type: {}, is not supported", field_type));
+        })
+    }
+
+    /// Get's the description of a member, the enumerators of an enum or `N/A` for other types.
+    fn description(&self, member: &c_parse::CMember) -> String {
+        let enum_definition = match &member.type_spec {
+            c_parse::TypeSpecs::Enum(tag) if member.pointer_depth == 0 => {
+                self.header.enum_definition(tag)
+            }
+            _ => None,
+        };
+
+        match enum_definition {
+            Some(enum_definition) if !enum_definition.values.is_empty() => enum_definition
+                .values
+                .iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect::<Vec<String>>()
+                .join(", "),
+            _ => String::from("N/A"),
+        }
+    }
+
     /// Writes the newly created grammar contents in `self.grammar_contents` to a toml file specified by `output_path`.
     pub fn write_grammar_file(&mut self, output_path: &str) -> Result<&mut Self, ()> {
         let mut grammar_file = match fs::File::create(output_path) {
@@ -138,8 +710,10 @@ impl CStruct {
         match grammar_file.write_all(self.grammar_contents.as_bytes()) {
             Ok(_) => {
                 println!(
-                    "[+] Successfully converted C struct {} to grammar file {}",
-                    self.name, output_path
+                    "[+] Successfully converted {} struct {} to grammar file {}",
+                    if self.rust_source { "Rust" } else { "C" },
+                    self.name,
+                    output_path
                 );
                 Ok(self)
             }
@@ -154,30 +728,452 @@ impl CStruct {
     }
 }
 
-/// Get's the field size based on the C basic type.
+/// Get's the alignment of a member of `record` whose type is aligned to `natural_align`.
 ///
-/// This assumes that a char is 1 byte and an int is 4 bytes etc.
-/// This may not allways be the case!
-fn get_field_size(field_type: &str) -> Result<&str, ()> {
-    let l_field_type = field_type.to_lowercase();
-    match &l_field_type[..] {
-        "char" | "signed char" | "unsigned char" => Ok("0x01"),
-        "short" | "short int" | "signed short" | "signed short int" | "unsigned short"
-        | "unsigned short int" => Ok("0x02"),
-        "float" | "int" | "signed" | "signed int" | "unsigned" | "unsigned int" | "long"
-        | "long int" | "signed long" | "signed long int" | "unsigned long"
-        | "unsigned long int" => Ok("0x04"),
-        "double"
-        | "long long"
-        | "long long int"
-        | "signed long long"
-        | "signed long long int"
-        | "unsigned long long"
-        | "unsigned long long int" => Ok("0x08"),
-        "long double" => Ok("0x10"),
-        _ => {
-            serror!(format!("Type: {}, is not supported", l_field_type));
-            Err(())
+/// Packed records and members are aligned to 1 byte and `#pragma pack(n)` lowers the alignment to at most `n`. An
+/// explicit alignment such as `aligned(n)` is applied last and can only raise the alignment.
+fn member_alignment(
+    record: &c_parse::CRecord,
+    member: &c_parse::CMember,
+    natural_align: usize,
+) -> usize {
+    let mut align = if record.attributes.packed || member.attributes.packed {
+        1
+    } else {
+        natural_align
+    };
+
+    if let Some(pack) = record.pack {
+        align = align.min(pack);
+    }
+
+    align.max(member.attributes.align.unwrap_or(1))
+}
+
+/// Checks that a bitfield `width` bits wide is an integer or enum, that is not an array, at most `size` bytes long.
+fn check_bitfield(member: &c_parse::CMember, width: usize, size: usize) -> Result<(), ()> {
+    if member.pointer_depth > 0
+        || !member.dimensions.is_empty()
+        || matches!(member.type_spec, c_parse::TypeSpecs::Record(_))
+    {
+        serror!(format!(
+            "Bitfield member: {}, must be an integer or enum",
+            member.name
+        ));
+        return Err(());
+    }
+
+    if width > size * 8 || size > 8 {
+        serror!(format!(
+            "Bitfield member: {}, is {} bits wide but its type is {} bytes",
+            member.name, width, size
+        ));
+        return Err(());
+    }
+
+    Ok(())
+}
+
+/// Rounds `offset` up to the next multiple of `align`.
+fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align.max(1)) * align.max(1)
+}
+
+/// Builds the grammar entry of the `number`th padding field of a struct, `size` bytes long.
+pub fn padding_contents(table: &str, number: usize, size: usize) -> String {
+    let mut contents = format!("\r\n[[{}]]\r\n", table);
+    contents.push_str(&format!("\tname = '_padding{}'\r\n", number));
+    contents.push_str(&format!("\tsize = {:#04X}\r\n", size));
+    contents.push_str(&format!("\tdata_type = '{}'\r\n", PADDING_TYPE));
+    contents.push_str(&format!("\tdisplay_format = '{}'\r\n", format::HEX_TYPE));
+    contents.push_str("\tdescription = 'Alignment padding'\r\n");
+
+    contents
+}
+
+/// Whether the file at `path` is Rust source rather than a C header, given by its `.rs` extension.
+fn is_rust_source(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(rust_convert::RUST_EXTENSION))
+}
+
+/// Checks that records are not nested deeper than `MAX_RECORD_DEPTH`, which happens when a record contains itself.
+fn check_depth(name: &str, depth: usize) -> Result<(), ()> {
+    if depth > MAX_RECORD_DEPTH {
+        serror!(format!(
+            "Struct or union: {}, is nested more than {} levels deep, it may contain itself",
+            name, MAX_RECORD_DEPTH
+        ));
+        return Err(());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Converts the C source `source` for the default ABI by writing it to a temporary file called `name`.
+    fn convert(name: &str, source: &str) -> Result<String, ()> {
+        convert_with(name, source, c_types::DEFAULT_ABI, "")
+    }
+
+    /// Converts the C source `source` for the ABI called `abi` with the root struct `root`, by writing it to a
+    /// temporary file called `name`, which is Rust source if `name` has the `.rs` extension.
+    fn convert_with(name: &str, source: &str, abi: &str, root: &str) -> Result<String, ()> {
+        let file_name = match Path::new(name).extension() {
+            Some(_) => format!("memgram_struct_convert_{}", name),
+            None => format!("memgram_struct_convert_{}.h", name),
+        };
+        let struct_path = env::temp_dir().join(file_name);
+        fs::write(&struct_path, source).unwrap();
+
+        let mut cmd_args = arg_parse::CMDArgParse::new();
+        cmd_args.cstruct_filepath = struct_path.to_str().unwrap().to_string();
+        cmd_args.abi = c_types::find_abi(abi).unwrap();
+        cmd_args.root = root.to_string();
+
+        let mut c_struct = CStruct::new();
+        let grammar_contents = c_struct
+            .parse_c_struct(&cmd_args)
+            .and_then(|c_struct| c_struct.build_grammar_contents())
+            .map(|c_struct| c_struct.grammar_contents.clone());
+
+        fs::remove_file(&struct_path).unwrap();
+        grammar_contents
+    }
+
+    #[test]
+    fn typedef_fields_get_their_size_and_display_format() {
+        let grammar_contents = convert(
+            "typedefs",
+            "struct Header { DWORD Magic; FILETIME Created; };",
+        )
+        .unwrap();
+
+        assert!(grammar_contents.contains(
+            "\tname = 'Magic'\r\n\tsize = 0x04\r\n\tdata_type = 'DWORD'\r\n\tdisplay_format = 'uint'\r\n"
+        ));
+        assert!(grammar_contents.contains(
+            "\tname = 'Created'\r\n\tsize = 0x08\r\n\tdata_type = 'FILETIME'\r\n\tdisplay_format = 'filetime'\r\n"
+        ));
+    }
+
+    #[test]
+    fn unknown_types_are_an_error() {
+        assert!(convert("unknown", "struct Header { MYTYPE Value; };").is_err());
+    }
+
+    #[test]
+    fn arrays_and_nested_records_become_counts_and_types() {
+        let grammar_contents = convert(
+            "nested",
+            "struct Inner { short a; char b[2]; };\n\
+             struct Outer { struct Inner items[3]; char name[8]; int values[4]; union { int i; char c; } u; };",
+        )
+        .unwrap();
+
+        assert!(grammar_contents.contains("\tname = 'Outer'\r\n"));
+        assert!(grammar_contents.contains(
+            "\tname = 'items'\r\n\tsize = 0x04\r\n\tcount = 3\r\n\ttype = 'Inner'\r\n\tdata_type = 'struct Inner'\r\n"
+        ));
+        assert!(grammar_contents.contains(
+            "\tname = 'name'\r\n\tsize = 0x08\r\n\tdata_type = 'char[8]'\r\n\tdisplay_format = 'ascii'\r\n"
+        ));
+        assert!(grammar_contents.contains(
+            "\tname = 'values'\r\n\tsize = 0x04\r\n\tcount = 4\r\n\tdata_type = 'int'\r\n"
+        ));
+        assert!(grammar_contents
+            .contains("[[types]]\r\n\tname = 'Inner'\r\n\tkind = 'struct'\r\n\tsize = 0x04\r\n"));
+        assert!(grammar_contents.contains(
+            "[[types]]\r\n\tname = 'Outer_union1'\r\n\tkind = 'union'\r\n\tsize = 0x04\r\n"
+        ));
+    }
+
+    #[test]
+    fn pointers_to_opaque_records_are_pointer_sized() {
+        let grammar_contents =
+            convert("opaque", "struct Node { struct Fwd *next; int value; };").unwrap();
+
+        assert!(grammar_contents.contains(
+            "\tname = 'next'\r\n\tsize = 0x08\r\n\tdata_type = 'struct Fwd *'\r\n\tdisplay_format = 'hexle'\r\n"
+        ));
+        assert!(!grammar_contents.contains("\tname = 'Fwd'"));
+    }
+
+    #[test]
+    fn records_that_contain_themselves_are_an_error() {
+        assert!(convert("recursive", "struct Loop { struct Loop inner; };").is_err());
+    }
+
+    /// Gets the size of the record `name` in the C source `source` and the (offset, size) of each of its members.
+    fn layout(source: &str, name: &str) -> (usize, Vec<(usize, usize)>) {
+        header_layout(c_parse::parse_header(source).unwrap(), name)
+    }
+
+    /// Lays out the record `name` of `header` for the default ABI as (size, [(offset, size)]).
+    fn header_layout(header: c_parse::CHeader, name: &str) -> (usize, Vec<(usize, usize)>) {
+        let mut c_struct = CStruct::new();
+        c_struct.header = header;
+        let layout = c_struct.record_layout(name, 0).unwrap();
+
+        (
+            layout.size,
+            layout
+                .members
+                .iter()
+                .map(|placement| (placement.offset, placement.size))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn members_are_aligned_and_padded() {
+        assert_eq!(
+            layout("struct a { char c; int i; short s; };", "a"),
+            (12, vec![(0, 1), (4, 4), (8, 2)])
+        );
+        assert_eq!(
+            layout(
+                "struct a { char c; double d; }; struct b { char c; struct a inner; };",
+                "b"
+            ),
+            (24, vec![(0, 1), (8, 16)])
+        );
+        assert_eq!(
+            layout("union u { char c[5]; int i; };", "u"),
+            (8, vec![(0, 5), (0, 4)])
+        );
+    }
+
+    #[test]
+    fn padding_fields_are_added_to_the_grammar() {
+        let grammar_contents = convert("padding", "struct a { char c; int i; short s; };").unwrap();
+
+        assert!(grammar_contents.contains(
+            "\tname = '_padding1'\r\n\tsize = 0x03\r\n\tdata_type = 'padding'\r\n\tdisplay_format = 'hex'\r\n"
+        ));
+        assert!(grammar_contents.contains("\tname = '_padding2'\r\n\tsize = 0x02\r\n"));
+    }
+
+    #[test]
+    fn pragma_pack_limits_alignment() {
+        let source = "#pragma pack(push, 2)\nstruct a { char c; int i; };\n#pragma pack(pop)\n\
+                      struct b { char c; int i; };";
+
+        assert_eq!(layout(source, "a"), (6, vec![(0, 1), (2, 4)]));
+        assert_eq!(layout(source, "b"), (8, vec![(0, 1), (4, 4)]));
+    }
+
+    #[test]
+    fn packed_and_aligned_attributes_change_the_layout() {
+        assert_eq!(
+            layout("struct __attribute__((packed)) a { char c; int i; };", "a"),
+            (5, vec![(0, 1), (1, 4)])
+        );
+        assert_eq!(
+            layout(
+                "struct a { char c; int i __attribute__((aligned(16))); };",
+                "a"
+            ),
+            (32, vec![(0, 1), (16, 4)])
+        );
+        assert_eq!(
+            layout("struct a { char c; alignas(8) char d; };", "a"),
+            (16, vec![(0, 1), (8, 1)])
+        );
+        assert_eq!(
+            layout("__declspec(align(8)) struct a { char c; };", "a"),
+            (8, vec![(0, 1)])
+        );
+    }
+
+    #[test]
+    fn abi_and_endian_are_recorded_in_the_metadata() {
+        let grammar_contents = convert_with(
+            "abi",
+            "struct Header { char c; long l; void *p; };",
+            "m68k",
+            "",
+        )
+        .unwrap();
+
+        assert!(grammar_contents.contains("\tabi = 'm68k'\r\n\tendian = 'big'\r\n"));
+        assert!(grammar_contents.contains("\tname = '_padding1'\r\n\tsize = 0x01\r\n"));
+        assert!(grammar_contents.contains(
+            "\tname = 'l'\r\n\tsize = 0x04\r\n\tdata_type = 'long'\r\n\tdisplay_format = 'intbe'\r\n"
+        ));
+        assert!(grammar_contents.contains(
+            "\tname = 'p'\r\n\tsize = 0x04\r\n\tdata_type = 'void *'\r\n\tdisplay_format = 'hex'\r\n"
+        ));
+    }
+
+    /// A header with an unrelated struct, a typedef of a struct and a struct using it.
+    const HEADER: &str = "#define MAX_NAME 15\n\
+                          enum state { IDLE, BUSY = 4 };\n\
+                          struct unrelated { int x; };\n\
+                          typedef struct { short x; short y; } POINT, *PPOINT;\n\
+                          typedef struct shape { POINT origin; PPOINT next; enum state state; char name[MAX_NAME + 1]; } SHAPE;\n\
+                          struct last { char c; };";
+
+    #[test]
+    fn every_record_is_a_type_without_a_root() {
+        let grammar_contents = convert("header", HEADER).unwrap();
+
+        assert!(grammar_contents.contains("\tname = 'last'\r\n\tvariable_size_fields"));
+        for name in ["unrelated", "POINT", "shape", "last"] {
+            assert!(
+                grammar_contents.contains(&format!("[[types]]\r\n\tname = '{}'\r\n", name)),
+                "{}",
+                name
+            );
         }
     }
+
+    #[test]
+    fn only_records_reachable_from_the_root_are_types() {
+        let grammar_contents = convert_with("root", HEADER, c_types::DEFAULT_ABI, "SHAPE").unwrap();
+
+        assert!(grammar_contents.contains("\tname = 'shape'\r\n\tvariable_size_fields"));
+        assert!(grammar_contents.contains("[[types]]\r\n\tname = 'POINT'\r\n"));
+        assert!(grammar_contents.contains("[[types]]\r\n\tname = 'shape'\r\n"));
+        assert!(!grammar_contents.contains("\tname = 'unrelated'"));
+        assert!(!grammar_contents.contains("\tname = 'last'"));
+    }
+
+    #[test]
+    fn typedefs_enums_and_constants_are_resolved() {
+        let grammar_contents =
+            convert_with("resolved", HEADER, c_types::DEFAULT_ABI, "shape").unwrap();
+
+        assert!(grammar_contents.contains(
+            "\tname = 'origin'\r\n\tsize = 0x04\r\n\ttype = 'POINT'\r\n\tdata_type = 'struct POINT'\r\n"
+        ));
+        assert!(grammar_contents
+            .contains("\tname = 'next'\r\n\tsize = 0x08\r\n\tdata_type = 'struct POINT *'\r\n"));
+        assert!(grammar_contents.contains(
+            "\tname = 'state'\r\n\tsize = 0x04\r\n\tdata_type = 'enum state'\r\n\tdisplay_format = 'int'\r\n\tdescription = 'IDLE = 0, BUSY = 4'\r\n"
+        ));
+        assert!(grammar_contents
+            .contains("\tname = 'name'\r\n\tsize = 0x10\r\n\tdata_type = 'char[16]'\r\n"));
+    }
+
+    #[test]
+    fn unknown_root_is_an_error() {
+        assert!(convert_with("unknown_root", HEADER, c_types::DEFAULT_ABI, "PPOINT").is_err());
+        assert!(convert_with("missing_root", HEADER, c_types::DEFAULT_ABI, "missing").is_err());
+    }
+
+    /// Bitfields that share a storage unit with GCC but not with MSVC.
+    const BITFIELDS: &str =
+        "struct a { unsigned int x : 3; int y : 7; char c; unsigned short z : 4; };";
+
+    #[test]
+    fn bitfields_share_units_the_gcc_way() {
+        let grammar_contents = convert_with("gcc_bits", BITFIELDS, "lp64", "").unwrap();
+
+        assert!(grammar_contents.contains(
+            "\tname = 'x_to_y'\r\n\tsize = 0x02\r\n\tdata_type = 'unsigned int'\r\n\tdisplay_format = 'bits'\r\n\tdescription = 'N/A'\r\n\
+             \r\n[[fields.bits]]\r\n\tname = 'x'\r\n\toffset = 0\r\n\twidth = 3\r\n\
+             \r\n[[fields.bits]]\r\n\tname = 'y'\r\n\toffset = 3\r\n\twidth = 7\r\n\tsigned = true\r\n"
+        ));
+        assert!(grammar_contents.contains("\tname = 'z'\r\n\tsize = 0x01\r\n"));
+        assert!(grammar_contents.contains("\tkind = 'struct'\r\n\tsize = 0x04\r\n"));
+    }
+
+    #[test]
+    fn bitfields_share_units_the_msvc_way() {
+        let grammar_contents = convert_with("msvc_bits", BITFIELDS, "llp64", "").unwrap();
+
+        assert!(grammar_contents.contains("\tname = 'x_to_y'\r\n\tsize = 0x04\r\n"));
+        assert!(grammar_contents.contains("\tname = '_padding1'\r\n\tsize = 0x01\r\n"));
+        assert!(grammar_contents.contains("\tname = 'z'\r\n\tsize = 0x02\r\n"));
+        assert!(grammar_contents.contains("\tkind = 'struct'\r\n\tsize = 0x08\r\n"));
+    }
+
+    #[test]
+    fn bitfields_that_would_straddle_a_unit_move_to_the_next() {
+        assert_eq!(
+            layout("struct a { char a; int b : 30; int c : 4; };", "a"),
+            (12, vec![(0, 1), (4, 4), (8, 4)])
+        );
+    }
+
+    #[test]
+    fn big_endian_bitfields_start_at_the_most_significant_bit() {
+        let grammar_contents = convert_with("be_bits", BITFIELDS, "ppc", "").unwrap();
+
+        assert!(grammar_contents.contains("\tdisplay_format = 'bitsbe'\r\n"));
+        assert!(grammar_contents.contains("\tname = 'x'\r\n\toffset = 13\r\n\twidth = 3\r\n"));
+        assert!(grammar_contents.contains("\tname = 'y'\r\n\toffset = 6\r\n\twidth = 7\r\n"));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn rust_structs_are_laid_out_like_rustc() {
+        #[repr(C)]
+        struct Inner {
+            a: u8,
+            b: u128,
+        }
+
+        #[repr(C)]
+        struct Outer {
+            a: u16,
+            b: [u32; 3],
+            c: i128,
+            d: Inner,
+            e: f64,
+            f: *const u8,
+            g: bool,
+            h: i128,
+        }
+
+        const SOURCE: &str = "#[repr(C)] struct Inner { a: u8, b: u128 }\n\
+                              #[repr(C)] struct Outer { a: u16, b: [u32; 3], c: i128, d: Inner, e: f64, \
+                              f: *const u8, g: bool, h: i128 }\n";
+
+        assert_eq!(
+            header_layout(rust_parse::parse_rust_source(SOURCE).unwrap(), "Inner"),
+            (
+                std::mem::size_of::<Inner>(),
+                vec![
+                    (std::mem::offset_of!(Inner, a), 1),
+                    (std::mem::offset_of!(Inner, b), 16)
+                ]
+            )
+        );
+        assert_eq!(
+            header_layout(rust_parse::parse_rust_source(SOURCE).unwrap(), "Outer"),
+            (
+                std::mem::size_of::<Outer>(),
+                vec![
+                    (std::mem::offset_of!(Outer, a), 2),
+                    (std::mem::offset_of!(Outer, b), 12),
+                    (std::mem::offset_of!(Outer, c), 16),
+                    (std::mem::offset_of!(Outer, d), std::mem::size_of::<Inner>()),
+                    (std::mem::offset_of!(Outer, e), 8),
+                    (std::mem::offset_of!(Outer, f), 8),
+                    (std::mem::offset_of!(Outer, g), 1),
+                    (std::mem::offset_of!(Outer, h), 16),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn rust_source_is_converted() {
+        let grammar = convert(
+            "rust_source.rs",
+            "#[repr(C)]\npub struct Header {\n    magic: [u8; 4],\n    length: u32,\n}\n",
+        )
+        .unwrap();
+
+        assert!(grammar.contains("name = 'Header'"));
+        assert!(grammar.contains("\tname = 'magic'\r\n\tsize = 0x04\r\n"));
+        assert!(grammar.contains("\tname = 'length'\r\n\tsize = 0x04\r\n\tdata_type = 'u32'\r\n"));
+    }
 }
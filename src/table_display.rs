@@ -1,11 +1,14 @@
 //! Module for creating and printing data extracted from a binary file based it's corrosponding grammar.
 use crate::arg_parse;
 use crate::format;
+use crate::formatter;
 use crate::gram_parse;
-use hex::ToHex;
-use prettytable::Table;
+use crate::script;
+use crate::theme;
+use prettytable::{Cell, Row, Table};
 use std::collections::HashMap;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::io::SeekFrom;
 
@@ -14,7 +17,7 @@ use std::io::SeekFrom;
 /// It also holds the description table and standard table which get populated with the formatted/converted data.
 pub struct TableData {
     pub field_hashmap: HashMap<String, Vec<u8>>,
-    field_fmt_hashmap: HashMap<String, String>,
+    pub field_fmt_hashmap: HashMap<String, String>,
     field_str_hashmap: HashMap<String, String>,
     description_table: Table,
     standard_table: Table,
@@ -40,11 +43,19 @@ impl TableData {
     }
 
     /// Prints either the standard table or description table based on the table argument.
-    pub fn print_table(&self, table: Tables) {
-        match table {
-            Tables::Standard => self.standard_table.printstd(),
-            Tables::Description => self.description_table.printstd(),
+    ///
+    /// Colour codes are only written if `theme.colour` is true.
+    pub fn print_table(&self, table: Tables, theme: &theme::Theme) {
+        let table = match table {
+            Tables::Standard => &self.standard_table,
+            Tables::Description => &self.description_table,
         };
+
+        if theme.colour {
+            table.print_tty(true).unwrap();
+        } else {
+            table.print(&mut io::stdout()).unwrap();
+        }
     }
 
     /// Fills `self.standard_table` with all of the formatted data extracted from a binary file.
@@ -54,6 +65,7 @@ impl TableData {
         &mut self,
         parsed_gram: &gram_parse::Grammar,
         mut struct_offset: usize,
+        theme: &theme::Theme,
     ) -> Result<&mut Self, ()> {
         self.standard_table.add_row(row![
             "ID",
@@ -76,13 +88,18 @@ impl TableData {
 
             let field_id = format!("{:03X}", index);
 
-            if index % 2 == 0 {
-                self.standard_table
-                        .add_row(row![bFG->field_id,bFG->field.name,bFG->format!("{:#X}", struct_offset),bFG->format!("{:#X}",field.size),bFG->field.data_type,bFG->raw_hex_string,bFG->formatted_data]);
-            } else {
-                self.standard_table
-                        .add_row(row![bFM->field_id,bFM->field.name,bFM->format!("{:#X}", struct_offset),bFM->format!("{:#X}",field.size),bFM->field.data_type,bFM->raw_hex_string,bFM->formatted_data]);
-            }
+            self.standard_table.add_row(styled_row(
+                &[
+                    &field_id,
+                    &field.name,
+                    &format!("{:#X}", struct_offset),
+                    &format!("{:#X}", field.size),
+                    &field.data_type,
+                    raw_hex_string,
+                    formatted_data,
+                ],
+                theme.style_spec(index, field),
+            ));
 
             struct_offset += field.size;
         }
@@ -93,20 +110,21 @@ impl TableData {
     /// Fills `self.description_table` with all of the formatted data extracted from a binary file.
     ///
     /// Note: No formatted data rows are created.
-    pub fn fill_description_table(&mut self, parsed_gram: &gram_parse::Grammar) -> &mut Self {
+    pub fn fill_description_table(
+        &mut self,
+        parsed_gram: &gram_parse::Grammar,
+        theme: &theme::Theme,
+    ) -> &mut Self {
         self.description_table
             .add_row(row!["ID", "Field", "Description"]);
 
         for (index, field) in parsed_gram.fields.iter().enumerate() {
             let field_id = format!("{:03X}", index);
 
-            if index % 2 == 0 {
-                self.description_table
-                    .add_row(row![bFG->field_id,bFG->field.name[..],bFG->field.description]);
-            } else {
-                self.description_table
-                    .add_row(row![bFM->field_id,bFM->field.name[..],bFM->field.description]);
-            }
+            self.description_table.add_row(styled_row(
+                &[&field_id, &field.name, &field.description],
+                theme.style_spec(index, field),
+            ));
         }
         self
     }
@@ -118,7 +136,7 @@ impl TableData {
         &mut self,
         parsed_gram: &mut gram_parse::Grammar,
         binary_file: &mut File,
-        var_sized_fields_vec: &mut Vec<gram_parse::VariableSizeEntry>,
+        var_sized_fields_vec: &mut [gram_parse::VariableSizeEntry],
     ) -> Result<(), ()> {
         let eof = binary_file.metadata().unwrap().len() as i64;
 
@@ -127,8 +145,7 @@ impl TableData {
                 if field.name == entry.var_field_name {
                     match entry.variable_options {
                         gram_parse::VariableOptions::NullChar => {
-                            let current_position =
-                                binary_file.seek(SeekFrom::Current(0)).unwrap() as i64;
+                            let current_position = binary_file.stream_position().unwrap() as i64;
                             let mut read_size: i64 = 512;
                             let mut byte_buffer: Vec<u8> = Vec::new();
                             let mut prev_null = false;
@@ -139,13 +156,7 @@ impl TableData {
                                 read_size = eof - current_position;
                             }
 
-                            byte_buffer.append(
-                                &mut binary_file
-                                    .bytes()
-                                    .take(read_size as usize)
-                                    .map(|r: Result<u8, _>| r.unwrap())
-                                    .collect(),
-                            );
+                            byte_buffer.append(&mut read_bytes(binary_file, read_size as usize));
 
                             for (index, byte) in byte_buffer.iter().enumerate() {
                                 if *byte == 0x00u8 {
@@ -188,21 +199,18 @@ impl TableData {
                     }
                 }
 
-                let pos_after_read =
-                    binary_file.seek(SeekFrom::Current(0)).unwrap() as usize + field.size;
+                if format::is_varint_format(&field.display_format) {
+                    field.size = read_varint_size(binary_file, field.size);
+                }
+
+                let pos_after_read = binary_file.stream_position().unwrap() as usize + field.size;
 
-                if eof <= pos_after_read as i64 {
+                if eof < pos_after_read as i64 {
                     serror!("Reached EOF");
                     return Ok(());
                 }
-                self.field_hashmap.insert(
-                    field.name.to_string(),
-                    binary_file
-                        .bytes()
-                        .take(field.size)
-                        .map(|r: Result<u8, _>| r.unwrap())
-                        .collect(),
-                );
+                self.field_hashmap
+                    .insert(field.name.to_string(), read_bytes(binary_file, field.size));
             }
         }
 
@@ -210,6 +218,8 @@ impl TableData {
     }
 
     /// Deals with creating either a fixed field size or variable field sized `self.field_hashmap`.
+    ///
+    /// The size of varint fields is set to the number of bytes they occupy in the binary.
     pub fn create_field_hashmap(
         &mut self,
         parsed_gram: &mut gram_parse::Grammar,
@@ -234,22 +244,19 @@ impl TableData {
                 &mut var_sized_fields_vec,
             )?;
         } else {
-            for field in &parsed_gram.fields {
-                let pos_after_read =
-                    binary_file.seek(SeekFrom::Current(0)).unwrap() as usize + field.size;
+            for field in &mut parsed_gram.fields {
+                if format::is_varint_format(&field.display_format) {
+                    field.size = read_varint_size(binary_file, field.size);
+                }
+
+                let pos_after_read = binary_file.stream_position().unwrap() as usize + field.size;
 
-                if eof <= pos_after_read as i64 {
+                if eof < pos_after_read as i64 {
                     serror!(format!("Structure size after read: {}, will be larger than file size: {} after next read",pos_after_read,eof));
                     return Err(());
                 }
-                self.field_hashmap.insert(
-                    field.name.to_string(),
-                    binary_file
-                        .bytes()
-                        .take(field.size)
-                        .map(|r: Result<u8, _>| r.unwrap())
-                        .collect(),
-                );
+                self.field_hashmap
+                    .insert(field.name.to_string(), read_bytes(binary_file, field.size));
             }
         }
         Ok(self)
@@ -257,77 +264,92 @@ impl TableData {
 
     /// Formats/Converts raw data extracted from the binary and stored in `self.field_hashmap` into `self.field_fmt_hashmap`.
     ///
-    /// The data is converted based on the `display_format` specified for each field in the grammar file.
-    /// If a unknown `display_format` is specified, the data will be formatted as a hex string. The data formatted here is
+    /// The data is converted by the formatter registered for the `display_format` of each field, an `Err(())` is
+    /// returned if a field uses an unknown format or a size its format does not accept. The data formatted here is
     /// what ultimately gets printed in the "Formatted Data" row of the output table.
     ///
-    /// The endianess of the formatted data is determined by the `display_type` or by the `fmt_endian_flag`.
+    /// The bytes of endian sensitive formats are reversed if `fmt_endian_flag` is set. Fields using the `script` format
+    /// are formatted by their script, which can read the raw bytes of every field and the values formatted before it.
+    ///
+    /// Code fields are disassembled with addresses starting from `field_offset`, the offset of the structure. x86 code is
+    /// disassembled in `asm_syntax`.
     pub fn format_fields(
         &mut self,
         parsed_gram: &gram_parse::Grammar,
         fmt_endian_flag: bool,
+        mut field_offset: usize,
+        asm_syntax: format::AsmSyntaxes,
     ) -> Result<&mut Self, ()> {
-        for field in parsed_gram.fields.iter() {
-            let mut raw_hex_string: String = self
-                .field_hashmap
-                .get(&field.name)
-                .ok_or_else(|| serror!(format!("Failed to get value for field: {}", field.name)))?
-                .encode_hex::<String>()
-                .to_uppercase();
-
-            if raw_hex_string.len() > 25 {
-                raw_hex_string = raw_hex_string[..25].to_string();
-                raw_hex_string.push_str("...");
-            }
-
-            self.field_str_hashmap
-                .insert(field.name.clone(), raw_hex_string.clone());
+        let scripts = script::ScriptEngine::new();
 
+        for field in parsed_gram.fields.iter() {
             let raw_field_data: &Vec<u8> =
                 self.field_hashmap.get(&field.name).ok_or_else(|| {
                     serror!(format!("Could not get value for field: {}", field.name));
                 })?;
 
-            let reverse_hex_string = || {
-                let mut reversed_raw_field_data: Vec<u8> = raw_field_data.clone();
-                reversed_raw_field_data.reverse();
-                reversed_raw_field_data
-                    .encode_hex::<String>()
-                    .to_uppercase()
-            };
-
-            let formatted_data = match &field.display_format[..] {
-                format::HEXLE_TYPE => reverse_hex_string(),
-                format::ASCII_TYPE => raw_field_data.iter().map(|ascii| *ascii as char).collect(),
-                format::IPV4BE_TYPE => format::ipv4_string(&raw_field_data)?,
-                format::IPV4LE_TYPE => {
-                    let mut reversed_raw_field_data: Vec<u8> = raw_field_data.clone();
-                    reversed_raw_field_data.reverse();
-                    format::ipv4_string(&reversed_raw_field_data)?
-                }
-                format::UTF16BE_TYPE => format::utf16_string(raw_field_data, false)?,
-                format::UTF16LE_TYPE => format::utf16_string(raw_field_data, true)?,
-                format::X86_TYPE => {
-                    let mut x86_disassembly = format::DissassOutput::new();
-                    x86_disassembly.format_x86(16, raw_field_data);
-
-                    if x86_disassembly.line_count > 5 {
-                        String::from("See table above ;)")
-                    } else {
-                        x86_disassembly.output
-                    }
-                }
-                _ => {
-                    if fmt_endian_flag {
-                        reverse_hex_string()
-                    } else {
-                        raw_hex_string.clone()
-                    }
-                }
-            };
+            self.field_str_hashmap
+                .insert(field.name.clone(), format::hex_preview(raw_field_data));
+
+            let formatted_data = formatter::format_field(
+                field,
+                raw_field_data,
+                fmt_endian_flag,
+                &formatter::FormatContext {
+                    display_format: &field.display_format,
+                    field_offset,
+                    asm_syntax,
+                    field_name: &field.name,
+                    script_path: &field.script,
+                    scripts: &scripts,
+                    sibling_bytes: &self.field_hashmap,
+                    sibling_values: &self.field_fmt_hashmap,
+                    bit_ranges: &field.bits,
+                },
+            )?;
+
             self.field_fmt_hashmap
                 .insert(field.name.clone(), formatted_data);
+
+            field_offset += field.size;
         }
         Ok(self)
     }
 }
+
+/// Creates a table row where every cell has the prettytable style spec `style_spec`.
+fn styled_row(cells: &[&str], style_spec: &str) -> Row {
+    Row::new(
+        cells
+            .iter()
+            .map(|cell| Cell::new(cell).style_spec(style_spec))
+            .collect(),
+    )
+}
+
+/// Reads up to `size` bytes from the current position of `binary_file`.
+fn read_bytes(binary_file: &mut File, size: usize) -> Vec<u8> {
+    let mut byte_buffer: Vec<u8> = Vec::new();
+
+    Read::take(&mut *binary_file, size as u64)
+        .read_to_end(&mut byte_buffer)
+        .unwrap();
+
+    byte_buffer
+}
+
+/// Gets the size of the varint at the current position of `binary_file` without moving the position.
+///
+/// At most `max_size` bytes are read, or `format::MAX_VARINT_SIZE` if `max_size` is 0.
+fn read_varint_size(binary_file: &mut File, max_size: usize) -> usize {
+    let current_position = binary_file.stream_position().unwrap();
+    let max_size = match max_size {
+        0 => format::MAX_VARINT_SIZE,
+        _ => max_size.min(format::MAX_VARINT_SIZE),
+    };
+
+    let varint_bytes = read_bytes(binary_file, max_size);
+    binary_file.seek(SeekFrom::Start(current_position)).unwrap();
+
+    format::varint_size(&varint_bytes)
+}
@@ -0,0 +1,280 @@
+//! Module for choosing whether output is coloured and which colours each field is displayed in.
+//!
+//! The same `Theme` is used for the standard table, description table, hex view and HTML report so a field
+//! always has the same colour wherever it is shown.
+use crate::arg_parse;
+use crate::format;
+use crate::gram_parse;
+use colored::Colorize;
+use std::env;
+use std::io::IsTerminal;
+
+/// Colours a field can be displayed in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Colours {
+    Green,
+    Magenta,
+    Blue,
+    Yellow,
+    Cyan,
+    Red,
+    White,
+    DarkBlue,
+    DarkRed,
+}
+
+/// Palettes that can be selected with the theme flag.
+#[derive(Clone, Copy)]
+pub enum Palettes {
+    /// Alternates green and magenta by field index.
+    Default,
+    /// Alternates blue and yellow by field index, which remain distinguishable with the common forms of colour blindness.
+    ColourBlind,
+    /// Alternates dark blue and dark red by field index, for terminals with a light background.
+    Light,
+    /// Colours each field by the category of its type, see `FieldCategories`.
+    PerType,
+}
+
+/// Broad categories of field used by `Palettes::PerType`.
+#[derive(PartialEq)]
+pub enum FieldCategories {
+    String,
+    Integer,
+    Pointer,
+    Padding,
+    Code,
+    Other,
+}
+
+/// Holds whether output should be coloured and the palette to colour it with.
+pub struct Theme {
+    /// true = Output is coloured. false = No colour codes are written.
+    pub colour: bool,
+    /// The palette fields are coloured with.
+    pub palette: Palettes,
+}
+
+impl Theme {
+    /// Creates a `Theme` from the colour mode and palette passed on the cmdline.
+    ///
+    /// In `ColourModes::Auto`, output is only coloured if stdout is a terminal and the `NO_COLOR` environment variable is unset or empty.
+    /// The result is also applied to the `colored` crate.
+    pub fn from_args(cmd_args: &arg_parse::CMDArgParse) -> Self {
+        let colour = match cmd_args.colour_mode {
+            arg_parse::ColourModes::Always => true,
+            arg_parse::ColourModes::Never => false,
+            arg_parse::ColourModes::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        };
+
+        colored::control::set_override(colour);
+
+        Self {
+            colour,
+            palette: cmd_args.palette,
+        }
+    }
+
+    /// Paints `text` in bold `colour`, or dimmed if `colour` is None. `text` is returned unchanged if output is not coloured.
+    pub fn paint(&self, text: &str, colour: Option<Colours>) -> String {
+        if !self.colour {
+            return text.to_string();
+        }
+
+        match colour {
+            Some(colour) => text.color(colour.terminal_colour()).bold().to_string(),
+            None => text.dimmed().to_string(),
+        }
+    }
+
+    /// Returns the colour of `field`, which is at `index` in the grammar.
+    pub fn field_colour(&self, index: usize, field: &gram_parse::GrammarFields) -> Colours {
+        let (even, odd) = match self.palette {
+            Palettes::Default => (Colours::Green, Colours::Magenta),
+            Palettes::ColourBlind => (Colours::Blue, Colours::Yellow),
+            Palettes::Light => (Colours::DarkBlue, Colours::DarkRed),
+            Palettes::PerType => {
+                return match field_category(field) {
+                    FieldCategories::String => Colours::Green,
+                    FieldCategories::Integer => Colours::Cyan,
+                    FieldCategories::Pointer => Colours::Yellow,
+                    FieldCategories::Padding => Colours::White,
+                    FieldCategories::Code => Colours::Red,
+                    FieldCategories::Other => Colours::Magenta,
+                }
+            }
+        };
+
+        match index % 2 {
+            0 => even,
+            _ => odd,
+        }
+    }
+
+    /// Returns the prettytable style spec for a cell of `field`, or an empty spec if output is not coloured.
+    pub fn style_spec(&self, index: usize, field: &gram_parse::GrammarFields) -> &'static str {
+        if !self.colour {
+            return "";
+        }
+
+        match self.field_colour(index, field) {
+            Colours::Green => "bFG",
+            Colours::Magenta => "bFM",
+            Colours::Blue => "bFB",
+            Colours::Yellow => "bFY",
+            Colours::Cyan => "bFC",
+            Colours::Red => "bFR",
+            Colours::White => "bFW",
+            Colours::DarkBlue => "bFb",
+            Colours::DarkRed => "bFr",
+        }
+    }
+}
+
+impl Colours {
+    /// Returns the terminal colour for the colour.
+    pub fn terminal_colour(self) -> colored::Color {
+        match self {
+            Colours::Green => colored::Color::Green,
+            Colours::Magenta => colored::Color::Magenta,
+            Colours::Blue => colored::Color::Blue,
+            Colours::Yellow => colored::Color::Yellow,
+            Colours::Cyan => colored::Color::Cyan,
+            Colours::Red => colored::Color::Red,
+            Colours::White => colored::Color::White,
+            Colours::DarkBlue => colored::Color::Blue,
+            Colours::DarkRed => colored::Color::Red,
+        }
+    }
+
+    /// Returns the CSS colour value for the colour.
+    pub fn css_colour(self) -> &'static str {
+        match self {
+            Colours::Green => "#138a36",
+            Colours::Magenta => "#a4249c",
+            Colours::Blue => "#0072b2",
+            Colours::Yellow => "#d55e00",
+            Colours::Cyan => "#00838f",
+            Colours::Red => "#c62828",
+            Colours::White => "#9e9e9e",
+            Colours::DarkBlue => "#1a237e",
+            Colours::DarkRed => "#8b0000",
+        }
+    }
+}
+
+/// Puts a field into a broad category based on its name, data type and display format.
+pub fn field_category(field: &gram_parse::GrammarFields) -> FieldCategories {
+    let name = field.name.to_lowercase();
+    let data_type = field.data_type.to_lowercase();
+    let type_contains = |keywords: &[&str]| keywords.iter().any(|k| data_type.contains(k));
+
+    if ["padding", "reserved", "unused"]
+        .iter()
+        .any(|k| name.contains(k) || data_type.contains(k))
+    {
+        FieldCategories::Padding
+    } else if type_contains(&["pointer", "ptr", "offset", "address"]) {
+        FieldCategories::Pointer
+    } else if format::is_text_format(&field.display_format)
+        || type_contains(&["ascii", "string", "char", "text", "utf"])
+    {
+        FieldCategories::String
+    } else if format::is_code_format(&field.display_format)
+        || type_contains(&["instruction", "opcode", "code"])
+    {
+        FieldCategories::Code
+    } else if field.display_format == format::HEXLE_TYPE
+        || format::is_varint_format(&field.display_format)
+        || type_contains(&[
+            "int", "short", "long", "word", "byte", "size", "length", "count",
+        ])
+    {
+        FieldCategories::Integer
+    } else {
+        FieldCategories::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, data_type: &str, display_format: &str) -> gram_parse::GrammarFields {
+        gram_parse::GrammarFields {
+            name: String::from(name),
+            size: 4,
+            data_type: String::from(data_type),
+            display_format: String::from(display_format),
+            description: String::new(),
+            scale: None,
+            offset: None,
+            unit: String::new(),
+            script: String::new(),
+            bits: Vec::new(),
+            count: None,
+            type_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn palettes_alternate_by_field_index() {
+        let theme = Theme {
+            colour: true,
+            palette: Palettes::ColourBlind,
+        };
+        let field = field("Length", "uint32_t", format::HEXLE_TYPE);
+
+        assert!(theme.field_colour(0, &field) == Colours::Blue);
+        assert!(theme.field_colour(1, &field) == Colours::Yellow);
+        assert!(theme.field_colour(2, &field) == Colours::Blue);
+    }
+
+    #[test]
+    fn per_type_palette_colours_by_category() {
+        let theme = Theme {
+            colour: true,
+            palette: Palettes::PerType,
+        };
+
+        assert!(
+            theme.field_colour(0, &field("Name", "char", format::ASCII_TYPE)) == Colours::Green
+        );
+        assert!(theme.field_colour(1, &field("Count", "uint16_t", "hex")) == Colours::Cyan);
+        assert!(theme.field_colour(2, &field("Next", "pointer", "hex")) == Colours::Yellow);
+        assert!(theme.field_colour(3, &field("Reserved", "uint32_t", "hex")) == Colours::White);
+        assert!(
+            theme.field_colour(4, &field("Entry", "code", format::X86_32_TYPE)) == Colours::Red
+        );
+        assert!(theme.field_colour(5, &field("Blob", "data", "hex")) == Colours::Magenta);
+    }
+
+    #[test]
+    fn uncoloured_output_has_no_style_spec() {
+        let field = field("Length", "uint32_t", "hex");
+        let theme = Theme {
+            colour: false,
+            palette: Palettes::Default,
+        };
+        assert_eq!(theme.style_spec(0, &field), "");
+
+        let theme = Theme {
+            colour: true,
+            palette: Palettes::Default,
+        };
+        assert_eq!(theme.style_spec(1, &field), "bFM");
+    }
+
+    #[test]
+    fn colour_mode_overrides_the_terminal_check() {
+        let mut cmd_args = arg_parse::CMDArgParse::new();
+        cmd_args.colour_mode = arg_parse::ColourModes::Never;
+        assert!(!Theme::from_args(&cmd_args).colour);
+
+        cmd_args.colour_mode = arg_parse::ColourModes::Always;
+        assert!(Theme::from_args(&cmd_args).colour);
+    }
+}